@@ -0,0 +1,76 @@
+//! Switch a fan on PA5 from the core temperature, which the ADC converts in the background
+//!
+//! The ADC runs in continuous mode, the control loop only picks up the latest result, without
+//! starting or waiting for a conversion.
+#![no_main]
+#![no_std]
+
+use panic_rtt_target as _;
+
+use cortex_m_rt::entry;
+use rtt_target::{rprint, rprintln};
+use stm32l4xx_hal::{
+    adc::{SampleTime, ADC},
+    delay::Delay,
+    pac,
+    prelude::*,
+};
+
+/// The fan is switched on above this temperature, in °C
+const FAN_ON: f32 = 40.0;
+
+/// ... and off again below this one, so it doesn't toggle around a single threshold
+const FAN_OFF: f32 = 35.0;
+
+#[entry]
+fn main() -> ! {
+    rtt_target::rtt_init_print!();
+    rprint!("Initializing...");
+
+    let cp = pac::CorePeripherals::take().unwrap();
+    let dp = pac::Peripherals::take().unwrap();
+
+    let mut rcc = dp.RCC.constrain();
+    let mut flash = dp.FLASH.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+
+    let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+    let mut fan = gpioa
+        .pa5
+        .into_push_pull_output(&mut gpioa.moder, &mut gpioa.otyper);
+
+    let mut delay = Delay::new(cp.SYST, clocks);
+    let mut adc = ADC::new(
+        dp.ADC1,
+        dp.ADC_COMMON,
+        &mut rcc.ahb2,
+        &mut rcc.ccipr,
+        &mut delay,
+    );
+
+    // The longest sample time, well above the minimum of the temperature sensor, also slows
+    // down the conversions
+    adc.set_sample_time(SampleTime::Cycles640_5);
+    let mut temperature = adc.enable_temperature();
+    adc.start_continuous(&mut temperature);
+
+    rprintln!(" done.");
+
+    let mut fan_on = false;
+    loop {
+        let celsius = ADC::to_degrees_centigrade(adc.latest());
+
+        if !fan_on && celsius > FAN_ON {
+            fan_on = true;
+            fan.set_high().unwrap();
+        } else if fan_on && celsius < FAN_OFF {
+            fan_on = false;
+            fan.set_low().unwrap();
+        }
+
+        rprintln!("{} °C, fan {}", celsius, if fan_on { "on" } else { "off" });
+        delay.delay_ms(500_u32);
+    }
+}
@@ -0,0 +1,54 @@
+//! Samples two ADC channels at the same instant, with ADC1 and ADC2 in dual mode
+#![no_main]
+#![no_std]
+
+use panic_rtt_target as _;
+
+use cortex_m_rt::entry;
+use rtt_target::{rprint, rprintln};
+use stm32l4xx_hal::{
+    adc::{DualAdc, ADC},
+    delay::Delay,
+    pac,
+    prelude::*,
+};
+
+#[entry]
+fn main() -> ! {
+    rtt_target::rtt_init_print!();
+    rprint!("Initializing...");
+
+    let cp = pac::CorePeripherals::take().unwrap();
+    let dp = pac::Peripherals::take().unwrap();
+
+    let mut rcc = dp.RCC.constrain();
+    let mut flash = dp.FLASH.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+
+    let mut delay = Delay::new(cp.SYST, clocks);
+    let adc = ADC::new(
+        dp.ADC1,
+        dp.ADC_COMMON,
+        &mut rcc.ahb2,
+        &mut rcc.ccipr,
+        &mut delay,
+    );
+    let mut adc = DualAdc::new(adc, dp.ADC2, &mut delay);
+
+    let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+    let mut a0 = gpioa.pa0.into_analog(&mut gpioa.moder, &mut gpioa.pupdr);
+    let mut a1 = gpioa.pa1.into_analog(&mut gpioa.moder, &mut gpioa.pupdr);
+
+    rprintln!(" done.");
+
+    loop {
+        // PA0 is sampled by ADC1 and PA1 by ADC2, at the same instant
+        let (pa0, pa1) = adc.read(&mut a0, &mut a1);
+        let pa0 = adc.master().to_millivolts(pa0);
+        let pa1 = adc.master().to_millivolts(pa1);
+
+        rprintln!("PA0: {} mV, PA1: {} mV", pa0, pa1);
+    }
+}
@@ -0,0 +1,59 @@
+//! Zeroes the baseline of a load cell in hardware, with the ADC offset correction
+//!
+//! The bridge is read through an instrumentation amplifier on PC0, whose output idles at some
+//! offset without load. That baseline is measured once, at start-up with the scale empty, and
+//! then subtracted from every conversion by the ADC.
+#![no_main]
+#![no_std]
+
+use panic_rtt_target as _;
+
+use cortex_m_rt::entry;
+use rtt_target::{rprint, rprintln};
+use stm32l4xx_hal::{adc::ADC, delay::Delay, pac, prelude::*};
+
+/// Number of samples averaged for the baseline
+const TARE_SAMPLES: u32 = 16;
+
+#[entry]
+fn main() -> ! {
+    rtt_target::rtt_init_print!();
+    rprint!("Initializing...");
+
+    let cp = pac::CorePeripherals::take().unwrap();
+    let dp = pac::Peripherals::take().unwrap();
+
+    let mut rcc = dp.RCC.constrain();
+    let mut flash = dp.FLASH.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+
+    let mut delay = Delay::new(cp.SYST, clocks);
+    let mut adc = ADC::new(
+        dp.ADC1,
+        dp.ADC_COMMON,
+        &mut rcc.ahb2,
+        &mut rcc.ccipr,
+        &mut delay,
+    );
+
+    let mut gpioc = dp.GPIOC.split(&mut rcc.ahb2);
+    let mut bridge = gpioc.pc0.into_analog(&mut gpioc.moder, &mut gpioc.pupdr);
+
+    rprintln!(" done.");
+
+    // Tare, with nothing on the scale
+    let baseline = (0..TARE_SAMPLES)
+        .map(|_| u32::from(adc.read(&mut bridge).unwrap()))
+        .sum::<u32>()
+        / TARE_SAMPLES;
+    adc.set_offset(&bridge, baseline as u16).unwrap();
+    rprintln!("Baseline: {}", baseline);
+
+    loop {
+        // The result is signed, as the bridge drifts slightly below the baseline without load
+        let load = adc.read(&mut bridge).unwrap() as i16;
+        rprintln!("Load: {}", load);
+    }
+}
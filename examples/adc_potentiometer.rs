@@ -0,0 +1,52 @@
+//! Read the position of a potentiometer connected between VDDA and GND, with the wiper on PA0
+//!
+//! The pin has to be in analog mode, without any pull resistor, before the ADC samples it.
+#![no_main]
+#![no_std]
+
+use panic_rtt_target as _;
+
+use cortex_m_rt::entry;
+use rtt_target::{rprint, rprintln};
+use stm32l4xx_hal::{adc::ADC, delay::Delay, pac, prelude::*};
+
+#[entry]
+fn main() -> ! {
+    rtt_target::rtt_init_print!();
+    rprint!("Initializing...");
+
+    let cp = pac::CorePeripherals::take().unwrap();
+    let dp = pac::Peripherals::take().unwrap();
+
+    let mut rcc = dp.RCC.constrain();
+    let mut flash = dp.FLASH.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+
+    // Configure the pin before handing it to the ADC
+    let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+    let mut wiper = gpioa.pa0.into_analog(&mut gpioa.moder, &mut gpioa.pupdr);
+
+    let mut delay = Delay::new(cp.SYST, clocks);
+    let mut adc = ADC::new(
+        dp.ADC1,
+        dp.ADC_COMMON,
+        &mut rcc.ahb2,
+        &mut rcc.ccipr,
+        &mut delay,
+    );
+    let mut vref = adc.enable_vref(&mut delay);
+
+    rprintln!(" done.");
+
+    loop {
+        // The potentiometer is supplied from VDDA, so the raw sample is already its position
+        let sample = adc.read(&mut wiper).unwrap();
+        let percent = u32::from(sample) * 100 / 4095;
+        let millivolts = adc.read_mv(&mut vref, &mut wiper);
+
+        rprintln!("Position: {}% ({} mV)", percent, millivolts);
+        delay.delay_ms(100.ms());
+    }
+}
@@ -0,0 +1,95 @@
+//! Measures the power of a mains load from voltage and current samples taken as pairs
+//!
+//! The voltage (e.g. from a transformer or a divider) is on PC0 and the current (e.g. from a
+//! current transformer) on PC1, both biased at half of VDDA. TIM6 triggers a pair of
+//! conversions 5000 times a second, and the real power is the average of the instantaneous
+//! power over a second, 50 cycles at 50 Hz.
+#![no_main]
+#![no_std]
+
+use panic_rtt_target as _;
+
+use cortex_m_rt::entry;
+use rtt_target::{rprint, rprintln};
+use stm32l4xx_hal::{
+    adc::{Edge, Error, ExternalTrigger, ADC},
+    delay::Delay,
+    pac,
+    prelude::*,
+    timer::{MasterMode, Timer},
+};
+
+/// Pairs per second, the rate of the trigger
+const RATE: u32 = 5000;
+
+/// Bias of both inputs, in millivolts
+const BIAS_MV: f32 = 1650.0;
+
+/// Mains volts per millivolt at PC0
+const VOLTS_PER_MV: f32 = 0.25;
+
+/// Load amperes per millivolt at PC1
+const AMPS_PER_MV: f32 = 0.01;
+
+#[entry]
+fn main() -> ! {
+    rtt_target::rtt_init_print!();
+    rprint!("Initializing...");
+
+    let cp = pac::CorePeripherals::take().unwrap();
+    let dp = pac::Peripherals::take().unwrap();
+
+    let mut rcc = dp.RCC.constrain();
+    let mut flash = dp.FLASH.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+
+    let mut delay = Delay::new(cp.SYST, clocks);
+    let mut adc = ADC::new(
+        dp.ADC1,
+        dp.ADC_COMMON,
+        &mut rcc.ahb2,
+        &mut rcc.ccipr,
+        &mut delay,
+    );
+
+    let mut gpioc = dp.GPIOC.split(&mut rcc.ahb2);
+    let mut voltage = gpioc.pc0.into_analog(&mut gpioc.moder, &mut gpioc.pupdr);
+    let mut current = gpioc.pc1.into_analog(&mut gpioc.moder, &mut gpioc.pupdr);
+
+    // The current is sampled one conversion after the voltage, well below a degree at 50 Hz
+    adc.start_triggered_pair(
+        &mut voltage,
+        &mut current,
+        ExternalTrigger::Tim6Trgo,
+        Edge::Rising,
+    );
+
+    let mut timer = Timer::tim6(dp.TIM6, RATE.hz(), clocks, &mut rcc.apb1r1);
+    timer.set_master_mode(MasterMode::Update);
+
+    rprintln!(" done.");
+
+    let mut power_sum = 0.0;
+    let mut pairs = 0;
+    loop {
+        let (v, i) = match nb::block!(adc.read_pair()) {
+            Ok(pair) => pair,
+            // Too slow to keep up, only the pairs of the same trigger are used
+            Err(Error::Overrun) => continue,
+            Err(error) => panic!("{:?}", error),
+        };
+
+        let volts = (f32::from(adc.to_millivolts(v)) - BIAS_MV) * VOLTS_PER_MV;
+        let amps = (f32::from(adc.to_millivolts(i)) - BIAS_MV) * AMPS_PER_MV;
+        power_sum += volts * amps;
+
+        pairs += 1;
+        if pairs == RATE {
+            rprintln!("Real power: {} W", power_sum / RATE as f32);
+            power_sum = 0.0;
+            pairs = 0;
+        }
+    }
+}
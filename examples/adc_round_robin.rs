@@ -0,0 +1,63 @@
+//! Samples two ADC channels round-robin without blocking on the conversions
+#![no_main]
+#![no_std]
+
+use panic_rtt_target as _;
+
+use cortex_m_rt::entry;
+use rtt_target::{rprint, rprintln};
+use stm32l4xx_hal::{adc::ADC, delay::Delay, pac, prelude::*};
+
+#[entry]
+fn main() -> ! {
+    rtt_target::rtt_init_print!();
+    rprint!("Initializing...");
+
+    let cp = pac::CorePeripherals::take().unwrap();
+    let dp = pac::Peripherals::take().unwrap();
+
+    let mut rcc = dp.RCC.constrain();
+    let mut flash = dp.FLASH.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+
+    let mut delay = Delay::new(cp.SYST, clocks);
+    let mut adc = ADC::new(
+        dp.ADC1,
+        dp.ADC_COMMON,
+        &mut rcc.ahb2,
+        &mut rcc.ccipr,
+        &mut delay,
+    );
+
+    let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+    let mut a0 = gpioa.pa0.into_analog(&mut gpioa.moder, &mut gpioa.pupdr);
+    let mut a1 = gpioa.pa1.into_analog(&mut gpioa.moder, &mut gpioa.pupdr);
+
+    rprintln!(" done.");
+
+    let mut values = [0u16; 2];
+    let mut current = 0;
+    adc.start_conversion(&mut a0);
+
+    loop {
+        match adc.read_result() {
+            Ok(value) => {
+                values[current] = value;
+                current = (current + 1) % values.len();
+
+                if current == 0 {
+                    rprintln!("PA0: {}, PA1: {}", values[0], values[1]);
+                    adc.start_conversion(&mut a0);
+                } else {
+                    adc.start_conversion(&mut a1);
+                }
+            }
+            Err(nb::Error::WouldBlock) => {
+                // Other work can be done here while the conversion is running
+            }
+            Err(nb::Error::Other(e)) => panic!("ADC error: {:?}", e),
+        }
+    }
+}
@@ -0,0 +1,71 @@
+//! Sample an analog input at 1 kHz, triggered by the TIM6 update event, into a DMA buffer
+#![no_main]
+#![no_std]
+
+use panic_rtt_target as _;
+
+use cortex_m::singleton;
+use cortex_m_rt::entry;
+use rtt_target::{rprint, rprintln};
+use stm32l4xx_hal::{
+    adc::{Edge, ExternalTrigger, ADC},
+    delay::Delay,
+    dma::Half,
+    pac,
+    prelude::*,
+    timer::{MasterMode, Timer},
+};
+
+#[entry]
+fn main() -> ! {
+    rtt_target::rtt_init_print!();
+    rprint!("Initializing...");
+
+    let cp = pac::CorePeripherals::take().unwrap();
+    let dp = pac::Peripherals::take().unwrap();
+
+    let mut rcc = dp.RCC.constrain();
+    let mut flash = dp.FLASH.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+
+    let mut delay = Delay::new(cp.SYST, clocks);
+    let mut adc = ADC::new(
+        dp.ADC1,
+        dp.ADC_COMMON,
+        &mut rcc.ahb2,
+        &mut rcc.ccipr,
+        &mut delay,
+    );
+
+    let mut gpioc = dp.GPIOC.split(&mut rcc.ahb2);
+    let mut a1 = gpioc.pc0.into_analog(&mut gpioc.moder, &mut gpioc.pupdr);
+
+    let channels = dp.DMA1.split(&mut rcc.ahb1);
+    let buf = singleton!(: [[u16; 64]; 2] = [[0; 64]; 2]).unwrap();
+
+    // Set up the DMA first, so that no conversion result is missed
+    let mut samples = adc.circ_read(channels.1, buf);
+    adc.start_triggered(&mut a1, ExternalTrigger::Tim6Trgo, Edge::Rising);
+
+    // One update event, and thus one conversion, per millisecond
+    let mut timer = Timer::tim6(dp.TIM6, 1.khz(), clocks, &mut rcc.apb1r1);
+    timer.set_master_mode(MasterMode::Update);
+
+    rprintln!(" done.");
+
+    loop {
+        while samples.readable_half().unwrap() != Half::First {}
+        let average = samples
+            .peek(|half, _| half.iter().map(|&s| u32::from(s)).sum::<u32>() / half.len() as u32)
+            .unwrap();
+        rprintln!("Average of the first half: {}", average);
+
+        while samples.readable_half().unwrap() != Half::Second {}
+        let average = samples
+            .peek(|half, _| half.iter().map(|&s| u32::from(s)).sum::<u32>() / half.len() as u32)
+            .unwrap();
+        rprintln!("Average of the second half: {}", average);
+    }
+}
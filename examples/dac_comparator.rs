@@ -0,0 +1,76 @@
+//! Compares PC5 against a threshold set by DAC channel 1, connected internally to the minus input
+//! of comparator 1. The DAC runs in sample and hold mode, so it doesn't drive PA4 and keeps the
+//! threshold in low-power modes.
+#![no_main]
+#![no_std]
+
+#[macro_use(entry, exception)]
+extern crate cortex_m_rt as rt;
+extern crate cortex_m;
+extern crate panic_semihosting;
+extern crate stm32l4xx_hal as hal;
+
+use crate::hal::dac::{Channel, Dac, DacMode, SampleAndHold, SampleAndHoldOutput};
+use crate::hal::prelude::*;
+use crate::rt::ExceptionFrame;
+use cortex_m::asm;
+
+/// COMP1_INMSEL value selecting DAC channel 1
+const INM_DAC_CH1: u8 = 0b100;
+
+/// COMP1_INPSEL value selecting PC5
+const INP_PC5: bool = false;
+
+#[entry]
+fn main() -> ! {
+    let dp = hal::stm32::Peripherals::take().unwrap();
+
+    // The comparators are clocked with the SYSCFG
+    dp.RCC.apb2enr.write(|w| w.syscfgen().set_bit());
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    // Sample and hold is clocked by the LSI
+    let _clocks = rcc.cfgr.lsi(true).freeze(&mut flash.acr, &mut pwr);
+
+    let mut gpioc = dp.GPIOC.split(&mut rcc.ahb2);
+    let _input = gpioc.pc5.into_analog(&mut gpioc.moder, &mut gpioc.pupdr);
+
+    let mut dac = Dac::new(dp.DAC1, &mut rcc.apb1r1);
+    dac.set_mode(
+        Channel::One,
+        DacMode::SampleAndHold(SampleAndHold {
+            output: SampleAndHoldOutput::ChipOnly,
+            sample: 20,
+            hold: 200,
+            refresh: 5,
+        }),
+    );
+    // Half of VDDA
+    dac.set_value(Channel::One, 2048);
+    dac.enable(Channel::One);
+
+    // NOTE(unsafe) the value selects a valid input
+    dp.COMP.comp1_csr.modify(|_, w| unsafe {
+        w.comp1_inmsel()
+            .bits(INM_DAC_CH1)
+            .comp1_inpsel()
+            .bit(INP_PC5)
+            .comp1_en()
+            .set_bit()
+    });
+
+    loop {
+        // when you reach this breakpoint `above` is true while PC5 is above the threshold
+        let above = dp.COMP.comp1_csr.read().comp1_value().bit_is_set();
+        let _ = above;
+        asm::bkpt();
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
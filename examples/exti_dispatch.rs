@@ -0,0 +1,84 @@
+//! Two buttons on EXTI lines 10 and 13, dispatched by the shared `EXTI15_10` handler
+//!
+//! The buttons pull PB10 and PC13 (the user button of the Nucleo boards) low.
+#![no_std]
+#![no_main]
+
+extern crate cortex_m;
+#[macro_use]
+extern crate cortex_m_rt as rt;
+extern crate cortex_m_semihosting as sh;
+extern crate panic_semihosting;
+extern crate stm32l4xx_hal as hal;
+
+use crate::hal::gpio::exti::{self, ExtiLine};
+use crate::hal::gpio::{Edge, ExtiPin};
+use crate::hal::interrupt;
+use crate::hal::prelude::*;
+use crate::hal::stm32;
+use crate::rt::ExceptionFrame;
+use crate::sh::hio;
+use core::fmt::Write;
+use cortex_m::peripheral::NVIC;
+
+const START: ExtiLine = ExtiLine::new(10);
+const STOP: ExtiLine = ExtiLine::new(13);
+
+#[entry]
+fn main() -> ! {
+    let mut dp = stm32::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+
+    let mut gpiob = dp.GPIOB.split(&mut rcc.ahb2);
+    let mut gpioc = dp.GPIOC.split(&mut rcc.ahb2);
+
+    let mut start = gpiob
+        .pb10
+        .into_pull_up_input(&mut gpiob.moder, &mut gpiob.pupdr);
+    let mut stop = gpioc
+        .pc13
+        .into_pull_up_input(&mut gpioc.moder, &mut gpioc.pupdr);
+    assert_eq!(start.exti_line(), START);
+    assert_eq!(stop.exti_line(), STOP);
+
+    for button in [&mut start as &mut dyn ExtiPin, &mut stop] {
+        button.make_interrupt_source(&mut dp.SYSCFG, &mut rcc.apb2);
+        button.trigger_on_edge(&mut dp.EXTI, Edge::Falling);
+        button.enable_interrupt(&mut dp.EXTI);
+    }
+
+    unsafe {
+        NVIC::unmask(stm32::Interrupt::EXTI15_10);
+    }
+
+    // The buttons are handled in the interrupt
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+#[interrupt]
+fn EXTI15_10() {
+    let mut hstdout = hio::hstdout().unwrap();
+
+    for line in exti::pending_lines().within(10..=15) {
+        // Cleared first, so a press during the handling triggers the interrupt again
+        exti::clear_interrupt(line);
+
+        match line {
+            START => writeln!(hstdout, "Start").unwrap(),
+            STOP => writeln!(hstdout, "Stop").unwrap(),
+            _ => {}
+        }
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
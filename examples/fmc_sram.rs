@@ -0,0 +1,95 @@
+//! External 16-bit SRAM (e.g. an IS62WV51216) on bank 1 (NE1) of the FMC
+//!
+//! Only the address lines A0 to A9 are used here, further address lines are set up the same way.
+// #![deny(warnings)]
+#![no_main]
+#![no_std]
+
+#[macro_use(entry, exception)]
+extern crate cortex_m_rt as rt;
+extern crate cortex_m;
+extern crate panic_semihosting;
+extern crate stm32l4xx_hal as hal;
+
+use crate::hal::fmc::{Bank, Config, Fmc, Timing};
+use crate::hal::gpio::Speed;
+use crate::hal::prelude::*;
+use crate::rt::ExceptionFrame;
+use core::ptr;
+use cortex_m::asm;
+
+/// Number of half-words addressed by A0 to A9
+const LEN: usize = 1 << 10;
+
+/// Puts the pins in alternate mode 12 (FMC), at the highest speed
+macro_rules! fmc_pins {
+    ($gpio:ident, $afr:ident: $($pin:ident),+) => {
+        $(
+            $gpio
+                .$pin
+                .into_af12(&mut $gpio.moder, &mut $gpio.$afr)
+                .set_speed(Speed::VeryHigh);
+        )+
+    };
+}
+
+#[entry]
+fn main() -> ! {
+    let p = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = p.FLASH.constrain();
+    let mut rcc = p.RCC.constrain();
+    let mut pwr = p.PWR.constrain(&mut rcc.apb1r1);
+
+    let _clocks = rcc.cfgr.sysclk(80.mhz()).freeze(&mut flash.acr, &mut pwr);
+
+    let mut gpiod = p.GPIOD.split(&mut rcc.ahb2);
+    let mut gpioe = p.GPIOE.split(&mut rcc.ahb2);
+    let mut gpiof = p.GPIOF.split(&mut rcc.ahb2);
+
+    // D0 to D15, NOE, NWE, NE1, NBL0 and NBL1
+    fmc_pins!(gpiod, afrl: pd0, pd1, pd4, pd5, pd7);
+    fmc_pins!(gpiod, afrh: pd8, pd9, pd10, pd14, pd15);
+    fmc_pins!(gpioe, afrl: pe0, pe1, pe7);
+    fmc_pins!(gpioe, afrh: pe8, pe9, pe10, pe11, pe12, pe13, pe14, pe15);
+    // A0 to A9
+    fmc_pins!(gpiof, afrl: pf0, pf1, pf2, pf3, pf4, pf5);
+    fmc_pins!(gpiof, afrh: pf12, pf13, pf14, pf15);
+
+    // 55 ns SRAM at 80 MHz HCLK: one cycle of address setup, five of data setup
+    let timing = Timing::default()
+        .address_setup(1)
+        .data_setup(5)
+        .bus_turnaround(0);
+
+    let mut fmc = Fmc::new(p.FMC, &mut rcc.ahb3);
+    let sram = fmc.nor_sram(Bank::Bank1, Config::default().read_timing(timing));
+    let mem = sram.as_mut_ptr::<u16>();
+
+    // Write a pattern and read it back
+    let mut errors = 0;
+    for i in 0..LEN {
+        // NOTE(unsafe) the SRAM is mapped at `mem` and holds `LEN` half-words
+        unsafe { ptr::write_volatile(mem.add(i), i as u16 ^ 0xa5a5) };
+    }
+    for i in 0..LEN {
+        // NOTE(unsafe) see above
+        let value = unsafe { ptr::read_volatile(mem.add(i)) };
+        if value != i as u16 ^ 0xa5a5 {
+            errors += 1;
+        }
+    }
+
+    // when you reach this breakpoint `errors` holds the number of mismatching half-words
+    let _ = errors;
+    asm::bkpt();
+
+    loop {
+        continue;
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
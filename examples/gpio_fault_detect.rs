@@ -0,0 +1,65 @@
+//! Detects the fault of an H-bridge driver on its shared enable / fault line
+//!
+//! Drivers like the L6206 have an open-drain `EN` pin: the MCU releases it to enable the bridge,
+//! and the driver pulls it low itself on an overcurrent or overtemperature fault. PA8 drives the
+//! line in open-drain mode, with a pull-up, and compares the level on the pin (`IDR`) with the
+//! level it is set to (`ODR`).
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+extern crate cortex_m;
+#[macro_use]
+extern crate cortex_m_rt as rt;
+extern crate cortex_m_semihosting as sh;
+extern crate panic_semihosting;
+extern crate stm32l4xx_hal as hal;
+
+use crate::hal::delay::Delay;
+use crate::hal::prelude::*;
+use crate::rt::ExceptionFrame;
+use crate::sh::hio;
+use core::fmt::Write;
+
+#[entry]
+fn main() -> ! {
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let dp = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+    let mut delay = Delay::new(cp.SYST, clocks);
+
+    let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+    let mut enable = gpioa
+        .pa8
+        .into_open_drain_output(&mut gpioa.moder, &mut gpioa.otyper);
+    enable.internal_pull_up(&mut gpioa.pupdr, true);
+
+    let mut hstdout = hio::hstdout().unwrap();
+
+    // Release the line, enabling the bridge
+    enable.set_high().unwrap();
+
+    loop {
+        delay.delay_ms(10_u32);
+
+        // The output is set high, the level on the pin tells if the driver pulls it low
+        if enable.is_set_high().unwrap() && enable.is_pin_low() {
+            writeln!(hstdout, "Bridge fault").unwrap();
+
+            // Disable the bridge until the fault is handled, the line is then low either way
+            enable.set_low().unwrap();
+            delay.delay_ms(1000_u32);
+            enable.set_high().unwrap();
+        }
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
@@ -0,0 +1,46 @@
+//! Locks the configuration of a relay driver pin, so nothing can turn it into an input
+//! or change its output type until the next reset
+#![deny(unsafe_code)]
+#![no_main]
+#![no_std]
+
+use panic_rtt_target as _;
+
+use cortex_m_rt::entry;
+use rtt_target::{rprintln, rtt_init_print};
+use stm32l4xx_hal::{delay::Delay, prelude::*, stm32};
+
+#[entry]
+fn main() -> ! {
+    rtt_init_print!();
+
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let dp = stm32::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+
+    let mut gpiob = dp.GPIOB.split(&mut rcc.ahb2);
+    let mut relay = gpiob
+        .pb0
+        .into_push_pull_output(&mut gpiob.moder, &mut gpiob.otyper);
+    relay.set_low().unwrap();
+
+    if relay.lock() {
+        rprintln!("Relay pin locked until reset");
+    } else {
+        rprintln!("GPIOB was already locked");
+    }
+
+    let mut delay = Delay::new(cp.SYST, clocks);
+
+    loop {
+        relay.set_high().unwrap();
+        delay.delay_ms(1000_u32);
+        relay.set_low().unwrap();
+        delay.delay_ms(1000_u32);
+    }
+}
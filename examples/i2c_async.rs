@@ -0,0 +1,121 @@
+//! Reads a temperature sensor on I2C1 with the `async` DMA bus
+//!
+//! The future is run by a minimal executor, which sleeps until an interrupt wakes it. The event
+//! and error interrupts of I2C1 are forwarded to the driver, see `hal::i2c::I2cDma`.
+#![no_main]
+#![no_std]
+
+extern crate cortex_m;
+#[macro_use(entry, exception)]
+extern crate cortex_m_rt as rt;
+extern crate cortex_m_semihosting as sh;
+extern crate panic_semihosting;
+
+extern crate stm32l4xx_hal as hal;
+
+use core::fmt::Write as _;
+use core::future::Future;
+use core::pin::pin;
+use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+use crate::hal::i2c::I2c;
+use crate::hal::interrupt;
+use crate::hal::pac::{self, I2C1, NVIC};
+use crate::hal::prelude::*;
+use crate::rt::ExceptionFrame;
+use crate::sh::hio;
+use cortex_m::asm;
+use embedded_hal_async::i2c::I2c as _;
+
+/// Address of the device, a temperature sensor
+const ADDR: u8 = 0x48;
+
+/// Temperature register of the device
+const TEMPERATURE: u8 = 0x00;
+
+#[entry]
+fn main() -> ! {
+    let mut hstdout = hio::hstdout().unwrap();
+
+    let p = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = p.FLASH.constrain();
+    let mut rcc = p.RCC.constrain();
+    let mut pwr = p.PWR.constrain(&mut rcc.apb1r1);
+
+    let mut gpioa = p.GPIOA.split(&mut rcc.ahb2);
+    let channels = p.DMA1.split(&mut rcc.ahb1);
+
+    let clocks = rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+
+    let scl = gpioa
+        .pa9
+        .into_open_drain_output(&mut gpioa.moder, &mut gpioa.otyper)
+        .into_af4(&mut gpioa.moder, &mut gpioa.afrh);
+    let sda = gpioa
+        .pa10
+        .into_open_drain_output(&mut gpioa.moder, &mut gpioa.otyper)
+        .into_af4(&mut gpioa.moder, &mut gpioa.afrh);
+
+    let i2c = I2c::i2c1(p.I2C1, (scl, sda), 100.khz(), clocks, &mut rcc.apb1r1);
+    let mut i2c = i2c.with_dma(channels.7, channels.6);
+
+    unsafe {
+        NVIC::unmask(pac::Interrupt::I2C1_EV);
+        NVIC::unmask(pac::Interrupt::I2C1_ER);
+    }
+
+    block_on(async {
+        loop {
+            let mut raw = [0; 2];
+            match i2c.write_read(ADDR, &[TEMPERATURE], &mut raw).await {
+                // 12 bits, left aligned, in 1/16 °C
+                Ok(()) => {
+                    let sixteenths = i16::from_be_bytes(raw) >> 4;
+                    writeln!(hstdout, "{} / 16 °C", sixteenths).unwrap();
+                }
+                Err(error) => writeln!(hstdout, "{:?}", error).unwrap(),
+            }
+        }
+    })
+}
+
+/// Polls `future` to completion, waiting for events in between
+///
+/// The waker sets the event register of the core, so a wake-up in between the poll and `wfe`
+/// isn't missed.
+fn block_on<F: Future>(future: F) -> F::Output {
+    fn clone(_: *const ()) -> RawWaker {
+        RawWaker::new(core::ptr::null(), &VTABLE)
+    }
+    fn wake(_: *const ()) {
+        asm::sev();
+    }
+    fn drop(_: *const ()) {}
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, wake, wake, drop);
+
+    let waker = unsafe { Waker::from_raw(clone(core::ptr::null())) };
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        asm::wfe();
+    }
+}
+
+#[interrupt]
+fn I2C1_EV() {
+    I2c::<I2C1, ()>::on_interrupt();
+}
+
+#[interrupt]
+fn I2C1_ER() {
+    I2c::<I2C1, ()>::on_interrupt();
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
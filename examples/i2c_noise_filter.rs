@@ -0,0 +1,92 @@
+//! Talks to a device at the end of a long cable, with the digital noise filter of I2C1
+//!
+//! The register encoding of the filters is checked on the way.
+#![no_std]
+#![no_main]
+
+extern crate cortex_m;
+#[macro_use]
+extern crate cortex_m_rt as rt;
+extern crate cortex_m_semihosting as sh;
+extern crate panic_semihosting;
+extern crate stm32l4xx_hal as hal;
+
+use crate::hal::i2c::I2c;
+use crate::hal::pac;
+use crate::hal::prelude::*;
+use crate::rt::ExceptionFrame;
+use crate::sh::hio;
+use core::fmt::Write;
+
+/// Address of the device, a temperature sensor
+const ADDR: u8 = 0x48;
+
+/// Digital filter, `CR1.DNF` in bits 8 to 11
+const DNF_SHIFT: u32 = 8;
+
+/// Analog filter off, `CR1.ANFOFF`
+const ANFOFF: u32 = 1 << 12;
+
+fn cr1() -> u32 {
+    // NOTE(unsafe) atomic read with no side effects
+    unsafe { (*pac::I2C1::ptr()).cr1.read().bits() }
+}
+
+#[entry]
+fn main() -> ! {
+    let mut hstdout = hio::hstdout().unwrap();
+
+    let dp = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc.cfgr.sysclk(80.mhz()).freeze(&mut flash.acr, &mut pwr);
+
+    let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+
+    let scl = gpioa
+        .pa9
+        .into_open_drain_output(&mut gpioa.moder, &mut gpioa.otyper)
+        .into_af4(&mut gpioa.moder, &mut gpioa.afrh);
+    let sda = gpioa
+        .pa10
+        .into_open_drain_output(&mut gpioa.moder, &mut gpioa.otyper)
+        .into_af4(&mut gpioa.moder, &mut gpioa.afrh);
+
+    let mut i2c = I2c::i2c1(dp.I2C1, (scl, sda), 100.khz(), clocks, &mut rcc.apb1r1);
+
+    // Analog filter alone by default
+    assert_eq!(i2c.filters(), (true, 0));
+    assert_eq!(cr1() & (0xf << DNF_SHIFT | ANFOFF), 0);
+
+    i2c.set_filters(false, 15);
+    assert_eq!(i2c.filters(), (false, 15));
+    assert_eq!(
+        cr1() & (0xf << DNF_SHIFT | ANFOFF),
+        0xf << DNF_SHIFT | ANFOFF
+    );
+
+    // Spikes of up to 100 ns at 80 MHz, on top of the analog filter
+    i2c.set_filters(true, 8);
+    assert_eq!(i2c.filters(), (true, 8));
+    assert_eq!(cr1() & (0xf << DNF_SHIFT | ANFOFF), 8 << DNF_SHIFT);
+    // The peripheral is enabled again
+    assert_eq!(cr1() & 1, 1);
+
+    writeln!(hstdout, "SCL at {} Hz", i2c.frequency(clocks).0).unwrap();
+
+    let mut temperature = [0; 2];
+    i2c.write_read(ADDR, &[0x00], &mut temperature).unwrap();
+    writeln!(hstdout, "Temperature: {} °C", temperature[0] as i8).unwrap();
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
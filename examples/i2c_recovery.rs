@@ -0,0 +1,92 @@
+//! Recovers from an I2C bus lockup, e.g. after a reset in the middle of a transfer
+
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+extern crate cortex_m;
+#[macro_use]
+extern crate cortex_m_rt as rt;
+extern crate cortex_m_semihosting as sh;
+extern crate panic_semihosting;
+extern crate stm32l4xx_hal as hal;
+
+use crate::hal::delay::Delay;
+use crate::hal::i2c::{BitBangI2c, I2c};
+use crate::hal::prelude::*;
+use crate::rt::entry;
+use crate::rt::ExceptionFrame;
+
+use crate::sh::hio;
+use core::fmt::Write;
+
+const MAX17048_ADDR: u8 = 0x6C;
+
+#[entry]
+fn main() -> ! {
+    let mut hstdout = hio::hstdout().unwrap();
+
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let dp = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+    let mut delay = Delay::new(cp.SYST, clocks);
+
+    let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+
+    let mut scl = gpioa
+        .pa9
+        .into_open_drain_output(&mut gpioa.moder, &mut gpioa.otyper);
+    scl.internal_pull_up(&mut gpioa.pupdr, true);
+    let scl = scl.into_af4(&mut gpioa.moder, &mut gpioa.afrh);
+
+    let mut sda = gpioa
+        .pa10
+        .into_open_drain_output(&mut gpioa.moder, &mut gpioa.otyper);
+    sda.internal_pull_up(&mut gpioa.pupdr, true);
+    let sda = sda.into_af4(&mut gpioa.moder, &mut gpioa.afrh);
+
+    let mut i2c = I2c::i2c1(dp.I2C1, (scl, sda), 100.khz(), clocks, &mut rcc.apb1r1);
+
+    loop {
+        let mut buffer = [0u8; 2];
+        if i2c.write_read(MAX17048_ADDR, &[0x08], &mut buffer).is_ok() {
+            writeln!(hstdout, "Silicon Version: {:?}", buffer).ok();
+            delay.delay_ms(1_000_u32);
+            continue;
+        }
+
+        // A stuck peripheral is recovered by a reset alone
+        i2c.reset(&mut rcc.apb1r1);
+        if i2c.write_read(MAX17048_ADDR, &[0x08], &mut buffer).is_ok() {
+            writeln!(hstdout, "Recovered the peripheral").ok();
+            continue;
+        }
+
+        // A slave holding SDA low needs clock pulses, generated with the pins as outputs
+        let (i2c1, (scl, sda)) = i2c.free();
+        let scl = scl.into_open_drain_output(&mut gpioa.moder, &mut gpioa.otyper);
+        let sda = sda.into_open_drain_output(&mut gpioa.moder, &mut gpioa.otyper);
+
+        let mut bitbang = BitBangI2c::new(scl, sda, 100.khz(), delay);
+        match bitbang.recover_bus() {
+            Ok(()) => writeln!(hstdout, "Recovered the bus").ok(),
+            Err(_) => writeln!(hstdout, "SDA is still held low").ok(),
+        };
+        let (scl, sda, d) = bitbang.free();
+        delay = d;
+
+        let scl = scl.into_af4(&mut gpioa.moder, &mut gpioa.afrh);
+        let sda = sda.into_af4(&mut gpioa.moder, &mut gpioa.afrh);
+        i2c = I2c::i2c1(i2c1, (scl, sda), 100.khz(), clocks, &mut rcc.apb1r1);
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
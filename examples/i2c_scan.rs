@@ -0,0 +1,68 @@
+//! Prints the addresses of the devices that answer on the I2C1 bus
+
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+extern crate cortex_m;
+#[macro_use]
+extern crate cortex_m_rt as rt;
+extern crate cortex_m_semihosting as sh;
+extern crate panic_semihosting;
+extern crate stm32l4xx_hal as hal;
+
+use crate::hal::i2c::I2c;
+use crate::hal::prelude::*;
+use crate::rt::ExceptionFrame;
+use crate::sh::hio;
+use core::fmt::Write;
+
+#[entry]
+fn main() -> ! {
+    let mut hstdout = hio::hstdout().unwrap();
+
+    let dp = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+
+    let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+
+    let mut scl = gpioa
+        .pa9
+        .into_open_drain_output(&mut gpioa.moder, &mut gpioa.otyper);
+    scl.internal_pull_up(&mut gpioa.pupdr, true);
+    let scl = scl.into_af4(&mut gpioa.moder, &mut gpioa.afrh);
+
+    let mut sda = gpioa
+        .pa10
+        .into_open_drain_output(&mut gpioa.moder, &mut gpioa.otyper);
+    sda.internal_pull_up(&mut gpioa.pupdr, true);
+    let sda = sda.into_af4(&mut gpioa.moder, &mut gpioa.afrh);
+
+    let mut i2c = I2c::i2c1(dp.I2C1, (scl, sda), 100.khz(), clocks, &mut rcc.apb1r1);
+
+    let mut count = 0;
+    let result = i2c.scan(|addr| {
+        writeln!(hstdout, "Found a device at {:#04x}", addr).unwrap();
+        count += 1;
+    });
+
+    match result {
+        Ok(()) => writeln!(hstdout, "{} device(s) found", count).unwrap(),
+        // e.g. a device holding SDA or SCL low
+        Err(error) => writeln!(hstdout, "Scan stopped: {:?}", error).unwrap(),
+    }
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
@@ -0,0 +1,102 @@
+//! Shares one I2C bus between the drivers of two devices, a fuel gauge and an EEPROM
+//!
+//! Each driver owns a proxy of the bus, as if it owned the bus itself.
+#![deny(unsafe_code)]
+#![deny(warnings)]
+#![no_std]
+#![no_main]
+
+extern crate cortex_m;
+#[macro_use]
+extern crate cortex_m_rt as rt;
+extern crate cortex_m_semihosting as sh;
+extern crate panic_semihosting;
+extern crate stm32l4xx_hal as hal;
+
+use crate::hal::hal::blocking::i2c::{Write, WriteRead};
+use crate::hal::i2c::{I2c, SharedBus};
+use crate::hal::prelude::*;
+use crate::rt::entry;
+use crate::rt::ExceptionFrame;
+
+use crate::sh::hio;
+use core::fmt::Write as _;
+
+/// MAX17048 fuel gauge
+struct FuelGauge<I2C> {
+    i2c: I2C,
+}
+
+impl<I2C: WriteRead> FuelGauge<I2C> {
+    const ADDRESS: u8 = 0x36;
+
+    /// State of charge, in percent
+    fn state_of_charge(&mut self) -> Result<u8, I2C::Error> {
+        let mut soc = [0; 2];
+        self.i2c.write_read(Self::ADDRESS, &[0x04], &mut soc)?;
+
+        Ok(soc[0])
+    }
+}
+
+/// 24C02 EEPROM
+struct Eeprom<I2C> {
+    i2c: I2C,
+}
+
+impl<I2C: Write> Eeprom<I2C> {
+    const ADDRESS: u8 = 0x50;
+
+    /// Writes a byte at `address`, the write cycle then takes up to 5 ms
+    fn write_byte(&mut self, address: u8, byte: u8) -> Result<(), I2C::Error> {
+        self.i2c.write(Self::ADDRESS, &[address, byte])
+    }
+}
+
+#[entry]
+fn main() -> ! {
+    let mut hstdout = hio::hstdout().unwrap();
+
+    let dp = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+
+    let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+
+    let mut scl = gpioa
+        .pa9
+        .into_open_drain_output(&mut gpioa.moder, &mut gpioa.otyper);
+    scl.internal_pull_up(&mut gpioa.pupdr, true);
+    let scl = scl.into_af4(&mut gpioa.moder, &mut gpioa.afrh);
+
+    let mut sda = gpioa
+        .pa10
+        .into_open_drain_output(&mut gpioa.moder, &mut gpioa.otyper);
+    sda.internal_pull_up(&mut gpioa.pupdr, true);
+    let sda = sda.into_af4(&mut gpioa.moder, &mut gpioa.afrh);
+
+    let i2c = I2c::i2c1(dp.I2C1, (scl, sda), 100.khz(), clocks, &mut rcc.apb1r1);
+
+    // Both drivers are used from `main`, the `RefCell` variant is enough
+    let bus = SharedBus::new(i2c);
+    let mut gauge = FuelGauge { i2c: bus.proxy() };
+    let mut eeprom = Eeprom { i2c: bus.proxy() };
+
+    // Log the state of charge to the first byte of the EEPROM
+    let soc = gauge.state_of_charge().unwrap();
+    eeprom.write_byte(0x00, soc).unwrap();
+    writeln!(hstdout, "Batt SoC: {}%", soc).ok();
+
+    loop {
+        continue;
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
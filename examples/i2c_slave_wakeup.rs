@@ -0,0 +1,98 @@
+//! I2C slave sleeping in Stop 1 until a master addresses it, like a sensor hub
+//!
+//! The master writes the index of a register, then reads it back, e.g. with a repeated START.
+//! The interrupts stay disabled, so the transfer is served in `main` once the MCU is awake.
+#![no_std]
+#![no_main]
+
+extern crate cortex_m;
+#[macro_use]
+extern crate cortex_m_rt as rt;
+extern crate panic_semihosting;
+extern crate stm32l4xx_hal as hal;
+
+use crate::hal::i2c::{Direction, I2cSlave};
+use crate::hal::prelude::*;
+use crate::hal::pwr::StopMode;
+use crate::rt::ExceptionFrame;
+use cortex_m::interrupt;
+use cortex_m::peripheral::NVIC;
+use hal::pac;
+
+/// Address of the slave
+const ADDRESS: u8 = 0x42;
+
+#[entry]
+fn main() -> ! {
+    let mut cp = cortex_m::Peripherals::take().unwrap();
+    let mut dp = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+
+    let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+
+    let mut scl = gpioa
+        .pa9
+        .into_open_drain_output(&mut gpioa.moder, &mut gpioa.otyper);
+    scl.internal_pull_up(&mut gpioa.pupdr, true);
+    let scl = scl.into_af4(&mut gpioa.moder, &mut gpioa.afrh);
+
+    let mut sda = gpioa
+        .pa10
+        .into_open_drain_output(&mut gpioa.moder, &mut gpioa.otyper);
+    sda.internal_pull_up(&mut gpioa.pupdr, true);
+    let sda = sda.into_af4(&mut gpioa.moder, &mut gpioa.afrh);
+
+    let mut slave = I2cSlave::i2c1(
+        dp.I2C1,
+        (scl, sda),
+        ADDRESS,
+        &mut rcc.apb1r1,
+        &mut rcc.ccipr,
+    );
+
+    // The interrupt has to be unmasked in the NVIC to wake the MCU up, but stays disabled in
+    // the core so no handler runs
+    interrupt::disable();
+    unsafe {
+        NVIC::unmask(pac::Interrupt::I2C1_EV);
+    }
+
+    let registers = [0x12, 0x34, 0x56, 0x78];
+    let mut index = 0;
+
+    loop {
+        pwr.stop_until(
+            StopMode::Stop1,
+            &mut cp.SCB,
+            &mut dp.EXTI,
+            &mut [&mut slave],
+        );
+
+        // A master may chain several transfers with repeated STARTs
+        while let Some(direction) = slave.is_addressed() {
+            match direction {
+                Direction::Write => {
+                    let mut buffer = [0];
+                    if let Ok(1) = slave.read(&mut buffer) {
+                        index = usize::from(buffer[0]) % registers.len();
+                    }
+                }
+                Direction::Read => {
+                    slave.write(&registers[index..]).ok();
+                }
+            }
+        }
+
+        NVIC::unpend(pac::Interrupt::I2C1_EV);
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
@@ -0,0 +1,84 @@
+//! Wakes up from Stop 2 mode once per second with LPTIM1, clocked by the LSI, toggling an LED
+#![no_std]
+#![no_main]
+
+extern crate panic_semihosting;
+
+use core::cell::RefCell;
+use core::ops::DerefMut;
+use cortex_m::{
+    interrupt::{free, Mutex},
+    peripheral::NVIC,
+};
+use cortex_m_rt::entry;
+use stm32l4xx_hal::{
+    interrupt,
+    lptimer::{ClockSource, CountMode, Event, LowPowerTimer},
+    prelude::*,
+    stm32,
+};
+
+static LPTIM: Mutex<RefCell<Option<LowPowerTimer<stm32::LPTIM1>>>> = Mutex::new(RefCell::new(None));
+
+#[entry]
+fn main() -> ! {
+    let mut cp = cortex_m::Peripherals::take().unwrap();
+    let mut dp = stm32::Peripherals::take().unwrap();
+
+    let mut rcc = dp.RCC.constrain();
+    let mut flash = dp.FLASH.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc.cfgr.lsi(true).freeze(&mut flash.acr, &mut pwr);
+
+    let mut gpiob = dp.GPIOB.split(&mut rcc.ahb2);
+    let mut led = gpiob
+        .pb3
+        .into_push_pull_output(&mut gpiob.moder, &mut gpiob.otyper);
+
+    let mut lptim = LowPowerTimer::lptim1(
+        dp.LPTIM1,
+        ClockSource::Lsi,
+        CountMode::Internal,
+        &mut rcc.apb1r1,
+        &mut rcc.ccipr,
+        clocks,
+    );
+    lptim.listen(&mut dp.EXTI, Event::AutoReloadMatch);
+    lptim.start(1.hz());
+
+    free(|cs| {
+        LPTIM.borrow(cs).replace(Some(lptim));
+    });
+
+    unsafe {
+        NVIC::unmask(stm32::Interrupt::LPTIM1);
+    }
+
+    // Enter Stop 2 on WFI
+    // NOTE(unsafe) 0b010 is a valid value for this field, and there is no PWR abstraction for it
+    unsafe { (*stm32::PWR::ptr()).cr1.modify(|_, w| w.lpms().bits(0b010)) };
+    cp.SCB.set_sleepdeep();
+
+    let mut on = false;
+    loop {
+        cortex_m::asm::wfi();
+
+        // Woken up by LPTIM1, the interrupt handler already ran
+        on = !on;
+        if on {
+            led.set_high().ok();
+        } else {
+            led.set_low().ok();
+        }
+    }
+}
+
+#[interrupt]
+fn LPTIM1() {
+    free(|cs| {
+        if let Some(ref mut lptim) = LPTIM.borrow(cs).borrow_mut().deref_mut() {
+            lptim.clear_pending(Event::AutoReloadMatch);
+        }
+    });
+}
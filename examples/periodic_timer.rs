@@ -0,0 +1,80 @@
+//! Blinks an LED from three different timing sources, with one function generic over them
+//!
+//! A basic timer, a low power timer and the RTC wakeup timer take turns, each for five periods.
+#![deny(unsafe_code)]
+#![deny(warnings)]
+#![no_main]
+#![no_std]
+
+extern crate cortex_m;
+#[macro_use(entry, exception)]
+extern crate cortex_m_rt as rt;
+#[macro_use(block)]
+extern crate nb;
+extern crate panic_semihosting;
+
+extern crate stm32l4xx_hal as hal;
+
+use crate::hal::lptimer::{ClockSource, CountMode, LowPowerTimer};
+use crate::hal::prelude::*;
+use crate::hal::rtc::{Rtc, RtcClockSource, RtcConfig};
+use crate::hal::timer::{PeriodicTimer, Timer};
+use crate::rt::ExceptionFrame;
+
+/// Toggles `led` on every expiry of `timer`, `toggles` times, whatever the timing source
+fn toggle_every<T, L>(timer: &mut T, period: T::Time, led: &mut L, toggles: u32)
+where
+    T: PeriodicTimer,
+    L: ToggleableOutputPin,
+{
+    timer.start(period);
+    for _ in 0..toggles {
+        block!(timer.wait()).unwrap();
+        led.toggle().ok();
+    }
+    timer.cancel();
+}
+
+#[entry]
+fn main() -> ! {
+    let p = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = p.FLASH.constrain();
+    let mut rcc = p.RCC.constrain();
+    let mut pwr = p.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc.cfgr.lsi(true).freeze(&mut flash.acr, &mut pwr);
+
+    let mut gpiob = p.GPIOB.split(&mut rcc.ahb2);
+    let mut led = gpiob
+        .pb3
+        .into_push_pull_output(&mut gpiob.moder, &mut gpiob.otyper);
+
+    let mut tim7 = Timer::tim7(p.TIM7, 4.hz(), clocks, &mut rcc.apb1r1);
+    let mut lptim1 = LowPowerTimer::lptim1(
+        p.LPTIM1,
+        ClockSource::Lsi,
+        CountMode::Internal,
+        &mut rcc.apb1r1,
+        &mut rcc.ccipr,
+        clocks,
+    );
+    let mut rtc = Rtc::rtc(
+        p.RTC,
+        &mut rcc.apb1r1,
+        &mut rcc.bdcr,
+        &mut pwr.cr1,
+        RtcConfig::default().clock_config(RtcClockSource::LSI),
+    );
+
+    loop {
+        toggle_every(&mut tim7, 4.hz(), &mut led, 5);
+        toggle_every(&mut lptim1, 2.hz(), &mut led, 5);
+        toggle_every(&mut rtc.wakeup_timer(), 1, &mut led, 5);
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
@@ -0,0 +1,62 @@
+//! Defines the pinout of a board in one place with `pinout!`, and lights its LED while the button
+//! is held
+#![deny(unsafe_code)]
+// #![deny(warnings)]
+#![no_main]
+#![no_std]
+
+extern crate cortex_m;
+#[macro_use]
+extern crate cortex_m_rt as rt;
+extern crate panic_semihosting;
+extern crate stm32l4xx_hal as hal;
+
+use crate::hal::delay::Delay;
+use crate::hal::gpio::{
+    gpioa::{self, PA5},
+    gpioc::{self, PC13},
+    Input, Output, PullUp, PushPull,
+};
+use crate::hal::prelude::*;
+use crate::rt::ExceptionFrame;
+
+hal::pinout! {
+    /// Pinout of a Nucleo board
+    struct Board(gpioa: gpioa::Parts, gpioc: gpioc::Parts) {
+        /// User LED (LD2)
+        led: PA5<Output<PushPull>> =
+            gpioa.pa5.into_push_pull_output(&mut gpioa.moder, &mut gpioa.otyper),
+        /// User button (B1), pressed when low
+        button: PC13<Input<PullUp>> =
+            gpioc.pc13.into_pull_up_input(&mut gpioc.moder, &mut gpioc.pupdr),
+    }
+}
+
+#[entry]
+fn main() -> ! {
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let dp = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc.cfgr.hclk(8.mhz()).freeze(&mut flash.acr, &mut pwr);
+
+    let mut board = Board::configure(dp.GPIOA.split(&mut rcc.ahb2), dp.GPIOC.split(&mut rcc.ahb2));
+
+    let mut timer = Delay::new(cp.SYST, clocks);
+    loop {
+        if board.button.is_low().unwrap() {
+            board.led.set_high().unwrap();
+        } else {
+            board.led.set_low().unwrap();
+        }
+        timer.delay_ms(200_u32);
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
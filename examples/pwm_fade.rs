@@ -0,0 +1,60 @@
+//! Fades an LED on PA0 (TIM2 channel 1) in and out, setting the duty cycle in percent
+
+#![deny(unsafe_code)]
+#![deny(warnings)]
+#![no_main]
+#![no_std]
+
+extern crate panic_halt;
+
+use cortex_m_rt::entry;
+use stm32l4xx_hal::{delay, prelude::*, stm32};
+
+#[entry]
+fn main() -> ! {
+    let c = cortex_m::Peripherals::take().unwrap();
+    let p = stm32::Peripherals::take().unwrap();
+
+    let mut flash = p.FLASH.constrain();
+    let mut rcc = p.RCC.constrain();
+    let mut pwr = p.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+
+    let mut gpioa = p.GPIOA.split(&mut rcc.ahb2);
+
+    // TIM2
+    let led = gpioa
+        .pa0
+        .into_push_pull_output(&mut gpioa.moder, &mut gpioa.otyper)
+        .into_af1(&mut gpioa.moder, &mut gpioa.afrl);
+
+    let mut pwm = p.TIM2.pwm(led, 1.khz(), clocks, &mut rcc.apb1r1);
+
+    // The ends of the range are exact: fully off and fully on, without a one tick pulse
+    pwm.set_duty_percent(0.0);
+    assert_eq!(pwm.get_duty(), 0);
+    pwm.set_duty_percent(100.0);
+    assert_eq!(pwm.get_duty(), pwm.get_max_duty() + 1);
+    assert_eq!(pwm.get_duty_percent(), 100.0);
+
+    // Out of range values are clamped
+    pwm.set_duty_percent(-5.0);
+    assert_eq!(pwm.get_duty(), 0);
+    pwm.set_duty_fraction(1.5);
+    assert_eq!(pwm.get_duty_percent(), 100.0);
+
+    pwm.enable();
+
+    let mut timer = delay::Delay::new(c.SYST, clocks);
+
+    // NB: the brightness of an LED is not linear in the duty cycle, the steps are squared so
+    //     the fade looks even.
+    loop {
+        for step in (0..=50).chain((0..50).rev()) {
+            let level = step as f32 / 50.0;
+            pwm.set_duty_percent(level * level * 100.0);
+            timer.delay_ms(20_u32);
+        }
+    }
+}
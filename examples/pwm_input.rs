@@ -0,0 +1,66 @@
+//! Measures a 2 kHz, 25 % duty cycle PWM signal
+//!
+//! TIM1 generates the signal on PA8, wire it to PA0, where TIM2 measures it.
+
+#![deny(unsafe_code)]
+#![deny(warnings)]
+#![no_main]
+#![no_std]
+
+extern crate cortex_m;
+#[macro_use(entry, exception)]
+extern crate cortex_m_rt as rt;
+extern crate cortex_m_semihosting as sh;
+extern crate panic_semihosting;
+extern crate stm32l4xx_hal as hal;
+
+use crate::hal::delay::Delay;
+use crate::hal::prelude::*;
+use crate::hal::pwm_input::PwmInput;
+use crate::rt::ExceptionFrame;
+use crate::sh::hio;
+use core::fmt::Write;
+
+#[entry]
+fn main() -> ! {
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let p = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = p.FLASH.constrain();
+    let mut rcc = p.RCC.constrain();
+    let mut pwr = p.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc.cfgr.sysclk(80.mhz()).freeze(&mut flash.acr, &mut pwr);
+
+    let mut gpioa = p.GPIOA.split(&mut rcc.ahb2);
+
+    let output = gpioa
+        .pa8
+        .into_push_pull_output(&mut gpioa.moder, &mut gpioa.otyper)
+        .into_af1(&mut gpioa.moder, &mut gpioa.afrh);
+    let mut pwm = p.TIM1.pwm(output, 2.khz(), clocks, &mut rcc.apb2);
+    let max = pwm.get_max_duty();
+    pwm.set_duty(max / 4);
+    pwm.enable();
+
+    let input = gpioa.pa0.into_af1(&mut gpioa.moder, &mut gpioa.afrl);
+    // 100 Hz and above fit in the 32 bit counter of TIM2 without prescaler, which gives a 1/40000
+    // resolution at 2 kHz
+    let mut pwm_input = PwmInput::tim2(p.TIM2, input, 100.hz(), clocks, &mut rcc.apb1r1);
+
+    let mut delay = Delay::new(cp.SYST, clocks);
+    let mut hstdout = hio::hstdout().unwrap();
+
+    loop {
+        if let Some((frequency, duty)) = pwm_input.read() {
+            writeln!(hstdout, "{} Hz, {} %", frequency.0, duty).unwrap();
+        }
+
+        delay.delay_ms(500_u32);
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
@@ -0,0 +1,78 @@
+//! Fades an RGB LED on TIM1, changing the duty cycles of the three channels in the same period
+
+#![deny(unsafe_code)]
+#![deny(warnings)]
+#![no_main]
+#![no_std]
+
+extern crate panic_halt;
+
+use cortex_m_rt::entry;
+use stm32l4xx_hal::{delay, prelude::*, stm32};
+
+#[entry]
+fn main() -> ! {
+    let c = cortex_m::Peripherals::take().unwrap();
+    let p = stm32::Peripherals::take().unwrap();
+
+    let mut flash = p.FLASH.constrain();
+    let mut rcc = p.RCC.constrain();
+    let mut pwr = p.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+
+    let mut gpioa = p.GPIOA.split(&mut rcc.ahb2);
+
+    // TIM1
+    let red = gpioa
+        .pa8
+        .into_push_pull_output(&mut gpioa.moder, &mut gpioa.otyper)
+        .into_af1(&mut gpioa.moder, &mut gpioa.afrh);
+    let green = gpioa
+        .pa9
+        .into_push_pull_output(&mut gpioa.moder, &mut gpioa.otyper)
+        .into_af1(&mut gpioa.moder, &mut gpioa.afrh);
+    let blue = gpioa
+        .pa10
+        .into_push_pull_output(&mut gpioa.moder, &mut gpioa.otyper)
+        .into_af1(&mut gpioa.moder, &mut gpioa.afrh);
+
+    let (mut red, mut green, mut blue) =
+        p.TIM1
+            .pwm((red, green, blue), 1.khz(), clocks, &mut rcc.apb2);
+
+    let max = red.get_max_duty();
+
+    red.enable();
+    green.enable();
+    blue.enable();
+
+    let mut timer = delay::Delay::new(c.SYST, clocks);
+    let mut step: u16 = 0;
+
+    loop {
+        let (r, g, b) = hue_to_rgb(step, max);
+
+        // All three duty cycles are latched by the same update event
+        red.pause_update();
+        red.set_duty(r);
+        green.set_duty(g);
+        blue.set_duty(b);
+        red.resume_update();
+
+        step = (step + 1) % 768;
+        timer.delay_ms(10_u32);
+    }
+}
+
+/// Maps a position on the color wheel (0 to 767) to duty cycles
+fn hue_to_rgb(step: u16, max: u16) -> (u16, u16, u16) {
+    let scale = |v: u16| (u32::from(max) * u32::from(v) / 255) as u16;
+    let v = step % 256;
+
+    match step / 256 {
+        0 => (scale(255 - v), scale(v), 0),
+        1 => (0, scale(255 - v), scale(v)),
+        _ => (scale(v), 0, scale(255 - v)),
+    }
+}
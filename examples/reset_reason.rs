@@ -0,0 +1,40 @@
+//! Logs the cause of the last reset at startup
+
+#![no_std]
+#![no_main]
+
+extern crate cortex_m;
+#[macro_use]
+extern crate cortex_m_rt as rt;
+extern crate cortex_m_semihosting as sh;
+extern crate panic_semihosting;
+extern crate stm32l4xx_hal as hal;
+
+use crate::hal::prelude::*;
+use crate::rt::ExceptionFrame;
+
+use crate::sh::hio;
+use core::fmt::Write;
+
+#[entry]
+fn main() -> ! {
+    let mut hstdout = hio::hstdout().unwrap();
+
+    let dp = hal::stm32::Peripherals::take().unwrap();
+    let mut rcc = dp.RCC.constrain();
+
+    let reason = rcc.csr.reset_reason();
+    // Clear the flags, so the next reset doesn't add up with this one
+    rcc.csr.clear_reset_flags();
+
+    writeln!(hstdout, "Reset by {:?}", reason).unwrap();
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
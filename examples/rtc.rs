@@ -58,6 +58,9 @@ fn main() -> ! {
 
     rtc.set_date_time(date, time);
 
+    // The shadow registers are synchronized before the read, so it returns the new date
+    assert_eq!(rtc.get_date(), date);
+
     timer.delay_ms(1000_u32);
     timer.delay_ms(1000_u32);
     timer.delay_ms(1000_u32);
@@ -66,6 +69,19 @@ fn main() -> ! {
 
     writeln!(hstdout, "Time: {:?}", rtc_time).unwrap();
     writeln!(hstdout, "Date: {:?}", rtc_date).unwrap();
+
+    // The second counter keeps counting across midnight
+    let time = Time::new(23.hours(), 59.minutes(), 58.seconds(), 0.micros(), false);
+    rtc.set_date_time(date, time);
+    let start = rtc.second_counter();
+
+    timer.delay_ms(1000_u32);
+    timer.delay_ms(1000_u32);
+    timer.delay_ms(1000_u32);
+
+    let elapsed = rtc.elapsed_secs_since(start);
+    assert!((2..=4).contains(&elapsed));
+    writeln!(hstdout, "Elapsed across midnight: {} s", elapsed).unwrap();
     writeln!(hstdout, "Good bye!").unwrap();
     loop {
         continue;
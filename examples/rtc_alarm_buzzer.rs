@@ -0,0 +1,80 @@
+//! Sounds an active buzzer on the RTC_ALARM output when alarm A fires
+//!
+//! The buzzer is driven by PC13, push-pull and active high. The RTC holds the output asserted
+//! until the alarm flag is cleared, without any software involved, so it also sounds while the
+//! MCU is in a low power mode.
+
+#![deny(unsafe_code)]
+#![deny(warnings)]
+#![no_std]
+#![no_main]
+
+extern crate cortex_m;
+#[macro_use]
+extern crate cortex_m_rt as rt;
+extern crate panic_semihosting;
+extern crate stm32l4xx_hal as hal;
+
+use crate::hal::datetime::{Date, Time};
+use crate::hal::delay::Delay;
+use crate::hal::prelude::*;
+use crate::hal::rcc::{ClockSecuritySystem, CrystalBypass};
+use crate::hal::rtc::{Alarm, AlarmOutput, Event, OutputConfig, Rtc, RtcClockSource, RtcConfig};
+use crate::rt::ExceptionFrame;
+
+#[entry]
+fn main() -> ! {
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let dp = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc
+        .cfgr
+        .lse(CrystalBypass::Disable, ClockSecuritySystem::Disable)
+        .freeze(&mut flash.acr, &mut pwr);
+
+    let mut rtc = Rtc::rtc(
+        dp.RTC,
+        &mut rcc.apb1r1,
+        &mut rcc.bdcr,
+        &mut pwr.cr1,
+        RtcConfig::default().clock_config(RtcClockSource::LSE),
+    );
+
+    let time = Time::new(6.hours(), 59.minutes(), 55.seconds(), 0.micros(), false);
+    let date = Date::new(1.day(), 24.date(), 4.month(), 2018.year());
+    rtc.set_date_time(date, time);
+
+    // Wake up at 07:00
+    let alarm = Time::new(7.hours(), 0.minutes(), 0.seconds(), 0.micros(), false);
+    rtc.set_alarm(Alarm::AlarmA, date, alarm);
+
+    rtc.set_output(
+        OutputConfig::default()
+            .output(AlarmOutput::AlarmA)
+            .push_pull(true)
+            .active_high(true),
+    )
+    .unwrap();
+
+    let mut delay = Delay::new(cp.SYST, clocks);
+
+    // Let the buzzer sound for 3 seconds, then silence it by clearing the alarm flag
+    while !rtc.is_pending(Event::AlarmA) {
+        delay.delay_ms(100_u32);
+    }
+    delay.delay_ms(3000_u32);
+    rtc.clear_pending(Event::AlarmA);
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
@@ -0,0 +1,111 @@
+//! Keeps a boot counter and a small config in the RTC backup registers across resets
+//!
+//! Reset the board to see the counter go up, the registers keep their value as long as the
+//! backup domain is powered.
+
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+extern crate cortex_m;
+#[macro_use]
+extern crate cortex_m_rt as rt;
+extern crate cortex_m_semihosting as sh;
+extern crate panic_semihosting;
+extern crate stm32l4xx_hal as hal;
+
+use crate::hal::prelude::*;
+use crate::hal::rcc::{ClockSecuritySystem, CrystalBypass};
+use crate::hal::rtc::{Error, Rtc, RtcClockSource, RtcConfig, BACKUP_REGISTERS};
+use crate::rt::ExceptionFrame;
+use crate::sh::hio;
+use core::fmt::Write;
+
+/// Marks the registers as written by this example, rather than after a backup domain reset
+const MAGIC: u32 = 0x5354_4c34;
+
+/// Stored in 4 registers, from `BKP0R`
+#[derive(Debug, PartialEq)]
+struct Config {
+    magic: u32,
+    boots: u32,
+    baudrate: u32,
+    flags: u32,
+}
+
+impl Config {
+    fn to_words(&self) -> [u32; 4] {
+        [self.magic, self.boots, self.baudrate, self.flags]
+    }
+
+    fn from_words(words: [u32; 4]) -> Self {
+        Config {
+            magic: words[0],
+            boots: words[1],
+            baudrate: words[2],
+            flags: words[3],
+        }
+    }
+}
+
+#[entry]
+fn main() -> ! {
+    let dp = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    rcc.cfgr
+        .lse(CrystalBypass::Disable, ClockSecuritySystem::Disable)
+        .freeze(&mut flash.acr, &mut pwr);
+
+    let mut rtc = Rtc::rtc(
+        dp.RTC,
+        &mut rcc.apb1r1,
+        &mut rcc.bdcr,
+        &mut pwr.cr1,
+        RtcConfig::default().clock_config(RtcClockSource::LSE),
+    );
+
+    let mut words = [0; 4];
+    rtc.read_backup(0, &mut words).unwrap();
+    let mut config = Config::from_words(words);
+    if config.magic != MAGIC {
+        config = Config {
+            magic: MAGIC,
+            boots: 0,
+            baudrate: 115_200,
+            flags: 0b101,
+        };
+    }
+    config.boots += 1;
+
+    // Round trip of the whole struct
+    rtc.write_backup(0, &config.to_words()).unwrap();
+    rtc.read_backup(0, &mut words).unwrap();
+    assert_eq!(Config::from_words(words), config);
+
+    // The last registers are in range, one more isn't
+    assert!(rtc.write_backup(BACKUP_REGISTERS - 4, &[0; 4]).is_ok());
+    assert!(matches!(
+        rtc.write_backup(BACKUP_REGISTERS - 3, &[0; 4]),
+        Err(Error::BackupOutOfRange)
+    ));
+    assert!(matches!(
+        rtc.read_backup(usize::MAX, &mut words),
+        Err(Error::BackupOutOfRange)
+    ));
+
+    let mut hstdout = hio::hstdout().unwrap();
+    writeln!(hstdout, "Boot {}: {:?}", config.boots, config).unwrap();
+
+    loop {
+        continue;
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
@@ -0,0 +1,83 @@
+//! Saves the whole state of the application in the RTC backup registers, and restores it
+//! after a reset
+//!
+//! The state is restored unless the reset reason tells the backup domain may have lost power,
+//! and only if its marker is found. Reset the board to see the state carried over.
+
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+extern crate cortex_m;
+#[macro_use]
+extern crate cortex_m_rt as rt;
+extern crate cortex_m_semihosting as sh;
+extern crate panic_semihosting;
+extern crate stm32l4xx_hal as hal;
+
+use crate::hal::prelude::*;
+use crate::hal::rcc::{ClockSecuritySystem, CrystalBypass, ResetReason};
+use crate::hal::rtc::{Rtc, RtcClockSource, RtcConfig, BACKUP_REGISTERS};
+use crate::rt::ExceptionFrame;
+use crate::sh::hio;
+use core::fmt::Write;
+
+/// Marks the state as written by this example, in the first register
+const MAGIC: u32 = 0x5354_4c34;
+
+#[entry]
+fn main() -> ! {
+    let mut hstdout = hio::hstdout().unwrap();
+
+    let dp = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    let reason = rcc.csr.reset_reason();
+    rcc.csr.clear_reset_flags();
+
+    rcc.cfgr
+        .lse(CrystalBypass::Disable, ClockSecuritySystem::Disable)
+        .freeze(&mut flash.acr, &mut pwr);
+
+    let mut rtc = Rtc::rtc(
+        dp.RTC,
+        &mut rcc.apb1r1,
+        &mut rcc.bdcr,
+        &mut pwr.cr1,
+        RtcConfig::default().clock_config(RtcClockSource::LSE),
+    );
+
+    // The state is a marker, a boot count and a log of the reset reasons
+    let saved = rtc.read_all_backup();
+    let mut state = if reason != ResetReason::PowerOn && saved[0] == MAGIC {
+        writeln!(hstdout, "Reset by {:?}, state restored", reason).unwrap();
+        saved
+    } else {
+        writeln!(hstdout, "Reset by {:?}, fresh state", reason).unwrap();
+        let mut fresh = [0; BACKUP_REGISTERS];
+        fresh[0] = MAGIC;
+        fresh
+    };
+
+    let boots = state[1] as usize;
+    state[2 + boots % (BACKUP_REGISTERS - 2)] = reason as u32;
+    state[1] += 1;
+
+    // All 32 words make the round trip
+    rtc.write_all_backup(&state);
+    assert_eq!(rtc.read_all_backup(), state);
+
+    writeln!(hstdout, "Boot {}", state[1]).unwrap();
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
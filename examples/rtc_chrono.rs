@@ -0,0 +1,101 @@
+//! Sets the RTC from `chrono` types, and checks the conversions at the limits of the calendar
+
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+extern crate cortex_m;
+#[macro_use]
+extern crate cortex_m_rt as rt;
+extern crate cortex_m_semihosting as sh;
+extern crate panic_semihosting;
+extern crate stm32l4xx_hal as hal;
+
+use core::convert::TryFrom;
+
+use chrono::{NaiveDate, NaiveTime};
+
+use crate::hal::datetime::{Date, OutOfRange, Time};
+use crate::hal::prelude::*;
+use crate::hal::rcc::{ClockSecuritySystem, CrystalBypass};
+use crate::hal::rtc::{Rtc, RtcClockSource, RtcConfig};
+use crate::rt::ExceptionFrame;
+use crate::sh::hio;
+use core::fmt::Write;
+
+#[entry]
+fn main() -> ! {
+    check_boundaries();
+
+    let dp = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    rcc.cfgr
+        .lse(CrystalBypass::Disable, ClockSecuritySystem::Disable)
+        .freeze(&mut flash.acr, &mut pwr);
+
+    let mut rtc = Rtc::rtc(
+        dp.RTC,
+        &mut rcc.apb1r1,
+        &mut rcc.bdcr,
+        &mut pwr.cr1,
+        RtcConfig::default().clock_config(RtcClockSource::LSE),
+    );
+
+    let date = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+    let time = NaiveTime::from_hms_opt(12, 30, 0).unwrap();
+    rtc.set_date_time(Date::try_from(date).unwrap(), Time::try_from(time).unwrap());
+
+    let (date, time) = rtc.get_date_time();
+    let date = NaiveDate::try_from(date).unwrap();
+    let time = NaiveTime::try_from(time).unwrap();
+
+    let mut hstdout = hio::hstdout().unwrap();
+    writeln!(hstdout, "{} {}", date, time).unwrap();
+
+    loop {
+        continue;
+    }
+}
+
+/// Converts the first and last days the RTC can hold, and the closest ones out of its range
+fn check_boundaries() {
+    let first = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+    let last = NaiveDate::from_ymd_opt(2099, 12, 31).unwrap();
+    // Saturday and Thursday, Monday being day 1
+    assert_eq!(
+        Date::try_from(first),
+        Ok(Date::new(6.day(), 1.date(), 1.month(), 2000.year()))
+    );
+    assert_eq!(
+        Date::try_from(last),
+        Ok(Date::new(4.day(), 31.date(), 12.month(), 2099.year()))
+    );
+    for date in [first, last, NaiveDate::from_ymd_opt(2024, 2, 29).unwrap()] {
+        assert_eq!(NaiveDate::try_from(Date::try_from(date).unwrap()), Ok(date));
+    }
+
+    assert_eq!(Date::try_from(first.pred_opt().unwrap()), Err(OutOfRange));
+    assert_eq!(Date::try_from(last.succ_opt().unwrap()), Err(OutOfRange));
+    let february_30 = Date::new(5.day(), 30.date(), 2.month(), 2024.year());
+    assert_eq!(NaiveDate::try_from(february_30), Err(OutOfRange));
+
+    let midnight = NaiveTime::from_hms_opt(0, 0, 0).unwrap();
+    let end_of_day = NaiveTime::from_hms_micro_opt(23, 59, 59, 999_999).unwrap();
+    for time in [midnight, end_of_day] {
+        assert_eq!(NaiveTime::try_from(Time::try_from(time).unwrap()), Ok(time));
+    }
+
+    let leap_second = NaiveTime::from_hms_milli_opt(23, 59, 59, 1_500).unwrap();
+    assert_eq!(Time::try_from(leap_second), Err(OutOfRange));
+    let hour_24 = Time::new(24.hours(), 0.minutes(), 0.seconds(), 0.micros(), false);
+    assert_eq!(NaiveTime::try_from(hour_24), Err(OutOfRange));
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
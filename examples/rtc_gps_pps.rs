@@ -0,0 +1,79 @@
+//! Sets the calendar on the PPS (pulse per second) edge of a GPS receiver
+//!
+//! The PPS output of the receiver is on PA0. The NMEA sentence following a pulse carries the
+//! time of that pulse, so the calendar is set to the next second and held paused until the
+//! next pulse starts it. Here the time is a constant instead of parsed from the receiver.
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+extern crate cortex_m;
+#[macro_use]
+extern crate cortex_m_rt as rt;
+extern crate cortex_m_semihosting as sh;
+extern crate panic_semihosting;
+extern crate stm32l4xx_hal as hal;
+
+use crate::hal::datetime::{parse_iso_date_time, RtcInstant};
+use crate::hal::prelude::*;
+use crate::hal::rcc::{ClockSecuritySystem, CrystalBypass};
+use crate::hal::rtc::{Rtc, RtcClockSource, RtcConfig};
+use crate::rt::ExceptionFrame;
+use crate::sh::hio;
+use core::fmt::Write;
+use core::time::Duration;
+
+/// Time of the last pulse, from the `$GPRMC` sentence of the receiver
+const LAST_PULSE: &str = "2024-06-01T11:59:59";
+
+#[entry]
+fn main() -> ! {
+    let dp = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    rcc.cfgr
+        .lse(CrystalBypass::Disable, ClockSecuritySystem::Disable)
+        .freeze(&mut flash.acr, &mut pwr);
+
+    let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+    let pps = gpioa
+        .pa0
+        .into_pull_down_input(&mut gpioa.moder, &mut gpioa.pupdr);
+
+    let mut rtc = Rtc::rtc(
+        dp.RTC,
+        &mut rcc.apb1r1,
+        &mut rcc.bdcr,
+        &mut pwr.cr1,
+        RtcConfig::default().clock_config(RtcClockSource::LSE),
+    );
+
+    let (date, time) = parse_iso_date_time(LAST_PULSE).unwrap();
+    let (date, time) =
+        (RtcInstant::from_date_time(date, time) + Duration::from_secs(1)).to_date_time();
+
+    // Stopped at the start of the next second until its pulse
+    rtc.pause();
+    rtc.set_date_time(date, time);
+    assert!(rtc.is_paused());
+
+    while pps.is_high().unwrap() {}
+    while pps.is_low().unwrap() {}
+    rtc.resume();
+
+    let mut hstdout = hio::hstdout().unwrap();
+    let (date, time) = rtc.get_date_time();
+    writeln!(hstdout, "Set on the pulse: {:?} {:?}", date, time).unwrap();
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
@@ -0,0 +1,111 @@
+//! Keeps the calendar in UTC and displays the local time, with a fixed UTC offset
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+extern crate cortex_m;
+#[macro_use]
+extern crate cortex_m_rt as rt;
+extern crate cortex_m_semihosting as sh;
+extern crate panic_semihosting;
+extern crate stm32l4xx_hal as hal;
+
+use crate::hal::datetime::{parse_iso_date_time, Date, RtcInstant, Time};
+use crate::hal::delay::Delay;
+use crate::hal::prelude::*;
+use crate::hal::rcc::{ClockSecuritySystem, CrystalBypass};
+use crate::hal::rtc::{Rtc, RtcClockSource, RtcConfig};
+use crate::rt::ExceptionFrame;
+use crate::sh::hio;
+use core::fmt::Write;
+
+/// Central European Summer Time, UTC+02:00
+const OFFSET: i16 = 120;
+
+/// `utc` formatted as "YYYY-MM-DDTHH:MM:SS" shifted by `minutes`
+fn shifted(utc: &str, minutes: i16) -> (Date, Time) {
+    let (date, time) = parse_iso_date_time(utc).unwrap();
+
+    RtcInstant::from_date_time(date, time)
+        .checked_offset(minutes)
+        .unwrap()
+        .to_date_time()
+}
+
+#[entry]
+fn main() -> ! {
+    // Offsets crossing the end of a year, a leap day and a month, weekdays included
+    assert_eq!(
+        shifted("2023-12-31T23:30:00", 120),
+        parse_iso_date_time("2024-01-01T01:30:00").unwrap()
+    );
+    assert_eq!(
+        shifted("2024-01-01T05:00:00", -600),
+        parse_iso_date_time("2023-12-31T19:00:00").unwrap()
+    );
+    assert_eq!(
+        shifted("2024-03-01T00:15:00", -30),
+        parse_iso_date_time("2024-02-29T23:45:00").unwrap()
+    );
+    assert_eq!(
+        shifted("2024-04-30T20:00:00", 330),
+        parse_iso_date_time("2024-05-01T01:30:00").unwrap()
+    );
+    assert_eq!(shifted("2024-02-29T23:45:00", -30).0.day, 4);
+    // Nothing before 1970, which the RTC can't hold anyway
+    let date = Date::new(4.day(), 1.date(), 1.month(), 1970.year());
+    let time = Time::new(0.hours(), 30.minutes(), 0.seconds(), 0.micros(), false);
+    assert_eq!(
+        RtcInstant::from_date_time(date, time).checked_offset(-60),
+        None
+    );
+
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let dp = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc
+        .cfgr
+        .lse(CrystalBypass::Disable, ClockSecuritySystem::Disable)
+        .freeze(&mut flash.acr, &mut pwr);
+
+    let mut delay = Delay::new(cp.SYST, clocks);
+
+    let mut rtc = Rtc::rtc(
+        dp.RTC,
+        &mut rcc.apb1r1,
+        &mut rcc.bdcr,
+        &mut pwr.cr1,
+        RtcConfig::default().clock_config(RtcClockSource::LSE),
+    );
+
+    // A minute before midnight UTC, the local date is already the next day
+    let (date, time) = parse_iso_date_time("2024-06-30T23:59:00").unwrap();
+    rtc.set_date_time(date, time);
+    rtc.set_utc_offset(OFFSET);
+
+    let mut hstdout = hio::hstdout().unwrap();
+    let mut buf = [0; 10];
+    loop {
+        let (date, time) = rtc.local_now();
+        writeln!(
+            hstdout,
+            "{} {:02}:{:02}:{:02} local",
+            date.format_iso(&mut buf).unwrap(),
+            time.hours,
+            time.minutes,
+            time.seconds
+        )
+        .unwrap();
+
+        delay.delay_ms(1000_u32);
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
@@ -0,0 +1,69 @@
+//! Sets a date with a typed month, and checks the conversions of month numbers
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+extern crate cortex_m;
+#[macro_use]
+extern crate cortex_m_rt as rt;
+extern crate cortex_m_semihosting as sh;
+extern crate panic_semihosting;
+extern crate stm32l4xx_hal as hal;
+
+use core::convert::TryFrom;
+
+use crate::hal::datetime::{Date, Month, OutOfRange, Time};
+use crate::hal::prelude::*;
+use crate::hal::rcc::{ClockSecuritySystem, CrystalBypass};
+use crate::hal::rtc::{Rtc, RtcClockSource, RtcConfig};
+use crate::rt::ExceptionFrame;
+use crate::sh::hio;
+use core::fmt::Write;
+
+#[entry]
+fn main() -> ! {
+    assert_eq!(Month::try_from(0), Err(OutOfRange));
+    assert_eq!(Month::try_from(1), Ok(Month::January));
+    assert_eq!(Month::try_from(12), Ok(Month::December));
+    assert_eq!(Month::try_from(13), Err(OutOfRange));
+    assert_eq!(u8::from(Month::September), 9);
+    assert_eq!(10.month(), Month::October);
+
+    let dp = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    rcc.cfgr
+        .lse(CrystalBypass::Disable, ClockSecuritySystem::Disable)
+        .freeze(&mut flash.acr, &mut pwr);
+
+    let mut rtc = Rtc::rtc(
+        dp.RTC,
+        &mut rcc.apb1r1,
+        &mut rcc.bdcr,
+        &mut pwr.cr1,
+        RtcConfig::default().clock_config(RtcClockSource::LSE),
+    );
+
+    // The BCD encoding of the month has a tens digit from October on
+    let date = Date::new(5.day(), 24.date(), Month::December, 2021.year());
+    let time = Time::new(18.hours(), 0.minutes(), 0.seconds(), 0.micros(), false);
+    rtc.set_date_time(date, time);
+
+    let read = rtc.get_date();
+    assert_eq!(read.month, Month::December);
+
+    let mut hstdout = hio::hstdout().unwrap();
+    writeln!(hstdout, "Month {}: {:?}", read.month.number(), read.month).unwrap();
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
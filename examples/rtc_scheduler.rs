@@ -0,0 +1,105 @@
+//! Runs three overlapping software timers on the RTC wakeup timer, sleeping in between
+
+#![no_std]
+#![no_main]
+
+extern crate cortex_m;
+#[macro_use]
+extern crate cortex_m_rt as rt;
+extern crate cortex_m_semihosting as sh;
+extern crate panic_semihosting;
+extern crate stm32l4xx_hal as hal;
+
+use crate::hal::datetime::{Date, Time};
+use crate::hal::prelude::*;
+use crate::hal::rcc::{ClockSecuritySystem, CrystalBypass};
+use crate::hal::rtc::scheduler::RtcScheduler;
+use crate::hal::rtc::{Rtc, RtcClockSource, RtcConfig};
+use crate::rt::ExceptionFrame;
+use cortex_m::interrupt::{free, Mutex};
+
+use crate::sh::hio;
+use core::{cell::RefCell, fmt::Write, ops::DerefMut};
+use hal::interrupt;
+use hal::pac;
+use pac::NVIC;
+
+/// Blinks every 2 seconds
+const BLINK: usize = 0;
+/// Measures every 3 seconds
+const MEASURE: usize = 1;
+/// Reports once, after 5 seconds
+const REPORT: usize = 2;
+
+static SCHEDULER: Mutex<RefCell<Option<RtcScheduler<3>>>> = Mutex::new(RefCell::new(None));
+
+#[entry]
+fn main() -> ! {
+    let mut dp = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    rcc.cfgr
+        .lse(CrystalBypass::Disable, ClockSecuritySystem::Disable)
+        .freeze(&mut flash.acr, &mut pwr);
+
+    let mut rtc = Rtc::rtc(
+        dp.RTC,
+        &mut rcc.apb1r1,
+        &mut rcc.bdcr,
+        &mut pwr.cr1,
+        RtcConfig::default().clock_config(RtcClockSource::LSE),
+    );
+
+    let time = Time::new(21.hours(), 57.minutes(), 32.seconds(), 0.micros(), false);
+    let date = Date::new(1.day(), 24.date(), 4.month(), 2018.year());
+    rtc.set_date_time(date, time);
+
+    let mut scheduler = RtcScheduler::new(rtc, &mut dp.EXTI);
+    scheduler.schedule(BLINK, 2);
+    scheduler.schedule(MEASURE, 3);
+    scheduler.schedule(REPORT, 5);
+
+    free(|cs| {
+        SCHEDULER.borrow(cs).replace(Some(scheduler));
+    });
+
+    unsafe {
+        NVIC::unmask(pac::Interrupt::RTC_WKUP);
+    }
+
+    // The timers are serviced in the interrupt, the core sleeps between their expiries
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+#[interrupt]
+fn RTC_WKUP() {
+    let mut hstdout = hio::hstdout().unwrap();
+    free(|cs| {
+        let mut scheduler_ref = SCHEDULER.borrow(cs).borrow_mut();
+        if let Some(ref mut scheduler) = scheduler_ref.deref_mut() {
+            while let Some(id) = scheduler.poll() {
+                match id {
+                    BLINK => {
+                        writeln!(hstdout, "Blink").unwrap();
+                        scheduler.schedule(BLINK, 2);
+                    }
+                    MEASURE => {
+                        writeln!(hstdout, "Measure").unwrap();
+                        scheduler.schedule(MEASURE, 3);
+                    }
+                    _ => writeln!(hstdout, "Report").unwrap(),
+                }
+            }
+        }
+    });
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
@@ -0,0 +1,99 @@
+//! Sets the RTC from a "YYYY-MM-DDTHH:MM:SS" line received on USART2, and echoes the calendar
+//!
+//! Malformed lines are answered with the parse error, e.g. "2024-13-01T12:00:00" with
+//! `OutOfRange` and "2024-06-01 12h00" with `Format`.
+#![deny(unsafe_code)]
+#![deny(warnings)]
+#![no_main]
+#![no_std]
+
+extern crate cortex_m;
+#[macro_use(entry, exception)]
+extern crate cortex_m_rt as rt;
+#[macro_use(block)]
+extern crate nb;
+extern crate panic_semihosting;
+extern crate stm32l4xx_hal as hal;
+
+use core::fmt::Write;
+
+use crate::hal::datetime::parse_iso_date_time;
+use crate::hal::prelude::*;
+use crate::hal::rcc::{ClockSecuritySystem, CrystalBypass};
+use crate::hal::rtc::{Rtc, RtcClockSource, RtcConfig};
+use crate::hal::serial::{Config, Serial};
+use crate::rt::ExceptionFrame;
+
+#[entry]
+fn main() -> ! {
+    let p = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = p.FLASH.constrain();
+    let mut rcc = p.RCC.constrain();
+    let mut pwr = p.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc
+        .cfgr
+        .lse(CrystalBypass::Disable, ClockSecuritySystem::Disable)
+        .freeze(&mut flash.acr, &mut pwr);
+
+    let mut rtc = Rtc::rtc(
+        p.RTC,
+        &mut rcc.apb1r1,
+        &mut rcc.bdcr,
+        &mut pwr.cr1,
+        RtcConfig::default().clock_config(RtcClockSource::LSE),
+    );
+
+    let mut gpioa = p.GPIOA.split(&mut rcc.ahb2);
+    let tx = gpioa.pa2.into_af7(&mut gpioa.moder, &mut gpioa.afrl);
+    let rx = gpioa.pa3.into_af7(&mut gpioa.moder, &mut gpioa.afrl);
+
+    let serial = Serial::usart2(
+        p.USART2,
+        (tx, rx),
+        Config::default().baudrate(115_200.bps()),
+        clocks,
+        &mut rcc.apb1r1,
+    );
+    let (mut tx, mut rx) = serial.split();
+
+    let mut line = [0; 32];
+    let mut len = 0;
+    loop {
+        let byte = match block!(rx.read()) {
+            Ok(byte) => byte,
+            // Drop the line on framing or overrun errors
+            Err(_) => {
+                len = 0;
+                continue;
+            }
+        };
+
+        if byte != b'\n' {
+            if len < line.len() {
+                line[len] = byte;
+            }
+            len += 1;
+            continue;
+        }
+
+        // Non UTF-8 lines are parsed as empty, which is a format error
+        let text = core::str::from_utf8(&line[..len.min(line.len())]).unwrap_or("");
+        match parse_iso_date_time(text) {
+            Ok((date, time)) => {
+                rtc.set_date_time(date, time);
+
+                let (date, time) = rtc.get_date_time();
+                writeln!(tx, "{}T{}\r", date.to_iso_string(), time.to_hms_string()).unwrap();
+            }
+            Err(error) => writeln!(tx, "{:?}\r", error).unwrap(),
+        }
+        len = 0;
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
@@ -0,0 +1,85 @@
+//! Wakes up from Stop 2 mode every 5 seconds with the RTC wakeup timer, toggling an LED
+#![no_std]
+#![no_main]
+
+extern crate panic_semihosting;
+
+use core::cell::RefCell;
+use core::ops::DerefMut;
+use cortex_m::{
+    interrupt::{free, Mutex},
+    peripheral::NVIC,
+};
+use cortex_m_rt::entry;
+use stm32l4xx_hal::{
+    interrupt,
+    prelude::*,
+    rtc::{Event, Rtc, RtcClockSource, RtcConfig},
+    stm32,
+};
+
+static RTC: Mutex<RefCell<Option<Rtc>>> = Mutex::new(RefCell::new(None));
+
+#[entry]
+fn main() -> ! {
+    let mut cp = cortex_m::Peripherals::take().unwrap();
+    let mut dp = stm32::Peripherals::take().unwrap();
+
+    let mut rcc = dp.RCC.constrain();
+    let mut flash = dp.FLASH.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    rcc.cfgr.lsi(true).freeze(&mut flash.acr, &mut pwr);
+
+    let mut gpiob = dp.GPIOB.split(&mut rcc.ahb2);
+    let mut led = gpiob
+        .pb3
+        .into_push_pull_output(&mut gpiob.moder, &mut gpiob.otyper);
+
+    let mut rtc = Rtc::rtc(
+        dp.RTC,
+        &mut rcc.apb1r1,
+        &mut rcc.bdcr,
+        &mut pwr.cr1,
+        RtcConfig::default().clock_config(RtcClockSource::LSI),
+    );
+
+    // Unmasks EXTI line 20 as well, without it the interrupt never fires
+    rtc.listen(&mut dp.EXTI, Event::WakeupTimer);
+    rtc.wakeup_timer().start(5_u32);
+
+    free(|cs| {
+        RTC.borrow(cs).replace(Some(rtc));
+    });
+
+    unsafe {
+        NVIC::unmask(stm32::Interrupt::RTC_WKUP);
+    }
+
+    // Enter Stop 2 on WFI
+    // NOTE(unsafe) 0b010 is a valid value for this field, and there is no PWR abstraction for it
+    unsafe { (*stm32::PWR::ptr()).cr1.modify(|_, w| w.lpms().bits(0b010)) };
+    cp.SCB.set_sleepdeep();
+
+    let mut on = false;
+    loop {
+        cortex_m::asm::wfi();
+
+        // Woken up by the RTC, the interrupt handler already ran
+        on = !on;
+        if on {
+            led.set_high().ok();
+        } else {
+            led.set_low().ok();
+        }
+    }
+}
+
+#[interrupt]
+fn RTC_WKUP() {
+    free(|cs| {
+        if let Some(ref mut rtc) = RTC.borrow(cs).borrow_mut().deref_mut() {
+            rtc.clear_pending(Event::WakeupTimer);
+        }
+    });
+}
@@ -0,0 +1,71 @@
+//! Fires alarm A 8 times a second on the sub-second counter alone, sleeping in between
+#![no_std]
+#![no_main]
+
+extern crate cortex_m;
+#[macro_use]
+extern crate cortex_m_rt as rt;
+extern crate cortex_m_semihosting as sh;
+extern crate panic_semihosting;
+extern crate stm32l4xx_hal as hal;
+
+use crate::hal::prelude::*;
+use crate::hal::rcc::{ClockSecuritySystem, CrystalBypass};
+use crate::hal::rtc::{Alarm, Event, Rtc, RtcClockSource, RtcConfig};
+use crate::rt::ExceptionFrame;
+use crate::sh::hio;
+use core::fmt::Write;
+
+/// 2^5 ticks of the 256 Hz sub-second counter of the default prescalers: 8 Hz
+const MASK_BITS: u8 = 5;
+
+#[entry]
+fn main() -> ! {
+    let mut dp = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    rcc.cfgr
+        .lse(CrystalBypass::Disable, ClockSecuritySystem::Disable)
+        .freeze(&mut flash.acr, &mut pwr);
+
+    let mut rtc = Rtc::rtc(
+        dp.RTC,
+        &mut rcc.apb1r1,
+        &mut rcc.bdcr,
+        &mut pwr.cr1,
+        RtcConfig::default().clock_config(RtcClockSource::LSE),
+    );
+
+    let interval = rtc.subsecond_alarm_interval(MASK_BITS);
+    assert_eq!(interval, 125_000);
+
+    let mut hstdout = hio::hstdout().unwrap();
+    writeln!(hstdout, "Alarm every {} µs", interval).unwrap();
+
+    rtc.set_subsecond_alarm(Alarm::AlarmA, 0, MASK_BITS);
+    // The EXTI event of the alarm wakes the core from `wfe`, without an interrupt
+    rtc.listen_event(&mut dp.EXTI, Event::AlarmA);
+
+    let mut alarms = 0u8;
+    let mut seconds = 0u32;
+    loop {
+        while !rtc.is_pending(Event::AlarmA) {
+            cortex_m::asm::wfe();
+        }
+        rtc.clear_pending(Event::AlarmA);
+
+        alarms = (alarms + 1) % 8;
+        if alarms == 0 {
+            seconds += 1;
+            writeln!(hstdout, "{} s", seconds).unwrap();
+        }
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
@@ -0,0 +1,85 @@
+//! Tracks the uptime in microseconds with the RTC, across calendar changes
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+extern crate cortex_m;
+#[macro_use]
+extern crate cortex_m_rt as rt;
+extern crate cortex_m_semihosting as sh;
+extern crate panic_semihosting;
+extern crate stm32l4xx_hal as hal;
+
+use crate::hal::datetime::{Date, RtcInstant, Time};
+use crate::hal::delay::Delay;
+use crate::hal::prelude::*;
+use crate::hal::rcc::{ClockSecuritySystem, CrystalBypass};
+use crate::hal::rtc::{Rtc, RtcClockSource, RtcConfig};
+use crate::rt::ExceptionFrame;
+use crate::sh::hio;
+use core::fmt::Write;
+
+#[entry]
+fn main() -> ! {
+    let mut hstdout = hio::hstdout().unwrap();
+
+    // A span of several days, as the uptime computes it, is well beyond a 32-bit count of
+    // microseconds
+    let monday = Date::new(1.day(), 28.date(), 2.month(), 2022.year());
+    let thursday = Date::new(4.day(), 3.date(), 3.month(), 2022.year());
+    let noon = Time::new(
+        12.hours(),
+        0.minutes(),
+        0.seconds(),
+        250_000.micros(),
+        false,
+    );
+    let span =
+        RtcInstant::from_date_time(thursday, noon) - RtcInstant::from_date_time(monday, noon);
+    assert_eq!(span.as_micros(), 3 * 86_400 * 1_000_000);
+    assert!(span.as_micros() > u128::from(u32::MAX));
+
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let dp = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc
+        .cfgr
+        .lse(CrystalBypass::Disable, ClockSecuritySystem::Disable)
+        .freeze(&mut flash.acr, &mut pwr);
+
+    let mut timer = Delay::new(cp.SYST, clocks);
+
+    let mut rtc = Rtc::rtc(
+        dp.RTC,
+        &mut rcc.apb1r1,
+        &mut rcc.bdcr,
+        &mut pwr.cr1,
+        RtcConfig::default().clock_config(RtcClockSource::LSE),
+    );
+
+    timer.delay_ms(1000_u32);
+    let before = rtc.uptime_us();
+    assert!((900_000..1_100_000).contains(&before));
+
+    // Setting the calendar, here a few days back, doesn't move the uptime
+    rtc.set_date_time(monday, noon);
+    timer.delay_ms(1000_u32);
+    rtc.add_hour();
+    let after = rtc.uptime_us();
+    assert!((1_900_000..2_100_000).contains(&after));
+
+    writeln!(hstdout, "Uptime: {} us, then {} us", before, after).unwrap();
+
+    loop {
+        continue;
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
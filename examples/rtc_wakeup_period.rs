@@ -0,0 +1,97 @@
+//! Blinks an LED with the RTC wakeup timer set from a `Duration`, checking the picked dividers
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+extern crate cortex_m;
+#[macro_use]
+extern crate cortex_m_rt as rt;
+extern crate cortex_m_semihosting as sh;
+extern crate panic_semihosting;
+extern crate stm32l4xx_hal as hal;
+
+use core::time::Duration;
+
+use crate::hal::prelude::*;
+use crate::hal::rcc::{ClockSecuritySystem, CrystalBypass};
+use crate::hal::rtc::{wakeup_config_for, Event, Rtc, RtcClockSource, RtcConfig, WakeupClock};
+use crate::rt::ExceptionFrame;
+use crate::sh::hio;
+use core::fmt::Write;
+
+#[entry]
+fn main() -> ! {
+    let lse = 32_768.hz();
+
+    // 8.192 ticks of RTCCLK / 2, rounded
+    assert_eq!(
+        wakeup_config_for(Duration::from_micros(500), lse),
+        Some((WakeupClock::RtcDiv2, 7))
+    );
+    assert_eq!(
+        wakeup_config_for(Duration::from_secs(1), lse),
+        Some((WakeupClock::RtcDiv2, 16_383))
+    );
+    // The longest period of RTCCLK / 16, the next ones are counted in seconds
+    assert_eq!(
+        wakeup_config_for(Duration::from_secs(32), lse),
+        Some((WakeupClock::RtcDiv16, 65_535))
+    );
+    assert_eq!(
+        wakeup_config_for(Duration::from_secs(18 * 3600), lse),
+        Some((WakeupClock::Spre, 64_799))
+    );
+    assert_eq!(
+        wakeup_config_for(Duration::from_secs(1 << 17), lse),
+        Some((WakeupClock::SpreExtended, 65_535))
+    );
+    assert_eq!(
+        wakeup_config_for(Duration::from_secs((1 << 17) + 1), lse),
+        None
+    );
+    assert_eq!(wakeup_config_for(Duration::from_secs(0), lse), None);
+
+    let mut dp = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    rcc.cfgr
+        .lse(CrystalBypass::Disable, ClockSecuritySystem::Disable)
+        .freeze(&mut flash.acr, &mut pwr);
+
+    let mut gpiob = dp.GPIOB.split(&mut rcc.ahb2);
+    let mut led = gpiob
+        .pb3
+        .into_push_pull_output(&mut gpiob.moder, &mut gpiob.otyper);
+
+    let mut rtc = Rtc::rtc(
+        dp.RTC,
+        &mut rcc.apb1r1,
+        &mut rcc.bdcr,
+        &mut pwr.cr1,
+        RtcConfig::default().clock_config(RtcClockSource::LSE),
+    );
+
+    // The EXTI event of the timer wakes the core from `wfe`
+    rtc.listen_event(&mut dp.EXTI, Event::WakeupTimer);
+    rtc.enable_wakeup(Duration::from_millis(250), lse);
+
+    let mut hstdout = hio::hstdout().unwrap();
+    writeln!(hstdout, "Blinking every 250 ms").unwrap();
+
+    loop {
+        while !rtc.check_interrupt(Event::WakeupTimer, true) {
+            cortex_m::asm::wfe();
+        }
+        rtc.clear_pending(Event::WakeupTimer);
+
+        led.toggle().unwrap();
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
@@ -0,0 +1,119 @@
+//! Echoes what is received on the serial port, with the `async` DMA drivers
+//!
+//! The future is run by a minimal executor, which sleeps until an interrupt wakes it. The
+//! interrupts of both DMA channels and of the USART are forwarded to the drivers, see the
+//! `hal::dma` documentation.
+#![no_main]
+#![no_std]
+
+extern crate cortex_m;
+#[macro_use(entry, exception)]
+extern crate cortex_m_rt as rt;
+extern crate panic_semihosting;
+
+extern crate stm32l4xx_hal as hal;
+
+use core::future::Future;
+use core::pin::pin;
+use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+use crate::hal::dma::dma1;
+use crate::hal::interrupt;
+use crate::hal::pac::{self, NVIC};
+use crate::hal::prelude::*;
+use crate::hal::serial::{Config, Rx, Serial};
+use crate::rt::ExceptionFrame;
+use cortex_m::asm;
+use embedded_io_async::{Read, Write};
+
+#[entry]
+fn main() -> ! {
+    let p = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = p.FLASH.constrain();
+    let mut rcc = p.RCC.constrain();
+    let mut pwr = p.PWR.constrain(&mut rcc.apb1r1);
+
+    let mut gpioa = p.GPIOA.split(&mut rcc.ahb2);
+    let channels = p.DMA1.split(&mut rcc.ahb1);
+
+    let clocks = rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+
+    let tx = gpioa.pa2.into_af7(&mut gpioa.moder, &mut gpioa.afrl);
+    let rx = gpioa.pa3.into_af7(&mut gpioa.moder, &mut gpioa.afrl);
+
+    let serial = Serial::usart2(
+        p.USART2,
+        (tx, rx),
+        Config::default().baudrate(115_200.bps()),
+        clocks,
+        &mut rcc.apb1r1,
+    );
+    let (tx, rx) = serial.split();
+    let mut tx = tx.with_dma(channels.7);
+    let mut rx = rx.with_dma(channels.6);
+
+    unsafe {
+        NVIC::unmask(pac::Interrupt::DMA1_CH6);
+        NVIC::unmask(pac::Interrupt::DMA1_CH7);
+        NVIC::unmask(pac::Interrupt::USART2);
+    }
+
+    block_on(async {
+        let mut buf = [0; 64];
+        loop {
+            // A read returns when the buffer is full, or once the line goes idle. The trait is
+            // named, as `RxDma::read` starts a `Transfer` instead.
+            match Read::read(&mut rx, &mut buf).await {
+                Ok(len) => tx.write_all(&buf[..len]).await.unwrap(),
+                // e.g. an overrun, the bytes are lost
+                Err(_) => continue,
+            }
+        }
+    })
+}
+
+/// Polls `future` to completion, waiting for events in between
+///
+/// The waker sets the event register of the core, so a wake-up in between the poll and `wfe`
+/// isn't missed.
+fn block_on<F: Future>(future: F) -> F::Output {
+    fn clone(_: *const ()) -> RawWaker {
+        RawWaker::new(core::ptr::null(), &VTABLE)
+    }
+    fn wake(_: *const ()) {
+        asm::sev();
+    }
+    fn drop(_: *const ()) {}
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, wake, wake, drop);
+
+    let waker = unsafe { Waker::from_raw(clone(core::ptr::null())) };
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        asm::wfe();
+    }
+}
+
+#[interrupt]
+fn DMA1_CH6() {
+    dma1::C6::on_interrupt();
+}
+
+#[interrupt]
+fn DMA1_CH7() {
+    dma1::C7::on_interrupt();
+}
+
+#[interrupt]
+fn USART2() {
+    Rx::<pac::USART2>::on_interrupt();
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
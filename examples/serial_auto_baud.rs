@@ -0,0 +1,69 @@
+//! Detects the baud rate of the host from a received 0x7F, then echoes at that rate
+//!
+//! The host sends 0x7F first, at any rate the divider can reach, like the synchronization byte
+//! of a bootloader.
+#![deny(unsafe_code)]
+#![deny(warnings)]
+#![no_main]
+#![no_std]
+
+extern crate cortex_m;
+#[macro_use(entry, exception)]
+extern crate cortex_m_rt as rt;
+#[macro_use(block)]
+extern crate nb;
+extern crate panic_semihosting;
+
+extern crate stm32l4xx_hal as hal;
+
+use crate::hal::prelude::*;
+use crate::hal::serial::{AutoBaudMode, Config, Serial};
+use crate::rt::ExceptionFrame;
+
+/// Byte acknowledging the detected baud rate to the host
+const ACK: u8 = 0x79;
+
+#[entry]
+fn main() -> ! {
+    let p = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = p.FLASH.constrain();
+    let mut rcc = p.RCC.constrain();
+    let mut pwr = p.PWR.constrain(&mut rcc.apb1r1);
+
+    let mut gpioa = p.GPIOA.split(&mut rcc.ahb2);
+
+    let clocks = rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+
+    let tx = gpioa.pa2.into_af7(&mut gpioa.moder, &mut gpioa.afrl);
+    let rx = gpioa.pa3.into_af7(&mut gpioa.moder, &mut gpioa.afrl);
+
+    // The configured baud rate is only a placeholder until the detection
+    let mut serial = Serial::usart2(
+        p.USART2,
+        (tx, rx),
+        Config::default().baudrate(115_200.bps()),
+        clocks,
+        &mut rcc.apb1r1,
+    );
+
+    // Retry until the host sends a 0x7F within the range of the divider
+    while serial.auto_baud(AutoBaudMode::Char0x7F, clocks).is_err() {}
+
+    let (mut tx, mut rx) = serial.split();
+
+    // The 0x7F is received like any other byte
+    block!(rx.read()).ok();
+    block!(tx.write(ACK)).ok();
+
+    loop {
+        if let Ok(byte) = block!(rx.read()) {
+            block!(tx.write(byte)).ok();
+        }
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
@@ -0,0 +1,129 @@
+//! Full-duplex serial over DMA in RTIC, with the transmitter and the receiver living in different
+//! interrupt handlers that share no resources.
+//!
+//! The transmitter keeps sending a short frame, the receiver reads whatever arrives into a
+//! circular buffer. Short (connect) the TX and RX pins, or use the debuggers VCP.
+//!
+//! This example only compiles for some targets so it is not part of the CI for now.
+
+#![deny(unsafe_code)]
+// #![deny(warnings)]
+#![no_main]
+#![no_std]
+
+use core::fmt::Write;
+use hal::{
+    dma::{self, consts, CircBuffer, DMAFrame, FrameSender, Half},
+    prelude::*,
+    serial::{self, Config, Serial},
+};
+use heapless::{
+    pool,
+    pool::singleton::{Box, Pool},
+};
+use panic_halt as _;
+use rtic::app;
+use stm32l4xx_hal as hal;
+
+// The pool gives out `Box<DMAFrame>`s that can hold 8 bytes
+pool!(
+    #[allow(non_upper_case_globals)]
+    SerialDMAPool: DMAFrame<consts::U8>
+);
+
+#[app(device = stm32l4xx_hal::stm32, peripherals = true)]
+const APP: () = {
+    struct Resources {
+        rx: serial::Rx<hal::stm32::USART2>,
+        circ_buffer: CircBuffer<&'static mut [[u8; 8]; 2], dma::dma1::C6>,
+        frame_sender: FrameSender<Box<SerialDMAPool>, dma::dma1::C7, consts::U8>,
+        #[init(0)]
+        received: usize,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> init::LateResources {
+        static mut MEMORY: [u8; 256] = [0; 256];
+        static mut RX_BUFFER: [[u8; 8]; 2] = [[0; 8]; 2];
+
+        SerialDMAPool::grow(MEMORY);
+
+        let dp = cx.device;
+
+        let mut flash = dp.FLASH.constrain();
+        let mut rcc = dp.RCC.constrain();
+        let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+        let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+
+        let clocks = rcc.cfgr.sysclk(80.mhz()).freeze(&mut flash.acr, &mut pwr);
+
+        // USART2 pins
+        let tx2 = gpioa.pa2.into_af7(&mut gpioa.moder, &mut gpioa.afrl);
+        let rx2 = gpioa.pa3.into_af7(&mut gpioa.moder, &mut gpioa.afrl);
+
+        let serial = Serial::usart2(
+            dp.USART2,
+            (tx2, rx2),
+            Config::default().baudrate(115_200.bps()),
+            clocks,
+            &mut rcc.apb1r1,
+        );
+        let (serial_tx, serial_rx) = serial.split();
+
+        let channels = dp.DMA1.split(&mut rcc.ahb1);
+        let mut dma_ch6 = channels.6;
+        let mut dma_ch7 = channels.7;
+        dma_ch6.listen(dma::Event::HalfTransfer);
+        dma_ch6.listen(dma::Event::TransferComplete);
+        dma_ch7.listen(dma::Event::TransferComplete);
+
+        // Each half gets its own DMA channel
+        let (serial_rx, circ_buffer) = serial_rx.with_dma(dma_ch6).circ_read(RX_BUFFER);
+        let (_serial_tx, mut frame_sender) = serial_tx.with_dma(dma_ch7).frame_sender();
+
+        // Kick off the first transmission, the rest is driven by the TX interrupt
+        send_frame(&mut frame_sender);
+
+        init::LateResources {
+            rx: serial_rx,
+            circ_buffer,
+            frame_sender,
+        }
+    }
+
+    /// Receive side, only touches the receiver and its DMA channel
+    #[task(binds = DMA1_CH6, resources = [rx, circ_buffer, received], priority = 2)]
+    fn serial_rx_dma_isr(cx: serial_rx_dma_isr::Context) {
+        if cx.resources.rx.check_for_error().is_err() {
+            // Framing, noise or overrun error, the bytes in the buffer are still delivered
+        }
+
+        if let Ok(Half::First) | Ok(Half::Second) = cx.resources.circ_buffer.readable_half() {
+            let len = cx
+                .resources
+                .circ_buffer
+                .peek(|half, _| half.len())
+                .unwrap_or(0);
+            *cx.resources.received += len;
+        }
+    }
+
+    /// Transmit side, only touches the transmitter and its DMA channel
+    #[task(binds = DMA1_CH7, resources = [frame_sender], priority = 1)]
+    fn serial_tx_dma_isr(cx: serial_tx_dma_isr::Context) {
+        let fs = cx.resources.frame_sender;
+
+        if let Some(_buf) = fs.transfer_complete_interrupt() {
+            // Frame sent, drop the buffer to return it to the pool and send the next one
+            send_frame(fs);
+        }
+    }
+};
+
+fn send_frame(frame_sender: &mut FrameSender<Box<SerialDMAPool>, dma::dma1::C7, consts::U8>) {
+    if let Some(dma_buf) = SerialDMAPool::alloc() {
+        let mut dma_buf = dma_buf.init(DMAFrame::new());
+        write!(dma_buf, "ping\r\n").ok();
+        frame_sender.send(dma_buf).ok();
+    }
+}
@@ -0,0 +1,96 @@
+//! Receives Modbus-RTU style frames over DMA, delimited by an idle line, in RTIC.
+//!
+//! Every time the line goes idle the bytes received since the previous frame are checked: a
+//! frame holds at least an address, a function code and the CRC-16 (Modbus) of the preceding
+//! bytes, low byte first. Strictly, Modbus-RTU delimits frames by 3.5 characters of silence,
+//! while the idle flag is set after a single one.
+//!
+//! This example only compiles for some targets so it is not part of the CI for now.
+
+#![deny(unsafe_code)]
+// #![deny(warnings)]
+#![no_main]
+#![no_std]
+
+use hal::{
+    dma,
+    prelude::*,
+    serial::{self, Config, DmaIdleRx, Serial},
+};
+use panic_halt as _;
+use rtic::app;
+use stm32l4xx_hal as hal;
+
+#[app(device = stm32l4xx_hal::stm32, peripherals = true)]
+const APP: () = {
+    struct Resources {
+        rx: DmaIdleRx<hal::stm32::USART2, dma::dma1::C6, &'static mut [u8; 256]>,
+        #[init(0)]
+        frames: usize,
+        #[init(0)]
+        bad_frames: usize,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> init::LateResources {
+        static mut RX_BUFFER: [u8; 256] = [0; 256];
+        static mut SCRATCH: [u8; 256] = [0; 256];
+
+        let dp = cx.device;
+
+        let mut flash = dp.FLASH.constrain();
+        let mut rcc = dp.RCC.constrain();
+        let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+        let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+
+        let clocks = rcc.cfgr.sysclk(80.mhz()).freeze(&mut flash.acr, &mut pwr);
+
+        // USART2 pins
+        let tx2 = gpioa.pa2.into_af7(&mut gpioa.moder, &mut gpioa.afrl);
+        let rx2 = gpioa.pa3.into_af7(&mut gpioa.moder, &mut gpioa.afrl);
+
+        let mut serial = Serial::usart2(
+            dp.USART2,
+            (tx2, rx2),
+            Config::default().baudrate(19_200.bps()).parity_even(),
+            clocks,
+            &mut rcc.apb1r1,
+        );
+        serial.listen(serial::Event::Idle);
+        let (_serial_tx, serial_rx) = serial.split();
+
+        let channels = dp.DMA1.split(&mut rcc.ahb1);
+
+        let rx = serial_rx.with_dma(channels.6).idle_read(RX_BUFFER, SCRATCH);
+
+        init::LateResources { rx }
+    }
+
+    #[task(binds = USART2, resources = [rx, frames, bad_frames])]
+    fn usart2_isr(cx: usart2_isr::Context) {
+        if let Some(frame) = cx.resources.rx.read_frame() {
+            if frame.len() >= 4 && crc16(frame) == 0 {
+                *cx.resources.frames += 1;
+            } else {
+                *cx.resources.bad_frames += 1;
+            }
+        }
+    }
+};
+
+/// CRC-16 (Modbus), which is 0 over a frame that ends with its CRC
+fn crc16(bytes: &[u8]) -> u16 {
+    let mut crc = 0xffff;
+    for byte in bytes {
+        crc ^= u16::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xa001
+            } else {
+                crc >> 1
+            };
+        }
+    }
+
+    crc
+}
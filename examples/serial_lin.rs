@@ -0,0 +1,80 @@
+//! Sends the header of a LIN frame: a break, the sync byte and a protected identifier
+//!
+//! A LIN transceiver connects the TX and RX pins to the bus, which echoes the header back.
+#![deny(unsafe_code)]
+#![deny(warnings)]
+#![no_main]
+#![no_std]
+
+extern crate cortex_m;
+#[macro_use(entry, exception)]
+extern crate cortex_m_rt as rt;
+#[macro_use(block)]
+extern crate nb;
+extern crate panic_semihosting;
+
+extern crate stm32l4xx_hal as hal;
+
+use crate::hal::prelude::*;
+use crate::hal::serial::{Config, Serial};
+use crate::rt::ExceptionFrame;
+use cortex_m::asm;
+
+/// Byte following the break, used by the slaves to synchronize on the baud rate
+const SYNC: u8 = 0x55;
+
+#[entry]
+fn main() -> ! {
+    let p = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = p.FLASH.constrain();
+    let mut rcc = p.RCC.constrain();
+    let mut pwr = p.PWR.constrain(&mut rcc.apb1r1);
+
+    let mut gpioa = p.GPIOA.split(&mut rcc.ahb2);
+
+    let clocks = rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+
+    let tx = gpioa.pa2.into_af7(&mut gpioa.moder, &mut gpioa.afrl);
+    let rx = gpioa.pa3.into_af7(&mut gpioa.moder, &mut gpioa.afrl);
+
+    let serial = Serial::usart2(
+        p.USART2,
+        (tx, rx),
+        Config::default().baudrate(19_200.bps()).with_lin_mode(),
+        clocks,
+        &mut rcc.apb1r1,
+    );
+    let (mut tx, mut rx) = serial.split();
+
+    // Start from an empty receiver, so the echo of the header is the first thing received
+    rx.flush();
+
+    tx.send_break();
+
+    // The break is echoed as a zero with a framing error, discard it
+    while !rx.is_lin_break(true) {}
+    rx.flush();
+    rx.check_for_error().ok();
+
+    block!(tx.write(SYNC)).ok();
+    let sync = block!(rx.read()).ok();
+
+    // Frame identifier 0x10, with its parity bits
+    block!(tx.write(0x50)).ok();
+    let id = block!(rx.read()).ok();
+
+    if sync == Some(SYNC) && id == Some(0x50) {
+        // if all goes well you should reach this breakpoint
+        asm::bkpt();
+    }
+
+    loop {
+        continue;
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
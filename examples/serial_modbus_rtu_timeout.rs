@@ -0,0 +1,121 @@
+//! Receives Modbus-RTU frames in RTIC, delimited by the receiver timeout of the USART.
+//!
+//! Modbus-RTU ends a frame with 3.5 characters of silence: with 8 data bits, even parity and
+//! a stop bit, a character takes 11 bit durations, so the receiver timeout is set to 39 of
+//! them. Unlike the idle flag, which is set after a single silent character (see the
+//! `serial_dma_idle_modbus` example), this doesn't split a frame at a short gap. The bytes are
+//! collected on `Rxne`, and checked against their CRC-16 (Modbus) once the timeout fires.
+//!
+//! This example only compiles for some targets so it is not part of the CI for now.
+
+#![deny(unsafe_code)]
+// #![deny(warnings)]
+#![no_main]
+#![no_std]
+
+use hal::{
+    prelude::*,
+    serial::{self, Config, Serial},
+};
+use panic_halt as _;
+use rtic::app;
+use stm32l4xx_hal as hal;
+
+/// Longest Modbus-RTU frame, in bytes
+const MAX_FRAME: usize = 256;
+
+/// 3.5 characters of 11 bits
+const INTER_FRAME_GAP_BITS: u32 = 39;
+
+#[app(device = stm32l4xx_hal::stm32, peripherals = true)]
+const APP: () = {
+    struct Resources {
+        rx: serial::Rx<hal::stm32::USART2>,
+        #[init([0; MAX_FRAME])]
+        frame: [u8; MAX_FRAME],
+        #[init(0)]
+        len: usize,
+        #[init(0)]
+        frames: usize,
+        #[init(0)]
+        bad_frames: usize,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> init::LateResources {
+        let dp = cx.device;
+
+        let mut flash = dp.FLASH.constrain();
+        let mut rcc = dp.RCC.constrain();
+        let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+        let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+
+        let clocks = rcc.cfgr.sysclk(80.mhz()).freeze(&mut flash.acr, &mut pwr);
+
+        // USART2 pins
+        let tx2 = gpioa.pa2.into_af7(&mut gpioa.moder, &mut gpioa.afrl);
+        let rx2 = gpioa.pa3.into_af7(&mut gpioa.moder, &mut gpioa.afrl);
+
+        let mut serial = Serial::usart2(
+            dp.USART2,
+            (tx2, rx2),
+            Config::default().baudrate(19_200.bps()).parity_even(),
+            clocks,
+            &mut rcc.apb1r1,
+        );
+        serial.set_receiver_timeout(INTER_FRAME_GAP_BITS);
+        serial.listen(serial::Event::Rxne);
+        serial.listen(serial::Event::ReceiverTimeout);
+        let (_serial_tx, rx) = serial.split();
+
+        init::LateResources { rx }
+    }
+
+    #[task(binds = USART2, resources = [rx, frame, len, frames, bad_frames])]
+    fn usart2_isr(cx: usart2_isr::Context) {
+        let rx = cx.resources.rx;
+        let len = cx.resources.len;
+
+        // The last byte of a frame is read before the end of the frame is handled
+        match rx.read() {
+            Ok(byte) => {
+                // The bytes of a frame longer than the maximum are dropped, it fails the CRC
+                if let Some(slot) = cx.resources.frame.get_mut(*len) {
+                    *slot = byte;
+                }
+                *len += 1;
+            }
+            Err(nb::Error::WouldBlock) => {}
+            // e.g. a parity error, the frame is bad
+            Err(nb::Error::Other(_)) => *len = MAX_FRAME + 1,
+        }
+
+        if rx.is_receiver_timeout(true) {
+            let frame = &cx.resources.frame[..(*len).min(MAX_FRAME)];
+            if *len <= MAX_FRAME && frame.len() >= 4 && crc16(frame) == 0 {
+                *cx.resources.frames += 1;
+            } else {
+                *cx.resources.bad_frames += 1;
+            }
+
+            *len = 0;
+        }
+    }
+};
+
+/// CRC-16 (Modbus), which is 0 over a frame that ends with its CRC
+fn crc16(bytes: &[u8]) -> u16 {
+    let mut crc = 0xffff;
+    for byte in bytes {
+        crc ^= u16::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xa001
+            } else {
+                crc >> 1
+            };
+        }
+    }
+
+    crc
+}
@@ -0,0 +1,71 @@
+//! Serial link on a board with crossed TX and RX traces, receiving through an inverting
+//! optocoupler
+//!
+//! The remote device transmits to PA2, through the optocoupler which idles low, and receives from
+//! PA3. The example sends a query byte and waits for the answer.
+#![deny(unsafe_code)]
+#![deny(warnings)]
+#![no_main]
+#![no_std]
+
+extern crate cortex_m;
+#[macro_use(entry, exception)]
+extern crate cortex_m_rt as rt;
+#[macro_use(block)]
+extern crate nb;
+extern crate panic_semihosting;
+
+extern crate stm32l4xx_hal as hal;
+
+use crate::hal::prelude::*;
+use crate::hal::serial::{Config, Serial};
+use crate::rt::ExceptionFrame;
+use cortex_m::asm;
+
+/// Byte the remote device answers
+const QUERY: u8 = b'?';
+
+#[entry]
+fn main() -> ! {
+    let p = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = p.FLASH.constrain();
+    let mut rcc = p.RCC.constrain();
+    let mut pwr = p.PWR.constrain(&mut rcc.apb1r1);
+
+    let mut gpioa = p.GPIOA.split(&mut rcc.ahb2);
+
+    let clocks = rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+
+    // The pins are passed in their usual order, the swap makes PA2 receive and PA3 transmit
+    let tx = gpioa.pa2.into_af7(&mut gpioa.moder, &mut gpioa.afrl);
+    let rx = gpioa.pa3.into_af7(&mut gpioa.moder, &mut gpioa.afrl);
+
+    let serial = Serial::usart2(
+        p.USART2,
+        (tx, rx),
+        Config::default()
+            .baudrate(9_600.bps())
+            .swap_tx_rx(true)
+            .invert_rx(true),
+        clocks,
+        &mut rcc.apb1r1,
+    );
+    let (mut tx, mut rx) = serial.split();
+
+    block!(tx.write(QUERY)).ok();
+
+    if block!(rx.read()).is_ok() {
+        // if all goes well you should reach this breakpoint
+        asm::bkpt();
+    }
+
+    loop {
+        continue;
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
@@ -0,0 +1,47 @@
+//! Sweeps a hobby servo on PA0 back and forth
+
+#![deny(unsafe_code)]
+#![deny(warnings)]
+#![no_main]
+#![no_std]
+
+extern crate panic_halt;
+
+use cortex_m_rt::entry;
+use stm32l4xx_hal::{delay, prelude::*, pwm::Servo, stm32};
+
+#[entry]
+fn main() -> ! {
+    let c = cortex_m::Peripherals::take().unwrap();
+    let p = stm32::Peripherals::take().unwrap();
+
+    let mut flash = p.FLASH.constrain();
+    let mut rcc = p.RCC.constrain();
+    let mut pwr = p.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+
+    let mut gpioa = p.GPIOA.split(&mut rcc.ahb2);
+
+    // TIM2 CH1, with its 32 bit auto-reload the 50 Hz frame leaves a fine duty cycle
+    let c1 = gpioa
+        .pa0
+        .into_push_pull_output(&mut gpioa.moder, &mut gpioa.otyper)
+        .into_af1(&mut gpioa.moder, &mut gpioa.afrl);
+
+    let pwm = p.TIM2.pwm(c1, 50.hz(), clocks, &mut rcc.apb1r1);
+    let mut servo = Servo::new(pwm);
+
+    let mut timer = delay::Delay::new(c.SYST, clocks);
+
+    loop {
+        for deg in (0..=180).step_by(5) {
+            servo.set_angle(deg);
+            timer.delay_ms(20_u32);
+        }
+        for deg in (0..=180).rev().step_by(5) {
+            servo.set_angle(deg);
+            timer.delay_ms(20_u32);
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! Sends a framebuffer to a 128x64 monochrome display, on an SPI bus that only sends
+//!
+//! The display has no data output, so the bus takes no MISO pin: PA6 stays free.
+#![deny(unsafe_code)]
+#![deny(warnings)]
+#![no_main]
+#![no_std]
+
+#[macro_use(entry, exception)]
+extern crate cortex_m_rt as rt;
+extern crate cortex_m;
+extern crate embedded_hal as ehal;
+extern crate panic_semihosting;
+extern crate stm32l4xx_hal as hal;
+
+use crate::ehal::spi::MODE_0;
+use crate::hal::prelude::*;
+use crate::hal::spi::Spi;
+use crate::rt::ExceptionFrame;
+
+const WIDTH: usize = 128;
+const HEIGHT: usize = 64;
+
+#[entry]
+fn main() -> ! {
+    let p = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = p.FLASH.constrain();
+    let mut rcc = p.RCC.constrain();
+    let mut pwr = p.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc
+        .cfgr
+        .sysclk(80.mhz())
+        .pclk2(80.mhz())
+        .freeze(&mut flash.acr, &mut pwr);
+
+    let mut gpioa = p.GPIOA.split(&mut rcc.ahb2);
+    let mut gpiob = p.GPIOB.split(&mut rcc.ahb2);
+
+    let sck = gpioa.pa5.into_af5(&mut gpioa.moder, &mut gpioa.afrl);
+    let mosi = gpioa.pa7.into_af5(&mut gpioa.moder, &mut gpioa.afrl);
+    let mut cs = gpiob.pb0.into_push_pull_output_with_state(
+        &mut gpiob.moder,
+        &mut gpiob.otyper,
+        hal::gpio::State::High,
+    );
+    // Data/command select of the display, high for data
+    let mut dc = gpiob
+        .pb1
+        .into_push_pull_output(&mut gpiob.moder, &mut gpiob.otyper);
+
+    let mut spi = Spi::spi1_tx_only(p.SPI1, (sck, mosi), MODE_0, 8.mhz(), clocks, &mut rcc.apb2);
+
+    // A checkerboard of 8x8 squares, in pages of 8 rows: one byte per column of a page
+    let mut framebuffer = [0u8; WIDTH * HEIGHT / 8];
+    for (i, byte) in framebuffer.iter_mut().enumerate() {
+        let (page, column) = (i / WIDTH, i % WIDTH);
+        if (page + column / 8) % 2 == 0 {
+            *byte = 0xff;
+        }
+    }
+
+    loop {
+        cs.set_low().unwrap();
+        dc.set_high().unwrap();
+        // The write returns once the last byte is sent, the chip select can be released
+        spi.write(&framebuffer).unwrap();
+        cs.set_high().unwrap();
+
+        // Invert the picture for the next frame
+        for byte in framebuffer.iter_mut() {
+            *byte = !*byte;
+        }
+        cortex_m::asm::delay(80_000_000);
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
@@ -0,0 +1,75 @@
+//! Drives a 74HC595 shift register, with its storage clock on the NSS pin driven by the SPI
+//!
+//! The 74HC595 copies the shifted byte to its outputs on the rising edge of the storage clock,
+//! which NSS gives right after the last clock edge of every transaction.
+#![deny(unsafe_code)]
+#![deny(warnings)]
+#![no_main]
+#![no_std]
+
+extern crate cortex_m;
+#[macro_use(entry, exception)]
+extern crate cortex_m_rt as rt;
+extern crate embedded_hal as ehal;
+extern crate panic_semihosting;
+extern crate stm32l4xx_hal as hal;
+
+use crate::ehal::spi::{Mode, Phase, Polarity};
+use crate::hal::delay::Delay;
+use crate::hal::prelude::*;
+use crate::hal::spi::Spi;
+use crate::rt::ExceptionFrame;
+
+/// SPI mode of the 74HC595, which samples on the rising edge of a clock idling low
+pub const MODE: Mode = Mode {
+    phase: Phase::CaptureOnFirstTransition,
+    polarity: Polarity::IdleLow,
+};
+
+#[entry]
+fn main() -> ! {
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let p = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = p.FLASH.constrain();
+    let mut rcc = p.RCC.constrain();
+    let mut pwr = p.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+
+    let mut gpioa = p.GPIOA.split(&mut rcc.ahb2);
+
+    let sck = gpioa.pa5.into_af5(&mut gpioa.moder, &mut gpioa.afrl);
+    let miso = gpioa.pa6.into_af5(&mut gpioa.moder, &mut gpioa.afrl);
+    let mosi = gpioa.pa7.into_af5(&mut gpioa.moder, &mut gpioa.afrl);
+    // NSS is not driven between transactions, the pull-up keeps the storage clock high
+    let nss = gpioa
+        .pa4
+        .into_af5(&mut gpioa.moder, &mut gpioa.afrl)
+        .internal_pull_up(true);
+
+    let mut spi = Spi::spi1_hardware_nss(
+        p.SPI1,
+        (sck, miso, mosi, nss),
+        MODE,
+        1.mhz(),
+        clocks,
+        &mut rcc.apb2,
+    );
+
+    let mut delay = Delay::new(cp.SYST, clocks);
+
+    // Walk a single lit output across the shift register
+    let mut output = 1u8;
+    loop {
+        spi.transaction(|spi| spi.write(&[output])).unwrap();
+        output = output.rotate_left(1);
+
+        delay.delay_ms(250_u32);
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
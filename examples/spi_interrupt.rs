@@ -0,0 +1,107 @@
+//! Background SPI transfer, driven byte by byte from the SPI interrupt
+//!
+//! Short (connect) MOSI (PA7) and MISO (PA6) to receive the sent bytes back.
+#![no_main]
+#![no_std]
+
+extern crate panic_rtt_target;
+
+use rtt_target::{rprint, rprintln};
+use stm32l4xx_hal::{
+    gpio::{gpioa::*, Alternate, Floating, Input, AF5},
+    hal::spi::{FullDuplex, Mode, Phase, Polarity},
+    pac::{self, SPI1},
+    prelude::*,
+    spi::{Event, Spi},
+};
+
+/// SPI mode
+pub const MODE: Mode = Mode {
+    phase: Phase::CaptureOnFirstTransition,
+    polarity: Polarity::IdleLow,
+};
+
+const TX_DATA: [u8; 8] = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08];
+
+type SpiPins = (
+    PA5<Alternate<AF5, Input<Floating>>>,
+    PA6<Alternate<AF5, Input<Floating>>>,
+    PA7<Alternate<AF5, Input<Floating>>>,
+);
+
+#[rtic::app(device = stm32l4xx_hal::pac)]
+const APP: () = {
+    struct Resources {
+        spi: Spi<SPI1, SpiPins>,
+        #[init([0; 8])]
+        rx_data: [u8; 8],
+        #[init(0)]
+        index: usize,
+    }
+
+    #[init]
+    fn init(_: init::Context) -> init::LateResources {
+        rtt_target::rtt_init_print!();
+        rprint!("Initializing... ");
+
+        let p = pac::Peripherals::take().unwrap();
+
+        let mut rcc = p.RCC.constrain();
+        let mut flash = p.FLASH.constrain();
+        let mut pwr = p.PWR.constrain(&mut rcc.apb1r1);
+
+        let clocks = rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+
+        let mut gpioa = p.GPIOA.split(&mut rcc.ahb2);
+        let sck = gpioa.pa5.into_af5(&mut gpioa.moder, &mut gpioa.afrl);
+        let miso = gpioa.pa6.into_af5(&mut gpioa.moder, &mut gpioa.afrl);
+        let mosi = gpioa.pa7.into_af5(&mut gpioa.moder, &mut gpioa.afrl);
+
+        let mut spi = Spi::spi1(
+            p.SPI1,
+            (sck, miso, mosi),
+            MODE,
+            100.khz(),
+            clocks,
+            &mut rcc.apb2,
+        );
+
+        // Every sent byte clocks in a received one, so RXNE alone drives the transfer
+        spi.listen(Event::Rxne);
+        spi.listen(Event::Error);
+
+        // Kick off the transfer, the rest happens in the interrupt
+        spi.send(TX_DATA[0]).unwrap();
+
+        rprintln!("done.");
+
+        init::LateResources { spi }
+    }
+
+    #[task(binds = SPI1, resources = [spi, rx_data, index])]
+    fn spi1(cx: spi1::Context) {
+        let spi = cx.resources.spi;
+        let index = cx.resources.index;
+
+        if spi.is_overrun() {
+            // A byte was missed, start over
+            spi.clear_overrun();
+            *index = 0;
+            spi.send(TX_DATA[0]).ok();
+            return;
+        }
+
+        if let Ok(byte) = spi.read() {
+            cx.resources.rx_data[*index] = byte;
+            *index += 1;
+
+            if *index < TX_DATA.len() {
+                spi.send(TX_DATA[*index]).ok();
+            } else {
+                spi.unlisten(Event::Rxne);
+                spi.unlisten(Event::Error);
+                rprintln!("Received: {:?}", cx.resources.rx_data);
+            }
+        }
+    }
+};
@@ -0,0 +1,67 @@
+//! Reads a 12-bit MCP3201 ADC, on an SPI bus that only receives
+//!
+//! The ADC has no data input, so the bus takes no MOSI pin: PA7 stays free.
+#![deny(unsafe_code)]
+#![deny(warnings)]
+#![no_main]
+#![no_std]
+
+#[macro_use(entry, exception)]
+extern crate cortex_m_rt as rt;
+extern crate cortex_m;
+extern crate cortex_m_semihosting as sh;
+extern crate embedded_hal as ehal;
+extern crate panic_semihosting;
+extern crate stm32l4xx_hal as hal;
+
+use crate::ehal::spi::MODE_0;
+use crate::hal::prelude::*;
+use crate::hal::spi::Spi;
+use crate::rt::ExceptionFrame;
+use crate::sh::hio;
+use core::fmt::Write;
+
+#[entry]
+fn main() -> ! {
+    let p = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = p.FLASH.constrain();
+    let mut rcc = p.RCC.constrain();
+    let mut pwr = p.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+
+    let mut gpioa = p.GPIOA.split(&mut rcc.ahb2);
+    let mut gpiob = p.GPIOB.split(&mut rcc.ahb2);
+
+    let sck = gpioa.pa5.into_af5(&mut gpioa.moder, &mut gpioa.afrl);
+    let miso = gpioa.pa6.into_af5(&mut gpioa.moder, &mut gpioa.afrl);
+    let mut cs = gpiob.pb0.into_push_pull_output_with_state(
+        &mut gpiob.moder,
+        &mut gpiob.otyper,
+        hal::gpio::State::High,
+    );
+
+    // The MCP3201 is clocked up to 1.6 MHz at 5 V
+    let mut spi = Spi::spi1_rx_only(p.SPI1, (sck, miso), MODE_0, 1.mhz(), clocks, &mut rcc.apb2);
+
+    let mut hstdout = hio::hstdout().unwrap();
+    loop {
+        // A conversion takes 16 clocks: 2 to sample, a null bit, the 12 bits MSB first, and the
+        // first bit again. The bytes sent don't leave the chip.
+        let mut words = [0; 2];
+        cs.set_low().unwrap();
+        spi.transfer(&mut words).unwrap();
+        cs.set_high().unwrap();
+
+        let sample = (u16::from_be_bytes(words) >> 1) & 0x0fff;
+        writeln!(hstdout, "{}", sample).unwrap();
+
+        cortex_m::asm::delay(8_000_000);
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
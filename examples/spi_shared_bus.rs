@@ -0,0 +1,101 @@
+//! A display and a flash chip sharing SPI1, each with its own chip select pin, mode and speed
+#![deny(unsafe_code)]
+// #![deny(warnings)]
+#![no_main]
+#![no_std]
+
+#[macro_use(entry, exception)]
+extern crate cortex_m_rt as rt;
+extern crate cortex_m;
+extern crate embedded_hal as ehal;
+extern crate panic_semihosting;
+extern crate stm32l4xx_hal as hal;
+
+use crate::ehal::spi::{MODE_0, MODE_3};
+use crate::hal::prelude::*;
+use crate::hal::spi::{DeviceConfig, SharedBus, Spi};
+use crate::rt::ExceptionFrame;
+use cortex_m::asm;
+
+/// JEDEC "Read Identification" command of SPI NOR flashes
+const READ_ID: u8 = 0x9f;
+
+#[entry]
+fn main() -> ! {
+    let p = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = p.FLASH.constrain();
+    let mut rcc = p.RCC.constrain();
+    let mut pwr = p.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc
+        .cfgr
+        .sysclk(80.mhz())
+        .pclk1(80.mhz())
+        .pclk2(80.mhz())
+        .freeze(&mut flash.acr, &mut pwr);
+
+    let mut gpioa = p.GPIOA.split(&mut rcc.ahb2);
+    let mut gpiob = p.GPIOB.split(&mut rcc.ahb2);
+
+    let sck = gpioa.pa5.into_af5(&mut gpioa.moder, &mut gpioa.afrl);
+    let miso = gpioa.pa6.into_af5(&mut gpioa.moder, &mut gpioa.afrl);
+    let mosi = gpioa.pa7.into_af5(&mut gpioa.moder, &mut gpioa.afrl);
+
+    let display_cs = gpioa
+        .pa4
+        .into_push_pull_output(&mut gpioa.moder, &mut gpioa.otyper);
+    let flash_cs = gpiob
+        .pb0
+        .into_push_pull_output(&mut gpiob.moder, &mut gpiob.otyper);
+
+    let spi = Spi::spi1(
+        p.SPI1,
+        (sck, miso, mosi),
+        MODE_0,
+        1.mhz(),
+        clocks,
+        &mut rcc.apb2,
+    );
+    let bus = SharedBus::new(spi);
+
+    let mut display = bus
+        .device(
+            display_cs,
+            Some(DeviceConfig {
+                mode: MODE_0,
+                freq: 8.mhz().into(),
+                clocks,
+            }),
+        )
+        .unwrap();
+    let mut flash_chip = bus
+        .device(
+            flash_cs,
+            Some(DeviceConfig {
+                mode: MODE_3,
+                freq: 1.mhz().into(),
+                clocks,
+            }),
+        )
+        .unwrap();
+
+    // Clear a few pixels of the display
+    display.write(&[0x00; 16]).unwrap();
+
+    // Read the manufacturer and device ID of the flash
+    let mut id = [READ_ID, 0, 0, 0];
+    flash_chip.transfer(&mut id).unwrap();
+
+    // when you reach this breakpoint you'll be able to inspect the ID of the flash in `id`
+    asm::bkpt();
+
+    loop {
+        continue;
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
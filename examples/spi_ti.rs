@@ -0,0 +1,68 @@
+//! Reads a converter speaking the TI synchronous serial frame format
+//!
+//! The converter starts a conversion on every NSS pulse and shifts the previous result out
+//! during the frame, so the first byte read is stale and the following ones are the samples.
+#![deny(unsafe_code)]
+#![deny(warnings)]
+#![no_main]
+#![no_std]
+
+extern crate cortex_m;
+#[macro_use(entry, exception)]
+extern crate cortex_m_rt as rt;
+extern crate cortex_m_semihosting as sh;
+extern crate panic_semihosting;
+extern crate stm32l4xx_hal as hal;
+
+use crate::hal::delay::Delay;
+use crate::hal::prelude::*;
+use crate::hal::spi::{FrameFormat, Spi};
+use crate::rt::ExceptionFrame;
+use crate::sh::hio;
+use core::fmt::Write;
+
+#[entry]
+fn main() -> ! {
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let p = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = p.FLASH.constrain();
+    let mut rcc = p.RCC.constrain();
+    let mut pwr = p.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+
+    let mut gpioa = p.GPIOA.split(&mut rcc.ahb2);
+
+    let sck = gpioa.pa5.into_af5(&mut gpioa.moder, &mut gpioa.afrl);
+    let miso = gpioa.pa6.into_af5(&mut gpioa.moder, &mut gpioa.afrl);
+    let mosi = gpioa.pa7.into_af5(&mut gpioa.moder, &mut gpioa.afrl);
+    // NSS is the frame sync of the converter
+    let nss = gpioa.pa4.into_af5(&mut gpioa.moder, &mut gpioa.afrl);
+
+    let mut spi = Spi::spi1_ti(
+        p.SPI1,
+        (sck, miso, mosi, nss),
+        1.mhz(),
+        clocks,
+        &mut rcc.apb2,
+    );
+    assert_eq!(spi.frame_format(), FrameFormat::Ti);
+
+    let mut delay = Delay::new(cp.SYST, clocks);
+    let mut hstdout = hio::hstdout().unwrap();
+
+    loop {
+        let mut samples = [0; 4];
+        spi.transaction(|spi| spi.transfer(&mut samples).map(|_| ()))
+            .unwrap();
+        writeln!(hstdout, "samples: {:?}", &samples[1..]).unwrap();
+
+        delay.delay_ms(500_u32);
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
@@ -0,0 +1,108 @@
+//! Sleeps in Stop 2 until either the button on PC13 is pressed or an RTC alarm fires
+//!
+//! The interrupts stay disabled, so no handler runs: the woken up source is checked, and cleared,
+//! once `stop_until` returns.
+
+#![no_std]
+#![no_main]
+
+extern crate cortex_m;
+#[macro_use]
+extern crate cortex_m_rt as rt;
+extern crate cortex_m_semihosting as sh;
+extern crate panic_semihosting;
+extern crate stm32l4xx_hal as hal;
+
+use crate::hal::datetime::{Date, Time};
+use crate::hal::gpio::{Edge, ExtiPin};
+use crate::hal::prelude::*;
+use crate::hal::pwr::StopMode;
+use crate::hal::rcc::{ClockSecuritySystem, CrystalBypass};
+use crate::hal::rtc::{Alarm, AlarmDay, Event, Rtc, RtcClockSource, RtcConfig};
+use crate::rt::ExceptionFrame;
+use cortex_m::interrupt;
+use cortex_m::peripheral::NVIC;
+use hal::pac;
+
+use crate::sh::hio;
+use core::fmt::Write;
+
+#[entry]
+fn main() -> ! {
+    let mut hstdout = hio::hstdout().unwrap();
+
+    let mut cp = cortex_m::Peripherals::take().unwrap();
+    let mut dp = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    rcc.cfgr
+        .lse(CrystalBypass::Disable, ClockSecuritySystem::Disable)
+        .freeze(&mut flash.acr, &mut pwr);
+
+    let mut rtc = Rtc::rtc(
+        dp.RTC,
+        &mut rcc.apb1r1,
+        &mut rcc.bdcr,
+        &mut pwr.cr1,
+        RtcConfig::default().clock_config(RtcClockSource::LSE),
+    );
+
+    let time = Time::new(21.hours(), 57.minutes(), 32.seconds(), 0.micros(), false);
+    let date = Date::new(1.day(), 24.date(), 4.month(), 2018.year());
+    rtc.set_date_time(date, time);
+
+    let mut gpioc = dp.GPIOC.split(&mut rcc.ahb2);
+    let mut button = gpioc
+        .pc13
+        .into_pull_up_input(&mut gpioc.moder, &mut gpioc.pupdr);
+    button.make_interrupt_source(&mut dp.SYSCFG, &mut rcc.apb2);
+    button.trigger_on_edge(&mut dp.EXTI, Edge::Falling);
+
+    // The interrupts have to be unmasked in the NVIC to wake the MCU up, but stay disabled in
+    // the core so their handlers don't run
+    interrupt::disable();
+    unsafe {
+        NVIC::unmask(pac::Interrupt::EXTI15_10);
+        NVIC::unmask(pac::Interrupt::RTC_ALARM);
+    }
+
+    loop {
+        // Wake up at the next minute, at the latest
+        let (_, now) = rtc.get_date_time();
+        let next = (now.hours * 60 + now.minutes + 1) % (24 * 60);
+        let alarm = Time::new(
+            (next / 60).hours(),
+            (next % 60).minutes(),
+            0.seconds(),
+            0.micros(),
+            false,
+        );
+        rtc.set_alarm_on(Alarm::AlarmA, AlarmDay::EveryDay, alarm);
+
+        pwr.stop_until(
+            StopMode::Stop2,
+            &mut cp.SCB,
+            &mut dp.EXTI,
+            &mut [&mut button, &mut rtc.wakeup_source(Event::AlarmA)],
+        );
+
+        if button.check_interrupt() {
+            button.clear_interrupt_pending_bit();
+            writeln!(hstdout, "Woken up by the button").unwrap();
+        }
+        if rtc.check_interrupt(Event::AlarmA, true) {
+            writeln!(hstdout, "Woken up by the alarm").unwrap();
+        }
+
+        NVIC::unpend(pac::Interrupt::EXTI15_10);
+        NVIC::unpend(pac::Interrupt::RTC_ALARM);
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
@@ -0,0 +1,57 @@
+//! Waits 5 minutes in a single period of the 32-bit TIM2, without extending the counter
+#![deny(unsafe_code)]
+#![deny(warnings)]
+#![no_main]
+#![no_std]
+
+#[macro_use(entry, exception)]
+extern crate cortex_m_rt as rt;
+extern crate cortex_m;
+extern crate cortex_m_semihosting as sh;
+#[macro_use(block)]
+extern crate nb;
+extern crate panic_semihosting;
+extern crate stm32l4xx_hal as hal;
+
+use crate::hal::prelude::*;
+use crate::hal::time::MicroSeconds;
+use crate::hal::timer::Counter32;
+use crate::rt::ExceptionFrame;
+use crate::sh::hio;
+use core::fmt::Write;
+
+/// 5 minutes, far beyond the 65 ms of a 16-bit timer at 1 MHz
+const DELAY: MicroSeconds = MicroSeconds(5 * 60 * 1_000_000);
+
+#[entry]
+fn main() -> ! {
+    let p = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = p.FLASH.constrain();
+    let mut rcc = p.RCC.constrain();
+    let mut pwr = p.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc.cfgr.sysclk(80.mhz()).freeze(&mut flash.acr, &mut pwr);
+
+    let mut counter = Counter32::tim2(p.TIM2, clocks, &mut rcc.apb1r1);
+    let mut hstdout = hio::hstdout().unwrap();
+
+    counter.start(DELAY);
+    writeln!(hstdout, "Period of {} µs", counter.auto_reload() + 1).unwrap();
+
+    // The counter is still running halfway through, it didn't wrap at 16 bits
+    while counter.count() < DELAY.0 / 2 {}
+    writeln!(hstdout, "Halfway, at {} µs", counter.count()).unwrap();
+
+    block!(counter.wait()).unwrap();
+    writeln!(hstdout, "5 minutes elapsed").unwrap();
+
+    loop {
+        continue;
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
@@ -0,0 +1,63 @@
+//! Measures how long TIM2 runs, with TIM5 counting only while it does
+//!
+//! TIM2 sends its counter enable on TRGO, which gates the counter of TIM5 through its internal
+//! trigger 0.
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+extern crate cortex_m;
+#[macro_use]
+extern crate cortex_m_rt as rt;
+extern crate cortex_m_semihosting as sh;
+extern crate panic_semihosting;
+extern crate stm32l4xx_hal as hal;
+
+use crate::hal::prelude::*;
+use crate::hal::stm32::TIM5;
+use crate::hal::timer::{MasterMode, PeriodicTimer, SlaveMode, Timer, TriggerInput};
+use crate::rt::ExceptionFrame;
+use crate::sh::hio;
+use core::fmt::Write;
+
+#[entry]
+fn main() -> ! {
+    let dp = hal::stm32::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+
+    // Counts microseconds, as long as TIM2 is enabled
+    let mut counter = Timer::free_running_tim5(dp.TIM5, clocks, 1.mhz(), false, &mut rcc.apb1r1);
+    counter.set_slave_mode(TriggerInput::Itr0, SlaveMode::Gated);
+    let before = Timer::<TIM5>::count();
+
+    // Runs for 3 periods of 100 ms
+    let mut gate = Timer::tim2(dp.TIM2, 10.hz(), clocks, &mut rcc.apb1r1);
+    gate.set_master_mode(MasterMode::Enable);
+    for _ in 0..3 {
+        nb::block!(PeriodicTimer::wait(&mut gate)).unwrap();
+    }
+    PeriodicTimer::cancel(&mut gate);
+
+    let gated = Timer::<TIM5>::count().wrapping_sub(before);
+
+    let mut hstdout = hio::hstdout().unwrap();
+    writeln!(hstdout, "TIM2 ran for {} µs", gated).unwrap();
+
+    // The counter holds its value while TIM2 is stopped
+    cortex_m::asm::delay(1_000_000);
+    assert_eq!(Timer::<TIM5>::count().wrapping_sub(before), gated);
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+#[exception]
+fn HardFault(ef: &ExceptionFrame) -> ! {
+    panic!("{:#?}", ef);
+}
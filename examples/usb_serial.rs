@@ -6,22 +6,12 @@
 extern crate panic_semihosting;
 
 use cortex_m_rt::entry;
+use stm32l4xx_hal::crs::{self, Crs};
 use stm32l4xx_hal::usb::{Peripheral, UsbBus};
 use stm32l4xx_hal::{prelude::*, stm32};
 use usb_device::prelude::*;
 use usbd_serial::{SerialPort, USB_CLASS_CDC};
 
-fn enable_crs() {
-    let rcc = unsafe { &(*stm32::RCC::ptr()) };
-    rcc.apb1enr1.modify(|_, w| w.crsen().set_bit());
-    let crs = unsafe { &(*stm32::CRS::ptr()) };
-    // Initialize clock recovery
-    // Set autotrim enabled.
-    crs.cr.modify(|_, w| w.autotrimen().set_bit());
-    // Enable CR
-    crs.cr.modify(|_, w| w.cen().set_bit());
-}
-
 /// Enables VddUSB power supply
 fn enable_usb_pwr() {
     // Enable PWR peripheral
@@ -49,7 +39,8 @@ fn main() -> ! {
         .pclk2(24.mhz())
         .freeze(&mut flash.acr, &mut pwr);
 
-    enable_crs();
+    // Trim the HSI48 against the USB SOF, no crystal needed
+    let _crs = Crs::new(dp.CRS, crs::Config::usb_sof(), &mut rcc.apb1r1);
 
     // disable Vddusb power isolation
     enable_usb_pwr();
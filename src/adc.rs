@@ -1,21 +1,52 @@
 //! # Analog to Digital converter
 
+use as_slice::AsMutSlice;
 use core::convert::Infallible;
+use core::ops::DerefMut;
 use core::ptr;
+use core::sync::atomic::{self, Ordering};
+use stable_deref_trait::StableDeref;
 
 use crate::{
+    dma::{dma1, CircBuffer},
     gpio::{self, Analog},
     hal::{
         adc::{Channel as EmbeddedHalChannel, OneShot},
         blocking::delay::DelayUs,
     },
     pac,
-    rcc::{AHB2, CCIPR},
+    rcc::{Clocks, AHB2, CCIPR},
     signature::{VrefCal, VtempCal130, VtempCal30, VDDA_CALIB_MV},
+    time::Hertz,
 };
 
+#[cfg(any(
+    feature = "stm32l4x1",
+    feature = "stm32l4x2",
+    feature = "stm32l4x5",
+    feature = "stm32l4x6",
+))]
+use pac::ADC2;
 use pac::{ADC1, ADC_COMMON};
 
+/// ADC error
+#[non_exhaustive]
+#[derive(Debug)]
+pub enum Error {
+    /// No conversion was started, see [`ADC::start_conversion`]
+    NotStarted,
+    /// The four offset registers are used by other channels, see [`ADC::set_offset`]
+    NoOffsetLeft,
+    /// A result was overwritten before it was read, see [`ADC::read_pair`]
+    Overrun,
+}
+
+/// Maximum ADC clock frequency, from the datasheet
+const MAX_FREQUENCY: u32 = 80_000_000;
+
+/// Number of ADC channels, including the internal ones
+const CHANNELS: usize = 19;
+
 /// Vref internal signal, used for calibration
 pub struct Vref;
 
@@ -30,8 +61,11 @@ pub struct ADC {
     adc: ADC1,
     common: ADC_COMMON,
     resolution: Resolution,
+    alignment: Alignment,
     sample_time: SampleTime,
+    channel_sample_times: [Option<SampleTime>; CHANNELS],
     calibrated_vdda: u32,
+    discard_conversion: bool,
 }
 
 impl ADC {
@@ -88,8 +122,11 @@ impl ADC {
             adc,
             common,
             resolution: Resolution::default(),
+            alignment: Alignment::default(),
             sample_time: SampleTime::default(),
+            channel_sample_times: [None; CHANNELS],
             calibrated_vdda: VDDA_CALIB_MV,
+            discard_conversion: false,
         };
 
         // Temporarily enable Vref
@@ -132,31 +169,565 @@ impl ADC {
     /// this before each ADC conversion.
     pub fn calibrate(&mut self, vref: &mut Vref) {
         let vref_cal = VrefCal::get().read();
-        let old_sample_time = self.sample_time;
+        let old_resolution = self.resolution;
+        let old_alignment = self.alignment;
 
-        // "Table 24. Embedded internal voltage reference" states that the sample time needs to be
-        // at a minimum 4 us. With 640.5 ADC cycles we have a minimum of 8 us at 80 MHz, leaving
-        // some headroom.
-        self.set_sample_time(SampleTime::Cycles640_5);
+        // The factory value is a right aligned 12-bit sample
+        self.set_resolution(Resolution::Bits12);
+        self.set_alignment(Alignment::Right);
 
+        // The sample time of `Vref` never goes below its minimum, see `Channel::min_sample_time`.
         // This can't actually fail, it's just in a result to satisfy hal trait
         let vref_samp = self.read(vref).unwrap();
 
-        self.set_sample_time(old_sample_time);
+        self.set_resolution(old_resolution);
+        self.set_alignment(old_alignment);
 
         self.calibrated_vdda = (VDDA_CALIB_MV * u32::from(vref_cal)) / u32::from(vref_samp);
     }
 
     /// Set the ADC resolution
+    ///
+    /// A lower resolution shortens the conversion time, see [`Resolution`].
     pub fn set_resolution(&mut self, resolution: Resolution) {
         self.resolution = resolution;
     }
 
-    /// Set the sample time
+    /// Set the alignment of the conversion results
+    pub fn set_alignment(&mut self, alignment: Alignment) {
+        self.alignment = alignment;
+    }
+
+    /// Set the sample time of all channels without their own, see [`ADC::set_channel_sample_time`]
     pub fn set_sample_time(&mut self, sample_time: SampleTime) {
         self.sample_time = sample_time;
     }
 
+    /// Set the sample time of a single channel, overriding the one of [`ADC::set_sample_time`]
+    ///
+    /// The internal channels are never sampled faster than their minimum, see
+    /// [`Channel::min_sample_time`]. Sources with a high impedance need a longer sample time, see
+    /// [`SampleTime`].
+    pub fn set_channel_sample_time<C>(&mut self, _channel: &C, sample_time: SampleTime)
+    where
+        C: Channel,
+    {
+        self.channel_sample_times[usize::from(C::channel())] = Some(sample_time);
+    }
+
+    /// Select the ADC clock and its divider, returns the resulting clock frequency
+    ///
+    /// A faster ADC clock shortens the conversion, which takes the sample time plus 12.5 cycles at
+    /// 12 bits, but also the [`SampleTime`] in cycles: a source with a high impedance then needs a
+    /// longer sample time to settle. E.g. an 80 MHz clock converts in ~0.19 us at 2.5 cycles,
+    /// while a 10 kOhm source needs 47.5 cycles there, where a 20 MHz clock gives it the same
+    /// time in 12.5 cycles.
+    ///
+    /// The clock is shared with ADC2, if any.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the ADC is enabled, if `prescaler` is not available for `clock` (see
+    /// [`AdcClock::Hclk`]), or if the resulting clock is beyond the 80 MHz maximum of the
+    /// datasheet.
+    pub fn set_clock(
+        &mut self,
+        clock: AdcClock,
+        prescaler: Prescaler,
+        ccipr: &mut CCIPR,
+        clocks: Clocks,
+    ) -> Hertz {
+        assert!(
+            self.adc.cr.read().aden().bit_is_clear(),
+            "ADC clock changed while the ADC is enabled"
+        );
+
+        let (adcsel, ckmode, frequency) = match clock {
+            AdcClock::SysClk => (0b11, 0b00, clocks.sysclk().0 / prescaler.divisor()),
+            AdcClock::PllSai1R(frequency) => (0b01, 0b00, frequency.0 / prescaler.divisor()),
+            AdcClock::Hclk => {
+                let ckmode = match prescaler {
+                    Prescaler::Div1 => {
+                        assert!(
+                            clocks.hclk().0 == clocks.sysclk().0,
+                            "undivided ADC clock from a divided AHB clock"
+                        );
+                        0b01
+                    }
+                    Prescaler::Div2 => 0b10,
+                    Prescaler::Div4 => 0b11,
+                    _ => panic!("ADC prescaler unavailable for the AHB clock"),
+                };
+                (0b11, ckmode, clocks.hclk().0 / prescaler.divisor())
+            }
+        };
+        assert!(frequency <= MAX_FREQUENCY, "impossible ADC clock");
+
+        ccipr.ccipr().modify(|_, w| {
+            // This is sound, as `0b01` and `0b11` are valid values for this field.
+            unsafe { w.adcsel().bits(adcsel) }
+        });
+        // The PRESC field is missing from the stm32l4x5 PAC, so it is written by its offset.
+        self.common.ccr.modify(|r, w| {
+            // This is sound, as all values used are valid for these fields.
+            unsafe {
+                w.bits((r.bits() & !(0b1111 << 18)) | ((prescaler as u32) << 18));
+                w.ckmode().bits(ckmode)
+            }
+        });
+
+        Hertz(frequency)
+    }
+
+    /// Subtract `offset` from every conversion of `channel`, in hardware
+    ///
+    /// `offset` is in 12 bit counts whatever the resolution, its bits below the current
+    /// resolution are cleared. Up to four channels can have an offset, setting it again for a channel replaces
+    /// the previous one.
+    ///
+    /// The result of the subtraction is signed, it goes below zero for an input under the offset.
+    /// The sign is extended to the upper bits of the sample, so it is read as `sample as i16`,
+    /// scaled by the left alignment if it is used. [`ADC::to_millivolts`] does not apply to
+    /// such samples.
+    ///
+    /// In differential mode, which this driver does not configure, the offset is subtracted from
+    /// the raw differential result, which is centered on half the range: an offset of `0x800`
+    /// gives a result signed around zero.
+    ///
+    /// Returns [`Error::NoOffsetLeft`] if the four offsets are used by other channels.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` does not fit in 12 bits.
+    pub fn set_offset<C>(&mut self, _channel: &C, offset: u16) -> Result<(), Error>
+    where
+        C: Channel,
+    {
+        assert!(offset <= 0xfff, "offset beyond 12 bits");
+
+        let offsets = self.offsets();
+        let index = offsets
+            .iter()
+            .position(|&(enabled, channel)| enabled && channel == C::channel())
+            .or_else(|| offsets.iter().position(|&(enabled, _)| !enabled))
+            .ok_or(Error::NoOffsetLeft)?;
+
+        // The bits below the resolution must be kept cleared
+        let unused_bits = 12 - self.resolution.to_max_count().count_ones();
+        let offset = offset & !((1 << unused_bits) - 1);
+        self.write_offset(index, true, C::channel(), offset);
+
+        Ok(())
+    }
+
+    /// Stop subtracting an offset from the conversions of `channel`, see [`ADC::set_offset`]
+    pub fn clear_offset<C>(&mut self, _channel: &C)
+    where
+        C: Channel,
+    {
+        let offsets = self.offsets();
+        if let Some(index) = offsets
+            .iter()
+            .position(|&(enabled, channel)| enabled && channel == C::channel())
+        {
+            self.write_offset(index, false, 0, 0);
+        }
+    }
+
+    /// Returns whether each offset register is enabled, and the channel it is assigned to
+    fn offsets(&self) -> [(bool, u8); 4] {
+        let ofr1 = self.adc.ofr1.read();
+        let ofr2 = self.adc.ofr2.read();
+        let ofr3 = self.adc.ofr3.read();
+        let ofr4 = self.adc.ofr4.read();
+
+        [
+            (ofr1.offset1_en().bit_is_set(), ofr1.offset1_ch().bits()),
+            (ofr2.offset2_en().bit_is_set(), ofr2.offset2_ch().bits()),
+            (ofr3.offset3_en().bit_is_set(), ofr3.offset3_ch().bits()),
+            (ofr4.offset4_en().bit_is_set(), ofr4.offset4_ch().bits()),
+        ]
+    }
+
+    /// Writes offset register `index`, which requires no conversion to be ongoing
+    fn write_offset(&mut self, index: usize, enable: bool, channel: u8, offset: u16) {
+        // This is sound, as all `Channel` implementations set valid channels, and the offset is
+        // checked to fit in 12 bits.
+        match index {
+            0 => self.adc.ofr1.write(|w| unsafe {
+                w.offset1_en()
+                    .bit(enable)
+                    .offset1_ch()
+                    .bits(channel)
+                    .offset1()
+                    .bits(offset)
+            }),
+            1 => self.adc.ofr2.write(|w| unsafe {
+                w.offset2_en()
+                    .bit(enable)
+                    .offset2_ch()
+                    .bits(channel)
+                    .offset2()
+                    .bits(offset)
+            }),
+            2 => self.adc.ofr3.write(|w| unsafe {
+                w.offset3_en()
+                    .bit(enable)
+                    .offset3_ch()
+                    .bits(channel)
+                    .offset3()
+                    .bits(offset)
+            }),
+            _ => self.adc.ofr4.write(|w| unsafe {
+                w.offset4_en()
+                    .bit(enable)
+                    .offset4_ch()
+                    .bits(channel)
+                    .offset4()
+                    .bits(offset)
+            }),
+        }
+    }
+
+    /// Returns the sample time used for conversions of `C`
+    fn sample_time_of<C>(&self) -> SampleTime
+    where
+        C: Channel,
+    {
+        self.channel_sample_times[usize::from(C::channel())]
+            .unwrap_or(self.sample_time)
+            .max(C::min_sample_time())
+    }
+
+    /// Start conversions of `channel` triggered by a timer or EXTI line
+    ///
+    /// Every `edge` on `trigger` starts exactly one regular conversion, giving jitter free
+    /// sampling at the rate of the trigger source. The results are requested from the DMA in
+    /// circular mode, set up the DMA with [`ADC::circ_read`] before calling this to not miss any
+    /// samples.
+    ///
+    /// Call [`ADC::stop_triggered`] before using the `OneShot` implementation again.
+    pub fn start_triggered<C>(&mut self, channel: &mut C, trigger: ExternalTrigger, edge: Edge)
+    where
+        C: Channel,
+    {
+        // Make sure bits are off
+        while self.adc.cr.read().addis().bit_is_set() {}
+
+        // Enable ADC
+        self.adc.isr.write(|w| w.adrdy().set_bit());
+        self.adc.cr.modify(|_, w| w.aden().set_bit());
+        while self.adc.isr.read().adrdy().bit_is_clear() {}
+
+        // Configure ADC
+        self.adc.cfgr.write(|w| {
+            // This is sound, as all `Resolution`, `ExternalTrigger` and `Edge` values are
+            // valid for these fields.
+            unsafe {
+                w.res()
+                    .bits(self.resolution as u8)
+                    .align()
+                    .bit(self.alignment == Alignment::Left)
+                    .extsel()
+                    .bits(trigger as u8)
+                    .exten()
+                    .bits(edge as u8);
+            }
+
+            // Circular DMA requests, one per conversion
+            w.dmaen().set_bit().dmacfg().set_bit()
+        });
+
+        // Configure channel
+        channel.set_sample_time(&self.adc, self.sample_time_of::<C>());
+
+        // Select channel
+        self.adc.sqr1.write(|w| {
+            // This is sound, as all `Channel` implementations set valid values.
+            unsafe {
+                w.sq1().bits(C::channel());
+            }
+
+            w
+        });
+
+        // Arm the hardware trigger
+        self.adc
+            .isr
+            .modify(|_, w| w.eos().set_bit().eoc().set_bit().ovr().set_bit());
+        self.adc.cr.modify(|_, w| w.adstart().set_bit());
+    }
+
+    /// Start converting `regular` and `injected` as a pair on every trigger, e.g. the voltage and
+    /// the current of a power meter
+    ///
+    /// Every `edge` on `trigger` starts a regular conversion of `regular`, which is followed
+    /// right away by an injected conversion of `injected` in the auto-injected mode (`JAUTO`),
+    /// so both samples belong to the same trigger event. Each pair is fetched with
+    /// [`ADC::read_pair`], there is no DMA request.
+    ///
+    /// # Trigger alignment
+    ///
+    /// The ADC converts one channel at a time, so `injected` is sampled one regular conversion
+    /// after `regular`: the sample time of `regular` plus the conversion time of the resolution,
+    /// 12.5 ADC clock cycles at 12 bits. With the default sample time at 80 MHz, this is 15
+    /// cycles or 187.5 ns, a phase shift of 0.003° at 50 Hz. The skew is the same for every pair,
+    /// so it can be compensated if needed. For samples taken at the same instant, see `DualAdc`
+    /// on the parts with ADC2.
+    ///
+    /// The triggers must be further apart than both conversions together, and the pair must be
+    /// read before the next trigger. The injected group has no trigger of its own in this mode.
+    ///
+    /// Call [`ADC::stop_triggered`] before using the `OneShot` implementation again.
+    pub fn start_triggered_pair<R, I>(
+        &mut self,
+        regular: &mut R,
+        injected: &mut I,
+        trigger: ExternalTrigger,
+        edge: Edge,
+    ) where
+        R: Channel,
+        I: Channel,
+    {
+        // Make sure bits are off
+        while self.adc.cr.read().addis().bit_is_set() {}
+
+        // Enable ADC
+        self.adc.isr.write(|w| w.adrdy().set_bit());
+        self.adc.cr.modify(|_, w| w.aden().set_bit());
+        while self.adc.isr.read().adrdy().bit_is_clear() {}
+
+        // Configure ADC
+        self.adc.cfgr.write(|w| {
+            // This is sound, as all `Resolution`, `ExternalTrigger` and `Edge` values are
+            // valid for these fields.
+            unsafe {
+                w.res()
+                    .bits(self.resolution as u8)
+                    .align()
+                    .bit(self.alignment == Alignment::Left)
+                    .extsel()
+                    .bits(trigger as u8)
+                    .exten()
+                    .bits(edge as u8);
+            }
+
+            // The injected group follows every regular conversion
+            w.jauto().set_bit()
+        });
+
+        // Configure channels
+        regular.set_sample_time(&self.adc, self.sample_time_of::<R>());
+        injected.set_sample_time(&self.adc, self.sample_time_of::<I>());
+
+        // Select channels, a single injected conversion without a trigger of its own
+        self.adc.sqr1.write(|w| {
+            // This is sound, as all `Channel` implementations set valid values.
+            unsafe {
+                w.sq1().bits(R::channel());
+            }
+
+            w
+        });
+        self.adc.jsqr.write(|w| {
+            // This is sound, as all `Channel` implementations set valid values, and `0b00`
+            // (hardware trigger disabled) and a length of 1 are valid.
+            unsafe {
+                w.jsq1().bits(I::channel()).jexten().bits(0b00).jl().bits(0);
+            }
+
+            w
+        });
+
+        // Arm the hardware trigger
+        self.adc.isr.write(|w| {
+            w.eos()
+                .set_bit()
+                .eoc()
+                .set_bit()
+                .jeos()
+                .set_bit()
+                .jeoc()
+                .set_bit()
+                .ovr()
+                .set_bit()
+        });
+        self.adc.cr.modify(|_, w| w.adstart().set_bit());
+    }
+
+    /// Returns the samples of the latest trigger, as `(regular, injected)`, see
+    /// [`ADC::start_triggered_pair`]
+    ///
+    /// Returns `WouldBlock` until both conversions of a trigger are done, and
+    /// [`Error::NotStarted`] if there are none. Returns [`Error::Overrun`] if the previous pair
+    /// wasn't read before the next trigger, as the samples are then of different triggers: that
+    /// pair is dropped, and the next call waits for a new one.
+    pub fn read_pair(&mut self) -> nb::Result<(u16, u16), Error> {
+        if self.adc.cr.read().aden().bit_is_clear() {
+            return Err(nb::Error::Other(Error::NotStarted));
+        }
+
+        let isr = self.adc.isr.read();
+        if isr.jeos().bit_is_clear() {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        let regular = self.adc.dr.read().bits() as u16;
+        let injected = self.adc.jdr1.read().jdata1().bits();
+        self.adc.isr.write(|w| {
+            w.eos()
+                .set_bit()
+                .eoc()
+                .set_bit()
+                .jeos()
+                .set_bit()
+                .jeoc()
+                .set_bit()
+                .ovr()
+                .set_bit()
+        });
+
+        // The regular result is kept on an overrun, while the injected one is overwritten
+        if isr.ovr().bit_is_set() {
+            return Err(nb::Error::Other(Error::Overrun));
+        }
+
+        Ok((regular, injected))
+    }
+
+    /// Stop triggered conversions started with [`ADC::start_triggered`] or
+    /// [`ADC::start_triggered_pair`]
+    pub fn stop_triggered(&mut self) {
+        // This also stops the injected conversions in the auto-injected mode
+        self.adc.cr.modify(|_, w| w.adstp().set_bit());
+        while self.adc.cr.read().adstp().bit_is_set() {}
+
+        self.adc.cfgr.modify(|_, w| {
+            // This is sound, as `0b00` (hardware trigger disabled) is a valid value.
+            unsafe {
+                w.exten().bits(0b00);
+            }
+
+            w.dmaen().clear_bit().jauto().clear_bit()
+        });
+
+        // Disable ADC
+        self.adc.cr.modify(|_, w| w.addis().set_bit());
+    }
+
+    /// Start converting `channel` continuously, in the background
+    ///
+    /// The ADC converts the channel again as soon as a conversion ends, and overwrites the
+    /// result with every new one, so [`ADC::latest`] always returns the most recent result
+    /// without waiting. This returns once the first conversion is done. The current resolution,
+    /// alignment and sample time apply, the sample time sets the rate of the conversions.
+    ///
+    /// Call [`ADC::stop_continuous`] before using the `OneShot` implementation again.
+    pub fn start_continuous<C>(&mut self, channel: &mut C)
+    where
+        C: Channel,
+    {
+        // Make sure bits are off
+        while self.adc.cr.read().addis().bit_is_set() {}
+
+        // Enable ADC
+        self.adc.isr.write(|w| w.adrdy().set_bit());
+        self.adc.cr.modify(|_, w| w.aden().set_bit());
+        while self.adc.isr.read().adrdy().bit_is_clear() {}
+
+        // Configure ADC
+        self.adc.cfgr.write(|w| {
+            // This is sound, as all `Resolution` values are valid for this
+            // field.
+            unsafe { w.res().bits(self.resolution as u8) }
+                .align()
+                .bit(self.alignment == Alignment::Left);
+
+            // Keep converting, and let new results overwrite the unread ones
+            w.cont().set_bit().ovrmod().set_bit()
+        });
+
+        // Configure channel
+        channel.set_sample_time(&self.adc, self.sample_time_of::<C>());
+
+        // Select channel
+        self.adc.sqr1.write(|w| {
+            // This is sound, as all `Channel` implementations set valid values.
+            unsafe {
+                w.sq1().bits(C::channel());
+            }
+
+            w
+        });
+
+        // Start conversions
+        self.adc
+            .isr
+            .modify(|_, w| w.eos().set_bit().eoc().set_bit().ovr().set_bit());
+        self.adc.cr.modify(|_, w| w.adstart().set_bit());
+
+        // Wait for a first result
+        while self.adc.isr.read().eoc().bit_is_clear() {}
+    }
+
+    /// Returns the result of the most recent conversion started by [`ADC::start_continuous`]
+    ///
+    /// The same result is returned again until the next conversion ends.
+    pub fn latest(&self) -> u16 {
+        self.adc.dr.read().bits() as u16
+    }
+
+    /// Stop continuous conversions started with [`ADC::start_continuous`]
+    pub fn stop_continuous(&mut self) {
+        self.adc.cr.modify(|_, w| w.adstp().set_bit());
+        while self.adc.cr.read().adstp().bit_is_set() {}
+
+        self.adc
+            .cfgr
+            .modify(|_, w| w.cont().clear_bit().ovrmod().clear_bit());
+
+        // Disable ADC
+        self.adc.cr.modify(|_, w| w.addis().set_bit());
+    }
+
+    /// Receive triggered conversion results into a circular, double-buffered DMA transfer
+    pub fn circ_read<B, H>(&self, mut chan: dma1::C1, mut buffer: B) -> CircBuffer<B, dma1::C1>
+    where
+        B: StableDeref<Target = [H; 2]> + DerefMut + 'static,
+        H: AsMutSlice<Element = u16>,
+    {
+        let buf = buffer[0].as_mut_slice();
+        chan.set_peripheral_address(&self.adc.dr as *const _ as u32, false);
+        chan.set_memory_address(buf.as_ptr() as u32, true);
+        chan.set_transfer_length((buf.len() * 2) as u16);
+
+        // Tell DMA to request from ADC1
+        chan.cselr().modify(|_, w| w.c1s().bits(0b0000));
+
+        chan.ccr().modify(|_, w| unsafe {
+            w.mem2mem()
+                .clear_bit()
+                // 00: 8-bits, 01: 16-bits, 10: 32-bits, 11: Reserved
+                .msize()
+                .bits(0b01)
+                // 00: 8-bits, 01: 16-bits, 10: 32-bits, 11: Reserved
+                .psize()
+                .bits(0b01)
+                .circ()
+                .set_bit()
+                .dir()
+                .clear_bit()
+        });
+
+        // NOTE(compiler_fence) operations on `buffer` should not be reordered after
+        // the next statement, which starts the DMA transfer
+        atomic::compiler_fence(Ordering::Release);
+
+        chan.start();
+
+        CircBuffer::new(buffer, chan)
+    }
+
     /// Release the ADC peripheral
     ///
     /// Drops `ADC` and returns the `(pac::ADC, pad::ADC_COMMON)` that is was wrapping, giving the
@@ -165,26 +736,47 @@ impl ADC {
         (self.adc, self.common)
     }
 
-    /// Convert a measurement to millivolts
+    /// Convert a measurement, taken with the current resolution and alignment, to millivolts
     pub fn to_millivolts(&self, sample: u16) -> u16 {
+        let sample = match self.alignment {
+            Alignment::Right => sample,
+            Alignment::Left => sample >> self.resolution.left_shift(),
+        };
+
         ((u32::from(sample) * self.calibrated_vdda) / self.resolution.to_max_count()) as u16
     }
 
-    /// Convert a raw sample from the `Temperature` to deg C
-    pub fn to_degrees_centigrade(sample: u16) -> f32 {
-        (130.0 - 30.0) / (VtempCal130::get().read() as f32 - VtempCal30::get().read() as f32)
-            * (sample as f32 - VtempCal30::get().read() as f32)
-            + 30.0
-    }
-}
+    /// Read a channel and convert the result to millivolts
+    ///
+    /// VDDA is measured against the internal reference right before the conversion, so the result
+    /// tracks a drifting supply:
+    ///
+    /// `VDDA = VDDA_CALIB_MV * VREFINT_CAL / VREFINT_DATA`
+    ///
+    /// `millivolts = VDDA * sample / max_count`
+    ///
+    /// where `VREFINT_CAL` is the factory calibration value, `VREFINT_DATA` the sampled `Vref`
+    /// and `max_count` the full scale value of the current resolution.
+    pub fn read_mv<C>(&mut self, vref: &mut Vref, channel: &mut C) -> u16
+    where
+        C: Channel,
+    {
+        self.calibrate(vref);
 
-impl<C> OneShot<ADC, u16, C> for ADC
-where
-    C: Channel,
-{
-    type Error = Infallible;
+        // This can't actually fail, it's just in a result to satisfy hal trait
+        let sample = self.read(channel).unwrap();
 
-    fn read(&mut self, channel: &mut C) -> nb::Result<u16, Self::Error> {
+        self.to_millivolts(sample)
+    }
+
+    /// Starts a single conversion of `channel`, without waiting for it
+    ///
+    /// The result is fetched with [`ADC::read_result`]. This is what the (blocking) `OneShot`
+    /// implementation does, so the current resolution, alignment and sample time apply.
+    pub fn start_conversion<C>(&mut self, channel: &mut C)
+    where
+        C: Channel,
+    {
         // Make sure bits are off
         while self.adc.cr.read().addis().bit_is_set() {}
 
@@ -198,10 +790,12 @@ where
             // This is sound, as all `Resolution` values are valid for this
             // field.
             unsafe { w.res().bits(self.resolution as u8) }
+                .align()
+                .bit(self.alignment == Alignment::Left)
         });
 
         // Configure channel
-        channel.set_sample_time(&self.adc, self.sample_time);
+        channel.set_sample_time(&self.adc, self.sample_time_of::<C>());
 
         // Select channel
         self.adc.sqr1.write(|w| {
@@ -213,23 +807,43 @@ where
             w
         });
 
+        // The errata states that if we do conversions slower than 1 kHz, the
+        // first read ADC value can be corrupted, so it is discarded by
+        // `read_result`, which then measures again.
+        self.discard_conversion = true;
+
         // Start conversion
         self.adc
             .isr
             .modify(|_, w| w.eos().set_bit().eoc().set_bit());
         self.adc.cr.modify(|_, w| w.adstart().set_bit());
-        while self.adc.isr.read().eos().bit_is_clear() {}
+    }
 
-        // Read ADC value first time and discard it, as per errata sheet.
-        // The errata states that if we do conversions slower than 1 kHz, the
-        // first read ADC value can be corrupted, so we discard it and measure again.
-        let _ = unsafe { ptr::read_volatile(&self.adc.dr.read().bits()) };
+    /// Returns the result of the conversion started by [`ADC::start_conversion`]
+    ///
+    /// Returns `WouldBlock` until the conversion is done, and [`Error::NotStarted`] if there is
+    /// none. The ADC is disabled again once the result is returned.
+    pub fn read_result(&mut self) -> nb::Result<u16, Error> {
+        if self.adc.cr.read().aden().bit_is_clear() {
+            return Err(nb::Error::Other(Error::NotStarted));
+        }
+        if self.adc.isr.read().eos().bit_is_clear() {
+            return Err(nb::Error::WouldBlock);
+        }
 
-        self.adc
-            .isr
-            .modify(|_, w| w.eos().set_bit().eoc().set_bit());
-        self.adc.cr.modify(|_, w| w.adstart().set_bit());
-        while self.adc.isr.read().eos().bit_is_clear() {}
+        if self.discard_conversion {
+            self.discard_conversion = false;
+
+            // Read ADC value first time and discard it, as per errata sheet.
+            let _ = unsafe { ptr::read_volatile(&self.adc.dr.read().bits()) };
+
+            self.adc
+                .isr
+                .modify(|_, w| w.eos().set_bit().eoc().set_bit());
+            self.adc.cr.modify(|_, w| w.adstart().set_bit());
+
+            return Err(nb::Error::WouldBlock);
+        }
 
         // Read ADC value
         let val = self.adc.dr.read().bits() as u16;
@@ -239,14 +853,250 @@ where
 
         Ok(val)
     }
+
+    /// Convert a raw sample from the `Temperature` to deg C
+    pub fn to_degrees_centigrade(sample: u16) -> f32 {
+        (130.0 - 30.0) / (VtempCal130::get().read() as f32 - VtempCal30::get().read() as f32)
+            * (sample as f32 - VtempCal30::get().read() as f32)
+            + 30.0
+    }
+}
+
+impl<C> OneShot<ADC, u16, C> for ADC
+where
+    C: Channel,
+{
+    type Error = Infallible;
+
+    fn read(&mut self, channel: &mut C) -> nb::Result<u16, Self::Error> {
+        self.start_conversion(channel);
+
+        loop {
+            match self.read_result() {
+                Ok(val) => return Ok(val),
+                Err(nb::Error::WouldBlock) => {}
+                Err(nb::Error::Other(_)) => unreachable!(),
+            }
+        }
+    }
+}
+
+/// ADC1 and ADC2 sampling simultaneously, in the regular simultaneous dual mode
+///
+/// ADC1 is the master and starts the conversions of both, so the two channels are sampled at
+/// the same instant, e.g. the current of two motor phases. The resolution, alignment and sample
+/// times of the master [`ADC`] apply to both.
+#[cfg(any(
+    feature = "stm32l4x1",
+    feature = "stm32l4x2",
+    feature = "stm32l4x5",
+    feature = "stm32l4x6",
+))]
+pub struct DualAdc {
+    master: ADC,
+    slave: ADC2,
+}
+
+#[cfg(any(
+    feature = "stm32l4x1",
+    feature = "stm32l4x2",
+    feature = "stm32l4x5",
+    feature = "stm32l4x6",
+))]
+impl DualAdc {
+    /// Powers up and calibrates ADC2, and pairs it with `master` in dual mode
+    pub fn new(master: ADC, slave: ADC2, delay: &mut impl DelayUs<u32>) -> Self {
+        // Same procedure as for ADC1 in `ADC::new`, the clock is shared
+        slave.cr.write(|w| w.deeppwd().clear_bit());
+        slave.cr.modify(|_, w| w.advregen().set_bit());
+        delay.delay_us(25);
+
+        slave
+            .cr
+            .modify(|_, w| w.adcal().set_bit().adcaldif().clear_bit());
+        while slave.cr.read().adcal().bit_is_set() {}
+        delay.delay_us(1);
+
+        // The dual mode can only be selected while both ADCs are disabled
+        while master.adc.cr.read().addis().bit_is_set() {}
+        // This is sound, as `0b00110` (regular simultaneous mode only) is a valid value.
+        #[cfg(not(feature = "stm32l4x5"))]
+        master
+            .common
+            .ccr
+            .modify(|_, w| unsafe { w.dual().bits(0b00110) });
+        #[cfg(feature = "stm32l4x5")]
+        master
+            .common
+            .ccr
+            .modify(|_, w| unsafe { w.mult().bits(0b00110) });
+
+        DualAdc { master, slave }
+    }
+
+    /// Starts a simultaneous conversion of `master_channel` on ADC1 and `slave_channel` on ADC2
+    ///
+    /// Both channels are sampled for the longer one of their sample times, so the conversions
+    /// stay aligned. The results are fetched with [`DualAdc::read_result`]. The internal
+    /// channels (`Vref`, `Temperature` and `Vbat`) are only connected to ADC1.
+    pub fn start_conversion<C1, C2>(&mut self, master_channel: &mut C1, _slave_channel: &mut C2)
+    where
+        C1: Channel,
+        C2: Channel,
+    {
+        let master = &self.master.adc;
+        let slave = &self.slave;
+        let sample_time = self
+            .master
+            .sample_time_of::<C1>()
+            .max(self.master.sample_time_of::<C2>());
+
+        // Make sure bits are off
+        while master.cr.read().addis().bit_is_set() || slave.cr.read().addis().bit_is_set() {}
+
+        // Enable both ADCs
+        for adc in [&**master, &**slave] {
+            adc.isr.write(|w| w.adrdy().set_bit());
+            adc.cr.modify(|_, w| w.aden().set_bit());
+            while adc.isr.read().adrdy().bit_is_clear() {}
+
+            adc.cfgr.write(|w| {
+                // This is sound, as all `Resolution` values are valid for this field.
+                unsafe { w.res().bits(self.master.resolution as u8) }
+                    .align()
+                    .bit(self.master.alignment == Alignment::Left)
+            });
+        }
+
+        // Configure and select the channels
+        master_channel.set_sample_time(master, sample_time);
+        set_sample_time(slave, C2::channel(), sample_time);
+        // This is sound, as all `Channel` implementations set valid values.
+        master
+            .sqr1
+            .write(|w| unsafe { w.sq1().bits(C1::channel()) });
+        slave.sqr1.write(|w| unsafe { w.sq1().bits(C2::channel()) });
+
+        // The first conversion is discarded as per errata sheet, see `ADC::start_conversion`
+        self.master.discard_conversion = true;
+
+        // Starting the master starts the slave
+        for adc in [&**master, &**slave] {
+            adc.isr.modify(|_, w| w.eos().set_bit().eoc().set_bit());
+        }
+        master.cr.modify(|_, w| w.adstart().set_bit());
+    }
+
+    /// Returns the results of the conversion started by [`DualAdc::start_conversion`], as
+    /// `(master, slave)`
+    ///
+    /// Both results are read at once from the common data register. Returns `WouldBlock` until
+    /// both conversions are done, and [`Error::NotStarted`] if there are none. The ADCs are
+    /// disabled again once the results are returned.
+    pub fn read_result(&mut self) -> nb::Result<(u16, u16), Error> {
+        let master = &self.master.adc;
+        let slave = &self.slave;
+
+        if master.cr.read().aden().bit_is_clear() {
+            return Err(nb::Error::Other(Error::NotStarted));
+        }
+        if master.isr.read().eos().bit_is_clear() || slave.isr.read().eos().bit_is_clear() {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        let cdr = self.master.common.cdr.read();
+        for adc in [&**master, &**slave] {
+            adc.isr.modify(|_, w| w.eos().set_bit().eoc().set_bit());
+        }
+
+        if self.master.discard_conversion {
+            self.master.discard_conversion = false;
+            master.cr.modify(|_, w| w.adstart().set_bit());
+
+            return Err(nb::Error::WouldBlock);
+        }
+
+        // Disable both ADCs
+        master.cr.modify(|_, w| w.addis().set_bit());
+        slave.cr.modify(|_, w| w.addis().set_bit());
+
+        Ok((cdr.rdata_mst().bits(), cdr.rdata_slv().bits()))
+    }
+
+    /// Converts `master_channel` and `slave_channel` simultaneously, blocking until done
+    pub fn read<C1, C2>(&mut self, master_channel: &mut C1, slave_channel: &mut C2) -> (u16, u16)
+    where
+        C1: Channel,
+        C2: Channel,
+    {
+        self.start_conversion(master_channel, slave_channel);
+
+        loop {
+            match self.read_result() {
+                Ok(values) => return values,
+                Err(nb::Error::WouldBlock) => {}
+                Err(nb::Error::Other(_)) => unreachable!(),
+            }
+        }
+    }
+
+    /// Returns the master ADC, e.g. to convert results with [`ADC::to_millivolts`]
+    pub fn master(&mut self) -> &mut ADC {
+        &mut self.master
+    }
+
+    /// Switches back to independent mode and releases both ADCs
+    pub fn free(self) -> (ADC, ADC2) {
+        while self.master.adc.cr.read().addis().bit_is_set()
+            || self.slave.cr.read().addis().bit_is_set()
+        {}
+
+        // This is sound, as `0b00000` (independent mode) is a valid value.
+        #[cfg(not(feature = "stm32l4x5"))]
+        self.master
+            .common
+            .ccr
+            .modify(|_, w| unsafe { w.dual().bits(0b00000) });
+        #[cfg(feature = "stm32l4x5")]
+        self.master
+            .common
+            .ccr
+            .modify(|_, w| unsafe { w.mult().bits(0b00000) });
+
+        (self.master, self.slave)
+    }
+}
+
+/// Sets the sample time of a channel by its number, for ADCs the `Channel` trait doesn't cover
+#[cfg(any(
+    feature = "stm32l4x1",
+    feature = "stm32l4x2",
+    feature = "stm32l4x5",
+    feature = "stm32l4x6",
+))]
+fn set_sample_time(adc: &pac::adc1::RegisterBlock, channel: u8, sample_time: SampleTime) {
+    let st = u32::from(sample_time as u8);
+
+    // This is sound, as the 3 bit fields accept all `SampleTime` values.
+    if channel < 10 {
+        let shift = 3 * u32::from(channel);
+        adc.smpr1
+            .modify(|r, w| unsafe { w.bits((r.bits() & !(0b111 << shift)) | (st << shift)) });
+    } else {
+        let shift = 3 * u32::from(channel - 10);
+        adc.smpr2
+            .modify(|r, w| unsafe { w.bits((r.bits() & !(0b111 << shift)) | (st << shift)) });
+    }
 }
 
 /// ADC resolution setting
 ///
-/// The default setting is 12 bits.
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+/// The default setting is 12 bits. A conversion takes 12.5, 10.5, 8.5 or 6.5 ADC clock cycles
+/// (plus the sample time) at 12, 10, 8 or 6 bits respectively.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd)]
 pub enum Resolution {
     /// 12-bit resolution
+    #[default]
     Bits12 = 0b00,
 
     /// 10-bit resolution
@@ -259,12 +1109,6 @@ pub enum Resolution {
     Bits6 = 0b11,
 }
 
-impl Default for Resolution {
-    fn default() -> Self {
-        Self::Bits12
-    }
-}
-
 impl Resolution {
     fn to_max_count(&self) -> u32 {
         match self {
@@ -274,14 +1118,189 @@ impl Resolution {
             Resolution::Bits6 => (1 << 6) - 1,
         }
     }
+
+    /// Shift of a left aligned result, 6-bit results are aligned to a byte
+    fn left_shift(&self) -> u8 {
+        match self {
+            Resolution::Bits12 => 4,
+            Resolution::Bits10 => 6,
+            Resolution::Bits8 => 8,
+            Resolution::Bits6 => 2,
+        }
+    }
+}
+
+/// ADC data alignment
+///
+/// The default setting is right alignment, where a sample ranges from 0 to the full scale value
+/// of the [`Resolution`]. With left alignment the most significant bit of a sample ends up in
+/// bit 15 (bit 7 for 6-bit resolution).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Alignment {
+    /// Right aligned data
+    #[default]
+    Right,
+
+    /// Left aligned data
+    Left,
+}
+
+/// External trigger for regular conversions
+///
+/// The available sources are listed in the "ADC1 - External triggers for regular channels" table
+/// of the reference manual.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ExternalTrigger {
+    /// Timer 1 capture/compare 1
+    Tim1Ch1 = 0b0000,
+
+    /// Timer 1 capture/compare 2
+    Tim1Ch2 = 0b0001,
+
+    /// Timer 1 capture/compare 3
+    Tim1Ch3 = 0b0010,
+
+    /// Timer 2 capture/compare 2
+    Tim2Ch2 = 0b0011,
+
+    /// Timer 3 trigger output
+    Tim3Trgo = 0b0100,
+
+    /// EXTI line 11
+    Exti11 = 0b0110,
+
+    /// Timer 8 trigger output
+    #[cfg(any(feature = "stm32l4x5", feature = "stm32l4x6"))]
+    Tim8Trgo = 0b0111,
+
+    /// Timer 8 trigger output 2
+    #[cfg(any(feature = "stm32l4x5", feature = "stm32l4x6"))]
+    Tim8Trgo2 = 0b1000,
+
+    /// Timer 1 trigger output
+    Tim1Trgo = 0b1001,
+
+    /// Timer 1 trigger output 2
+    Tim1Trgo2 = 0b1010,
+
+    /// Timer 2 trigger output
+    Tim2Trgo = 0b1011,
+
+    /// Timer 4 trigger output
+    #[cfg(any(feature = "stm32l4x5", feature = "stm32l4x6"))]
+    Tim4Trgo = 0b1100,
+
+    /// Timer 6 trigger output
+    Tim6Trgo = 0b1101,
+
+    /// Timer 15 trigger output
+    Tim15Trgo = 0b1110,
+
+    /// Timer 3 capture/compare 4
+    Tim3Ch4 = 0b1111,
+}
+
+/// Edge of the external trigger that starts a conversion
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Edge {
+    /// Trigger on the rising edge
+    Rising = 0b01,
+
+    /// Trigger on the falling edge
+    Falling = 0b10,
+
+    /// Trigger on both edges
+    RisingFalling = 0b11,
+}
+
+/// Clock of the ADC, see [`ADC::set_clock`]
+///
+/// The default setting is [`AdcClock::SysClk`], undivided.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum AdcClock {
+    /// The system clock, asynchronous to the AHB and divided by the [`Prescaler`]
+    #[default]
+    SysClk,
+    /// The R output of the PLLSAI1, running at the given frequency and divided by the
+    /// [`Prescaler`]
+    ///
+    /// The PLLSAI1 is not configured by this HAL, it has to be set up and enabled beforehand.
+    PllSai1R(Hertz),
+    /// The AHB clock, synchronous and divided by 1, 2 or 4
+    ///
+    /// Being in sync with the AHB, the latency of triggered conversions has no jitter. Only
+    /// [`Prescaler::Div1`], [`Prescaler::Div2`] and [`Prescaler::Div4`] apply, and `Div1` only
+    /// if the AHB clock is not divided from the system clock.
+    Hclk,
+}
+
+/// Divider of the ADC clock, see [`ADC::set_clock`]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Prescaler {
+    /// Clock not divided
+    #[default]
+    Div1,
+    /// Clock divided by 2
+    Div2,
+    /// Clock divided by 4
+    Div4,
+    /// Clock divided by 6
+    Div6,
+    /// Clock divided by 8
+    Div8,
+    /// Clock divided by 10
+    Div10,
+    /// Clock divided by 12
+    Div12,
+    /// Clock divided by 16
+    Div16,
+    /// Clock divided by 32
+    Div32,
+    /// Clock divided by 64
+    Div64,
+    /// Clock divided by 128
+    Div128,
+    /// Clock divided by 256
+    Div256,
+}
+
+impl Prescaler {
+    /// Division factor of the clock
+    pub fn divisor(self) -> u32 {
+        match self {
+            Prescaler::Div1 => 1,
+            Prescaler::Div2 => 2,
+            Prescaler::Div4 => 4,
+            Prescaler::Div6 => 6,
+            Prescaler::Div8 => 8,
+            Prescaler::Div10 => 10,
+            Prescaler::Div12 => 12,
+            Prescaler::Div16 => 16,
+            Prescaler::Div32 => 32,
+            Prescaler::Div64 => 64,
+            Prescaler::Div128 => 128,
+            Prescaler::Div256 => 256,
+        }
+    }
 }
 
 /// ADC sample time
 ///
 /// The default setting is 2.5 ADC clock cycles.
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+///
+/// During the sample time, the sampling capacitor `C_ADC` charges through the source impedance
+/// `R_AIN` and the internal switch resistance `R_ADC`. To settle within half an LSB at `N` bits,
+/// the sample time needs to be at least
+///
+/// `t_s >= (R_AIN + R_ADC) * C_ADC * ln(2^(N + 2))`
+///
+/// so it grows linearly with the source impedance. E.g. with `C_ADC` around 5 pF and `R_ADC`
+/// around 1 kOhm, a 10 kOhm source at 12 bits needs ~0.6 us, which is 47.5 cycles at 80 MHz. See
+/// the datasheet of your device for the exact values and the maximum `R_AIN` per sample time.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd)]
 pub enum SampleTime {
     /// 2.5 ADC clock cycles
+    #[default]
     Cycles2_5 = 0b000,
 
     /// 6.5 ADC clock cycles
@@ -306,15 +1325,14 @@ pub enum SampleTime {
     Cycles640_5 = 0b111,
 }
 
-impl Default for SampleTime {
-    fn default() -> Self {
-        Self::Cycles2_5
-    }
-}
-
 /// Implemented for all types that represent ADC channels
 pub trait Channel: EmbeddedHalChannel<ADC, ID = u8> {
     fn set_sample_time(&mut self, adc: &ADC1, sample_time: SampleTime);
+
+    /// Minimum sample time at the maximum ADC clock of 80 MHz, enforced for every conversion
+    fn min_sample_time() -> SampleTime {
+        SampleTime::Cycles2_5
+    }
 }
 
 macro_rules! adc_pins {
@@ -323,7 +1341,8 @@ macro_rules! adc_pins {
             $id:expr,
             $pin:ty,
             $smpr:ident,
-            $smp:ident;
+            $smp:ident
+            $(, $min:ident)?;
         )*
     ) => {
         $(
@@ -348,13 +1367,22 @@ macro_rules! adc_pins {
                         }
                     })
                 }
+
+                $(
+                    fn min_sample_time() -> SampleTime {
+                        SampleTime::$min
+                    }
+                )?
             }
         )*
     };
 }
 
 adc_pins!(
-    0,  Vref,              smpr1, smp0;
+    // "Table 24. Embedded internal voltage reference" states that the sample time needs to be
+    // at a minimum 4 us, the temperature sensor needs 5 us and Vbat 12 us. With 640.5 ADC cycles
+    // we have a minimum of 8 us at 80 MHz, the most we can get.
+    0,  Vref,              smpr1, smp0, Cycles640_5;
     1,  gpio::PC0<Analog>, smpr1, smp1;
     2,  gpio::PC1<Analog>, smpr1, smp2;
     3,  gpio::PC2<Analog>, smpr1, smp3;
@@ -371,6 +1399,29 @@ adc_pins!(
     14, gpio::PC5<Analog>, smpr2, smp14;
     15, gpio::PB0<Analog>, smpr2, smp15;
     16, gpio::PB1<Analog>, smpr2, smp16;
-    17, Temperature,       smpr2, smp17;
-    18, Vbat,              smpr2, smp18;
+    17, Temperature,       smpr2, smp17, Cycles640_5;
+    18, Vbat,              smpr2, smp18, Cycles640_5;
 );
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn left_alignment() {
+        // The most significant bit of a left aligned sample is bit 15, or bit 7 at 6 bits
+        for (resolution, msb) in [
+            (Resolution::Bits12, 15),
+            (Resolution::Bits10, 15),
+            (Resolution::Bits8, 15),
+            (Resolution::Bits6, 7),
+        ] {
+            let full_scale = resolution.to_max_count() << resolution.left_shift();
+            assert_eq!(31 - full_scale.leading_zeros(), msb, "{:?}", resolution);
+            assert_eq!(
+                full_scale >> resolution.left_shift(),
+                resolution.to_max_count()
+            );
+        }
+    }
+}
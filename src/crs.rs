@@ -0,0 +1,299 @@
+//! Clock recovery system (CRS)
+//!
+//! The CRS trims the HSI48 oscillator against a synchronization signal, which makes it accurate
+//! enough for USB without a crystal when synchronized on the USB start of frame (SOF) packets,
+//! sent every millisecond by the host.
+//!
+//! Enable the HSI48 with [`CFGR::hsi48`], which also selects it as the 48 MHz clock, and then
+//! start the CRS with [`Config::usb_sof`].
+//!
+//! [`CFGR::hsi48`]: crate::rcc::CFGR::hsi48
+
+use crate::rcc::APB1R1;
+use crate::stm32::CRS;
+use crate::time::Hertz;
+
+/// Frequency of the HSI48 oscillator
+const HSI48: u32 = 48_000_000;
+
+/// Frequency of the USB SOF packets
+const USB_SOF: u32 = 1_000;
+
+/// Trimming step of the HSI48, in hundredths of a percent
+const TRIM_STEP: u32 = 14;
+
+/// Synchronization signal source
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SyncSource {
+    /// GPIO, the CRS_SYNC alternate function
+    Gpio = 0b00,
+    /// LSE oscillator
+    Lse = 0b01,
+    /// USB start of frame
+    UsbSof = 0b10,
+}
+
+/// Synchronization signal divider
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SyncDivider {
+    Div1 = 0b000,
+    Div2 = 0b001,
+    Div4 = 0b010,
+    Div8 = 0b011,
+    Div16 = 0b100,
+    Div32 = 0b101,
+    Div64 = 0b110,
+    Div128 = 0b111,
+}
+
+/// Synchronization signal polarity
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SyncPolarity {
+    Rising,
+    Falling,
+}
+
+/// CRS configuration
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Config {
+    source: SyncSource,
+    divider: SyncDivider,
+    polarity: SyncPolarity,
+    reload: u16,
+    error_limit: u8,
+}
+
+impl Config {
+    /// Configuration for a synchronization signal of `sync` (after the divider)
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sync` is too slow for the reload value, i.e. below ~733 Hz.
+    pub fn new(source: SyncSource, sync: Hertz) -> Self {
+        Config {
+            source,
+            divider: SyncDivider::Div1,
+            polarity: SyncPolarity::Rising,
+            reload: reload_value(Hertz(HSI48), sync),
+            error_limit: error_limit(Hertz(HSI48), sync),
+        }
+    }
+
+    /// Configuration for synchronizing on the USB SOF packets, for crystal-less USB
+    pub fn usb_sof() -> Self {
+        Self::new(SyncSource::UsbSof, Hertz(USB_SOF))
+    }
+
+    /// Divides the synchronization signal
+    ///
+    /// The reload value is not updated, pass the divided frequency to [`Config::new`].
+    pub fn divider(mut self, divider: SyncDivider) -> Self {
+        self.divider = divider;
+        self
+    }
+
+    /// Synchronization signal polarity
+    pub fn polarity(mut self, polarity: SyncPolarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+}
+
+/// Reload value of the CRS counter, for a `target` frequency and a `sync` signal
+///
+/// The counter counts `target` clock cycles between two synchronization events, e.g. 47999 for a
+/// 48 MHz clock against the 1 kHz USB SOF.
+///
+/// # Panics
+///
+/// Panics if the value does not fit the 16 bit counter.
+pub fn reload_value(target: Hertz, sync: Hertz) -> u16 {
+    let reload = (target.0 + sync.0 / 2) / sync.0 - 1;
+    assert!(reload <= u32::from(u16::MAX));
+
+    reload as u16
+}
+
+/// Frequency error limit, for a `target` frequency and a `sync` signal
+///
+/// This is half of the counter cycles covered by one trimming step, e.g. 34 for a 48 MHz clock
+/// against the 1 kHz USB SOF.
+pub fn error_limit(target: Hertz, sync: Hertz) -> u8 {
+    let cycles = target.0 / sync.0;
+    let limit = (cycles * TRIM_STEP / 10_000).div_ceil(2);
+
+    limit.min(u32::from(u8::MAX)) as u8
+}
+
+/// CRS events
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+    /// Synchronized
+    SyncOk,
+    /// Synchronized, but the trimming is close to its limit
+    SyncWarning,
+    /// Expected synchronization, the counter reached zero
+    ExpectedSync,
+    /// Synchronization or trimming error, see [`Crs::check`]
+    Error,
+}
+
+/// CRS error
+#[non_exhaustive]
+#[derive(Debug)]
+pub enum Error {
+    /// The frequency error exceeded the trimming range
+    Sync,
+    /// A synchronization event was missed
+    SyncMissed,
+    /// The trimming value overflowed
+    TrimOverflow,
+}
+
+/// Clock recovery system
+pub struct Crs {
+    crs: CRS,
+}
+
+impl Crs {
+    /// Configures the CRS and starts automatic trimming
+    pub fn new(crs: CRS, config: Config, apb1r1: &mut APB1R1) -> Self {
+        apb1r1.enr().modify(|_, w| w.crsen().set_bit());
+
+        // The configuration can only be changed while the counter is disabled
+        crs.cr
+            .modify(|_, w| w.cen().clear_bit().autotrimen().clear_bit());
+
+        // This is sound, as all values are valid for these fields.
+        crs.cfgr.write(|w| unsafe {
+            w.syncsrc()
+                .bits(config.source as u8)
+                .syncdiv()
+                .bits(config.divider as u8)
+                .syncpol()
+                .bit(config.polarity == SyncPolarity::Falling)
+                .felim()
+                .bits(config.error_limit)
+                .reload()
+                .bits(config.reload)
+        });
+
+        crs.icr.write(|w| {
+            w.syncokc()
+                .set_bit()
+                .syncwarnc()
+                .set_bit()
+                .errc()
+                .set_bit()
+                .esyncc()
+                .set_bit()
+        });
+        crs.cr
+            .modify(|_, w| w.autotrimen().set_bit().cen().set_bit());
+
+        Crs { crs }
+    }
+
+    /// Starts listening for an `event`, on the `CRS` interrupt
+    pub fn listen(&mut self, event: Event) {
+        match event {
+            Event::SyncOk => self.crs.cr.modify(|_, w| w.syncokie().set_bit()),
+            Event::SyncWarning => self.crs.cr.modify(|_, w| w.syncwarnie().set_bit()),
+            Event::ExpectedSync => self.crs.cr.modify(|_, w| w.esyncie().set_bit()),
+            Event::Error => self.crs.cr.modify(|_, w| w.errie().set_bit()),
+        }
+    }
+
+    /// Stops listening for an `event`
+    pub fn unlisten(&mut self, event: Event) {
+        match event {
+            Event::SyncOk => self.crs.cr.modify(|_, w| w.syncokie().clear_bit()),
+            Event::SyncWarning => self.crs.cr.modify(|_, w| w.syncwarnie().clear_bit()),
+            Event::ExpectedSync => self.crs.cr.modify(|_, w| w.esyncie().clear_bit()),
+            Event::Error => self.crs.cr.modify(|_, w| w.errie().clear_bit()),
+        }
+    }
+
+    /// Checks if an `event` is pending
+    pub fn is_pending(&self, event: Event) -> bool {
+        let isr = self.crs.isr.read();
+        match event {
+            Event::SyncOk => isr.syncokf().bit_is_set(),
+            Event::SyncWarning => isr.syncwarnf().bit_is_set(),
+            Event::ExpectedSync => isr.esyncf().bit_is_set(),
+            Event::Error => isr.errf().bit_is_set(),
+        }
+    }
+
+    /// Clears a pending `event`
+    pub fn clear_pending(&mut self, event: Event) {
+        match event {
+            Event::SyncOk => self.crs.icr.write(|w| w.syncokc().set_bit()),
+            Event::SyncWarning => self.crs.icr.write(|w| w.syncwarnc().set_bit()),
+            Event::ExpectedSync => self.crs.icr.write(|w| w.esyncc().set_bit()),
+            Event::Error => self.crs.icr.write(|w| w.errc().set_bit()),
+        }
+    }
+
+    /// Returns and clears a synchronization or trimming error
+    ///
+    /// A [`Error::Sync`] or [`Error::TrimOverflow`] means the HSI48 is no longer trimmed, and
+    /// might be outside of the USB tolerance.
+    pub fn check(&mut self) -> Result<(), Error> {
+        let isr = self.crs.isr.read();
+        if isr.errf().bit_is_clear() {
+            return Ok(());
+        }
+
+        self.crs.icr.write(|w| w.errc().set_bit());
+
+        if isr.syncerr().bit_is_set() {
+            Err(Error::Sync)
+        } else if isr.trimovf().bit_is_set() {
+            Err(Error::TrimOverflow)
+        } else {
+            Err(Error::SyncMissed)
+        }
+    }
+
+    /// Returns the current HSI48 trimming value, 32 being the middle of the range
+    pub fn trim(&self) -> u8 {
+        self.crs.cr.read().trim().bits()
+    }
+
+    /// Stops trimming and releases the CRS peripheral
+    pub fn free(self) -> CRS {
+        self.crs
+            .cr
+            .modify(|_, w| w.cen().clear_bit().autotrimen().clear_bit());
+
+        self.crs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reload_values() {
+        assert_eq!(reload_value(Hertz(48_000_000), Hertz(1_000)), 47_999);
+        // 48 MHz / 32.768 kHz = 1464.8 cycles, rounded to the closest
+        assert_eq!(reload_value(Hertz(48_000_000), Hertz(32_768)), 1_464);
+        assert_eq!(reload_value(Hertz(65_536_000), Hertz(1_000)), u16::MAX);
+    }
+
+    #[test]
+    #[should_panic]
+    fn reload_value_too_large() {
+        reload_value(Hertz(48_000_000), Hertz(100));
+    }
+
+    #[test]
+    fn error_limits() {
+        assert_eq!(error_limit(Hertz(48_000_000), Hertz(1_000)), 34);
+        assert_eq!(error_limit(Hertz(48_000_000), Hertz(32_768)), 1);
+        // Saturated to the 8 bit field
+        assert_eq!(error_limit(Hertz(48_000_000), Hertz(10)), u8::MAX);
+    }
+}
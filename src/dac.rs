@@ -0,0 +1,180 @@
+//! Digital to analog converter (DAC)
+//!
+//! The outputs DAC1_OUT1 and DAC1_OUT2 are on PA4 and PA5, which have to be in analog mode when
+//! the output is connected to the pin.
+
+use crate::rcc::APB1R1;
+#[cfg(feature = "stm32l4x6")]
+use crate::stm32::DAC as DAC1;
+#[cfg(not(feature = "stm32l4x6"))]
+use crate::stm32::DAC1;
+
+/// DAC channel
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Channel {
+    /// Channel 1, output on PA4
+    One,
+    /// Channel 2, output on PA5
+    Two,
+}
+
+/// Output connection of a channel, see [`Dac::set_mode`]
+///
+/// The output buffer can drive the pin, while on-chip peripherals (comparators, op amps and the
+/// ADC) are connected directly. Without the buffer, the DAC has a high output impedance.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DacMode {
+    /// Normal mode, output on the pin through the buffer
+    Buffered,
+    /// Normal mode, output on the pin through the buffer, and to on-chip peripherals
+    BufferedToChip,
+    /// Normal mode, output on the pin without the buffer
+    Unbuffered,
+    /// Normal mode, output to on-chip peripherals only, without the buffer
+    ChipOnly,
+    /// Sample and hold mode, see [`SampleAndHold`]
+    SampleAndHold(SampleAndHold),
+}
+
+/// Output connection in sample and hold mode
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SampleAndHoldOutput {
+    /// Output on the pin through the buffer
+    Buffered = 0b100,
+    /// Output on the pin through the buffer, and to on-chip peripherals
+    BufferedToChip = 0b101,
+    /// Output on the pin and to on-chip peripherals, without the buffer
+    UnbufferedToChip = 0b110,
+    /// Output to on-chip peripherals only, without the buffer
+    ChipOnly = 0b111,
+}
+
+/// Sample and hold configuration, with the phases in LSI clock cycles
+///
+/// In sample and hold mode the DAC only drives the output during the sample phase and turns
+/// everything but the hold capacitor off during the hold phase, refreshing it periodically. As it
+/// is clocked by the LSI, which has to be enabled, this keeps the output valid in Stop mode at a
+/// much lower consumption.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SampleAndHold {
+    /// Output connection
+    pub output: SampleAndHoldOutput,
+    /// Duration of the sample phase, 0 to 1023 cycles
+    pub sample: u16,
+    /// Duration of the hold phase, 0 to 1023 cycles
+    pub hold: u16,
+    /// Duration of the refresh phase, 0 to 255 cycles
+    pub refresh: u8,
+}
+
+/// DAC peripheral
+pub struct Dac {
+    dac: DAC1,
+}
+
+impl Dac {
+    /// Enables and resets the DAC, both channels start disabled in [`DacMode::Buffered`] mode
+    pub fn new(dac: DAC1, apb1r1: &mut APB1R1) -> Self {
+        apb1r1.enr().modify(|_, w| w.dac1en().set_bit());
+        apb1r1.rstr().modify(|_, w| w.dac1rst().set_bit());
+        apb1r1.rstr().modify(|_, w| w.dac1rst().clear_bit());
+
+        Dac { dac }
+    }
+
+    /// Selects the output connection of a channel
+    ///
+    /// The mode can only be changed while the channel is disabled, so this disables it; enable
+    /// it again with [`Dac::enable`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sample and hold phases exceed their range.
+    pub fn set_mode(&mut self, channel: Channel, mode: DacMode) {
+        self.disable(channel);
+
+        let bits = match mode {
+            DacMode::Buffered => 0b000,
+            DacMode::BufferedToChip => 0b001,
+            DacMode::Unbuffered => 0b010,
+            DacMode::ChipOnly => 0b011,
+            DacMode::SampleAndHold(sh) => {
+                assert!(sh.sample < 1024 && sh.hold < 1024);
+
+                // The sample time can only be written once the previous write is done
+                match channel {
+                    Channel::One => {
+                        while self.dac.sr.read().bwst1().bit_is_set() {}
+                        // This is sound, as the sample time is limited to 10 bits.
+                        self.dac
+                            .shsr1
+                            .write(|w| unsafe { w.tsample1().bits(sh.sample) });
+                        self.dac
+                            .shhr
+                            .modify(|_, w| unsafe { w.thold1().bits(sh.hold) });
+                        self.dac
+                            .shrr
+                            .modify(|_, w| unsafe { w.trefresh1().bits(sh.refresh) });
+                    }
+                    Channel::Two => {
+                        while self.dac.sr.read().bwst2().bit_is_set() {}
+                        // This is sound, as the sample time is limited to 10 bits.
+                        self.dac
+                            .shsr2
+                            .write(|w| unsafe { w.tsample2().bits(sh.sample) });
+                        self.dac
+                            .shhr
+                            .modify(|_, w| unsafe { w.thold2().bits(sh.hold) });
+                        self.dac
+                            .shrr
+                            .modify(|_, w| unsafe { w.trefresh2().bits(sh.refresh) });
+                    }
+                }
+
+                sh.output as u8
+            }
+        };
+
+        // This is sound, as all modes are valid values for these fields.
+        match channel {
+            Channel::One => self.dac.mcr.modify(|_, w| unsafe { w.mode1().bits(bits) }),
+            Channel::Two => self.dac.mcr.modify(|_, w| unsafe { w.mode2().bits(bits) }),
+        }
+    }
+
+    /// Enables a channel
+    pub fn enable(&mut self, channel: Channel) {
+        match channel {
+            Channel::One => self.dac.cr.modify(|_, w| w.en1().set_bit()),
+            Channel::Two => self.dac.cr.modify(|_, w| w.en2().set_bit()),
+        }
+    }
+
+    /// Disables a channel
+    pub fn disable(&mut self, channel: Channel) {
+        match channel {
+            Channel::One => self.dac.cr.modify(|_, w| w.en1().clear_bit()),
+            Channel::Two => self.dac.cr.modify(|_, w| w.en2().clear_bit()),
+        }
+    }
+
+    /// Sets the output of a channel, as a right aligned 12-bit value
+    pub fn set_value(&mut self, channel: Channel, value: u16) {
+        // This is sound, as the value is limited to 12 bits.
+        match channel {
+            Channel::One => self
+                .dac
+                .dhr12r1
+                .write(|w| unsafe { w.dacc1dhr().bits(value & 0xfff) }),
+            Channel::Two => self
+                .dac
+                .dhr12r2
+                .write(|w| unsafe { w.dacc2dhr().bits(value & 0xfff) }),
+        }
+    }
+
+    /// Releases the DAC peripheral
+    pub fn free(self) -> DAC1 {
+        self.dac
+    }
+}
@@ -1,5 +1,9 @@
 //! Date and timer units & helper functions
 
+use core::convert::TryFrom;
+use core::ops::{Add, AddAssign, RangeInclusive, Sub, SubAssign};
+use core::time::Duration;
+
 /// Micors
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Micros(pub u32);
@@ -27,9 +31,84 @@ pub struct DateInMonth(pub u32);
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Week(pub u32);
 
-/// Month (1-12)
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub struct Month(pub u32);
+/// Month, numbered from 1 (January) to 12 (December) like the calendar of the RTC
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Month {
+    January = 1,
+    February,
+    March,
+    April,
+    May,
+    June,
+    July,
+    August,
+    September,
+    October,
+    November,
+    December,
+}
+
+impl Month {
+    const ALL: [Month; 12] = [
+        Month::January,
+        Month::February,
+        Month::March,
+        Month::April,
+        Month::May,
+        Month::June,
+        Month::July,
+        Month::August,
+        Month::September,
+        Month::October,
+        Month::November,
+        Month::December,
+    ];
+
+    /// Returns the number of the month, 1 to 12
+    pub const fn number(self) -> u8 {
+        self as u8
+    }
+
+    /// Month numbered `number`, which is known to be 1 to 12
+    fn from_valid(number: u32) -> Month {
+        Month::ALL[number as usize - 1]
+    }
+
+    /// Returns the number of days of the month in `year`
+    fn days(self, year: u32) -> u32 {
+        match self {
+            Month::February if is_leap_year(year) => 29,
+            Month::February => 28,
+            Month::April | Month::June | Month::September | Month::November => 30,
+            _ => 31,
+        }
+    }
+}
+
+/// Checks if `year` has a 29th of February, in the Gregorian calendar
+pub(crate) fn is_leap_year(year: u32) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+impl From<Month> for u8 {
+    fn from(month: Month) -> u8 {
+        month.number()
+    }
+}
+
+impl TryFrom<u8> for Month {
+    type Error = OutOfRange;
+
+    /// Converts the number of the month, failing unless it is 1 to 12
+    fn try_from(number: u8) -> Result<Month, OutOfRange> {
+        usize::from(number)
+            .checked_sub(1)
+            .and_then(|index| Month::ALL.get(index))
+            .copied()
+            .ok_or(OutOfRange)
+    }
+}
 
 /// Year
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -49,7 +128,11 @@ pub trait U32Ext {
     fn day(self) -> Day;
     /// Seconds
     fn date(self) -> DateInMonth;
-    /// Month
+    /// Month, see [`Month::try_from`] for a fallible conversion
+    ///
+    /// # Panics
+    ///
+    /// Panics unless the number is 1 to 12.
     fn month(self) -> Month;
     /// Year
     fn year(self) -> Year;
@@ -80,7 +163,10 @@ impl U32Ext for u32 {
     }
 
     fn month(self) -> Month {
-        Month(self)
+        u8::try_from(self)
+            .ok()
+            .and_then(|number| Month::try_from(number).ok())
+            .expect("month out of range")
     }
 
     fn year(self) -> Year {
@@ -113,13 +199,58 @@ impl Time {
             daylight_savings,
         }
     }
+
+    /// Formats the time as "HH:MM:SS" into `buf`, which needs at least 8 bytes
+    pub fn format_hms<'b>(&self, buf: &'b mut [u8]) -> Result<&'b str, BufferTooSmall> {
+        format_fields(
+            buf,
+            &[(self.hours, 2), (self.minutes, 2), (self.seconds, 2)],
+            b':',
+        )
+    }
+
+    /// Formats the time as "HH:MM:SS", see [`Time::format_hms`]
+    #[cfg(feature = "heapless")]
+    pub fn to_hms_string(&self) -> heapless::String<heapless::consts::U8> {
+        let mut buf = [0; 8];
+        // Can't panic, as the buffer and the string have the length of the formatted time.
+        let mut string = heapless::String::new();
+        string.push_str(self.format_hms(&mut buf).unwrap()).unwrap();
+
+        string
+    }
+
+    /// Parses a time formatted as "HH:MM:SS", e.g. by [`Time::format_hms`]
+    ///
+    /// Surrounding whitespace, e.g. the end of a line, is ignored. The sub-seconds and the
+    /// daylight saving bit are cleared.
+    pub fn parse_hms(s: &str) -> Result<Time, ParseError> {
+        let [hours, minutes, seconds] = parse_fields(s.trim(), b':', [2, 2, 2])?;
+        if hours > 23 || minutes > 59 || seconds > 59 {
+            return Err(ParseError::OutOfRange);
+        }
+
+        Ok(Time {
+            hours,
+            minutes,
+            seconds,
+            micros: 0,
+            daylight_savings: false,
+        })
+    }
 }
 
+/// Years the RTC can hold, its year field counts the years since 2000
+///
+/// The hardware takes every year whose two digits are divisible by 4 as a leap year, which
+/// matches the calendar only within this century.
+pub(crate) const RTC_YEARS: RangeInclusive<u32> = 2000..=2099;
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Date {
     pub day: u32,
     pub date: u32,
-    pub month: u32,
+    pub month: Month,
     pub year: u32,
 }
 
@@ -128,10 +259,520 @@ impl Date {
         Self {
             day: day.0,
             date: date.0,
-            month: month.0,
+            month,
             year: year.0,
         }
     }
+
+    /// Formats the date as "YYYY-MM-DD" into `buf`, which needs at least 10 bytes
+    pub fn format_iso<'b>(&self, buf: &'b mut [u8]) -> Result<&'b str, BufferTooSmall> {
+        format_fields(
+            buf,
+            &[
+                (self.year, 4),
+                (u32::from(self.month.number()), 2),
+                (self.date, 2),
+            ],
+            b'-',
+        )
+    }
+
+    /// Formats the date as "YYYY-MM-DD", see [`Date::format_iso`]
+    #[cfg(feature = "heapless")]
+    pub fn to_iso_string(&self) -> heapless::String<heapless::consts::U10> {
+        let mut buf = [0; 10];
+        // Can't panic, as the buffer and the string have the length of the formatted date.
+        let mut string = heapless::String::new();
+        string.push_str(self.format_iso(&mut buf).unwrap()).unwrap();
+
+        string
+    }
+
+    /// Parses a date formatted as "YYYY-MM-DD" (ISO 8601), e.g. by [`Date::format_iso`]
+    ///
+    /// Surrounding whitespace, e.g. the end of a line, is ignored. The weekday is computed from
+    /// the date. Only the years 2000 to 2099, which the RTC can hold, are accepted.
+    pub fn parse_iso(s: &str) -> Result<Date, ParseError> {
+        let [year, month, date] = parse_fields(s.trim(), b'-', [4, 2, 2])?;
+
+        // The month has 2 digits, so it fits
+        let month = Month::try_from(month as u8).map_err(|_| ParseError::OutOfRange)?;
+        if !RTC_YEARS.contains(&year) || date == 0 || date > month.days(year) {
+            return Err(ParseError::OutOfRange);
+        }
+
+        let mut parsed = Date {
+            day: 0,
+            date,
+            month,
+            year,
+        };
+        // 1970-01-01 was a Thursday, weekdays are numbered from Monday = 1
+        parsed.day = (parsed.days_since_epoch() + 3) % 7 + 1;
+
+        Ok(parsed)
+    }
+}
+
+/// Parses a date and time formatted as "YYYY-MM-DDTHH:MM:SS" (ISO 8601)
+///
+/// See [`Date::parse_iso`] and [`Time::parse_hms`], the date and time can also be separated by a
+/// space. A time zone is not accepted, the calendar of the RTC has none.
+pub fn parse_iso_date_time(s: &str) -> Result<(Date, Time), ParseError> {
+    let s = s.trim();
+    let separator = s.find(['T', ' ']).ok_or(ParseError::Format)?;
+    let (date, time) = s.split_at(separator);
+
+    Ok((Date::parse_iso(date)?, Time::parse_hms(&time[1..])?))
+}
+
+/// Error of the parsing helpers, e.g. [`Date::parse_iso`]
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParseError {
+    /// The string does not have the expected format
+    Format,
+    /// A field is out of its range, e.g. a 13th month or a 25th hour
+    OutOfRange,
+}
+
+/// The buffer passed to a formatting helper, e.g. [`Time::format_hms`], is too small
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BufferTooSmall;
+
+/// Writes `fields`, zero-padded to their number of digits, separated by `separator`
+fn format_fields<'b>(
+    buf: &'b mut [u8],
+    fields: &[(u32, usize)],
+    separator: u8,
+) -> Result<&'b str, BufferTooSmall> {
+    let len = fields.iter().map(|&(_, digits)| digits + 1).sum::<usize>() - 1;
+    let out = buf.get_mut(..len).ok_or(BufferTooSmall)?;
+
+    let mut end = 0;
+    for &(value, digits) in fields {
+        if end > 0 {
+            out[end] = separator;
+            end += 1;
+        }
+
+        let mut value = value;
+        for digit in out[end..end + digits].iter_mut().rev() {
+            *digit = b'0' + (value % 10) as u8;
+            value /= 10;
+        }
+        end += digits;
+    }
+
+    // Can't panic, as only ASCII digits and separators were written
+    Ok(core::str::from_utf8(out).unwrap())
+}
+
+/// Reads the `digits` long decimal fields of `s`, separated by `separator`
+fn parse_fields<const N: usize>(
+    s: &str,
+    separator: u8,
+    digits: [usize; N],
+) -> Result<[u32; N], ParseError> {
+    let bytes = s.as_bytes();
+    let len = digits.iter().map(|&digits| digits + 1).sum::<usize>() - 1;
+    if bytes.len() != len {
+        return Err(ParseError::Format);
+    }
+
+    let mut values = [0; N];
+    let mut start = 0;
+    for (value, &digits) in values.iter_mut().zip(digits.iter()) {
+        if start > 0 {
+            if bytes[start] != separator {
+                return Err(ParseError::Format);
+            }
+            start += 1;
+        }
+
+        for &digit in &bytes[start..start + digits] {
+            if !digit.is_ascii_digit() {
+                return Err(ParseError::Format);
+            }
+            *value = *value * 10 + u32::from(digit - b'0');
+        }
+        start += digits;
+    }
+
+    Ok(values)
+}
+
+impl Date {
+    /// Converts days since 1970-01-01 into a date
+    ///
+    /// Based on the `civil_from_days` algorithm from
+    /// http://howardhinnant.github.io/date_algorithms.html
+    pub(crate) fn from_days_since_epoch(days: u32) -> Self {
+        let z = days + 719_468;
+        let era = z / 146_097;
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let date = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        Self {
+            // 1970-01-01 was a Thursday, and Monday is day 1
+            day: (days + 3) % 7 + 1,
+            date,
+            month: Month::from_valid(month),
+            year,
+        }
+    }
+
+    /// Converts the date into days since 1970-01-01
+    ///
+    /// Based on the `days_from_civil` algorithm from
+    /// http://howardhinnant.github.io/date_algorithms.html
+    pub(crate) fn days_since_epoch(&self) -> u32 {
+        let month = u32::from(self.month.number());
+        let (y, m) = if month <= 2 {
+            (self.year - 1, month + 9)
+        } else {
+            (self.year, month - 3)
+        };
+        let era = y / 400;
+        let yoe = y - era * 400;
+        let doy = (153 * m + 2) / 5 + self.date - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+        era * 146_097 + doe - 719_468
+    }
+}
+
+/// Week of the month of a [`Transition`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WeekOfMonth {
+    First,
+    Second,
+    Third,
+    Fourth,
+    Last,
+}
+
+/// Daylight saving time transition, e.g. the last Sunday of March at 02:00
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transition {
+    /// Month, 1 to 12
+    pub month: u32,
+    /// Week of the month
+    pub week: WeekOfMonth,
+    /// Day of the week, 1 (Monday) to 7 (Sunday)
+    pub day: u32,
+    /// Hour of the transition, in the local time in effect before it
+    pub hour: u32,
+}
+
+impl Transition {
+    /// Day of the transition in `year`, as days since 1970-01-01
+    fn days_since_epoch(&self, year: u32) -> u32 {
+        let weekday = |days: u32| (days + 3) % 7 + 1;
+
+        match self.week {
+            WeekOfMonth::Last => {
+                let (year, month) = if self.month == 12 {
+                    (year + 1, 1)
+                } else {
+                    (year, self.month + 1)
+                };
+                let last =
+                    Date::new(1.day(), 1.date(), month.month(), year.year()).days_since_epoch() - 1;
+
+                last - (weekday(last) + 7 - self.day) % 7
+            }
+            week => {
+                let first = Date::new(1.day(), 1.date(), self.month.month(), year.year())
+                    .days_since_epoch();
+
+                first + (self.day + 7 - weekday(first)) % 7 + 7 * week as u32
+            }
+        }
+    }
+}
+
+/// Daylight saving time rule, see `Rtc::apply_dst`
+///
+/// The rule applies to a single time zone, as the transition hours are in local time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DstRule {
+    /// Start of the summer time, with the hour in standard time
+    pub start: Transition,
+    /// End of the summer time, with the hour in summer time
+    pub end: Transition,
+}
+
+impl DstRule {
+    /// European Union, in Central European Time: from the last Sunday of March at 02:00 to the
+    /// last Sunday of October at 03:00
+    ///
+    /// For Western and Eastern European Time, the hours are one lower and higher.
+    pub const EU: DstRule = DstRule {
+        start: Transition {
+            month: 3,
+            week: WeekOfMonth::Last,
+            day: 7,
+            hour: 2,
+        },
+        end: Transition {
+            month: 10,
+            week: WeekOfMonth::Last,
+            day: 7,
+            hour: 3,
+        },
+    };
+
+    /// United States: from the second Sunday of March at 02:00 to the first Sunday of November
+    /// at 02:00
+    pub const US: DstRule = DstRule {
+        start: Transition {
+            month: 3,
+            week: WeekOfMonth::Second,
+            day: 7,
+            hour: 2,
+        },
+        end: Transition {
+            month: 11,
+            week: WeekOfMonth::First,
+            day: 7,
+            hour: 2,
+        },
+    };
+
+    /// Returns whether the summer time is in effect at `secs`, in standard time seconds since
+    /// 1970-01-01
+    pub(crate) fn is_summer_time(&self, secs: u32) -> bool {
+        let year = Date::from_days_since_epoch(secs / 86_400).year;
+        let start = self.start.days_since_epoch(year) * 86_400 + self.start.hour * 3600;
+        let end = self.end.days_since_epoch(year) * 86_400 + self.end.hour * 3600 - 3600;
+
+        if start < end {
+            start <= secs && secs < end
+        } else {
+            // Southern hemisphere, the summer time spans the new year
+            secs >= start || secs < end
+        }
+    }
+}
+
+/// Point in time of the RTC calendar, with its subseconds, see `Rtc::now`
+///
+/// The instant is kept as microseconds since 1970-01-01 00:00:00, so the arithmetic crosses
+/// days, months and years like the calendar does. The daylight saving bit of the calendar is not
+/// part of it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RtcInstant {
+    micros: u64,
+}
+
+impl RtcInstant {
+    /// 1970-01-01 00:00:00, the earliest instant
+    pub(crate) const EPOCH: RtcInstant = RtcInstant { micros: 0 };
+
+    /// Converts a calendar date and time into an instant
+    pub fn from_date_time(date: Date, time: Time) -> Self {
+        let secs = date.days_since_epoch() as u64 * 86_400
+            + time.hours as u64 * 3600
+            + time.minutes as u64 * 60
+            + time.seconds as u64;
+
+        RtcInstant {
+            micros: secs * 1_000_000 + time.micros as u64,
+        }
+    }
+
+    /// Converts the instant back into a calendar date and time, without daylight saving
+    ///
+    /// # Panics
+    ///
+    /// Panics if the instant lies beyond the year 2106.
+    pub fn to_date_time(self) -> (Date, Time) {
+        let secs = self.micros / 1_000_000;
+        let days = u32::try_from(secs / 86_400).expect("instant beyond the calendar range");
+        let secs_of_day = (secs % 86_400) as u32;
+
+        let time = Time {
+            hours: secs_of_day / 3600,
+            minutes: secs_of_day / 60 % 60,
+            seconds: secs_of_day % 60,
+            micros: (self.micros % 1_000_000) as u32,
+            daylight_savings: false,
+        };
+
+        (Date::from_days_since_epoch(days), time)
+    }
+
+    /// Returns the time elapsed since `earlier`, or zero if `earlier` is later than `self`
+    pub fn duration_since(self, earlier: RtcInstant) -> Duration {
+        self.checked_duration_since(earlier).unwrap_or_default()
+    }
+
+    /// Returns the time elapsed since `earlier`, or `None` if `earlier` is later than `self`
+    pub fn checked_duration_since(self, earlier: RtcInstant) -> Option<Duration> {
+        self.micros
+            .checked_sub(earlier.micros)
+            .map(Duration::from_micros)
+    }
+
+    /// Returns the instant `duration` later, or `None` on overflow
+    ///
+    /// The duration is truncated to whole microseconds.
+    pub fn checked_add(self, duration: Duration) -> Option<RtcInstant> {
+        let micros = u64::try_from(duration.as_micros()).ok()?;
+
+        self.micros
+            .checked_add(micros)
+            .map(|micros| RtcInstant { micros })
+    }
+
+    /// Returns the instant shifted by a UTC offset of `minutes`, e.g. to the local time, or
+    /// `None` if it would be before 1970
+    pub fn checked_offset(self, minutes: i16) -> Option<RtcInstant> {
+        let offset = Duration::from_secs(u64::from(minutes.unsigned_abs()) * 60);
+
+        if minutes < 0 {
+            self.checked_sub(offset)
+        } else {
+            self.checked_add(offset)
+        }
+    }
+
+    /// Returns the instant `duration` earlier, or `None` if it would be before 1970
+    ///
+    /// The duration is truncated to whole microseconds.
+    pub fn checked_sub(self, duration: Duration) -> Option<RtcInstant> {
+        let micros = u64::try_from(duration.as_micros()).ok()?;
+
+        self.micros
+            .checked_sub(micros)
+            .map(|micros| RtcInstant { micros })
+    }
+}
+
+impl Add<Duration> for RtcInstant {
+    type Output = RtcInstant;
+
+    fn add(self, duration: Duration) -> RtcInstant {
+        self.checked_add(duration)
+            .expect("overflow when adding duration to instant")
+    }
+}
+
+impl AddAssign<Duration> for RtcInstant {
+    fn add_assign(&mut self, duration: Duration) {
+        *self = *self + duration;
+    }
+}
+
+impl Sub<Duration> for RtcInstant {
+    type Output = RtcInstant;
+
+    fn sub(self, duration: Duration) -> RtcInstant {
+        self.checked_sub(duration)
+            .expect("overflow when subtracting duration from instant")
+    }
+}
+
+impl SubAssign<Duration> for RtcInstant {
+    fn sub_assign(&mut self, duration: Duration) {
+        *self = *self - duration;
+    }
+}
+
+impl Sub<RtcInstant> for RtcInstant {
+    type Output = Duration;
+
+    /// Returns the time elapsed since `earlier`, see [`RtcInstant::duration_since`]
+    fn sub(self, earlier: RtcInstant) -> Duration {
+        self.duration_since(earlier)
+    }
+}
+
+/// A value does not fit the other type, e.g. a month number above 12 or a `chrono` date
+///
+/// Converting a number into a [`Month`] fails unless it is 1 to 12. Converting from `chrono` fails
+/// for the years the RTC can't hold, outside 2000 to 2099, and for leap seconds. Converting to
+/// `chrono` fails for fields out of their range, e.g. a 13th month, a 30th of February or a 25th
+/// hour.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OutOfRange;
+
+#[cfg(feature = "chrono")]
+impl TryFrom<chrono::NaiveDate> for Date {
+    type Error = OutOfRange;
+
+    /// Converts the date, with its weekday, Monday being day 1
+    fn try_from(date: chrono::NaiveDate) -> Result<Date, OutOfRange> {
+        use chrono::Datelike;
+
+        if !RTC_YEARS.contains(&(date.year() as u32)) {
+            return Err(OutOfRange);
+        }
+
+        Ok(Date {
+            day: date.weekday().number_from_monday(),
+            date: date.day(),
+            month: Month::from_valid(date.month()),
+            year: date.year() as u32,
+        })
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl TryFrom<Date> for chrono::NaiveDate {
+    type Error = OutOfRange;
+
+    /// Converts the date, the weekday follows from it and `Date::day` is ignored
+    fn try_from(date: Date) -> Result<chrono::NaiveDate, OutOfRange> {
+        let year = i32::try_from(date.year).map_err(|_| OutOfRange)?;
+
+        chrono::NaiveDate::from_ymd_opt(year, date.month.number().into(), date.date)
+            .ok_or(OutOfRange)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl TryFrom<chrono::NaiveTime> for Time {
+    type Error = OutOfRange;
+
+    /// Converts the time, truncated to microseconds, without daylight saving
+    fn try_from(time: chrono::NaiveTime) -> Result<Time, OutOfRange> {
+        use chrono::Timelike;
+
+        // `chrono` keeps a leap second in the nanoseconds, from 1_000_000_000 on
+        if time.nanosecond() >= 1_000_000_000 {
+            return Err(OutOfRange);
+        }
+
+        Ok(Time {
+            hours: time.hour(),
+            minutes: time.minute(),
+            seconds: time.second(),
+            micros: time.nanosecond() / 1000,
+            daylight_savings: false,
+        })
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl TryFrom<Time> for chrono::NaiveTime {
+    type Error = OutOfRange;
+
+    /// Converts the time, the daylight saving bit is dropped
+    fn try_from(time: Time) -> Result<chrono::NaiveTime, OutOfRange> {
+        if time.micros >= 1_000_000 {
+            return Err(OutOfRange);
+        }
+
+        chrono::NaiveTime::from_hms_micro_opt(time.hours, time.minutes, time.seconds, time.micros)
+            .ok_or(OutOfRange)
+    }
 }
 
 impl Into<Micros> for Second {
@@ -190,12 +831,392 @@ impl_from_struct!(
     Minute: [u32, u16, u8],
     Day: [u32, u16, u8],
     DateInMonth: [u32, u16, u8],
-    Month: [u32, u16, u8],
     Year: [u32, u16, u8],
 );
 
 impl_to_struct!(
-    u32: [Hour, Minute, Second, Micros, Day, DateInMonth, Month, Year],
-    u16: [Hour, Minute, Second, Micros, Day, DateInMonth, Month, Year],
-    u8: [Hour, Minute, Second, Micros, Day, DateInMonth, Month, Year],
+    u32: [Hour, Minute, Second, Micros, Day, DateInMonth, Year],
+    u16: [Hour, Minute, Second, Micros, Day, DateInMonth, Year],
+    u8: [Hour, Minute, Second, Micros, Day, DateInMonth, Year],
 );
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leap_years() {
+        assert!(is_leap_year(2000));
+        assert!(is_leap_year(2024));
+        assert!(!is_leap_year(2023));
+        assert!(!is_leap_year(2100));
+        assert!(is_leap_year(2400));
+
+        assert_eq!(Month::February.days(2024), 29);
+        assert_eq!(Month::February.days(2100), 28);
+        assert_eq!(Month::April.days(2024), 30);
+        assert_eq!(Month::December.days(2023), 31);
+    }
+
+    #[test]
+    fn month_numbers() {
+        for (number, &month) in (1..).zip(Month::ALL.iter()) {
+            assert_eq!(Month::try_from(number), Ok(month));
+            assert_eq!(u8::from(month), number);
+        }
+
+        assert_eq!(Month::try_from(0), Err(OutOfRange));
+        assert_eq!(Month::try_from(13), Err(OutOfRange));
+    }
+
+    #[test]
+    fn days_since_epoch_round_trip() {
+        let mut previous = Date::from_days_since_epoch(0);
+        assert_eq!(
+            previous,
+            Date::new(4.day(), 1.date(), Month::January, 1970.year())
+        );
+
+        // Every day up to the end of the RTC range follows the previous one
+        for days in 1..=47_481 {
+            let date = Date::from_days_since_epoch(days);
+            assert_eq!(date.days_since_epoch(), days);
+            assert_eq!(date.day, previous.day % 7 + 1);
+
+            if date.date == 1 {
+                assert_eq!(previous.date, previous.month.days(previous.year));
+            } else {
+                assert_eq!(
+                    (date.date, date.month, date.year),
+                    (previous.date + 1, previous.month, previous.year)
+                );
+            }
+            previous = date;
+        }
+        assert_eq!(
+            previous,
+            Date::new(4.day(), 31.date(), Month::December, 2099.year())
+        );
+    }
+
+    #[test]
+    fn days_since_epoch_dates() {
+        let days = |date: u32, month: Month, year: u32| {
+            Date::new(1.day(), date.date(), month, year.year()).days_since_epoch()
+        };
+
+        assert_eq!(days(1, Month::January, 2000), 10_957);
+        assert_eq!(days(29, Month::February, 2024), 19_782);
+        assert_eq!(days(1, Month::March, 2024), 19_783);
+        assert_eq!(days(1, Month::March, 2100), 47_541);
+        assert_eq!(days(28, Month::February, 2100) + 1, 47_541);
+        // Saturday, Thursday and Monday
+        assert_eq!(Date::from_days_since_epoch(10_957).day, 6);
+        assert_eq!(Date::from_days_since_epoch(19_782).day, 4);
+        assert_eq!(Date::from_days_since_epoch(47_541).day, 1);
+    }
+
+    #[test]
+    fn transition_days() {
+        let sunday = |month, week| Transition {
+            month,
+            week,
+            day: 7,
+            hour: 2,
+        };
+
+        assert_eq!(DstRule::EU.start.days_since_epoch(2024), 19_813);
+        assert_eq!(DstRule::EU.end.days_since_epoch(2024), 20_023);
+        assert_eq!(DstRule::EU.start.days_since_epoch(2025), 20_177);
+        assert_eq!(DstRule::US.start.days_since_epoch(2024), 19_792);
+        assert_eq!(DstRule::US.end.days_since_epoch(2024), 20_030);
+        // The last week of December ends in the next year
+        assert_eq!(sunday(12, WeekOfMonth::Last).days_since_epoch(2024), 20_086);
+        // 2024-03-03, the first Sunday of March, and the fourth one
+        assert_eq!(sunday(3, WeekOfMonth::First).days_since_epoch(2024), 19_785);
+        assert_eq!(
+            sunday(3, WeekOfMonth::Fourth).days_since_epoch(2024),
+            19_806
+        );
+    }
+
+    #[test]
+    fn summer_time() {
+        let at = |days: u32, hour: u32| days * 86_400 + hour * 3600;
+
+        // EU: 2024-03-31 02:00 standard time, to 2024-10-27 03:00 summer time, 02:00 standard
+        assert!(!DstRule::EU.is_summer_time(at(19_813, 2) - 1));
+        assert!(DstRule::EU.is_summer_time(at(19_813, 2)));
+        assert!(DstRule::EU.is_summer_time(at(20_023, 2) - 1));
+        assert!(!DstRule::EU.is_summer_time(at(20_023, 2)));
+        // US: 2024-03-10 02:00 standard time, to 2024-11-03 02:00 summer time, 01:00 standard
+        assert!(!DstRule::US.is_summer_time(at(19_792, 2) - 1));
+        assert!(DstRule::US.is_summer_time(at(19_792, 2)));
+        assert!(DstRule::US.is_summer_time(at(20_030, 1) - 1));
+        assert!(!DstRule::US.is_summer_time(at(20_030, 1)));
+
+        // Southern hemisphere: from the first Sunday of October to the first Sunday of April
+        let south = DstRule {
+            start: Transition {
+                month: 10,
+                week: WeekOfMonth::First,
+                day: 7,
+                hour: 2,
+            },
+            end: Transition {
+                month: 4,
+                week: WeekOfMonth::First,
+                day: 7,
+                hour: 3,
+            },
+        };
+        // 2024-01-15, 2024-06-15 and 2024-12-15
+        assert!(south.is_summer_time(at(19_737, 12)));
+        assert!(!south.is_summer_time(at(19_889, 12)));
+        assert!(south.is_summer_time(at(20_072, 12)));
+    }
+
+    #[test]
+    fn instant_date_time_round_trip() {
+        // 2024-02-29 23:59:59.999999
+        let date = Date::new(4.day(), 29.date(), Month::February, 2024.year());
+        let time = Time::new(
+            23.hours(),
+            59.minutes(),
+            59.seconds(),
+            999_999.micros(),
+            false,
+        );
+        let instant = RtcInstant::from_date_time(date, time);
+        assert_eq!(instant.to_date_time(), (date, time));
+
+        // The next microsecond is in March
+        let (date, time) = (instant + Duration::from_micros(1)).to_date_time();
+        assert_eq!(
+            date,
+            Date::new(5.day(), 1.date(), Month::March, 2024.year())
+        );
+        assert_eq!(
+            time,
+            Time::new(0.hours(), 0.minutes(), 0.seconds(), 0.micros(), false)
+        );
+
+        // The daylight saving bit is not part of the instant
+        let summer = Time::new(12.hours(), 0.minutes(), 0.seconds(), 0.micros(), true);
+        let (_, time) = RtcInstant::from_date_time(date, summer).to_date_time();
+        assert!(!time.daylight_savings);
+    }
+
+    #[test]
+    fn instant_arithmetic() {
+        let date = Date::new(2.day(), 31.date(), Month::December, 2024.year());
+        let time = Time::new(23.hours(), 0.minutes(), 0.seconds(), 0.micros(), false);
+        let start = RtcInstant::from_date_time(date, time);
+
+        let later = start + Duration::from_secs(3600);
+        assert_eq!(later.to_date_time().0.year, 2025);
+        assert_eq!(later - start, Duration::from_secs(3600));
+        assert_eq!(later.duration_since(start), Duration::from_secs(3600));
+        // Saturating and checked the other way around
+        assert_eq!(start - later, Duration::ZERO);
+        assert_eq!(start.checked_duration_since(later), None);
+
+        let mut instant = later;
+        instant -= Duration::from_secs(3600);
+        assert_eq!(instant, start);
+        instant += Duration::from_nanos(1_999);
+        assert_eq!(instant - start, Duration::from_micros(1));
+
+        assert_eq!(
+            RtcInstant::EPOCH.checked_sub(Duration::from_micros(1)),
+            None
+        );
+        assert_eq!(
+            RtcInstant { micros: u64::MAX }.checked_add(Duration::from_micros(1)),
+            None
+        );
+        assert!(start < later);
+    }
+
+    #[test]
+    fn utc_offset() {
+        let date = Date::new(1.day(), 1.date(), Month::January, 2024.year());
+        let time = Time::new(0.hours(), 30.minutes(), 0.seconds(), 0.micros(), false);
+        let utc = RtcInstant::from_date_time(date, time);
+
+        // UTC+2 is still the first of January
+        let (date, time) = utc.checked_offset(120).unwrap().to_date_time();
+        assert_eq!(
+            date,
+            Date::new(1.day(), 1.date(), Month::January, 2024.year())
+        );
+        assert_eq!(
+            time,
+            Time::new(2.hours(), 30.minutes(), 0.seconds(), 0.micros(), false)
+        );
+
+        // UTC-5 is the previous year
+        let (date, time) = utc.checked_offset(-300).unwrap().to_date_time();
+        assert_eq!(
+            date,
+            Date::new(7.day(), 31.date(), Month::December, 2023.year())
+        );
+        assert_eq!(
+            time,
+            Time::new(19.hours(), 30.minutes(), 0.seconds(), 0.micros(), false)
+        );
+
+        assert_eq!(utc.checked_offset(0), Some(utc));
+        assert_eq!(RtcInstant::EPOCH.checked_offset(-1), None);
+        assert_eq!(
+            RtcInstant::EPOCH.checked_offset(i16::MIN),
+            None,
+            "unsigned_abs must not overflow"
+        );
+    }
+
+    #[test]
+    fn format() {
+        let date = Date::new(4.day(), 29.date(), Month::February, 2024.year());
+        let time = Time::new(9.hours(), 5.minutes(), 0.seconds(), 0.micros(), false);
+
+        let mut buf = [0; 16];
+        assert_eq!(date.format_iso(&mut buf), Ok("2024-02-29"));
+        assert_eq!(time.format_hms(&mut buf), Ok("09:05:00"));
+        // The rest of the buffer is left alone
+        assert_eq!(&buf[8..], b"29\0\0\0\0\0\0");
+
+        assert_eq!(date.format_iso(&mut [0; 9]), Err(BufferTooSmall));
+        assert_eq!(time.format_hms(&mut [0; 7]), Err(BufferTooSmall));
+        assert_eq!(time.format_hms(&mut [0; 8]), Ok("09:05:00"));
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            Date::parse_iso("2024-02-29\r\n"),
+            Ok(Date::new(4.day(), 29.date(), Month::February, 2024.year()))
+        );
+        assert_eq!(
+            Time::parse_hms(" 23:59:59"),
+            Ok(Time::new(
+                23.hours(),
+                59.minutes(),
+                59.seconds(),
+                0.micros(),
+                false
+            ))
+        );
+        assert_eq!(
+            parse_iso_date_time("2000-01-01T00:00:00"),
+            parse_iso_date_time("2000-01-01 00:00:00")
+        );
+        assert_eq!(
+            parse_iso_date_time("2099-12-31T12:00:00").unwrap().0,
+            Date::new(4.day(), 31.date(), Month::December, 2099.year())
+        );
+
+        for s in [
+            "2024-2-29",
+            "2024/02/29",
+            "2024-02-2x",
+            "+024-02-29",
+            "2024-02-29Z",
+            "",
+        ] {
+            assert_eq!(Date::parse_iso(s), Err(ParseError::Format), "{}", s);
+        }
+        for s in [
+            "2023-02-29",
+            "2024-13-01",
+            "2024-00-10",
+            "2024-04-31",
+            "1999-12-31",
+        ] {
+            assert_eq!(Date::parse_iso(s), Err(ParseError::OutOfRange), "{}", s);
+        }
+        assert_eq!(Date::parse_iso("2100-01-01"), Err(ParseError::OutOfRange));
+
+        assert_eq!(Time::parse_hms("12:00"), Err(ParseError::Format));
+        assert_eq!(Time::parse_hms("24:00:00"), Err(ParseError::OutOfRange));
+        assert_eq!(Time::parse_hms("12:60:00"), Err(ParseError::OutOfRange));
+        assert_eq!(Time::parse_hms("12:00:60"), Err(ParseError::OutOfRange));
+
+        assert_eq!(parse_iso_date_time("2024-02-29"), Err(ParseError::Format));
+        assert_eq!(
+            parse_iso_date_time("2024-02-29T12:00:00+01:00"),
+            Err(ParseError::Format)
+        );
+        assert_eq!(
+            parse_iso_date_time("2024-02-30T12:00:00"),
+            Err(ParseError::OutOfRange)
+        );
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn chrono_date() {
+        use chrono::NaiveDate;
+
+        let first = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let last = NaiveDate::from_ymd_opt(2099, 12, 31).unwrap();
+        // Saturday and Thursday, Monday being day 1
+        assert_eq!(
+            Date::try_from(first),
+            Ok(Date::new(6.day(), 1.date(), Month::January, 2000.year()))
+        );
+        assert_eq!(
+            Date::try_from(last),
+            Ok(Date::new(4.day(), 31.date(), Month::December, 2099.year()))
+        );
+        for date in [first, last, NaiveDate::from_ymd_opt(2024, 2, 29).unwrap()] {
+            assert_eq!(NaiveDate::try_from(Date::try_from(date).unwrap()), Ok(date));
+        }
+
+        assert_eq!(Date::try_from(first.pred_opt().unwrap()), Err(OutOfRange));
+        assert_eq!(Date::try_from(last.succ_opt().unwrap()), Err(OutOfRange));
+        let february_30 = Date::new(5.day(), 30.date(), Month::February, 2024.year());
+        assert_eq!(NaiveDate::try_from(february_30), Err(OutOfRange));
+        let february_29 = Date::new(4.day(), 29.date(), Month::February, 2023.year());
+        assert_eq!(NaiveDate::try_from(february_29), Err(OutOfRange));
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn chrono_time() {
+        use chrono::NaiveTime;
+
+        let midnight = NaiveTime::from_hms_opt(0, 0, 0).unwrap();
+        let end_of_day = NaiveTime::from_hms_micro_opt(23, 59, 59, 999_999).unwrap();
+        for time in [midnight, end_of_day] {
+            assert_eq!(NaiveTime::try_from(Time::try_from(time).unwrap()), Ok(time));
+        }
+        // Truncated to microseconds
+        let nanos = NaiveTime::from_hms_nano_opt(12, 0, 0, 1_999).unwrap();
+        assert_eq!(
+            Time::try_from(nanos),
+            Ok(Time::new(
+                12.hours(),
+                0.minutes(),
+                0.seconds(),
+                1.micros(),
+                false
+            ))
+        );
+
+        let leap_second = NaiveTime::from_hms_milli_opt(23, 59, 59, 1_500).unwrap();
+        assert_eq!(Time::try_from(leap_second), Err(OutOfRange));
+        let hour_24 = Time::new(24.hours(), 0.minutes(), 0.seconds(), 0.micros(), false);
+        assert_eq!(NaiveTime::try_from(hour_24), Err(OutOfRange));
+        let minute_60 = Time::new(0.hours(), 60.minutes(), 0.seconds(), 0.micros(), false);
+        assert_eq!(NaiveTime::try_from(minute_60), Err(OutOfRange));
+        let micros = Time::new(
+            0.hours(),
+            0.minutes(),
+            0.seconds(),
+            1_000_000.micros(),
+            false,
+        );
+        assert_eq!(NaiveTime::try_from(micros), Err(OutOfRange));
+    }
+}
@@ -1,13 +1,14 @@
 //! Delays
 
-use cast::u32;
+use cast::{u16, u32};
 use cortex_m::asm;
 use cortex_m::peripheral::syst::SystClkSource;
 use cortex_m::peripheral::SYST;
 
 use crate::hal::blocking::delay::{DelayMs, DelayUs};
-use crate::rcc::Clocks;
-use crate::time::Hertz;
+use crate::rcc::{Clocks, APB1R1};
+use crate::stm32::{TIM6, TIM7};
+use crate::time::{Hertz, MicroSeconds, MilliSeconds};
 
 /// System timer (SysTick) as a delay provider
 pub struct Delay {
@@ -29,6 +30,18 @@ impl Delay {
     }
 }
 
+impl DelayMs<MilliSeconds> for Delay {
+    fn delay_ms(&mut self, ms: MilliSeconds) {
+        self.delay_us(ms.0 * 1_000);
+    }
+}
+
+impl DelayUs<MicroSeconds> for Delay {
+    fn delay_us(&mut self, us: MicroSeconds) {
+        self.delay_us(us.0);
+    }
+}
+
 /// System timer (SysTick) as a delay provider.
 impl DelayMs<u32> for Delay {
     fn delay_ms(&mut self, ms: u32) {
@@ -111,6 +124,18 @@ impl DelayCM {
     }
 }
 
+impl DelayMs<MilliSeconds> for DelayCM {
+    fn delay_ms(&mut self, ms: MilliSeconds) {
+        self.delay_us(ms.0 * 1_000);
+    }
+}
+
+impl DelayUs<MicroSeconds> for DelayCM {
+    fn delay_us(&mut self, us: MicroSeconds) {
+        self.delay_us(us.0);
+    }
+}
+
 impl DelayMs<u32> for DelayCM {
     fn delay_ms(&mut self, ms: u32) {
         self.delay_us(ms * 1_000);
@@ -149,3 +174,186 @@ impl DelayUs<u8> for DelayCM {
         self.delay_us(u32(us))
     }
 }
+
+/// Basic timer (TIM6 or TIM7) as a delay provider, which leaves SysTick free
+/// for other uses such as an RTOS
+///
+/// The timer counts at 1 MHz, or slightly slower when the timer clock is not a whole number of
+/// megahertz, so delays are never shorter than requested. Delays longer than its 16-bit counter
+/// are split. The timer can't count a single tick, so a 1 us delay takes 2 us.
+pub struct TimerDelay<TIM> {
+    tim: TIM,
+}
+
+/// Returns the ticks of the next timer run of a delay with `remaining` ticks left
+///
+/// With an auto-reload value of 0 the update flag is never raised, so every run counts at least
+/// 2 ticks: a remainder of 1 tick after a full run is shared with that run.
+fn chunk_ticks(remaining: u32) -> u32 {
+    const FULL: u32 = 1 << 16;
+
+    match remaining {
+        0..=2 => 2,
+        r if r <= FULL => r,
+        r if r == FULL + 1 => FULL - 1,
+        _ => FULL,
+    }
+}
+
+macro_rules! timer_delay {
+    ($($TIM:ident: ($tim:ident, $timXen:ident, $timXrst:ident),)+) => {
+        $(
+            impl TimerDelay<$TIM> {
+                /// Configures the timer as a delay provider
+                ///
+                /// # Panics
+                ///
+                /// Panics if the timer clock is slower than 1 MHz.
+                pub fn $tim(tim: $TIM, clocks: Clocks, apb: &mut APB1R1) -> Self {
+                    // enable and reset peripheral to a clean slate state
+                    apb.enr().modify(|_, w| w.$timXen().set_bit());
+                    apb.rstr().modify(|_, w| w.$timXrst().set_bit());
+                    apb.rstr().modify(|_, w| w.$timXrst().clear_bit());
+
+                    // The timers run at twice the APB clock, unless APB is not divided
+                    let timclk = if clocks.ppre1() == 1 {
+                        clocks.pclk1().0
+                    } else {
+                        clocks.pclk1().0 * 2
+                    };
+                    assert!(timclk >= 1_000_000);
+
+                    // 1 MHz tick, rounding the prescaler up so that a tick is never shorter
+                    // than 1 us
+                    let psc = u16(timclk.div_ceil(1_000_000) - 1).unwrap();
+                    tim.psc.write(|w| w.psc().bits(psc));
+
+                    // Stop counting at the update event
+                    tim.cr1.modify(|_, w| w.opm().set_bit());
+
+                    TimerDelay { tim }
+                }
+
+                /// Releases the TIM peripheral
+                pub fn free(self) -> $TIM {
+                    self.tim
+                }
+            }
+
+            impl DelayUs<u32> for TimerDelay<$TIM> {
+                fn delay_us(&mut self, us: u32) {
+                    let mut remaining = us;
+
+                    while remaining != 0 {
+                        let ticks = chunk_ticks(remaining);
+                        remaining = remaining.saturating_sub(ticks);
+
+                        self.tim.arr.write(|w| w.arr().bits((ticks - 1) as u16));
+
+                        // Reset the counter and load the prescaler. This raises
+                        // the update flag, which has to be cleared again.
+                        self.tim.egr.write(|w| w.ug().set_bit());
+                        self.tim.sr.modify(|_, w| w.uif().clear_bit());
+
+                        self.tim.cr1.modify(|_, w| w.cen().set_bit());
+                        while self.tim.sr.read().uif().bit_is_clear() {}
+                    }
+                }
+            }
+
+            impl DelayUs<u16> for TimerDelay<$TIM> {
+                fn delay_us(&mut self, us: u16) {
+                    self.delay_us(u32(us))
+                }
+            }
+
+            impl DelayUs<u8> for TimerDelay<$TIM> {
+                fn delay_us(&mut self, us: u8) {
+                    self.delay_us(u32(us))
+                }
+            }
+
+            impl DelayUs<MicroSeconds> for TimerDelay<$TIM> {
+                fn delay_us(&mut self, us: MicroSeconds) {
+                    self.delay_us(us.0);
+                }
+            }
+
+            impl DelayMs<u32> for TimerDelay<$TIM> {
+                fn delay_ms(&mut self, ms: u32) {
+                    // Split up, so the microseconds can't overflow
+                    for _ in 0..ms / 1_000 {
+                        self.delay_us(1_000_000_u32);
+                    }
+                    self.delay_us(ms % 1_000 * 1_000);
+                }
+            }
+
+            impl DelayMs<u16> for TimerDelay<$TIM> {
+                fn delay_ms(&mut self, ms: u16) {
+                    self.delay_ms(u32(ms));
+                }
+            }
+
+            impl DelayMs<u8> for TimerDelay<$TIM> {
+                fn delay_ms(&mut self, ms: u8) {
+                    self.delay_ms(u32(ms));
+                }
+            }
+
+            impl DelayMs<MilliSeconds> for TimerDelay<$TIM> {
+                fn delay_ms(&mut self, ms: MilliSeconds) {
+                    self.delay_ms(ms.0);
+                }
+            }
+        )+
+    }
+}
+
+timer_delay! {
+    TIM6: (tim6, tim6en, tim6rst),
+    TIM7: (tim7, tim7en, tim7rst),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Splits a delay of `us` into the runs of the timer
+    fn chunks(us: u32) -> impl Iterator<Item = u32> {
+        let mut remaining = us;
+
+        core::iter::from_fn(move || {
+            if remaining == 0 {
+                return None;
+            }
+
+            let ticks = chunk_ticks(remaining);
+            remaining = remaining.saturating_sub(ticks);
+            Some(ticks)
+        })
+    }
+
+    #[test]
+    fn timer_delay_chunks() {
+        for &us in &[
+            2, 3, 1000, 65_535, 65_536, 65_537, 65_538, 131_072, 131_073, 1_000_000,
+        ] {
+            assert!(
+                chunks(us).all(|ticks| (2..=1 << 16).contains(&ticks)),
+                "{}",
+                us
+            );
+            assert_eq!(chunks(us).sum::<u32>(), us, "{}", us);
+        }
+
+        // The only delay that runs longer than requested
+        assert_eq!(chunks(1).sum::<u32>(), 2);
+        assert_eq!(chunks(0).count(), 0);
+
+        let mut runs = chunks(65_537);
+        assert_eq!(runs.next(), Some(65_535));
+        assert_eq!(runs.next(), Some(2));
+        assert_eq!(runs.next(), None);
+    }
+}
@@ -1,4 +1,31 @@
 //! Direct Memory Access Engine
+//!
+//! # Async
+//!
+//! With the `async` feature, the DMA drivers of the serial, SPI and I2C peripherals implement the
+//! `embedded-io-async` and `embedded-hal-async` traits. Their futures start the transfer, and
+//! complete once the channel reports the end of the transfer in its interrupt. The firmware only
+//! has to forward the interrupts of the channels in use to the drivers, e.g. for a serial port on
+//! DMA1 channels 6 and 7:
+//!
+//! ```ignore
+//! #[interrupt]
+//! fn DMA1_CH6() {
+//!     dma1::C6::on_interrupt();
+//! }
+//!
+//! #[interrupt]
+//! fn DMA1_CH7() {
+//!     dma1::C7::on_interrupt();
+//! }
+//! ```
+//!
+//! and to unmask them in the NVIC. The drivers document the other interrupts they need, if any;
+//! the I2C driver completes on the interrupts of the peripheral alone, see `i2c::I2cDma`.
+//!
+//! Dropping a future stops its transfer, the buffer is never accessed by the DMA once the future
+//! is gone. Leaking the future with `mem::forget` instead leaves the DMA running, into a buffer
+//! that may be reused.
 
 #![allow(dead_code)]
 
@@ -8,6 +35,12 @@ use core::mem::MaybeUninit;
 use core::ops::{Deref, DerefMut};
 use core::ptr;
 use core::slice;
+#[cfg(feature = "async")]
+use core::{
+    future,
+    sync::atomic::{self, Ordering},
+    task::{Poll, Waker},
+};
 
 use crate::rcc::AHB1;
 use as_slice::AsSlice;
@@ -25,6 +58,23 @@ pub enum Error {
 pub enum Event {
     HalfTransfer,
     TransferComplete,
+    /// The DMA accessed a reserved address, and disabled the channel
+    TransferError,
+}
+
+/// Priority of a DMA channel, see the `set_priority` method of the channels
+///
+/// When several channels of a DMA controller request at the same time, the arbiter serves the
+/// one of highest priority first, and among channels of equal priority the one with the lowest
+/// number. E.g. with the ADC on DMA1 channel 1 and a serial receiver on channel 5, both at
+/// `Medium`, the ADC goes first; raising the serial receiver to `High` makes it go first. The
+/// two DMA controllers arbitrate independently. The default is `Medium`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Priority {
+    Low = 0b00,
+    Medium = 0b01,
+    High = 0b10,
+    VeryHigh = 0b11,
 }
 
 #[derive(Clone, Copy, PartialEq)]
@@ -322,6 +372,18 @@ pub trait DmaExt {
     fn split(self, ahb: &mut AHB1) -> Self::Channels;
 }
 
+/// An ongoing DMA transfer
+///
+/// The transfer owns the buffer, the channel and the peripheral (`PAYLOAD`) for
+/// as long as the DMA may access the buffer. They are only handed back by
+/// `wait`, after the transfer complete flag is set, so touching the buffer
+/// before the transfer is done does not compile. Use `is_done` to poll without
+/// blocking.
+///
+/// `MODE` is [`R`] when the DMA reads the buffer (memory to peripheral), which
+/// allows reading the buffer through `Deref` in the meantime, and [`W`] when
+/// the DMA writes the buffer (peripheral to memory), where only the already
+/// written part is accessible through `peek`.
 pub struct Transfer<MODE, BUFFER, CHANNEL, PAYLOAD> {
     _mode: PhantomData<MODE>,
     buffer: BUFFER,
@@ -365,6 +427,106 @@ impl<BUFFER, CHANNEL, PAYLOAD> Deref for Transfer<R, BUFFER, CHANNEL, PAYLOAD> {
     }
 }
 
+/// Channel of the DMA able to complete the futures of the `async` drivers
+#[cfg(feature = "async")]
+pub(crate) trait AsyncChannel {
+    /// Registers the waker of the task waiting for the transfer
+    fn register(waker: &Waker);
+
+    /// Wakes the task waiting for the transfer, if any
+    fn wake();
+
+    /// Returns `true` once the transfer is complete
+    fn is_complete(&self) -> bool;
+
+    /// Returns `true` if the transfer failed, and clears the error
+    fn take_error(&mut self) -> bool;
+
+    /// Number of bytes left to transfer
+    fn remaining(&self) -> usize;
+
+    /// Enables or disables the transfer complete and transfer error interrupts
+    fn listen_end(&mut self, listen: bool);
+
+    /// Stops the transfer and clears the flags of the channel
+    fn stop_transfer(&mut self);
+}
+
+/// Transfer of an `async` driver, started on `channel` and stopped on drop
+#[cfg(feature = "async")]
+pub(crate) struct AsyncTransfer<'c, C>
+where
+    C: AsyncChannel,
+{
+    channel: &'c mut C,
+}
+
+#[cfg(feature = "async")]
+impl<'c, C> AsyncTransfer<'c, C>
+where
+    C: AsyncChannel,
+{
+    /// Takes over the running transfer of `channel`
+    pub(crate) fn new(channel: &'c mut C) -> Self {
+        AsyncTransfer { channel }
+    }
+
+    /// Number of bytes left to transfer
+    pub(crate) fn remaining(&self) -> usize {
+        self.channel.remaining()
+    }
+
+    /// Waits until the transfer is complete, or `done` returns `true`
+    ///
+    /// `done` is called every time the task is polled, after the registration of its waker, so
+    /// a driver can complete a transfer early on an event of its peripheral, e.g. an idle line.
+    ///
+    /// # Panics
+    ///
+    /// Panics on a transfer error, which only happens on an invalid buffer address.
+    pub(crate) async fn wait<F>(&mut self, mut done: F)
+    where
+        F: FnMut(&C) -> bool,
+    {
+        let channel = &mut *self.channel;
+        future::poll_fn(|cx| {
+            C::register(cx.waker());
+
+            if channel.take_error() {
+                panic!("DMA transfer error");
+            }
+            if channel.is_complete() || done(channel) {
+                return Poll::Ready(());
+            }
+
+            // Enabled after the checks: a transfer ending in between still raises the interrupt
+            channel.listen_end(true);
+
+            Poll::Pending
+        })
+        .await;
+
+        // NOTE(compiler_fence) operations on the buffer should not be reordered before the end
+        // of the transfer
+        atomic::compiler_fence(Ordering::SeqCst);
+    }
+}
+
+#[cfg(feature = "async")]
+impl<C> Drop for AsyncTransfer<'_, C>
+where
+    C: AsyncChannel,
+{
+    fn drop(&mut self) {
+        self.channel.listen_end(false);
+        self.channel.stop_transfer();
+
+        // NOTE(compiler_fence) operations on the buffer should not be reordered before the DMA
+        // is stopped
+        atomic::compiler_fence(Ordering::SeqCst);
+    }
+}
+
 /// Read transfer
 pub struct R;
 
@@ -402,9 +564,16 @@ macro_rules! dma {
                 use core::ptr;
                 use stable_deref_trait::StableDeref;
 
-                use crate::dma::{CircBuffer, FrameReader, FrameSender, DMAFrame, DmaExt, Error, Event, Half, Transfer, W};
+                use crate::dma::{CircBuffer, FrameReader, FrameSender, DMAFrame, DmaExt, Error, Event, Half, Priority, Transfer, W};
                 use crate::rcc::AHB1;
 
+                #[cfg(feature = "async")]
+                use core::{cell::RefCell, task::Waker};
+                #[cfg(feature = "async")]
+                use cortex_m::interrupt::{self, Mutex};
+                #[cfg(feature = "async")]
+                use crate::dma::AsyncChannel;
+
                 #[allow(clippy::manual_non_exhaustive)]
                 pub struct Channels((), $(pub $CX),+);
 
@@ -459,6 +628,14 @@ macro_rules! dma {
                             self.isr().$tcifX().bit_is_clear()
                         }
 
+                        /// Sets the priority of the channel against the others of the controller
+                        ///
+                        /// The priority is kept across the transfers of the drivers, see [`Priority`].
+                        #[inline]
+                        pub fn set_priority(&mut self, priority: Priority) {
+                            self.ccr().modify(|_, w| w.pl().bits(priority as u8));
+                        }
+
                         #[inline]
                         pub fn listen(&mut self, event: Event) {
                             match event {
@@ -466,6 +643,9 @@ macro_rules! dma {
                                 Event::TransferComplete => {
                                     self.ccr().modify(|_, w| w.tcie().set_bit())
                                 }
+                                Event::TransferError => {
+                                    self.ccr().modify(|_, w| w.teie().set_bit())
+                                }
                             }
                         }
 
@@ -478,7 +658,24 @@ macro_rules! dma {
                                 Event::TransferComplete => {
                                     self.ccr().modify(|_, w| w.tcie().clear_bit())
                                 }
+                                Event::TransferError => {
+                                    self.ccr().modify(|_, w| w.teie().clear_bit())
+                                }
+                            }
+                        }
+
+                        /// Returns `true` if a transfer error occurred, and optionally clears it
+                        ///
+                        /// On a transfer error the hardware disables the channel, the transfer
+                        /// never completes.
+                        #[inline]
+                        pub fn is_transfer_error(&mut self, clear: bool) -> bool {
+                            let error = self.isr().$teifX().bit_is_set();
+                            if error && clear {
+                                self.ifcr().write(|w| w.$cteifX().set_bit());
                             }
+
+                            error
                         }
 
                         #[inline]
@@ -525,6 +722,76 @@ macro_rules! dma {
 
                     }
 
+                    #[cfg(feature = "async")]
+                    impl $CX {
+                        /// Completes the future of the `async` driver using the channel, to be
+                        /// called from the interrupt handler of the channel
+                        ///
+                        /// The interrupts of the channel stay disabled until the driver polls
+                        /// again, see the [module documentation](crate::dma#async).
+                        pub fn on_interrupt() {
+                            interrupt::free(|_| {
+                                // NOTE(unsafe) the drivers only change the interrupt enables in
+                                // a critical section as well
+                                unsafe {
+                                    (*$DMAX::ptr())
+                                        .$ccrX
+                                        .modify(|_, w| w.tcie().clear_bit().teie().clear_bit())
+                                };
+                            });
+
+                            <Self as AsyncChannel>::wake();
+                        }
+
+                        fn waker() -> &'static Mutex<RefCell<Option<Waker>>> {
+                            static WAKER: Mutex<RefCell<Option<Waker>>> =
+                                Mutex::new(RefCell::new(None));
+
+                            &WAKER
+                        }
+                    }
+
+                    #[cfg(feature = "async")]
+                    impl AsyncChannel for $CX {
+                        fn register(waker: &Waker) {
+                            interrupt::free(|cs| {
+                                let mut registered = Self::waker().borrow(cs).borrow_mut();
+                                match registered.as_ref() {
+                                    Some(old) if old.will_wake(waker) => {}
+                                    _ => *registered = Some(waker.clone()),
+                                }
+                            });
+                        }
+
+                        fn wake() {
+                            if let Some(waker) = interrupt::free(|cs| Self::waker().borrow(cs).take()) {
+                                waker.wake();
+                            }
+                        }
+
+                        fn is_complete(&self) -> bool {
+                            !self.in_progress()
+                        }
+
+                        fn take_error(&mut self) -> bool {
+                            self.is_transfer_error(true)
+                        }
+
+                        fn remaining(&self) -> usize {
+                            self.get_cndtr() as usize
+                        }
+
+                        fn listen_end(&mut self, listen: bool) {
+                            interrupt::free(|_| {
+                                self.ccr().modify(|_, w| w.tcie().bit(listen).teie().bit(listen))
+                            });
+                        }
+
+                        fn stop_transfer(&mut self) {
+                            self.stop();
+                        }
+                    }
+
                     impl<BUFFER, N> FrameSender<BUFFER, $CX, N>
                     where
                         BUFFER: Sized + StableDeref<Target = DMAFrame<N>> + DerefMut + 'static,
@@ -817,16 +1084,30 @@ macro_rules! dma {
                     }
 
                     impl<BUFFER, PAYLOAD, MODE> Transfer<MODE, BUFFER, $CX, PAYLOAD> {
+                        /// Returns `true` once the transfer is complete
                         pub fn is_done(&self) -> bool {
                             self.channel.isr().$tcifX().bit_is_set()
                         }
 
+                        /// Returns `true` if the transfer failed, see `is_transfer_error` of the
+                        /// channel
+                        pub fn is_error(&self) -> bool {
+                            self.channel.isr().$teifX().bit_is_set()
+                        }
+
+                        /// Blocks until the transfer is complete, then returns the buffer,
+                        /// the channel and the peripheral
+                        ///
+                        /// # Panics
+                        ///
+                        /// Panics on a transfer error, which would never complete. The manual
+                        /// says "A DMA transfer error can be generated by reading from or writing
+                        /// to a reserved address space", which takes a peripheral or memory
+                        /// address set up outside of the type safe API.
                         pub fn wait(mut self) -> (BUFFER, $CX, PAYLOAD) {
-                            // XXX should we check for transfer errors here?
-                            // The manual says "A DMA transfer error can be generated by reading
-                            // from or writing to a reserved address space". I think it's impossible
-                            // to get to that state with our type safe API and *safe* Rust.
-                            while !self.is_done() {}
+                            while !self.is_done() {
+                                assert!(!self.is_error(), "DMA transfer error");
+                            }
 
                             self.channel.ifcr().write(|w| w.$cgifX().set_bit());
 
@@ -842,6 +1123,7 @@ macro_rules! dma {
                     }
 
                     impl<BUFFER, PAYLOAD> Transfer<W, &'static mut BUFFER, $CX, PAYLOAD> {
+                        /// Returns the part of the buffer the DMA has already written
                         pub fn peek<T>(&self) -> &[T]
                         where
                             BUFFER: AsSlice<Element=T>,
@@ -861,9 +1143,11 @@ macro_rules! dma {
                     fn split(self, ahb: &mut AHB1) -> Channels {
                         ahb.enr().modify(|_, w| w.$dmaXen().set_bit());
 
-                        // reset the DMA control registers (stops all on-going transfers)
+                        // reset the DMA control registers (stops all on-going transfers), the
+                        // channels start at the default priority
                         $(
                             self.$ccrX.reset();
+                            self.$ccrX.modify(|_, w| w.pl().bits(Priority::Medium as u8));
                         )+
 
                         Channels((), $($CX { }),+)
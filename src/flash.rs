@@ -27,6 +27,9 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! With the `embedded-storage` feature, [`FlashRegion`] implements the `embedded-storage` NOR
+//! flash traits for a part of the flash, e.g. for key-value stores.
 
 #![deny(missing_docs)]
 
@@ -343,3 +346,136 @@ impl<'a> FlashProgramming<'a> {
         res
     }
 }
+
+/// Size of a flash page, the smallest erasable unit
+#[cfg(feature = "embedded-storage")]
+const PAGE_SIZE: usize = 2048;
+
+/// Error of [`FlashRegion`]
+#[cfg(feature = "embedded-storage")]
+#[derive(Copy, Clone, Debug)]
+pub enum StorageError {
+    /// The offset or length is not a multiple of the write or erase size
+    NotAligned,
+    /// The access lies outside of the region
+    OutOfBounds,
+    /// The flash controller reported an error
+    Flash(Error),
+}
+
+#[cfg(feature = "embedded-storage")]
+impl embedded_storage::nor_flash::NorFlashError for StorageError {
+    fn kind(&self) -> embedded_storage::nor_flash::NorFlashErrorKind {
+        use embedded_storage::nor_flash::NorFlashErrorKind;
+
+        match self {
+            StorageError::NotAligned => NorFlashErrorKind::NotAligned,
+            StorageError::OutOfBounds => NorFlashErrorKind::OutOfBounds,
+            StorageError::Flash(_) => NorFlashErrorKind::Other,
+        }
+    }
+}
+
+#[cfg(feature = "embedded-storage")]
+impl From<embedded_storage::nor_flash::NorFlashErrorKind> for StorageError {
+    fn from(kind: embedded_storage::nor_flash::NorFlashErrorKind) -> Self {
+        match kind {
+            embedded_storage::nor_flash::NorFlashErrorKind::NotAligned => StorageError::NotAligned,
+            _ => StorageError::OutOfBounds,
+        }
+    }
+}
+
+/// A region of the internal flash, implementing the `embedded-storage` NOR flash traits
+///
+/// Offsets are relative to the start of the region, and all accesses are bound to it, so the
+/// firmware outside of it cannot be overwritten. Writes are done in double words
+/// (`WRITE_SIZE` = 8), which can only be written once between erases, and erases in pages
+/// (`ERASE_SIZE` = 2048).
+#[cfg(feature = "embedded-storage")]
+pub struct FlashRegion<'a> {
+    prog: FlashProgramming<'a>,
+    start: usize,
+    len: usize,
+}
+
+#[cfg(feature = "embedded-storage")]
+impl<'a> FlashRegion<'a> {
+    /// Creates a region of `len` bytes from the physical address `start`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `start` or `len` are not a multiple of the page size, or the region does not
+    /// lie in the flash of the device, whose size is read from the flash size register.
+    pub fn new(prog: FlashProgramming<'a>, start: usize, len: usize) -> Self {
+        let first_page = FlashPage(0).to_address();
+        let flash_end = first_page + usize::from(crate::signature::flash_size_kb()) * 1024;
+        assert!(start % PAGE_SIZE == 0 && len % PAGE_SIZE == 0);
+        assert!(start >= first_page && start + len <= flash_end);
+
+        FlashRegion { prog, start, len }
+    }
+
+    /// Releases the flash programming interface
+    pub fn free(self) -> FlashProgramming<'a> {
+        self.prog
+    }
+}
+
+#[cfg(feature = "embedded-storage")]
+impl<'a> embedded_storage::nor_flash::ErrorType for FlashRegion<'a> {
+    type Error = StorageError;
+}
+
+#[cfg(feature = "embedded-storage")]
+impl<'a> embedded_storage::nor_flash::ReadNorFlash for FlashRegion<'a> {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> core::result::Result<(), StorageError> {
+        embedded_storage::nor_flash::check_read(self, offset, bytes.len())?;
+
+        Read::read(&self.prog, self.start + offset as usize, bytes);
+
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.len
+    }
+}
+
+#[cfg(feature = "embedded-storage")]
+impl<'a> embedded_storage::nor_flash::NorFlash for FlashRegion<'a> {
+    const WRITE_SIZE: usize = mem::size_of::<u64>();
+    const ERASE_SIZE: usize = PAGE_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> core::result::Result<(), StorageError> {
+        embedded_storage::nor_flash::check_erase(self, from, to)?;
+
+        let first = (self.start + from as usize - FlashPage(0).to_address()) / PAGE_SIZE;
+        let last = (self.start + to as usize - FlashPage(0).to_address()) / PAGE_SIZE;
+        for page in first..last {
+            self.prog
+                .erase_page(FlashPage(page))
+                .map_err(StorageError::Flash)?;
+        }
+
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> core::result::Result<(), StorageError> {
+        embedded_storage::nor_flash::check_write(self, offset, bytes.len())?;
+
+        let mut address = self.start + offset as usize;
+        for chunk in bytes.chunks_exact(Self::WRITE_SIZE) {
+            let dword = u64::from_le_bytes(chunk.try_into().unwrap());
+
+            self.prog
+                .write_native(address, &[dword])
+                .map_err(StorageError::Flash)?;
+            address += Self::WRITE_SIZE;
+        }
+
+        Ok(())
+    }
+}
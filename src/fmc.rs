@@ -0,0 +1,415 @@
+//! Flexible static memory controller (FMC)
+//!
+//! The NOR/SRAM controller maps external asynchronous memories, e.g. SRAM or 8080-style parallel
+//! displays, into the address space at `0x6000_0000`, one 64 MiB region per chip select (NE1 to
+//! NE4). Once a bank is enabled, the memory is accessed through volatile reads and writes at
+//! [`NorSramBank::as_mut_ptr`].
+//!
+//! The FMC pins (data, address, NOE, NWE, NEx and NBLx) have to be put in alternate mode 12 by
+//! the caller, ideally at a high speed.
+
+use core::ptr;
+
+use crate::rcc::{Clocks, AHB3};
+use crate::stm32::FMC;
+
+/// Base address of the NOR/SRAM banks
+const BANK_BASE: usize = 0x6000_0000;
+
+/// Size of the memory region of a bank
+const BANK_SIZE: usize = 0x0400_0000;
+
+/// NOR/SRAM bank, selected by the chip select pin NE1 to NE4
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Bank {
+    /// Selected by NE1, mapped at `0x6000_0000`
+    Bank1,
+    /// Selected by NE2, mapped at `0x6400_0000`
+    Bank2,
+    /// Selected by NE3, mapped at `0x6800_0000`
+    Bank3,
+    /// Selected by NE4, mapped at `0x6C00_0000`
+    Bank4,
+}
+
+/// Type of the external memory
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MemoryType {
+    /// SRAM, also used for displays
+    Sram = 0b00,
+    /// PSRAM (CRAM)
+    Psram = 0b01,
+    /// NOR flash
+    Nor = 0b10,
+}
+
+/// Width of the external data bus
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DataWidth {
+    /// 8-bit bus, D0 to D7
+    Bits8 = 0b00,
+    /// 16-bit bus, D0 to D15
+    Bits16 = 0b01,
+}
+
+/// Asynchronous access mode, see the reference manual for the waveforms
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AccessMode {
+    /// Mode A, for SRAM and PSRAM
+    ModeA = 0b00,
+    /// Mode B, for NOR flash
+    ModeB = 0b01,
+    /// Mode C, for NOR flash
+    ModeC = 0b10,
+    /// Mode D, with an address hold phase
+    ModeD = 0b11,
+}
+
+/// Timing of asynchronous accesses, in HCLK cycles
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Timing {
+    address_setup: u8,
+    address_hold: u8,
+    data_setup: u8,
+    bus_turnaround: u8,
+    access_mode: AccessMode,
+}
+
+impl Default for Timing {
+    /// The reset timing, the slowest possible
+    fn default() -> Self {
+        Timing {
+            address_setup: 15,
+            address_hold: 15,
+            data_setup: 255,
+            bus_turnaround: 15,
+            access_mode: AccessMode::ModeA,
+        }
+    }
+}
+
+impl Timing {
+    /// Sets the address setup phase, 0 to 15 cycles
+    pub fn address_setup(mut self, cycles: u8) -> Self {
+        assert!(cycles <= 15);
+        self.address_setup = cycles;
+        self
+    }
+
+    /// Sets the address hold phase of multiplexed accesses, 1 to 15 cycles
+    pub fn address_hold(mut self, cycles: u8) -> Self {
+        assert!((1..=15).contains(&cycles));
+        self.address_hold = cycles;
+        self
+    }
+
+    /// Sets the data setup phase, 1 to 255 cycles
+    pub fn data_setup(mut self, cycles: u8) -> Self {
+        assert!(cycles >= 1);
+        self.data_setup = cycles;
+        self
+    }
+
+    /// Sets the bus turnaround phase between accesses, 0 to 15 cycles
+    pub fn bus_turnaround(mut self, cycles: u8) -> Self {
+        assert!(cycles <= 15);
+        self.bus_turnaround = cycles;
+        self
+    }
+
+    /// Sets the access mode, only used in extended mode
+    pub fn access_mode(mut self, access_mode: AccessMode) -> Self {
+        self.access_mode = access_mode;
+        self
+    }
+}
+
+/// Configuration of a NOR/SRAM bank
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Config {
+    memory_type: MemoryType,
+    data_width: DataWidth,
+    multiplexed: bool,
+    write_enable: bool,
+    read_timing: Timing,
+    write_timing: Option<Timing>,
+}
+
+impl Default for Config {
+    /// Writable 16-bit SRAM, with non-multiplexed address and data lines and the reset timing
+    fn default() -> Self {
+        Config {
+            memory_type: MemoryType::Sram,
+            data_width: DataWidth::Bits16,
+            multiplexed: false,
+            write_enable: true,
+            read_timing: Timing::default(),
+            write_timing: None,
+        }
+    }
+}
+
+impl Config {
+    /// 8080-style display interface, as found on ILI9341-class controllers
+    ///
+    /// The D/CX (register select) line of the display is driven by an address line, see
+    /// [`NorSramBank::lcd`]. The timing follows the ILI9341 datasheet: a write cycle of at least
+    /// 66 ns with WR low and high for at least 15 ns each, and reads with RD low for at least
+    /// 355 ns and high for at least 90 ns.
+    pub fn lcd_8080(data_width: DataWidth, clocks: Clocks) -> Self {
+        let cycles = |ns: u32| {
+            let cycles = (clocks.hclk().0 / 1_000 * ns).div_ceil(1_000_000);
+            cycles.min(255) as u8
+        };
+
+        let write_setup = cycles(15).clamp(1, 15);
+        let write_data = cycles(66)
+            .saturating_sub(write_setup)
+            .max(cycles(15))
+            .max(1);
+        let read_setup = cycles(90).clamp(1, 15);
+        let read_data = cycles(355).max(1);
+
+        Config {
+            memory_type: MemoryType::Sram,
+            data_width,
+            multiplexed: false,
+            write_enable: true,
+            read_timing: Timing::default()
+                .address_setup(read_setup)
+                .data_setup(read_data)
+                .bus_turnaround(0),
+            write_timing: Some(
+                Timing::default()
+                    .address_setup(write_setup)
+                    .data_setup(write_data)
+                    .bus_turnaround(0),
+            ),
+        }
+    }
+
+    /// Sets the memory type
+    pub fn memory_type(mut self, memory_type: MemoryType) -> Self {
+        self.memory_type = memory_type;
+        self
+    }
+
+    /// Sets the data bus width
+    pub fn data_width(mut self, data_width: DataWidth) -> Self {
+        self.data_width = data_width;
+        self
+    }
+
+    /// Multiplexes the lower address lines onto the data lines (A/D0 to A/D15, latched by NL)
+    pub fn multiplexed(mut self, multiplexed: bool) -> Self {
+        self.multiplexed = multiplexed;
+        self
+    }
+
+    /// Enables or disables writes to the memory
+    pub fn write_enable(mut self, write_enable: bool) -> Self {
+        self.write_enable = write_enable;
+        self
+    }
+
+    /// Sets the timing of reads, and of writes if no write timing is set
+    pub fn read_timing(mut self, timing: Timing) -> Self {
+        self.read_timing = timing;
+        self
+    }
+
+    /// Sets a separate timing for writes (extended mode)
+    pub fn write_timing(mut self, timing: Timing) -> Self {
+        self.write_timing = Some(timing);
+        self
+    }
+}
+
+/// FMC peripheral
+pub struct Fmc {
+    fmc: FMC,
+}
+
+/// Writes the control and timing registers of a bank
+macro_rules! configure_bank {
+    ($fmc:expr, $config:expr, $bcrX:ident, $btrX:ident, $bwtrX:ident) => {{
+        let config = $config;
+        let read = config.read_timing;
+        let write = config.write_timing.unwrap_or(read);
+
+        // Disable the bank while changing its configuration
+        $fmc.$bcrX.modify(|_, w| w.mbken().clear_bit());
+
+        // This is sound, as the timings are limited to the width of their fields by `Timing`.
+        $fmc.$btrX.write(|w| unsafe {
+            w.addset()
+                .bits(read.address_setup)
+                .addhld()
+                .bits(read.address_hold)
+                .datast()
+                .bits(read.data_setup)
+                .busturn()
+                .bits(read.bus_turnaround)
+                .accmod()
+                .bits(read.access_mode as u8)
+        });
+        $fmc.$bwtrX.write(|w| unsafe {
+            w.addset()
+                .bits(write.address_setup)
+                .addhld()
+                .bits(write.address_hold)
+                .datast()
+                .bits(write.data_setup)
+                .accmod()
+                .bits(write.access_mode as u8)
+        });
+
+        // This is sound, as all `MemoryType` and `DataWidth` values are valid for these fields.
+        $fmc.$bcrX.modify(|_, w| unsafe {
+            w.muxen()
+                .bit(config.multiplexed)
+                .mtyp()
+                .bits(config.memory_type as u8)
+                .mwid()
+                .bits(config.data_width as u8)
+                .faccen()
+                .bit(config.memory_type == MemoryType::Nor)
+                .bursten()
+                .clear_bit()
+                .waiten()
+                .clear_bit()
+                .asyncwait()
+                .clear_bit()
+                .wren()
+                .bit(config.write_enable)
+                .extmod()
+                .bit(config.write_timing.is_some())
+                .cburstrw()
+                .clear_bit()
+                .mbken()
+                .set_bit()
+        });
+    }};
+}
+
+impl Fmc {
+    /// Enables and resets the FMC
+    pub fn new(fmc: FMC, ahb3: &mut AHB3) -> Self {
+        ahb3.enr().modify(|_, w| w.fmcen().set_bit());
+        ahb3.rstr().modify(|_, w| w.fmcrst().set_bit());
+        ahb3.rstr().modify(|_, w| w.fmcrst().clear_bit());
+
+        // NOTE(unsafe) FMCEN is bit 31 of BCR1, which enables the whole controller but is not
+        // described by the PAC
+        fmc.bcr1
+            .modify(|r, w| unsafe { w.bits(r.bits() | (1 << 31)) });
+
+        Fmc { fmc }
+    }
+
+    /// Configures and enables a NOR/SRAM bank
+    pub fn nor_sram(&mut self, bank: Bank, config: Config) -> NorSramBank {
+        match bank {
+            Bank::Bank1 => configure_bank!(self.fmc, config, bcr1, btr1, bwtr1),
+            Bank::Bank2 => configure_bank!(self.fmc, config, bcr2, btr2, bwtr2),
+            Bank::Bank3 => configure_bank!(self.fmc, config, bcr3, btr3, bwtr3),
+            Bank::Bank4 => configure_bank!(self.fmc, config, bcr4, btr4, bwtr4),
+        }
+
+        NorSramBank {
+            bank,
+            data_width: config.data_width,
+        }
+    }
+
+    /// Releases the FMC peripheral
+    pub fn free(self) -> FMC {
+        self.fmc
+    }
+}
+
+/// Enabled NOR/SRAM bank, see [`Fmc::nor_sram`]
+pub struct NorSramBank {
+    bank: Bank,
+    data_width: DataWidth,
+}
+
+impl NorSramBank {
+    /// Returns the address the bank is mapped at
+    pub fn address(&self) -> usize {
+        BANK_BASE + self.bank as usize * BANK_SIZE
+    }
+
+    /// Returns a pointer to the start of the memory
+    ///
+    /// Accesses must go through `ptr::read_volatile` and `ptr::write_volatile`, and stay within
+    /// the size of the memory.
+    pub fn as_mut_ptr<T>(&self) -> *mut T {
+        self.address() as *mut T
+    }
+
+    /// Creates an 8080-style display interface on the bank, see [`Config::lcd_8080`]
+    ///
+    /// `address_line` is the FMC address line (`A0` to `A25`) connected to the D/CX pin of the
+    /// display, low for commands and high for data.
+    pub fn lcd(self, address_line: u8) -> Lcd {
+        assert!(address_line <= 25);
+
+        // The address lines select half-words on a 16-bit bus
+        let shift = match self.data_width {
+            DataWidth::Bits8 => address_line,
+            DataWidth::Bits16 => address_line + 1,
+        };
+
+        Lcd {
+            command: self.address(),
+            data: self.address() + (1 << shift),
+            data_width: self.data_width,
+        }
+    }
+}
+
+/// 8080-style display interface, see [`NorSramBank::lcd`]
+///
+/// On an 8-bit bus every access is a single byte access, so only the lower byte of the values
+/// is written, and reads return the byte in the lower half.
+pub struct Lcd {
+    command: usize,
+    data: usize,
+    data_width: DataWidth,
+}
+
+impl Lcd {
+    /// Writes to the command register (D/CX low)
+    pub fn write_command(&mut self, command: u16) {
+        self.write(self.command, command)
+    }
+
+    /// Writes to the data register (D/CX high)
+    pub fn write_data(&mut self, data: u16) {
+        self.write(self.data, data)
+    }
+
+    /// Reads from the data register (D/CX high)
+    pub fn read_data(&mut self) -> u16 {
+        // NOTE(unsafe) the address lies within the memory region of an enabled bank. A 16-bit
+        // access on an 8-bit bank is split into two byte accesses at consecutive addresses, which
+        // would toggle D/CX on A0, so the access size follows the bus width.
+        unsafe {
+            match self.data_width {
+                DataWidth::Bits8 => ptr::read_volatile(self.data as *const u8) as u16,
+                DataWidth::Bits16 => ptr::read_volatile(self.data as *const u16),
+            }
+        }
+    }
+
+    fn write(&mut self, address: usize, value: u16) {
+        // NOTE(unsafe) see `read_data`
+        unsafe {
+            match self.data_width {
+                DataWidth::Bits8 => ptr::write_volatile(address as *mut u8, value as u8),
+                DataWidth::Bits16 => ptr::write_volatile(address as *mut u16, value),
+            }
+        }
+    }
+}
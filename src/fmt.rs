@@ -0,0 +1,10 @@
+//! Logging of the drivers, through `defmt` with the `defmt` feature
+//!
+//! Without the feature the macros expand to nothing, and their arguments are not evaluated.
+
+macro_rules! trace {
+    ($($arg:tt)+) => {
+        #[cfg(feature = "defmt")]
+        defmt::trace!($($arg)+);
+    };
+}
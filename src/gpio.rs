@@ -1,4 +1,13 @@
 //! General Purpose Input / Output
+//!
+//! # Digital readback
+//!
+//! The level on a pin is read through `IDR`, from its input buffer, which is on in every mode but
+//! analog. So [`InputPin`](crate::hal::digital::v2::InputPin) is implemented for pins in
+//! `Input`, `Output<OpenDrain>`, `Alternate` and `AlternateOD` mode. `StatefulOutputPin` reports
+//! the level an output is set to in `ODR`, while `is_pin_high` and `is_pin_low` read the level
+//! on an output pin of either type in `IDR`, e.g. to tell another device pulls the line. In
+//! `Analog` mode the input buffer is disconnected and `IDR` reads low, see `read_raw_idr`.
 
 // Based on
 // https://github.com/japaric/stm32f30x-hal/blob/master/src/gpio.rs
@@ -8,6 +17,11 @@ use core::marker::PhantomData;
 use crate::rcc::{AHB2, APB2};
 use crate::stm32::{EXTI, SYSCFG};
 
+pub mod debounce;
+pub mod exti;
+
+pub use exti::ExtiLine;
+
 /// Extension trait to split a GPIO peripheral in independent pins and registers
 pub trait GpioExt {
     /// The to split the GPIO into
@@ -17,6 +31,65 @@ pub trait GpioExt {
     fn split(self, ahb: &mut AHB2) -> Self::Parts;
 }
 
+/// Defines a struct holding the configured pins of a board, and a `configure` constructor
+///
+/// Each field is initialized by an expression on the split GPIO ports passed to `configure`, so
+/// the whole pinout lives in one place. This expands to plain field initializations, there is no
+/// runtime cost.
+///
+/// ```ignore
+/// stm32l4xx_hal::pinout! {
+///     /// Pinout of the board
+///     pub struct Board(gpioa: gpioa::Parts, gpiob: gpiob::Parts) {
+///         /// User LED
+///         led: PA5<Output<PushPull>> =
+///             gpioa.pa5.into_push_pull_output(&mut gpioa.moder, &mut gpioa.otyper),
+///         /// User button
+///         button: PB3<Input<PullUp>> =
+///             gpiob.pb3.into_pull_up_input(&mut gpiob.moder, &mut gpiob.pupdr),
+///         spi_sck: PA1<Alternate<AF5, Input<Floating>>> =
+///             gpioa.pa1.into_af5(&mut gpioa.moder, &mut gpioa.afrl),
+///     }
+/// }
+///
+/// let board = Board::configure(gpioa, gpiob);
+/// ```
+///
+/// The types are resolved where the macro is used, so the pin and mode types must be in scope
+/// there.
+#[macro_export]
+macro_rules! pinout {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $Name:ident($($gpio:ident: $Parts:ty),+ $(,)?) {
+            $(
+                $(#[$field_meta:meta])*
+                $field:ident: $Pin:ty = $init:expr
+            ),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $Name {
+            $(
+                $(#[$field_meta])*
+                pub $field: $Pin,
+            )+
+        }
+
+        impl $Name {
+            /// Configures the pins
+            #[allow(unused_mut)]
+            pub fn configure($(mut $gpio: $Parts),+) -> Self {
+                $(
+                    let $field = $init;
+                )+
+
+                $Name { $($field),+ }
+            }
+        }
+    };
+}
+
 /// Input mode (type state)
 pub struct Input<MODE> {
     _mode: PhantomData<MODE>,
@@ -62,6 +135,8 @@ pub struct AlternateOD<AF, MODE> {
     _mode: PhantomData<MODE>,
 }
 
+/// Initial level of an output pin
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum State {
     High,
     Low,
@@ -176,7 +251,7 @@ macro_rules! gpio {
             use core::marker::PhantomData;
             use core::convert::Infallible;
 
-            use crate::hal::digital::v2::{OutputPin, InputPin};
+            use crate::hal::digital::v2::{OutputPin, InputPin, StatefulOutputPin, toggleable};
             use crate::stm32::{$gpioy, $GPIOX, EXTI, SYSCFG};
 
             use crate::rcc::{AHB2, APB2};
@@ -184,7 +259,7 @@ macro_rules! gpio {
 
                 Alternate, AlternateOD,
                 AF0, AF1, AF2, AF3, AF4, AF5, AF6, AF7, AF8, AF9, AF10, AF11, AF12, AF13, AF14, AF15,
-                Floating, GpioExt, Input, OpenDrain, Output, Analog, Edge, ExtiPin,
+                Floating, GpioExt, Input, OpenDrain, Output, Analog, Edge, ExtiPin, ExtiLine,
                 PullDown, PullUp, PushPull, State, Speed,
             };
 
@@ -302,6 +377,29 @@ macro_rules! gpio {
                 _mode: PhantomData<MODE>,
             }
 
+            impl<MODE> $PXx<MODE> {
+                /// Returns the EXTI line of the pin, see [`exti`](super::exti)
+                pub fn exti_line(&self) -> ExtiLine {
+                    ExtiLine::new(self.i)
+                }
+            }
+
+            impl<MODE> $PXx<Output<MODE>> {
+                /// Returns `true` if the level on the pin is high, whatever the output is set to
+                ///
+                /// See the pins with their number in the type, e.g.
+                /// [`PA0::is_pin_high`](super::gpioa::PA0::is_pin_high).
+                pub fn is_pin_high(&self) -> bool {
+                    !self.is_pin_low()
+                }
+
+                /// Returns `true` if the level on the pin is low, see [`Self::is_pin_high`]
+                pub fn is_pin_low(&self) -> bool {
+                    // NOTE(unsafe) atomic read with no side effects
+                    unsafe { (*$GPIOX::ptr()).idr.read().bits() & (1 << self.i) == 0 }
+                }
+            }
+
             impl<MODE> OutputPin for $PXx<Output<MODE>> {
                 type Error = Infallible;
 
@@ -318,6 +416,32 @@ macro_rules! gpio {
                 }
             }
 
+            impl<MODE> StatefulOutputPin for $PXx<Output<MODE>> {
+                fn is_set_high(&self) -> Result<bool, Self::Error> {
+                    Ok(!self.is_set_low().unwrap())
+                }
+
+                fn is_set_low(&self) -> Result<bool, Self::Error> {
+                    // NOTE(unsafe) atomic read with no side effects
+                    Ok(unsafe { (*$GPIOX::ptr()).odr.read().bits() & (1 << self.i) == 0 })
+                }
+            }
+
+            impl<MODE> toggleable::Default for $PXx<Output<MODE>> {}
+
+            impl InputPin for $PXx<Output<OpenDrain>> {
+                type Error = Infallible;
+
+                fn is_high(&self) -> Result<bool, Self::Error> {
+                    Ok(!self.is_low().unwrap())
+                }
+
+                fn is_low(&self) -> Result<bool, Self::Error> {
+                    // NOTE(unsafe) atomic read with no side effects
+                    Ok(unsafe { (*$GPIOX::ptr()).idr.read().bits() & (1 << self.i) == 0 })
+                }
+            }
+
             impl<MODE> ExtiPin for $PXx<Input<MODE>> {
                 /// Make corresponding EXTI line sensitive to this pin
                 fn make_interrupt_source(&mut self, syscfg: &mut SYSCFG, apb2: &mut APB2) {
@@ -395,6 +519,9 @@ macro_rules! gpio {
 
                 impl<MODE> $PXi<MODE> {
                     /// Configures the pin to operate as a floating input pin
+                    ///
+                    /// `PUPDR` is written along with `MODER`, so a pull resistor enabled by an
+                    /// earlier configuration of the pin is disabled.
                     pub fn into_floating_input(
                         self,
                         moder: &mut MODER,
@@ -416,6 +543,8 @@ macro_rules! gpio {
                     }
 
                     /// Configures the pin to operate as a pulled down input pin
+                    ///
+                    /// `PUPDR` is written along with `MODER`, replacing any earlier pull-up.
                     pub fn into_pull_down_input(
                         self,
                         moder: &mut MODER,
@@ -437,6 +566,8 @@ macro_rules! gpio {
                     }
 
                     /// Configures the pin to operate as a pulled up input pin
+                    ///
+                    /// `PUPDR` is written along with `MODER`, replacing any earlier pull-down.
                     pub fn into_pull_up_input(
                         self,
                         moder: &mut MODER,
@@ -458,6 +589,9 @@ macro_rules! gpio {
                     }
 
                     /// Configures the pin to operate as an open drain output pin
+                    ///
+                    /// The output keeps the level previously written to `ODR`, see
+                    /// [`Self::into_open_drain_output_with_state`] to choose it.
                     pub fn into_open_drain_output(
                         self,
                         moder: &mut MODER,
@@ -465,20 +599,41 @@ macro_rules! gpio {
                     ) -> $PXi<Output<OpenDrain>> {
                         let offset = 2 * $i;
 
+                        // open drain output, set before the output is enabled
+                        otyper
+                            .otyper()
+                            .modify(|r, w| unsafe { w.bits(r.bits() | (0b1 << $i)) });
+
                         // general purpose output mode
                         let mode = 0b01;
                         moder.moder().modify(|r, w| unsafe {
                             w.bits((r.bits() & !(0b11 << offset)) | (mode << offset))
                         });
 
-                        // open drain output
-                        otyper
-                            .otyper()
-                            .modify(|r, w| unsafe { w.bits(r.bits() | (0b1 << $i)) });
-
                         $PXi { _mode: PhantomData }
                     }
 
+                    /// Configures the pin to operate as an open drain output pin
+                    /// Initial state can be chosen to be high (released) or low
+                    ///
+                    /// The level is written to `BSRR` before `MODER` enables the output, see
+                    /// [`Self::into_push_pull_output_with_state`].
+                    pub fn into_open_drain_output_with_state(
+                        self,
+                        moder: &mut MODER,
+                        otyper: &mut OTYPER,
+                        initial_state: State,
+                    ) -> $PXi<Output<OpenDrain>> {
+                        let mut res: $PXi<Output<OpenDrain>> = $PXi { _mode: PhantomData };
+
+                        match initial_state {
+                            State::High => res.set_high().unwrap(),
+                            State::Low => res.set_low().unwrap(),
+                        }
+
+                        res.into_open_drain_output(moder, otyper)
+                    }
+
                     /// Configures the pin to operate as an push pull output pin
                     /// Initial state will be low
                     pub fn into_push_pull_output(
@@ -491,6 +646,11 @@ macro_rules! gpio {
 
                     /// Configures the pin to operate as an push pull output pin
                     /// Initial state can be chosen to be high or low
+                    ///
+                    /// The level is written to `BSRR`, and the output type to `OTYPER`, before
+                    /// `MODER` enables the output, so the pin never drives another level, e.g.
+                    /// for an active low reset line or a MOSFET gate. Only the pull resistors,
+                    /// if any, drive the pin until then.
                     pub fn into_push_pull_output_with_state(
                         self,
                         moder: &mut MODER,
@@ -501,7 +661,6 @@ macro_rules! gpio {
 
                         // set pin high/low before activating, to prevent
                         // spurious signals (e.g. LED flash)
-                        // TODO: I still see a flash of LED using this order
                         match initial_state {
                             State::High => res.set_high().unwrap(),
                             State::Low => res.set_low().unwrap(),
@@ -509,23 +668,30 @@ macro_rules! gpio {
 
                         let offset = 2 * $i;
 
+                        // push pull output, set before the output is enabled, as an open drain
+                        // output set earlier would release a high level
+                        otyper
+                            .otyper()
+                            .modify(|r, w| unsafe { w.bits(r.bits() & !(0b1 << $i)) });
+
                         // general purpose output mode
                         let mode = 0b01;
                         moder.moder().modify(|r, w| unsafe {
                             w.bits((r.bits() & !(0b11 << offset)) | (mode << offset))
                         });
 
-                        // push pull output
-                        otyper
-                            .otyper()
-                            .modify(|r, w| unsafe { w.bits(r.bits() & !(0b1 << $i)) });
-
                         res
                     }
 
                         /// Configures the pin to operate as analog.
                         /// This mode is suitable when the pin is connected to the DAC or ADC,
                         /// COMP, OPAMP.
+                        ///
+                        /// The pull-up and pull-down resistors are disconnected, as they would
+                        /// skew the readings. On parts with an analog switch control register
+                        /// (ASCR), the pin is also connected to the ADC input.
+                        ///
+                        /// Convert the pin before using it as an ADC channel.
                         pub fn into_analog(
                         self,
                         moder: &mut MODER,
@@ -543,6 +709,17 @@ macro_rules! gpio {
                         pupdr
                             .pupdr()
                             .modify(|r, w| unsafe { w.bits(r.bits() & !(0b11 << offset)) });
+
+                        // connect the analog switch
+                        // NOTE(unsafe) ASCR is only written here, while holding the exclusive
+                        // `MODER` of the same port, so this read-modify-write can't race.
+                        #[cfg(feature = "stm32l4x6")]
+                        unsafe {
+                            (*$GPIOX::ptr())
+                                .ascr
+                                .modify(|r, w| w.bits(r.bits() | (1 << $i)));
+                        }
+
                         $PXi { _mode: PhantomData }
                     }
 
@@ -587,6 +764,26 @@ macro_rules! gpio {
                 }
 
                 impl<MODE> $PXi<Output<MODE>> {
+                    /// Returns `true` if the level on the pin is high, whatever the output is set
+                    /// to
+                    ///
+                    /// This reads `IDR`, from the input buffer, which stays connected in output
+                    /// mode, while `StatefulOutputPin::is_set_high` reads back `ODR`, the level
+                    /// the output is set to. The two differ when something else drives the line:
+                    /// an open-drain output set high is released, so another device can pull it
+                    /// low, and a push pull output fighting a shorted line doesn't reach its
+                    /// level. For open-drain outputs, this is what `InputPin::is_high` returns.
+                    pub fn is_pin_high(&self) -> bool {
+                        !self.is_pin_low()
+                    }
+
+                    /// Returns `true` if the level on the pin is low, see
+                    /// [`Self::is_pin_high`]
+                    pub fn is_pin_low(&self) -> bool {
+                        // NOTE(unsafe) atomic read with no side effects
+                        unsafe { (*$GPIOX::ptr()).idr.read().bits() & (1 << $i) == 0 }
+                    }
+
                     /// Erases the pin number from the type
                     ///
                     /// This is useful when you want to collect the pins into an array where you
@@ -652,6 +849,43 @@ macro_rules! gpio {
                     }
                 }
 
+                impl<MODE> $PXi<MODE> {
+                    /// Locks the configuration of the pin until the next reset
+                    ///
+                    /// This runs the `LCKR` lock sequence, after which the hardware ignores writes
+                    /// to the mode, output type, speed, pull and alternate function of the pin.
+                    /// Reconfiguring the pin through its type afterwards compiles, but has no
+                    /// effect. This is irreversible until reset.
+                    ///
+                    /// The lock applies to the whole port: once a pin is locked, no further pins of
+                    /// the port can be locked. Returns `false` if the pin could not be locked.
+                    pub fn lock(&mut self) -> bool {
+                        // NOTE(unsafe) the lock sequence is the only write to `LCKR`, and runs in
+                        // a critical section so it isn't interleaved with another one
+                        let gpio = unsafe { &*$GPIOX::ptr() };
+
+                        cortex_m::interrupt::free(|_| {
+                            let pins = (gpio.lckr.read().bits() & 0xffff) | (1 << $i);
+
+                            // Write LCKK=1, LCKK=0, LCKK=1, with the same pin bits every time
+                            gpio.lckr.write(|w| unsafe { w.bits(pins | (1 << 16)) });
+                            gpio.lckr.write(|w| unsafe { w.bits(pins) });
+                            gpio.lckr.write(|w| unsafe { w.bits(pins | (1 << 16)) });
+                            let _ = gpio.lckr.read();
+                        });
+
+                        self.is_locked()
+                    }
+
+                    /// Returns `true` if the configuration of the pin is locked
+                    pub fn is_locked(&self) -> bool {
+                        // NOTE(unsafe) atomic read with no side effects
+                        let lckr = unsafe { (*$GPIOX::ptr()).lckr.read() };
+
+                        lckr.lckk().bit_is_set() && lckr.bits() & (1 << $i) != 0
+                    }
+                }
+
                 impl<MODE> OutputPin for $PXi<Output<MODE>> {
                     type Error = Infallible;
 
@@ -668,6 +902,19 @@ macro_rules! gpio {
                     }
                 }
 
+                impl<MODE> StatefulOutputPin for $PXi<Output<MODE>> {
+                    fn is_set_high(&self) -> Result<bool, Self::Error> {
+                        Ok(!self.is_set_low().unwrap())
+                    }
+
+                    fn is_set_low(&self) -> Result<bool, Self::Error> {
+                        // NOTE(unsafe) atomic read with no side effects
+                        Ok(unsafe { (*$GPIOX::ptr()).odr.read().bits() & (1 << $i) == 0 })
+                    }
+                }
+
+                impl<MODE> toggleable::Default for $PXi<Output<MODE>> {}
+
                 impl<MODE> InputPin for $PXi<Input<MODE>> {
                     type Error = Infallible;
 
@@ -681,6 +928,67 @@ macro_rules! gpio {
                     }
                 }
 
+                impl InputPin for $PXi<Output<OpenDrain>> {
+                    type Error = Infallible;
+
+                    fn is_high(&self) -> Result<bool, Self::Error> {
+                        Ok(!self.is_low().unwrap())
+                    }
+
+                    fn is_low(&self) -> Result<bool, Self::Error> {
+                        // NOTE(unsafe) atomic read with no side effects
+                        Ok(unsafe { (*$GPIOX::ptr()).idr.read().bits() & (1 << $i) == 0 })
+                    }
+                }
+
+                /// Reads the level on the pin, as its input buffer stays on in alternate mode
+                impl<AF, MODE> InputPin for $PXi<Alternate<AF, MODE>> {
+                    type Error = Infallible;
+
+                    fn is_high(&self) -> Result<bool, Self::Error> {
+                        Ok(!self.is_low().unwrap())
+                    }
+
+                    fn is_low(&self) -> Result<bool, Self::Error> {
+                        // NOTE(unsafe) atomic read with no side effects
+                        Ok(unsafe { (*$GPIOX::ptr()).idr.read().bits() & (1 << $i) == 0 })
+                    }
+                }
+
+                /// Reads the level on the pin, as its input buffer stays on in alternate mode
+                impl<AF, MODE> InputPin for $PXi<AlternateOD<AF, MODE>> {
+                    type Error = Infallible;
+
+                    fn is_high(&self) -> Result<bool, Self::Error> {
+                        Ok(!self.is_low().unwrap())
+                    }
+
+                    fn is_low(&self) -> Result<bool, Self::Error> {
+                        // NOTE(unsafe) atomic read with no side effects
+                        Ok(unsafe { (*$GPIOX::ptr()).idr.read().bits() & (1 << $i) == 0 })
+                    }
+                }
+
+                impl $PXi<Analog> {
+                    /// Reads the bit of the pin in `IDR`, whatever the level on the pin
+                    ///
+                    /// The input buffer is disconnected in analog mode, so the bit reads `false`
+                    /// (low) as long as the pin stays in this mode. This is no digital readback,
+                    /// hence no `InputPin` implementation; it is meant to check the state of the
+                    /// register for debugging.
+                    pub fn read_raw_idr(&self) -> bool {
+                        // NOTE(unsafe) atomic read with no side effects
+                        unsafe { (*$GPIOX::ptr()).idr.read().bits() & (1 << $i) != 0 }
+                    }
+                }
+
+                impl<MODE> $PXi<MODE> {
+                    /// Returns the EXTI line of the pin, see [`exti`](super::exti)
+                    pub fn exti_line(&self) -> ExtiLine {
+                        ExtiLine::new($i)
+                    }
+                }
+
                 impl<MODE> ExtiPin for $PXi<Input<MODE>> {
                     /// Configure EXTI Line $i to trigger from this pin.
                     fn make_interrupt_source(&mut self, syscfg: &mut SYSCFG, apb2: &mut APB2) {
@@ -0,0 +1,166 @@
+//! Debouncing of digital inputs
+//!
+//! A [`Debouncer`] wraps any `InputPin` and only reports a new logical state
+//! after the pin was sampled at that level a number of times in a row. Call
+//! [`Debouncer::update`] at a fixed rate, e.g. from a timer interrupt; with a
+//! 1 kHz sample rate and 10 samples, a level change is accepted after 10 ms.
+
+use crate::hal::digital::v2::InputPin;
+
+/// Debounced input
+pub struct Debouncer<PIN> {
+    pin: PIN,
+    samples: u8,
+    count: u8,
+    active_low: bool,
+    pressed: bool,
+    rose: bool,
+    fell: bool,
+}
+
+impl<PIN> Debouncer<PIN>
+where
+    PIN: InputPin,
+{
+    /// Wraps `pin`, accepting a new level after `samples` identical samples
+    ///
+    /// The input starts out released, and is pressed while the pin is high,
+    /// see [`Debouncer::active_low`] for buttons pulling the pin low.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `samples` is zero.
+    pub fn new(pin: PIN, samples: u8) -> Self {
+        assert!(samples > 0);
+
+        Debouncer {
+            pin,
+            samples,
+            count: 0,
+            active_low: false,
+            pressed: false,
+            rose: false,
+            fell: false,
+        }
+    }
+
+    /// Treats a low pin as pressed
+    pub fn active_low(mut self) -> Self {
+        self.active_low = true;
+        self
+    }
+
+    /// Samples the pin, to be called at a fixed rate
+    ///
+    /// The edges reported by [`Debouncer::rose`] and [`Debouncer::fell`] are
+    /// only valid until the next call.
+    pub fn update(&mut self) -> Result<(), PIN::Error> {
+        let pressed = self.pin.is_high()? != self.active_low;
+
+        self.rose = false;
+        self.fell = false;
+
+        if pressed == self.pressed {
+            self.count = 0;
+        } else {
+            self.count += 1;
+            if self.count == self.samples {
+                self.count = 0;
+                self.pressed = pressed;
+                self.rose = pressed;
+                self.fell = !pressed;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns `true` while the debounced input is pressed
+    pub fn is_pressed(&self) -> bool {
+        self.pressed
+    }
+
+    /// Returns `true` if the last update accepted a press
+    pub fn rose(&self) -> bool {
+        self.rose
+    }
+
+    /// Returns `true` if the last update accepted a release
+    pub fn fell(&self) -> bool {
+        self.fell
+    }
+
+    /// Releases the pin
+    pub fn free(self) -> PIN {
+        self.pin
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+    use core::convert::Infallible;
+
+    /// Pin whose level is set by the test
+    struct FakePin<'a>(&'a Cell<bool>);
+
+    impl InputPin for FakePin<'_> {
+        type Error = Infallible;
+
+        fn is_high(&self) -> Result<bool, Infallible> {
+            Ok(self.0.get())
+        }
+
+        fn is_low(&self) -> Result<bool, Infallible> {
+            Ok(!self.0.get())
+        }
+    }
+
+    /// Samples `levels` in turn, returning the pressed state and edges after each
+    fn sample(input: &mut Debouncer<FakePin>, level: &Cell<bool>, levels: &[bool]) -> [bool; 3] {
+        let mut last = [false; 3];
+        for &high in levels {
+            level.set(high);
+            input.update().unwrap();
+            last = [input.is_pressed(), input.rose(), input.fell()];
+        }
+
+        last
+    }
+
+    #[test]
+    fn bounces_are_ignored() {
+        let level = Cell::new(false);
+        let mut input = Debouncer::new(FakePin(&level), 3);
+
+        // Two samples are not enough, and a bounce restarts the count
+        assert_eq!(sample(&mut input, &level, &[true, true, false]), [false; 3]);
+        assert_eq!(sample(&mut input, &level, &[true, true]), [false; 3]);
+        // The third sample in a row is, and the edge lasts one update
+        assert_eq!(sample(&mut input, &level, &[true]), [true, true, false]);
+        assert_eq!(sample(&mut input, &level, &[true]), [true, false, false]);
+
+        assert_eq!(
+            sample(&mut input, &level, &[false, false, false]),
+            [false, false, true]
+        );
+        assert_eq!(sample(&mut input, &level, &[false]), [false; 3]);
+    }
+
+    #[test]
+    fn active_low() {
+        let level = Cell::new(true);
+        let mut input = Debouncer::new(FakePin(&level), 1).active_low();
+
+        assert_eq!(sample(&mut input, &level, &[true]), [false; 3]);
+        assert_eq!(sample(&mut input, &level, &[false]), [true, true, false]);
+        assert_eq!(sample(&mut input, &level, &[true]), [false, false, true]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn no_samples() {
+        Debouncer::new(FakePin(&Cell::new(false)), 0);
+    }
+}
@@ -0,0 +1,121 @@
+//! Dispatch of the EXTI interrupts of the pins
+//!
+//! The pins of all ports share the 16 EXTI lines by their number, e.g. line 13 is the line of
+//! PA13, PB13, PC13 etc., of which [`ExtiPin::make_interrupt_source`] selects one. Lines 0 to 4
+//! have an interrupt each, while lines 5 to 9 share `EXTI9_5` and lines 10 to 15 share
+//! `EXTI15_10`: the handler of a shared interrupt goes through the lines that fired with
+//! [`pending_lines`], and clears each one with [`clear_interrupt`].
+//!
+//! ```ignore
+//! #[interrupt]
+//! fn EXTI15_10() {
+//!     for line in exti::pending_lines().within(10..=15) {
+//!         exti::clear_interrupt(line);
+//!         match line.number() {
+//!             10 => on_door(),
+//!             13 => on_button(),
+//!             _ => {}
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! [`ExtiPin::make_interrupt_source`]: super::ExtiPin::make_interrupt_source
+
+use core::ops::RangeInclusive;
+
+use crate::stm32::EXTI;
+
+/// EXTI line of the pins, see the [module documentation](self)
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct ExtiLine(u8);
+
+impl ExtiLine {
+    /// Line `number`, the line of the pins with this number
+    ///
+    /// # Panics
+    ///
+    /// Panics if `number` is above 15.
+    pub const fn new(number: u8) -> Self {
+        assert!(number < 16, "no such EXTI line of the pins");
+
+        ExtiLine(number)
+    }
+
+    /// Returns the number of the line, which is also the number of its pins
+    pub const fn number(self) -> u8 {
+        self.0
+    }
+
+    fn mask(self) -> u32 {
+        1 << self.0
+    }
+}
+
+/// Returns `true` if the interrupt of `line` is pending
+pub fn check_interrupt(line: ExtiLine) -> bool {
+    // NOTE(unsafe) atomic read with no side effects
+    unsafe { (*EXTI::ptr()).pr1.read().bits() & line.mask() != 0 }
+}
+
+/// Clears the pending interrupt of `line`
+///
+/// The interrupt is triggered again as long as the line stays pending.
+pub fn clear_interrupt(line: ExtiLine) {
+    // NOTE(unsafe) write 1 to clear, the other lines are not affected
+    unsafe { (*EXTI::ptr()).pr1.write(|w| w.bits(line.mask())) };
+}
+
+/// Returns the lines of the pins whose interrupt is both enabled and pending
+///
+/// The lines are read once, lines firing afterwards are found by the next call.
+pub fn pending_lines() -> PendingLines {
+    // NOTE(unsafe) atomic reads with no side effects
+    let (pending, enabled) = unsafe {
+        let exti = &*EXTI::ptr();
+        (exti.pr1.read().bits(), exti.imr1.read().bits())
+    };
+
+    PendingLines {
+        lines: (pending & enabled) as u16,
+    }
+}
+
+/// Iterator over the pending lines, in increasing order, see [`pending_lines`]
+#[derive(Clone, Copy, Debug)]
+pub struct PendingLines {
+    lines: u16,
+}
+
+impl PendingLines {
+    /// Keeps the lines numbered within `numbers`, e.g. `10..=15` for `EXTI15_10`
+    pub fn within(self, numbers: RangeInclusive<u8>) -> Self {
+        let mask = (0..16)
+            .filter(|number| numbers.contains(number))
+            .fold(0, |mask, number| mask | 1 << number);
+
+        PendingLines {
+            lines: self.lines & mask,
+        }
+    }
+
+    /// Returns `true` if no line is pending
+    pub fn is_empty(&self) -> bool {
+        self.lines == 0
+    }
+}
+
+impl Iterator for PendingLines {
+    type Item = ExtiLine;
+
+    fn next(&mut self) -> Option<ExtiLine> {
+        if self.lines == 0 {
+            return None;
+        }
+
+        let number = self.lines.trailing_zeros() as u8;
+        self.lines &= !(1 << number);
+
+        Some(ExtiLine(number))
+    }
+}
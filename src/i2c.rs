@@ -2,21 +2,44 @@
 //! [stm32h7xx-hal](https://github.com/stm32-rs/stm32h7xx-hal) implementation,
 //! as of 2021-02-25.
 
+#[cfg(feature = "async")]
+use core::future;
+#[cfg(feature = "async")]
+use core::sync::atomic::{self, Ordering};
+#[cfg(feature = "async")]
+use core::task::{Poll, Waker};
+
+#[cfg(feature = "async")]
+use crate::dma::{dma1, AsyncTransfer};
 use crate::gpio::{Alternate, OpenDrain, Output, AF4};
-use crate::hal::blocking::i2c::{Read, Write, WriteRead};
-use crate::pac::{i2c1, I2C1, I2C2};
-use crate::rcc::{Clocks, APB1R1};
+use crate::hal::blocking::delay::DelayUs;
+use crate::hal::blocking::i2c::{Operation, Read, Transactional, Write, WriteRead};
+use crate::hal::digital::v2::{InputPin, OutputPin};
+use crate::pac::{i2c1, EXTI, I2C1, I2C2, RCC};
+use crate::pwr::WakeupSource;
+use crate::rcc::{Clocks, APB1R1, CCIPR};
 #[cfg(feature = "stm32l4x5")]
 use crate::stm32::I2C3;
 use crate::time::Hertz;
 use cast::{u16, u8};
+use core::cell::RefCell;
+use core::cmp;
+use core::convert::Infallible;
 use core::ops::Deref;
+use cortex_m::interrupt::{self, Mutex};
+#[cfg(feature = "async")]
+use embedded_hal_async::i2c::{ErrorKind, NoAcknowledgeSource, Operation as AsyncOperation};
 
 const MAX_NBYTE_SIZE: usize = 255;
 
+/// Reads of `ISR` before a probe of [`I2c::scan`] gives up, many times the length of a probe at
+/// 10 kHz for any bus clock above 1 MHz
+const PROBE_POLLS: u32 = 100_000;
+
 /// I2C error
 #[non_exhaustive]
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     /// Bus error
     Bus,
@@ -24,9 +47,10 @@ pub enum Error {
     Arbitration,
     /// NACK
     Nack,
+    /// The bus didn't complete a transfer in time, see [`I2c::scan`]
+    Timeout,
     // Overrun, // slave mode only
     // Pec, // SMBUS mode only
-    // Timeout, // SMBUS mode only
     // Alert, // SMBUS mode only
 }
 
@@ -60,6 +84,15 @@ pub struct I2c<I2C, PINS> {
     pins: PINS,
 }
 
+/// Configuration registers kept across a reset, see `I2c::reset`
+struct Registers {
+    timingr: u32,
+    timeoutr: u32,
+    oar1: u32,
+    oar2: u32,
+    cr1: u32,
+}
+
 /// Start conditions that govern repeated sequential transfer
 #[derive(Debug, Clone)]
 enum StartCondition {
@@ -131,6 +164,17 @@ impl<SCL, SDA> I2c<I2C1, (SCL, SDA)> {
         apb1.rstr().modify(|_, w| w.i2c1rst().clear_bit());
         Self::new(i2c, pins, freq, clocks)
     }
+
+    /// Pulses the reset line of the peripheral and applies the configuration again
+    ///
+    /// This recovers the peripheral from a stuck state, e.g. a busy flag that never clears. A bus
+    /// held low by a slave has to be released before, see [`BitBangI2c::recover_bus`].
+    pub fn reset(&mut self, apb1: &mut APB1R1) {
+        let config = self.config();
+        apb1.rstr().modify(|_, w| w.i2c1rst().set_bit());
+        apb1.rstr().modify(|_, w| w.i2c1rst().clear_bit());
+        self.restore(config);
+    }
 }
 
 impl<SCL, SDA> I2c<I2C2, (SCL, SDA)> {
@@ -145,6 +189,16 @@ impl<SCL, SDA> I2c<I2C2, (SCL, SDA)> {
         apb1.rstr().modify(|_, w| w.i2c2rst().clear_bit());
         Self::new(i2c, pins, freq, clocks)
     }
+
+    /// Pulses the reset line of the peripheral and applies the configuration again
+    ///
+    /// See [`I2c::reset`](I2c#method.reset).
+    pub fn reset(&mut self, apb1: &mut APB1R1) {
+        let config = self.config();
+        apb1.rstr().modify(|_, w| w.i2c2rst().set_bit());
+        apb1.rstr().modify(|_, w| w.i2c2rst().clear_bit());
+        self.restore(config);
+    }
 }
 
 impl<SCL, SDA, I2C> I2c<I2C, (SCL, SDA)>
@@ -152,6 +206,11 @@ where
     I2C: Deref<Target = i2c1::RegisterBlock>,
 {
     /// Configures the I2C peripheral to work in master mode
+    ///
+    /// # Panics
+    ///
+    /// Panics if `freq` can't be reached from PCLK1, which has to run at least 7 times faster
+    /// than SCL, and at 0.8, 2 or 4 MHz for the standard, fast or fast plus modes.
     fn new<F>(i2c: I2C, pins: (SCL, SDA), freq: F, clocks: Clocks) -> Self
     where
         F: Into<Hertz>,
@@ -172,6 +231,7 @@ where
         // t_SYNC1 + t_SYNC2 > 4 * t_I2CCLK
         // t_SCL ~= t_SYNC1 + t_SYNC2 + t_SCLL + t_SCLH
         let i2cclk = clocks.pclk1().0;
+        assert!(i2cclk / freq >= 7, "impossible I2C frequency");
         let ratio = i2cclk / freq - 4;
         let (presc, scll, sclh, sdadel, scldel) = if freq >= 100_000 {
             // fast-mode or fast-mode plus
@@ -183,12 +243,14 @@ where
 
             let (sdadel, scldel) = if freq > 400_000 {
                 // fast-mode plus
+                assert!(i2cclk >= 4_000_000, "impossible I2C frequency");
                 let sdadel = 0;
                 let scldel = i2cclk / 4_000_000 / (presc + 1) - 1;
 
                 (sdadel, scldel)
             } else {
                 // fast-mode
+                assert!(i2cclk >= 2_000_000, "impossible I2C frequency");
                 let sdadel = i2cclk / 8_000_000 / (presc + 1);
                 let scldel = i2cclk / 2_000_000 / (presc + 1) - 1;
 
@@ -199,13 +261,18 @@ where
         } else {
             // standard-mode
             // here we pick SCLL = SCLH
+            assert!(i2cclk >= 800_000, "impossible I2C frequency");
             let presc = ratio / 514;
 
             let sclh = ((ratio / (presc + 1)) - 2) / 2;
             let scll = sclh;
 
-            let sdadel = i2cclk / 2_000_000 / (presc + 1);
-            let scldel = i2cclk / 800_000 / (presc + 1) - 1;
+            // Below 0.8 MHz per prescaled cycle, a single cycle of SCLDEL is already longer than
+            // the 1.25 µs setup time aimed for. The fields end at 15 cycles, which still exceeds
+            // the 250 ns setup time of the standard mode, as the prescaled clock stays below
+            // 64 MHz here.
+            let sdadel = cmp::min(i2cclk / 2_000_000 / (presc + 1), 15);
+            let scldel = cmp::min((i2cclk / 800_000 / (presc + 1)).saturating_sub(1), 15);
 
             (presc, scll, sclh, sdadel, scldel)
         };
@@ -239,6 +306,89 @@ where
         I2c { i2c, pins }
     }
 
+    /// Returns the achieved SCL frequency, computed from the timings and `clocks`
+    ///
+    /// This assumes the same synchronization delay of 4 PCLK1 cycles as the configuration, plus
+    /// those of the digital filter on both edges, see [`I2c::set_filters`]. The actual frequency
+    /// is somewhat lower as the delay depends on the rise time of the bus.
+    pub fn frequency(&self, clocks: Clocks) -> Hertz {
+        let timingr = self.i2c.timingr.read();
+        let presc = u32::from(timingr.presc().bits()) + 1;
+        let scl = u32::from(timingr.scll().bits()) + u32::from(timingr.sclh().bits()) + 2;
+        let filter = 2 * u32::from(self.i2c.cr1.read().dnf().bits());
+
+        Hertz(clocks.pclk1().0 / (presc * scl + 4 + filter))
+    }
+
+    /// Configures the noise filters of SCL and SDA
+    ///
+    /// The analog filter, enabled by default, suppresses spikes shorter than 50 ns. The digital
+    /// filter suppresses spikes of up to `digital_cycles` PCLK1 cycles, 0 disabling it. The
+    /// peripheral is disabled while the filters are changed, as the hardware requires.
+    ///
+    /// Both filters delay the detection of the edges of SCL, which lengthens the SCL period and
+    /// shortens the data hold time left by the timings: each digital filter cycle adds 2 PCLK1
+    /// cycles to the period. This matters most in Fast-mode Plus, where the low period of SCL
+    /// is only 0.5 µs, so the digital filter should be kept short there, or PCLK1 fast.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `digital_cycles` is above 15.
+    pub fn set_filters(&mut self, analog: bool, digital_cycles: u8) {
+        assert!(digital_cycles <= 15, "at most 15 digital filter cycles");
+
+        // PE stays cleared for the 3 APB cycles of the accesses in between
+        self.i2c.cr1.modify(|_, w| w.pe().clear_bit());
+        self.i2c
+            .cr1
+            .modify(|_, w| w.anfoff().bit(!analog).dnf().bits(digital_cycles));
+        self.i2c.cr1.modify(|_, w| w.pe().set_bit());
+    }
+
+    /// Returns whether the analog filter is enabled and the digital filter cycles, see
+    /// [`I2c::set_filters`]
+    pub fn filters(&self) -> (bool, u8) {
+        let cr1 = self.i2c.cr1.read();
+
+        (cr1.anfoff().bit_is_clear(), cr1.dnf().bits())
+    }
+
+    /// Returns the configuration registers, see [`I2c::restore`]
+    fn config(&self) -> Registers {
+        Registers {
+            timingr: self.i2c.timingr.read().bits(),
+            timeoutr: self.i2c.timeoutr.read().bits(),
+            oar1: self.i2c.oar1.read().bits(),
+            oar2: self.i2c.oar2.read().bits(),
+            cr1: self.i2c.cr1.read().bits(),
+        }
+    }
+
+    /// Writes back the configuration registers after a reset, enabling the peripheral last
+    fn restore(&mut self, config: Registers) {
+        // This is sound, as the values were read back from the same registers. The own addresses
+        // can only be changed while disabled, and the filters of `CR1` while PE is cleared, so
+        // the peripheral is enabled last.
+        self.i2c
+            .timingr
+            .write(|w| unsafe { w.bits(config.timingr) });
+        self.i2c
+            .timeoutr
+            .write(|w| unsafe { w.bits(config.timeoutr) });
+        self.i2c
+            .oar1
+            .write(|w| unsafe { w.bits(config.oar1) }.oa1en().clear_bit());
+        self.i2c.oar1.write(|w| unsafe { w.bits(config.oar1) });
+        self.i2c
+            .oar2
+            .write(|w| unsafe { w.bits(config.oar2) }.oa2en().clear_bit());
+        self.i2c.oar2.write(|w| unsafe { w.bits(config.oar2) });
+        self.i2c
+            .cr1
+            .write(|w| unsafe { w.bits(config.cr1) }.pe().clear_bit());
+        self.i2c.cr1.modify(|_, w| w.pe().bit(config.cr1 & 1 != 0));
+    }
+
     /// Releases the I2C peripheral and associated pins
     pub fn free(self) -> (I2C, (SCL, SDA)) {
         (self.i2c, self.pins)
@@ -269,12 +419,15 @@ macro_rules! busy_wait {
             if isr.$flag().$variant() {
                 break;
             } else if isr.berr().is_error() {
+                trace!("I2C bus error");
                 $i2c.icr.write(|w| w.berrcf().set_bit());
                 return Err(Error::Bus);
             } else if isr.arlo().is_lost() {
+                trace!("I2C arbitration lost");
                 $i2c.icr.write(|w| w.arlocf().set_bit());
                 return Err(Error::Arbitration);
             } else if isr.nackf().bit_is_set() {
+                trace!("I2C NACK");
                 $i2c.icr.write(|w| w.stopcf().set_bit().nackcf().set_bit());
                 flush_txdr!($i2c);
                 return Err(Error::Nack);
@@ -285,6 +438,253 @@ macro_rules! busy_wait {
     };
 }
 
+impl<PINS, I2C> I2c<I2C, PINS>
+where
+    I2C: Deref<Target = i2c1::RegisterBlock>,
+{
+    /// Probes the 7-bit addresses `0x08..=0x77` with a zero-length write, calling `found` with
+    /// each address that is acknowledged
+    ///
+    /// The reserved addresses are skipped. Returns [`Error::Bus`] if the bus stays busy before a
+    /// probe, e.g. as SDA is held low or another master is active, and [`Error::Timeout`] if a
+    /// probe doesn't end, e.g. as a device stretches SCL indefinitely. The peripheral is reset
+    /// in that case, so it may be used again once the bus is released.
+    pub fn scan<F>(&mut self, mut found: F) -> Result<(), Error>
+    where
+        F: FnMut(u8),
+    {
+        // Wait for any previous address sequence to end
+        while self.i2c.cr2.read().start().bit_is_set() {}
+
+        for addr in 0x08..=0x77 {
+            if self.probe(addr)? {
+                found(addr);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sends the address alone and a STOP, returns `true` if it was acknowledged
+    fn probe(&mut self, addr: u8) -> Result<bool, Error> {
+        let mut polls = 0;
+        while self.i2c.isr.read().busy().is_busy() {
+            polls += 1;
+            if polls == PROBE_POLLS {
+                return Err(Error::Bus);
+            }
+        }
+
+        // The STOP follows the address with NBYTES = 0, or the NACK of the address
+        self.i2c.cr2.write(|w| {
+            w.start()
+                .set_bit()
+                .sadd()
+                .bits(u16(addr << 1))
+                .add10()
+                .clear_bit()
+                .rd_wrn()
+                .write()
+                .nbytes()
+                .bits(0)
+                .autoend()
+                .automatic()
+        });
+
+        let mut polls = 0;
+        loop {
+            let isr = self.i2c.isr.read();
+
+            if isr.stopf().is_stop() {
+                let ack = isr.nackf().bit_is_clear();
+                self.i2c
+                    .icr
+                    .write(|w| w.stopcf().set_bit().nackcf().set_bit());
+                return Ok(ack);
+            } else if isr.berr().is_error() {
+                self.i2c.icr.write(|w| w.berrcf().set_bit());
+                return Err(Error::Bus);
+            } else if isr.arlo().is_lost() {
+                self.i2c.icr.write(|w| w.arlocf().set_bit());
+                return Err(Error::Arbitration);
+            }
+
+            polls += 1;
+            if polls == PROBE_POLLS {
+                self.software_reset();
+                return Err(Error::Timeout);
+            }
+        }
+    }
+
+    /// Resets the state machines and releases the lines, keeping the configuration
+    fn software_reset(&mut self) {
+        self.i2c.cr1.modify(|_, w| w.pe().clear_bit());
+        // PE must stay cleared for 3 APB cycles, each read takes at least one
+        for _ in 0..3 {
+            let _ = self.i2c.cr1.read();
+        }
+        self.i2c.cr1.modify(|_, w| w.pe().set_bit());
+    }
+
+    /// Programs CR2 for the next chunk of at most 255 bytes of a transfer, and
+    /// returns the length of that chunk
+    fn start_chunk(
+        &mut self,
+        addr: u8,
+        read: bool,
+        state: &mut State,
+        remaining: usize,
+        last: bool,
+    ) -> usize {
+        let chunk_len = cmp::min(remaining, MAX_NBYTE_SIZE);
+        let condition = state.start_condition(chunk_len);
+
+        self.i2c.cr2.write(|w| {
+            let w = condition
+                .config(w)
+                .sadd()
+                .bits(u16(addr << 1))
+                .add10()
+                .clear_bit()
+                .nbytes()
+                .bits(chunk_len as u8);
+            let w = if read {
+                w.rd_wrn().read()
+            } else {
+                w.rd_wrn().write()
+            };
+
+            // Only the last transfer of a transaction may end it with a STOP
+            if last {
+                w
+            } else {
+                w.autoend().software()
+            }
+        });
+
+        chunk_len
+    }
+
+    /// Runs adjacent operations of the same direction as one transfer, started
+    /// with a (repeated) START
+    fn exec_transfer(
+        &mut self,
+        addr: u8,
+        read: bool,
+        operations: &mut [Operation<'_>],
+        last: bool,
+    ) -> Result<(), Error> {
+        let total: usize = operations
+            .iter()
+            .map(|op| match op {
+                Operation::Read(buffer) => buffer.len(),
+                Operation::Write(bytes) => bytes.len(),
+            })
+            .sum();
+        // Empty reads are skipped by `exec`
+        debug_assert!(total > 0 || !read);
+
+        let mut state = State::new(total);
+        let mut chunk_left = self.start_chunk(addr, read, &mut state, total, last);
+        let mut remaining = total - chunk_left;
+
+        for op in operations {
+            match op {
+                Operation::Write(bytes) => {
+                    for byte in bytes.iter() {
+                        if chunk_left == 0 {
+                            // Wait until NBYTES may be reloaded
+                            busy_wait!(self.i2c, tcr, is_complete);
+                            chunk_left = self.start_chunk(addr, read, &mut state, remaining, last);
+                            remaining -= chunk_left;
+                        }
+
+                        // Wait until we are allowed to send data
+                        busy_wait!(self.i2c, txis, is_empty);
+
+                        // Put byte on the wire
+                        self.i2c.txdr.write(|w| w.txdata().bits(*byte));
+                        chunk_left -= 1;
+                    }
+                }
+                Operation::Read(buffer) => {
+                    for byte in buffer.iter_mut() {
+                        if chunk_left == 0 {
+                            // Wait until NBYTES may be reloaded
+                            busy_wait!(self.i2c, tcr, is_complete);
+                            chunk_left = self.start_chunk(addr, read, &mut state, remaining, last);
+                            remaining -= chunk_left;
+                        }
+
+                        // Wait until we have received something
+                        busy_wait!(self.i2c, rxne, is_not_empty);
+
+                        *byte = self.i2c.rxdr.read().rxdata().bits();
+                        chunk_left -= 1;
+                    }
+                }
+            }
+        }
+
+        if last {
+            // Wait for the automatic STOP
+            busy_wait!(self.i2c, stopf, is_stop);
+            self.i2c.icr.write(|w| w.stopcf().set_bit());
+        } else {
+            // Wait until the transfer finishes, before the repeated START
+            busy_wait!(self.i2c, tc, is_complete);
+        }
+
+        Ok(())
+    }
+}
+
+impl<PINS, I2C> Transactional for I2c<I2C, PINS>
+where
+    I2C: Deref<Target = i2c1::RegisterBlock>,
+{
+    type Error = Error;
+
+    /// Executes `operations` as a single transaction
+    ///
+    /// Adjacent operations of the same direction are sent back to back, a
+    /// repeated START is generated whenever the direction changes, and a single
+    /// STOP ends the transaction. Operations longer than 255 bytes are split
+    /// using `RELOAD`. Reads of no byte are skipped, as a read can't end
+    /// before the first byte.
+    fn exec(&mut self, addr: u8, operations: &mut [Operation<'_>]) -> Result<(), Error> {
+        let is_empty_read =
+            |op: &Operation<'_>| matches!(op, Operation::Read(buffer) if buffer.is_empty());
+
+        // Wait for any previous address sequence to end
+        // automatically. This could be up to 50% of a bus
+        // cycle (ie. up to 0.5/freq)
+        while self.i2c.cr2.read().start().bit_is_set() {}
+
+        let mut rest = operations;
+        loop {
+            let skip = rest.iter().take_while(|op| is_empty_read(op)).count();
+            rest = &mut core::mem::take(&mut rest)[skip..];
+            if rest.is_empty() {
+                break;
+            }
+
+            let read = matches!(rest[0], Operation::Read(_));
+            let len = rest
+                .iter()
+                .position(|op| matches!(op, Operation::Read(_)) != read)
+                .unwrap_or(rest.len());
+            let (transfer, tail) = core::mem::take(&mut rest).split_at_mut(len);
+
+            self.exec_transfer(addr, read, transfer, tail.iter().all(is_empty_read))?;
+            rest = tail;
+        }
+
+        Ok(())
+    }
+}
+
 impl<PINS, I2C> Write for I2c<I2C, PINS>
 where
     I2C: Deref<Target = i2c1::RegisterBlock>,
@@ -457,6 +857,1074 @@ where
     }
 }
 
+#[cfg(feature = "async")]
+impl embedded_hal_async::i2c::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Bus => ErrorKind::Bus,
+            Error::Arbitration => ErrorKind::ArbitrationLoss,
+            Error::Nack => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown),
+            Error::Timeout => ErrorKind::Other,
+        }
+    }
+}
+
+/// I2C paired with its DMA channels, see `I2c::with_dma`
+///
+/// This is the bus of the `async` feature, implementing `embedded_hal_async::i2c::I2c`. The DMA
+/// moves the bytes, and the events of the peripheral end the chunks of at most 255 bytes and the
+/// transfers, so the event and error interrupts of the peripheral have to call
+/// `I2c::on_interrupt`, and be unmasked in the NVIC:
+///
+/// ```ignore
+/// #[interrupt]
+/// fn I2C1_EV() {
+///     I2c::<I2C1, ()>::on_interrupt();
+/// }
+///
+/// #[interrupt]
+/// fn I2C1_ER() {
+///     I2c::<I2C1, ()>::on_interrupt();
+/// }
+/// ```
+///
+/// The interrupts of the DMA channels are not used. Dropping a future stops its transfer and
+/// resets the peripheral, which releases the bus without a STOP.
+#[cfg(feature = "async")]
+pub struct I2cDma<I2C, PINS, RXCH, TXCH> {
+    i2c: I2c<I2C, PINS>,
+    rx_channel: RXCH,
+    tx_channel: TXCH,
+}
+
+#[cfg(feature = "async")]
+impl<I2C, PINS, RXCH, TXCH> I2cDma<I2C, PINS, RXCH, TXCH> {
+    /// Splits the pair back into the I2C and the DMA channels
+    pub fn release(self) -> (I2c<I2C, PINS>, RXCH, TXCH) {
+        (self.i2c, self.rx_channel, self.tx_channel)
+    }
+}
+
+/// Task waiting for an event of the `async` transfer of a peripheral
+#[cfg(feature = "async")]
+type WakerSlot = Mutex<RefCell<Option<Waker>>>;
+
+/// Waits until `flag` is set in `ISR`, or for an error, with the interrupts of the events enabled
+#[cfg(feature = "async")]
+async fn wait_for<F>(i2c: &i2c1::RegisterBlock, slot: &WakerSlot, flag: F) -> Result<(), Error>
+where
+    F: Fn(&i2c1::isr::R) -> bool,
+{
+    future::poll_fn(|cx| {
+        interrupt::free(|cs| {
+            let mut registered = slot.borrow(cs).borrow_mut();
+            match registered.as_ref() {
+                Some(old) if old.will_wake(cx.waker()) => {}
+                _ => *registered = Some(cx.waker().clone()),
+            }
+        });
+
+        let isr = i2c.isr.read();
+        if isr.berr().is_error() {
+            trace!("I2C bus error");
+            i2c.icr.write(|w| w.berrcf().set_bit());
+            return Poll::Ready(Err(Error::Bus));
+        } else if isr.arlo().is_lost() {
+            trace!("I2C arbitration lost");
+            i2c.icr.write(|w| w.arlocf().set_bit());
+            return Poll::Ready(Err(Error::Arbitration));
+        } else if isr.nackf().bit_is_set() {
+            trace!("I2C NACK");
+            i2c.icr.write(|w| w.stopcf().set_bit().nackcf().set_bit());
+            flush_txdr!(i2c);
+            return Poll::Ready(Err(Error::Nack));
+        } else if flag(&isr) {
+            return Poll::Ready(Ok(()));
+        }
+
+        // Enabled after the checks: an event in between still raises the interrupt
+        interrupt::free(|_| {
+            i2c.cr1.modify(|_, w| {
+                w.tcie()
+                    .set_bit()
+                    .stopie()
+                    .set_bit()
+                    .nackie()
+                    .set_bit()
+                    .errie()
+                    .set_bit()
+            })
+        });
+
+        Poll::Pending
+    })
+    .await
+}
+
+/// Disables the DMA requests and the interrupts of an `async` transaction on drop, and resets
+/// the peripheral if the transaction did not end
+#[cfg(feature = "async")]
+struct AsyncGuard<'a> {
+    i2c: &'a i2c1::RegisterBlock,
+    done: bool,
+}
+
+#[cfg(feature = "async")]
+impl Drop for AsyncGuard<'_> {
+    fn drop(&mut self) {
+        interrupt::free(|_| {
+            self.i2c.cr1.modify(|_, w| {
+                w.tcie()
+                    .clear_bit()
+                    .stopie()
+                    .clear_bit()
+                    .nackie()
+                    .clear_bit()
+                    .errie()
+                    .clear_bit()
+                    .txdmaen()
+                    .clear_bit()
+                    .rxdmaen()
+                    .clear_bit()
+            })
+        });
+
+        if !self.done {
+            // The future was dropped in the middle of a transfer, which clearing PE aborts,
+            // see `I2c::software_reset`
+            self.i2c.cr1.modify(|_, w| w.pe().clear_bit());
+            for _ in 0..3 {
+                let _ = self.i2c.cr1.read();
+            }
+            self.i2c.cr1.modify(|_, w| w.pe().set_bit());
+        }
+    }
+}
+
+/// Waits for the end of the last chunk of a transfer, see `I2c::exec_transfer`
+#[cfg(feature = "async")]
+async fn wait_end(i2c: &i2c1::RegisterBlock, slot: &WakerSlot, last: bool) -> Result<(), Error> {
+    if last {
+        // Wait for the automatic STOP
+        wait_for(i2c, slot, |isr| isr.stopf().is_stop()).await?;
+        i2c.icr.write(|w| w.stopcf().set_bit());
+    } else {
+        // Wait until the transfer finishes, before the repeated START
+        wait_for(i2c, slot, |isr| isr.tc().is_complete()).await?;
+    }
+
+    Ok(())
+}
+
+/// Starts a DMA transfer of `len` bytes between `memory` and the data register `register`
+#[cfg(feature = "async")]
+macro_rules! start_dma {
+    (
+        $channel:expr,
+        $csel:ident,
+        $request:expr,
+        $register:expr,
+        $memory:expr,
+        $len:expr,
+        $to_peripheral:expr
+    ) => {
+        $channel.stop();
+        $channel.set_peripheral_address($register as *const _ as u32, false);
+        $channel.set_memory_address($memory, true);
+        $channel.set_transfer_length($len as u16);
+        $channel.cselr().modify(|_, w| w.$csel().bits($request));
+        $channel.ccr().modify(|_, w| unsafe {
+            w.mem2mem()
+                .clear_bit()
+                // 00: 8-bits, 01: 16-bits, 10: 32-bits, 11: Reserved
+                .msize()
+                .bits(0b00)
+                // 00: 8-bits, 01: 16-bits, 10: 32-bits, 11: Reserved
+                .psize()
+                .bits(0b00)
+                .circ()
+                .clear_bit()
+                // Peripheral -> Mem or Mem -> Peripheral
+                .dir()
+                .bit($to_peripheral)
+        });
+        $channel.start();
+    };
+}
+
+#[cfg(feature = "async")]
+macro_rules! async_i2c {
+    ($($I2CX:ident: (
+        rx: ($dmacsr:ident, $rx_chan:path),
+        tx: ($dmacst:ident, $tx_chan:path),
+        $dma_request:expr
+    ),)+) => {
+        $(
+            impl<PINS> I2c<$I2CX, PINS> {
+                /// Pairs the I2C with its DMA channels, for the `async` bus, see [`I2cDma`]
+                pub fn with_dma(
+                    self,
+                    rx_channel: $rx_chan,
+                    tx_channel: $tx_chan,
+                ) -> I2cDma<$I2CX, PINS, $rx_chan, $tx_chan> {
+                    I2cDma {
+                        i2c: self,
+                        rx_channel,
+                        tx_channel,
+                    }
+                }
+            }
+
+            impl I2c<$I2CX, ()> {
+                /// Completes the `async` transfer of the peripheral, to be called from its event
+                /// and error interrupt handlers, see [`I2cDma`]
+                ///
+                /// The interrupts of the peripheral stay disabled until the transfer polls again.
+                pub fn on_interrupt() {
+                    // NOTE(unsafe) the transfers only change the interrupt enables in a critical
+                    // section as well
+                    let i2c = unsafe { &*$I2CX::ptr() };
+                    interrupt::free(|_| {
+                        i2c.cr1.modify(|_, w| {
+                            w.tcie()
+                                .clear_bit()
+                                .stopie()
+                                .clear_bit()
+                                .nackie()
+                                .clear_bit()
+                                .errie()
+                                .clear_bit()
+                        })
+                    });
+
+                    if let Some(waker) = interrupt::free(|cs| Self::waker().borrow(cs).take()) {
+                        waker.wake();
+                    }
+                }
+
+                fn waker() -> &'static WakerSlot {
+                    static WAKER: WakerSlot = Mutex::new(RefCell::new(None));
+
+                    &WAKER
+                }
+            }
+
+            impl<PINS> I2cDma<$I2CX, PINS, $rx_chan, $tx_chan> {
+                /// Runs adjacent operations of the same direction as one transfer, see
+                /// `I2c::exec_transfer`
+                ///
+                /// Every chunk lies within one operation, so it is a single DMA transfer, which
+                /// ends on the `TCR`, `TC` or `STOPF` event of the peripheral.
+                async fn transfer_dma(
+                    &mut self,
+                    addr: u8,
+                    read: bool,
+                    operations: &mut [AsyncOperation<'_>],
+                    last: bool,
+                ) -> Result<(), Error> {
+                    let slot = I2c::<$I2CX, ()>::waker();
+                    // NOTE(unsafe) the registers are only read and written by this transfer
+                    let i2c = unsafe { &*$I2CX::ptr() };
+
+                    let total: usize = operations
+                        .iter()
+                        .map(|op| match op {
+                            AsyncOperation::Read(buffer) => buffer.len(),
+                            AsyncOperation::Write(bytes) => bytes.len(),
+                        })
+                        .sum();
+                    let mut state = State::new(total);
+                    let mut remaining = total;
+
+                    if total == 0 {
+                        // The address alone, e.g. to probe a device
+                        self.i2c.start_chunk(addr, false, &mut state, 0, last);
+                    }
+
+                    for op in operations {
+                        let (buffer, len) = match op {
+                            AsyncOperation::Read(buffer) => (buffer.as_mut_ptr(), buffer.len()),
+                            AsyncOperation::Write(bytes) => {
+                                (bytes.as_ptr() as *mut u8, bytes.len())
+                            }
+                        };
+
+                        let mut offset = 0;
+                        while offset < len {
+                            let chunk_len = cmp::min(len - offset, MAX_NBYTE_SIZE);
+                            // NOTE(unsafe) the chunk lies within the buffer of the operation
+                            let address = unsafe { buffer.add(offset) } as u32;
+
+                            // NOTE(compiler_fence) operations on the buffers should not be
+                            // reordered after the next statements, which start the DMA transfer
+                            atomic::compiler_fence(Ordering::Release);
+
+                            // Dropping the transfer stops the channel
+                            let mut rx_transfer = None;
+                            let mut tx_transfer = None;
+                            if read {
+                                start_dma!(
+                                    self.rx_channel,
+                                    $dmacsr,
+                                    $dma_request,
+                                    &i2c.rxdr,
+                                    address,
+                                    chunk_len,
+                                    false
+                                );
+                                rx_transfer = Some(AsyncTransfer::new(&mut self.rx_channel));
+                            } else {
+                                start_dma!(
+                                    self.tx_channel,
+                                    $dmacst,
+                                    $dma_request,
+                                    &i2c.txdr,
+                                    address,
+                                    chunk_len,
+                                    true
+                                );
+                                tx_transfer = Some(AsyncTransfer::new(&mut self.tx_channel));
+                            }
+
+                            self.i2c.start_chunk(addr, read, &mut state, len - offset, last);
+                            remaining -= chunk_len;
+                            offset += chunk_len;
+
+                            if remaining > 0 {
+                                // Wait until NBYTES may be reloaded
+                                wait_for(i2c, slot, |isr| isr.tcr().is_complete()).await?;
+                            } else {
+                                wait_end(i2c, slot, last).await?;
+                            }
+
+                            // The DMA reads the last byte right after the event
+                            if let Some(transfer) = &rx_transfer {
+                                while transfer.remaining() != 0 {}
+                            }
+                            drop(rx_transfer);
+                            drop(tx_transfer);
+                        }
+                    }
+
+                    if total == 0 {
+                        wait_end(i2c, slot, last).await?;
+                    }
+
+                    Ok(())
+                }
+            }
+            impl<PINS> embedded_hal_async::i2c::ErrorType
+                for I2cDma<$I2CX, PINS, $rx_chan, $tx_chan>
+            {
+                type Error = Error;
+            }
+
+            /// Transfers with the DMA, see [`I2cDma`]
+            ///
+            /// As with [`Transactional`], adjacent operations of the same direction are sent back
+            /// to back, a repeated START is generated whenever the direction changes, and a single
+            /// STOP ends the transaction. Reads of no byte are skipped.
+            impl<PINS> embedded_hal_async::i2c::I2c for I2cDma<$I2CX, PINS, $rx_chan, $tx_chan> {
+                async fn transaction(
+                    &mut self,
+                    address: u8,
+                    operations: &mut [AsyncOperation<'_>],
+                ) -> Result<(), Error> {
+                    let is_empty_read = |op: &AsyncOperation<'_>| {
+                        matches!(op, AsyncOperation::Read(buffer) if buffer.is_empty())
+                    };
+
+                    // Wait for any previous address sequence to end
+                    while self.i2c.i2c.cr2.read().start().bit_is_set() {}
+
+                    // NOTE(unsafe) the guard only disables what the transaction enables
+                    let mut guard = AsyncGuard {
+                        i2c: unsafe { &*$I2CX::ptr() },
+                        done: false,
+                    };
+                    interrupt::free(|_| {
+                        guard
+                            .i2c
+                            .cr1
+                            .modify(|_, w| w.txdmaen().set_bit().rxdmaen().set_bit())
+                    });
+
+                    let mut rest = operations;
+                    let result = loop {
+                        let skip = rest.iter().take_while(|op| is_empty_read(op)).count();
+                        rest = &mut core::mem::take(&mut rest)[skip..];
+                        if rest.is_empty() {
+                            break Ok(());
+                        }
+
+                        let read = matches!(rest[0], AsyncOperation::Read(_));
+                        let len = rest
+                            .iter()
+                            .position(|op| matches!(op, AsyncOperation::Read(_)) != read)
+                            .unwrap_or(rest.len());
+                        let (transfer, tail) = core::mem::take(&mut rest).split_at_mut(len);
+
+                        let last = tail.iter().all(is_empty_read);
+                        if let Err(error) = self.transfer_dma(address, read, transfer, last).await {
+                            break Err(error);
+                        }
+                        rest = tail;
+                    };
+
+                    guard.done = true;
+                    result
+                }
+            }
+        )+
+    }
+}
+
+#[cfg(feature = "async")]
+async_i2c! {
+    I2C1: (
+        rx: (c7s, dma1::C7),
+        tx: (c6s, dma1::C6),
+        0b0011
+    ),
+    I2C2: (
+        rx: (c5s, dma1::C5),
+        tx: (c4s, dma1::C4),
+        0b0011
+    ),
+}
+
+/// I2C peripheral operating in slave mode, answering to its own 7-bit address
+///
+/// The peripheral is clocked from HSI16, which keeps running, or is woken up, while the MCU is in
+/// Stop mode, so the slave can wake the MCU up when it is addressed, see
+/// [`I2cSlave::wakeup_on_address`]. The timings suit the standard and fast modes, up to 400 kHz.
+///
+/// A transfer starts with [`I2cSlave::is_addressed`], and is served with [`I2cSlave::read`] or
+/// [`I2cSlave::write`] depending on its direction. The slave stretches the clock until then.
+pub struct I2cSlave<I2C, PINS> {
+    i2c: I2C,
+    pins: PINS,
+}
+
+/// Direction of a transfer addressed to an [`I2cSlave`], from the point of view of the master
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Direction {
+    /// The master writes, the slave receives with [`I2cSlave::read`]
+    Write,
+    /// The master reads, the slave transmits with [`I2cSlave::write`]
+    Read,
+}
+
+macro_rules! slave {
+    ($($I2CX:ident: ($i2cX:ident, $i2cXen:ident, $i2cXrst:ident, $i2cXsel:ident, $line:expr),)+) => {
+        $(
+            impl<SCL, SDA> I2cSlave<$I2CX, (SCL, SDA)> {
+                /// Configures the I2C peripheral as a slave answering to `address`
+                ///
+                /// This selects HSI16 as clock of the peripheral, and turns it on.
+                pub fn $i2cX(
+                    i2c: $I2CX,
+                    pins: (SCL, SDA),
+                    address: u8,
+                    apb1: &mut APB1R1,
+                    ccipr: &mut CCIPR,
+                ) -> Self
+                where
+                    SCL: SclPin<$I2CX>,
+                    SDA: SdaPin<$I2CX>,
+                {
+                    // NOTE(unsafe) atomic read-modify-write in a critical section, the HSI16 stays
+                    // on for the clocks frozen before, whatever they are
+                    interrupt::free(|_| unsafe {
+                        let rcc = &*RCC::ptr();
+                        rcc.cr.modify(|_, w| w.hsion().set_bit());
+                        while rcc.cr.read().hsirdy().bit_is_clear() {}
+                    });
+
+                    ccipr.ccipr().modify(|_, w| {
+                        // This is sound, as `0b10` is a valid value for this field.
+                        unsafe { w.$i2cXsel().bits(0b10) }
+                    });
+
+                    apb1.enr().modify(|_, w| w.$i2cXen().set_bit());
+                    apb1.rstr().modify(|_, w| w.$i2cXrst().set_bit());
+                    apb1.rstr().modify(|_, w| w.$i2cXrst().clear_bit());
+
+                    Self::new(i2c, pins, address)
+                }
+            }
+
+            impl<PINS> WakeupSource for I2cSlave<$I2CX, PINS> {
+                /// Wakes the MCU up when the slave is addressed, on the `I2C_EV` interrupt
+                ///
+                /// This wakes the MCU up from Stop 0 and Stop 1 modes only, except for I2C3 which
+                /// also supports Stop 2.
+                fn arm(&mut self, exti: &mut EXTI) {
+                    // This is sound, as only the bit of the wakeup line is set.
+                    exti.imr1.modify(|r, w| unsafe { w.bits(r.bits() | (1 << $line)) });
+                    self.i2c.cr1.modify(|_, w| w.addrie().set_bit());
+                    self.wakeup_on_address(true);
+                }
+
+                fn disarm(&mut self, _exti: &mut EXTI) {
+                    self.wakeup_on_address(false);
+                    self.i2c.cr1.modify(|_, w| w.addrie().clear_bit());
+                }
+            }
+        )+
+    }
+}
+
+slave! {
+    I2C1: (i2c1, i2c1en, i2c1rst, i2c1sel, 23),
+    I2C2: (i2c2, i2c2en, i2c2rst, i2c2sel, 24),
+}
+
+impl<SCL, SDA, I2C> I2cSlave<I2C, (SCL, SDA)>
+where
+    I2C: Deref<Target = i2c1::RegisterBlock>,
+{
+    fn new(i2c: I2C, pins: (SCL, SDA), address: u8) -> Self {
+        assert!(address < 0x80, "I2C address beyond 7 bits");
+
+        i2c.cr1.modify(|_, w| w.pe().clear_bit());
+
+        // Data setup and hold times for the standard and fast modes, from HSI16: t_PRESC is
+        // 125 ns, SCLDEL gives a setup time of 500 ns and SDADEL a hold time of 250 ns. SCLL and
+        // SCLH only apply to a master.
+        i2c.timingr
+            .write(|w| w.presc().bits(1).scldel().bits(3).sdadel().bits(2));
+
+        // The own address can only be changed while disabled
+        i2c.oar1.write(|w| w.oa1en().clear_bit());
+        i2c.oar1
+            .write(|w| w.oa1().bits(u16(address) << 1).oa1en().set_bit());
+
+        i2c.cr1.write(|w| w.pe().set_bit());
+
+        I2cSlave { i2c, pins }
+    }
+
+    /// Releases the I2C peripheral and associated pins
+    pub fn free(self) -> (I2C, (SCL, SDA)) {
+        (self.i2c, self.pins)
+    }
+}
+
+impl<PINS, I2C> I2cSlave<I2C, PINS>
+where
+    I2C: Deref<Target = i2c1::RegisterBlock>,
+{
+    /// Lets the slave wake the MCU up from Stop mode when it is addressed (`WUPEN`)
+    ///
+    /// The MCU is woken up by the address match interrupt, see [`I2cSlave::listen`], and the
+    /// address is acknowledged as soon as HSI16 runs. [`I2cSlave`] also implements
+    /// [`WakeupSource`] for [`Pwr::stop_until`](crate::pwr::Pwr::stop_until), arming both.
+    pub fn wakeup_on_address(&mut self, on: bool) {
+        self.i2c.cr1.modify(|_, w| w.wupen().bit(on));
+    }
+
+    /// Starts listening for the address match interrupt, on `I2C_EV`
+    pub fn listen(&mut self) {
+        self.i2c.cr1.modify(|_, w| w.addrie().set_bit());
+    }
+
+    /// Stops listening for the address match interrupt
+    pub fn unlisten(&mut self) {
+        self.i2c.cr1.modify(|_, w| w.addrie().clear_bit());
+    }
+
+    /// Returns the direction of the transfer, if the slave is addressed
+    ///
+    /// The clock is stretched from the address until the transfer is served.
+    pub fn is_addressed(&self) -> Option<Direction> {
+        let isr = self.i2c.isr.read();
+        if isr.addr().is_match_() {
+            Some(if isr.dir().is_read() {
+                Direction::Read
+            } else {
+                Direction::Write
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Receives the bytes written by the master into `buffer`, until its STOP or repeated START
+    ///
+    /// Returns the number of bytes received. Bytes beyond the length of `buffer` are
+    /// acknowledged and dropped.
+    pub fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Error> {
+        self.i2c.icr.write(|w| w.addrcf().set_bit());
+
+        let mut count = 0;
+        loop {
+            let isr = self.i2c.isr.read();
+
+            if isr.rxne().is_not_empty() {
+                let byte = self.i2c.rxdr.read().rxdata().bits();
+                if let Some(slot) = buffer.get_mut(count) {
+                    *slot = byte;
+                    count += 1;
+                }
+            } else if isr.stopf().is_stop() {
+                self.i2c.icr.write(|w| w.stopcf().set_bit());
+                return Ok(count);
+            } else if isr.addr().is_match_() {
+                // A repeated START, left to the next `is_addressed`
+                return Ok(count);
+            } else if isr.berr().is_error() {
+                self.i2c.icr.write(|w| w.berrcf().set_bit());
+                return Err(Error::Bus);
+            }
+        }
+    }
+
+    /// Transmits `bytes` to the master, until it NACKs the last byte it wants
+    ///
+    /// Returns the number of bytes acknowledged by the master. If it reads more than `bytes`,
+    /// the slave sends `0xFF`.
+    pub fn write(&mut self, bytes: &[u8]) -> Result<usize, Error> {
+        // Drop any byte left over from a previous transfer
+        self.i2c.isr.write(|w| w.txe().set_bit());
+        self.i2c.icr.write(|w| w.addrcf().set_bit());
+
+        let mut count = 0;
+        let mut nacked = false;
+        loop {
+            let isr = self.i2c.isr.read();
+
+            if isr.txis().is_empty() {
+                let byte = bytes.get(count).copied().unwrap_or(0xff);
+                self.i2c.txdr.write(|w| w.txdata().bits(byte));
+                count += 1;
+            } else if isr.nackf().bit_is_set() {
+                self.i2c.icr.write(|w| w.nackcf().set_bit());
+                nacked = true;
+            } else if isr.stopf().is_stop() {
+                self.i2c.icr.write(|w| w.stopcf().set_bit());
+                break;
+            } else if isr.berr().is_error() {
+                self.i2c.icr.write(|w| w.berrcf().set_bit());
+                return Err(Error::Bus);
+            }
+        }
+
+        // The last byte sent is not acknowledged if the master NACKed it
+        Ok(cmp::min(count - usize::from(nacked), bytes.len()))
+    }
+}
+
+/// Mutex an I2C bus of a [`SharedBus`] lives in
+///
+/// Implemented by `RefCell`, to share the bus within one execution context, and by
+/// `cortex_m::interrupt::Mutex<RefCell<_>>`, to share it with interrupt handlers.
+pub trait BusMutex {
+    /// The I2C bus
+    type Bus;
+
+    /// Runs `f` with exclusive access to the bus
+    fn lock<R, F>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut Self::Bus) -> R;
+}
+
+impl<I2C> BusMutex for RefCell<I2C> {
+    type Bus = I2C;
+
+    fn lock<R, F>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut I2C) -> R,
+    {
+        f(&mut self.borrow_mut())
+    }
+}
+
+impl<I2C> BusMutex for Mutex<RefCell<I2C>> {
+    type Bus = I2C;
+
+    fn lock<R, F>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut I2C) -> R,
+    {
+        interrupt::free(|cs| f(&mut self.borrow(cs).borrow_mut()))
+    }
+}
+
+/// I2C bus shared by several device drivers, each holding an [`I2cProxy`]
+///
+/// Every call of a proxy is a transaction of its own, with the bus locked for its duration, so
+/// the drivers of devices at different addresses can be used in turns. There are two variants:
+///
+/// - [`SharedBus::new`] keeps the bus in a `RefCell`, for drivers in the same execution context,
+///   e.g. all in `main`. A transaction started from within another one, e.g. from an interrupt
+///   handler, panics.
+/// - [`SharedBus::new_interrupt_safe`] also locks the bus in a critical section, so proxies can be
+///   used from interrupt handlers too, at the cost of disabling interrupts for the whole
+///   transaction. Proxies are only `Send` with a `'static` bus, e.g. created with
+///   `cortex_m::singleton!`.
+pub struct SharedBus<M> {
+    mutex: M,
+}
+
+impl<I2C> SharedBus<RefCell<I2C>> {
+    /// Wraps the I2C bus, to share it within one execution context
+    pub fn new(i2c: I2C) -> Self {
+        SharedBus {
+            mutex: RefCell::new(i2c),
+        }
+    }
+
+    /// Releases the I2C bus
+    pub fn free(self) -> I2C {
+        self.mutex.into_inner()
+    }
+}
+
+impl<I2C> SharedBus<Mutex<RefCell<I2C>>> {
+    /// Wraps the I2C bus, to share it with interrupt handlers
+    ///
+    /// The bus can't be released afterwards, as it is meant to live for the rest of the program.
+    pub fn new_interrupt_safe(i2c: I2C) -> Self {
+        SharedBus {
+            mutex: Mutex::new(RefCell::new(i2c)),
+        }
+    }
+}
+
+impl<M> SharedBus<M>
+where
+    M: BusMutex,
+{
+    /// Creates a proxy of the bus, to hand to a device driver
+    pub fn proxy(&self) -> I2cProxy<'_, M> {
+        I2cProxy { mutex: &self.mutex }
+    }
+}
+
+/// Handle of a [`SharedBus`], see [`SharedBus::proxy`]
+///
+/// Implements the blocking I2C traits of the bus, locking it for every call.
+pub struct I2cProxy<'a, M> {
+    mutex: &'a M,
+}
+
+impl<M> Clone for I2cProxy<'_, M> {
+    fn clone(&self) -> Self {
+        I2cProxy { mutex: self.mutex }
+    }
+}
+
+impl<M> Write for I2cProxy<'_, M>
+where
+    M: BusMutex,
+    M::Bus: Write,
+{
+    type Error = <M::Bus as Write>::Error;
+
+    fn write(&mut self, addr: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.mutex.lock(|bus| bus.write(addr, bytes))
+    }
+}
+
+impl<M> Read for I2cProxy<'_, M>
+where
+    M: BusMutex,
+    M::Bus: Read,
+{
+    type Error = <M::Bus as Read>::Error;
+
+    fn read(&mut self, addr: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.mutex.lock(|bus| bus.read(addr, buffer))
+    }
+}
+
+impl<M> WriteRead for I2cProxy<'_, M>
+where
+    M: BusMutex,
+    M::Bus: WriteRead,
+{
+    type Error = <M::Bus as WriteRead>::Error;
+
+    fn write_read(&mut self, addr: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.mutex.lock(|bus| bus.write_read(addr, bytes, buffer))
+    }
+}
+
+impl<M> Transactional for I2cProxy<'_, M>
+where
+    M: BusMutex,
+    M::Bus: Transactional,
+{
+    type Error = <M::Bus as Transactional>::Error;
+
+    fn exec(&mut self, addr: u8, operations: &mut [Operation<'_>]) -> Result<(), Self::Error> {
+        self.mutex.lock(|bus| bus.exec(addr, operations))
+    }
+}
+
+/// Number of half clock periods a slave may hold SCL low (clock stretching) before the bit-banged
+/// master gives up and reports a bus error
+const BITBANG_STRETCH_LIMIT: u32 = 1_000;
+
+/// Bit-banged I2C peripheral operating in master mode
+///
+/// This is a fallback for pin combinations that are not routed to any hardware I2C instance. Both
+/// pins must be open drain outputs with pull-ups (internal or external), so that the lines can be
+/// released and read back. Clock stretching is supported by reading back SCL after it has been
+/// released.
+pub struct BitBangI2c<SCL, SDA, DELAY> {
+    scl: SCL,
+    sda: SDA,
+    delay: DELAY,
+    half_period_us: u32,
+}
+
+impl<SCL, SDA, DELAY> BitBangI2c<SCL, SDA, DELAY>
+where
+    SCL: OutputPin<Error = Infallible> + InputPin<Error = Infallible>,
+    SDA: OutputPin<Error = Infallible> + InputPin<Error = Infallible>,
+    DELAY: DelayUs<u32>,
+{
+    /// Configures the pins to work as a bit-banged I2C master
+    ///
+    /// `freq` is an upper bound for the SCL frequency, the actual frequency will be lower due to
+    /// the delay granularity of 1 us and the time spent toggling the pins.
+    pub fn new<F>(mut scl: SCL, mut sda: SDA, freq: F, delay: DELAY) -> Self
+    where
+        F: Into<Hertz>,
+    {
+        let freq = freq.into();
+        assert!(freq.0 > 0 && freq.0 <= 400_000);
+
+        // Release both lines, leaving the bus idle
+        scl.set_high().unwrap();
+        sda.set_high().unwrap();
+
+        BitBangI2c {
+            scl,
+            sda,
+            delay,
+            half_period_us: bitbang_half_period_us(freq),
+        }
+    }
+
+    /// Releases a bus held by a slave that got stuck in the middle of a transfer
+    ///
+    /// A slave interrupted by a reset of the master keeps driving SDA low while it waits for the
+    /// rest of its byte. Up to 9 clock pulses let it shift the byte out, and a STOP condition
+    /// brings it back to idle. Returns [`Error::Bus`] if SDA is still held low.
+    ///
+    /// The pins of a hardware [`I2c`] can be switched to open drain outputs for this, before
+    /// resetting the peripheral with [`I2c::reset`](I2c#method.reset).
+    pub fn recover_bus(&mut self) -> Result<(), Error> {
+        self.sda.set_high().unwrap();
+
+        for _ in 0..9 {
+            if self.sda.is_high().unwrap() {
+                break;
+            }
+
+            self.scl.set_low().unwrap();
+            self.wait_half_period();
+            self.release_scl()?;
+            self.wait_half_period();
+        }
+
+        if self.sda.is_low().unwrap() {
+            return Err(Error::Bus);
+        }
+
+        self.scl.set_low().unwrap();
+        self.wait_half_period();
+        self.stop()
+    }
+
+    /// Releases the pins and the delay provider
+    pub fn free(self) -> (SCL, SDA, DELAY) {
+        (self.scl, self.sda, self.delay)
+    }
+
+    fn wait_half_period(&mut self) {
+        self.delay.delay_us(self.half_period_us);
+    }
+
+    /// Releases SCL and waits for any slave stretching the clock
+    fn release_scl(&mut self) -> Result<(), Error> {
+        self.scl.set_high().unwrap();
+
+        let mut stretched = 0;
+        while self.scl.is_low().unwrap() {
+            if stretched == BITBANG_STRETCH_LIMIT {
+                return Err(Error::Bus);
+            }
+
+            self.wait_half_period();
+            stretched += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Generates a (repeated) START condition, leaves SCL low
+    fn start(&mut self) -> Result<(), Error> {
+        self.sda.set_high().unwrap();
+        self.wait_half_period();
+        self.release_scl()?;
+        self.wait_half_period();
+
+        self.sda.set_low().unwrap();
+        self.wait_half_period();
+        self.scl.set_low().unwrap();
+
+        Ok(())
+    }
+
+    /// Generates a STOP condition, leaves the bus idle
+    fn stop(&mut self) -> Result<(), Error> {
+        self.sda.set_low().unwrap();
+        self.wait_half_period();
+        self.release_scl()?;
+        self.wait_half_period();
+
+        self.sda.set_high().unwrap();
+        self.wait_half_period();
+
+        Ok(())
+    }
+
+    fn write_bit(&mut self, bit: bool) -> Result<(), Error> {
+        if bit {
+            self.sda.set_high().unwrap();
+        } else {
+            self.sda.set_low().unwrap();
+        }
+        self.wait_half_period();
+        self.release_scl()?;
+        self.wait_half_period();
+        self.scl.set_low().unwrap();
+
+        Ok(())
+    }
+
+    fn read_bit(&mut self) -> Result<bool, Error> {
+        // Release SDA so the slave can drive it
+        self.sda.set_high().unwrap();
+        self.wait_half_period();
+        self.release_scl()?;
+        self.wait_half_period();
+        let bit = self.sda.is_high().unwrap();
+        self.scl.set_low().unwrap();
+
+        Ok(bit)
+    }
+
+    /// Shifts out a byte MSB first and checks the slave's acknowledge
+    fn write_byte(&mut self, byte: u8) -> Result<(), Error> {
+        for i in (0..8).rev() {
+            self.write_bit(byte & (1 << i) != 0)?;
+        }
+
+        if self.read_bit()? {
+            Err(Error::Nack)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Shifts in a byte MSB first, acknowledging it if `ack` is set
+    fn read_byte(&mut self, ack: bool) -> Result<u8, Error> {
+        let mut byte = 0;
+        for _ in 0..8 {
+            byte = (byte << 1) | self.read_bit()? as u8;
+        }
+
+        self.write_bit(!ack)?;
+
+        Ok(byte)
+    }
+
+    fn write_bytes(&mut self, addr: u8, bytes: &[u8]) -> Result<(), Error> {
+        self.start()?;
+        self.write_byte(addr << 1)?;
+        for byte in bytes {
+            self.write_byte(*byte)?;
+        }
+
+        Ok(())
+    }
+
+    fn read_bytes(&mut self, addr: u8, buffer: &mut [u8]) -> Result<(), Error> {
+        self.start()?;
+        self.write_byte(addr << 1 | 1)?;
+
+        let last = buffer.len().saturating_sub(1);
+        for (i, byte) in buffer.iter_mut().enumerate() {
+            // NACK the last byte to tell the slave we are done
+            *byte = self.read_byte(i != last)?;
+        }
+
+        Ok(())
+    }
+
+    /// Terminates a transfer with STOP, also when the transfer itself failed
+    fn finish(&mut self, result: Result<(), Error>) -> Result<(), Error> {
+        let stop = self.stop();
+        result.and(stop)
+    }
+}
+
+/// Computes the delay between SCL edges, rounding up so that the requested frequency is never
+/// exceeded
+fn bitbang_half_period_us(freq: Hertz) -> u32 {
+    500_000_u32.div_ceil(freq.0).max(1)
+}
+
+impl<SCL, SDA, DELAY> Write for BitBangI2c<SCL, SDA, DELAY>
+where
+    SCL: OutputPin<Error = Infallible> + InputPin<Error = Infallible>,
+    SDA: OutputPin<Error = Infallible> + InputPin<Error = Infallible>,
+    DELAY: DelayUs<u32>,
+{
+    type Error = Error;
+
+    fn write(&mut self, addr: u8, bytes: &[u8]) -> Result<(), Error> {
+        let result = self.write_bytes(addr, bytes);
+        self.finish(result)
+    }
+}
+
+impl<SCL, SDA, DELAY> Read for BitBangI2c<SCL, SDA, DELAY>
+where
+    SCL: OutputPin<Error = Infallible> + InputPin<Error = Infallible>,
+    SDA: OutputPin<Error = Infallible> + InputPin<Error = Infallible>,
+    DELAY: DelayUs<u32>,
+{
+    type Error = Error;
+
+    fn read(&mut self, addr: u8, buffer: &mut [u8]) -> Result<(), Error> {
+        let result = self.read_bytes(addr, buffer);
+        self.finish(result)
+    }
+}
+
+impl<SCL, SDA, DELAY> WriteRead for BitBangI2c<SCL, SDA, DELAY>
+where
+    SCL: OutputPin<Error = Infallible> + InputPin<Error = Infallible>,
+    SDA: OutputPin<Error = Infallible> + InputPin<Error = Infallible>,
+    DELAY: DelayUs<u32>,
+{
+    type Error = Error;
+
+    fn write_read(&mut self, addr: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), Error> {
+        // The repeated START is generated by `read_bytes`
+        let result = self
+            .write_bytes(addr, bytes)
+            .and_then(|_| self.read_bytes(addr, buffer));
+        self.finish(result)
+    }
+}
+
 use crate::gpio::gpioa::{PA10, PA9};
 use crate::gpio::gpiob::{PB10, PB11, PB6, PB7};
 
@@ -489,3 +1957,25 @@ pins!(I2C1, AF4, SCL: [PB8], SDA: []);
 
 #[cfg(feature = "stm32l4x3")]
 pins!(I2C2, AF4, SCL: [PC0], SDA: [PC1]);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bitbang_half_period() {
+        // 5 us + 5 us for 100 kHz, exactly
+        assert_eq!(bitbang_half_period_us(Hertz(100_000)), 5);
+        // 1.25 us rounds up to 2 us, 250 kHz instead of 400 kHz
+        assert_eq!(bitbang_half_period_us(Hertz(400_000)), 2);
+        assert_eq!(bitbang_half_period_us(Hertz(3_000)), 167);
+        assert_eq!(bitbang_half_period_us(Hertz(1)), 500_000);
+
+        for freq in 1..=400_000 {
+            let half_period_us = bitbang_half_period_us(Hertz(freq));
+            // Never faster than requested, and as fast as the 1 us steps allow
+            assert!(2 * half_period_us * freq >= 1_000_000, "{}", freq);
+            assert!(2 * (half_period_us - 1) * freq < 1_000_000, "{}", freq);
+        }
+    }
+}
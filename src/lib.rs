@@ -7,6 +7,20 @@
 //! to become ubiquitous for the STM32L4 family of devices (well-tested, feature-complete,
 //! well-documented). However! At this time, actual testing has only been performed for
 //! the STM32L432KC microcontroller. Participation is of course very welcome!
+//!
+//! # Logging
+//!
+//! With the `defmt` feature, the error types of the RTC, I2C, SPI and serial drivers implement
+//! `defmt::Format`, and the drivers log the errors they detect, e.g. an I2C NACK or a serial
+//! overrun, at trace level. Without the feature, nothing is logged and no code is generated.
+//!
+//! The log is sent through the global logger of the application, e.g. over RTT with
+//! `defmt-rtt`:
+//!
+//! - depend on `defmt` and `defmt-rtt`, and link the transport in with `use defmt_rtt as _;`
+//! - add `-C link-arg=-Tdefmt.x` to the `rustflags` of `.cargo/config.toml`
+//! - build with `DEFMT_LOG=trace` to keep the trace level, and run with `probe-rs run`, which
+//!   decodes the log
 
 #![no_std]
 
@@ -58,6 +72,9 @@ pub use crate::pac as device;
 ))]
 pub use crate::pac as stm32;
 
+#[macro_use]
+mod fmt;
+
 pub mod traits;
 
 #[cfg(any(
@@ -76,6 +93,21 @@ pub mod adc;
     feature = "stm32l4x6"
 ))]
 pub mod crc;
+#[cfg(any(
+    feature = "stm32l4x1",
+    feature = "stm32l4x2",
+    feature = "stm32l4x3",
+    feature = "stm32l4x6"
+))]
+pub mod crs;
+#[cfg(any(
+    feature = "stm32l4x1",
+    feature = "stm32l4x2",
+    feature = "stm32l4x3",
+    feature = "stm32l4x5",
+    feature = "stm32l4x6"
+))]
+pub mod dac;
 pub mod datetime;
 #[cfg(any(
     feature = "stm32l4x1",
@@ -101,6 +133,8 @@ pub mod dma;
     feature = "stm32l4x6"
 ))]
 pub mod flash;
+#[cfg(any(feature = "stm32l4x5", feature = "stm32l4x6"))]
+pub mod fmc;
 #[cfg(any(
     feature = "stm32l4x1",
     feature = "stm32l4x2",
@@ -124,6 +158,14 @@ pub mod i2c;
     feature = "stm32l4x5",
     feature = "stm32l4x6"
 ))]
+pub mod lptimer;
+#[cfg(any(
+    feature = "stm32l4x1",
+    feature = "stm32l4x2",
+    feature = "stm32l4x3",
+    feature = "stm32l4x5",
+    feature = "stm32l4x6"
+))]
 pub mod prelude;
 #[cfg(any(
     feature = "stm32l4x1",
@@ -140,6 +182,14 @@ pub mod pwm;
     feature = "stm32l4x5",
     feature = "stm32l4x6"
 ))]
+pub mod pwm_input;
+#[cfg(any(
+    feature = "stm32l4x1",
+    feature = "stm32l4x2",
+    feature = "stm32l4x3",
+    feature = "stm32l4x5",
+    feature = "stm32l4x6"
+))]
 pub mod pwr;
 
 #[cfg(any(
@@ -0,0 +1,352 @@
+//! Low power timers (LPTIM)
+//!
+//! The low power timers keep running in Stop mode when clocked by the LSE, the LSI or their
+//! external input, so they can periodically wake the MCU up, output a PWM or count pulses while
+//! everything else is stopped. LPTIM1 runs in Stop 0, 1 and 2, LPTIM2 in Stop 0 and 1 only.
+//!
+//! The pins are not checked, configure them to their alternate function before:
+//!
+//! - LPTIM1: `IN1` on PB5 and PC0, `IN2` on PB7 and PC2, `OUT` on PA14, PB2 and PC1 (AF1)
+//! - LPTIM2: `IN1` on PB1 and PC0, `OUT` on PA4, PA8 and PB13 (AF14)
+
+use crate::hal::timer::{CountDown, Periodic};
+use crate::rcc::{Clocks, APB1R1, APB1R2, CCIPR};
+use crate::stm32::{EXTI, LPTIM1, LPTIM2};
+use crate::time::Hertz;
+use void::Void;
+
+/// Frequency of the LSE oscillator
+const LSE: u32 = 32_768;
+
+/// Frequency of the LSI oscillator
+const LSI: u32 = 32_000;
+
+/// Frequency of the HSI16 oscillator
+const HSI16: u32 = 16_000_000;
+
+/// Kernel clock of a low power timer
+///
+/// Only the LSE and the LSI keep running in Stop mode. With the HSI16, `RCC_CR.HSIKERON` needs to
+/// be set for it to stay on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ClockSource {
+    /// APB1 clock
+    Pclk = 0b00,
+    /// LSI oscillator, enable it with `CFGR::lsi`
+    Lsi = 0b01,
+    /// HSI16 oscillator
+    Hsi16 = 0b10,
+    /// LSE oscillator, enable it with `CFGR::lse`
+    Lse = 0b11,
+}
+
+/// Edges of `IN1` that are counted with [`CountMode::External`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Edge {
+    Rising = 0b00,
+    Falling = 0b01,
+    Both = 0b10,
+}
+
+/// What the counter counts
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CountMode {
+    /// The prescaled kernel clock, for periodic events and PWM
+    Internal,
+    /// Edges on `IN1`, which clocks the counter directly, so this even works without a kernel
+    /// clock
+    External(Edge),
+    /// A quadrature encoder on `IN1` and `IN2`, counting all edges of both inputs
+    ///
+    /// The kernel clock needs to be at least 4 times faster than the inputs.
+    Encoder,
+}
+
+/// Interrupt events
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+    /// The counter reached the auto-reload value, i.e. the period ended
+    AutoReloadMatch,
+    /// The counter reached the compare value
+    CompareMatch,
+}
+
+/// Low power timer
+pub struct LowPowerTimer<LPTIM> {
+    lptim: LPTIM,
+    input_freq: Hertz,
+}
+
+macro_rules! hal {
+    ($($LPTIM:ident: ($lptim:ident, $lptimXen:ident, $lptimXrst:ident, $lptimXsel:ident, $apb:ident, $mr:ident),)+) => {
+        $(
+            impl LowPowerTimer<$LPTIM> {
+                /// Configures a low power timer, the counter is started with
+                /// [`CountDown::start`], [`LowPowerTimer::pwm`] or [`LowPowerTimer::start_counting`]
+                ///
+                /// # Panics
+                ///
+                /// Panics if the LSI or LSE is selected without being enabled.
+                pub fn $lptim(
+                    lptim: $LPTIM,
+                    clock: ClockSource,
+                    mode: CountMode,
+                    apb: &mut $apb,
+                    ccipr: &mut CCIPR,
+                    clocks: Clocks,
+                ) -> Self {
+                    // enable and reset peripheral to a clean slate state
+                    apb.enr().modify(|_, w| w.$lptimXen().set_bit());
+                    apb.rstr().modify(|_, w| w.$lptimXrst().set_bit());
+                    apb.rstr().modify(|_, w| w.$lptimXrst().clear_bit());
+
+                    let input_freq = match clock {
+                        ClockSource::Pclk => clocks.pclk1(),
+                        ClockSource::Lsi => {
+                            assert!(clocks.lsi(), "LSI selected, but not enabled");
+                            Hertz(LSI)
+                        }
+                        ClockSource::Hsi16 => Hertz(HSI16),
+                        ClockSource::Lse => {
+                            assert!(clocks.lse(), "LSE selected, but not enabled");
+                            Hertz(LSE)
+                        }
+                    };
+
+                    // This is sound, as all `ClockSource` values are valid for this field.
+                    ccipr
+                        .ccipr()
+                        .modify(|_, w| unsafe { w.$lptimXsel().bits(clock as u8) });
+
+                    // This is sound, as all `Edge` values are valid for this field.
+                    lptim.cfgr.write(|w| unsafe {
+                        match mode {
+                            CountMode::Internal => w,
+                            CountMode::External(edge) => {
+                                w.cksel().set_bit().ckpol().bits(edge as u8)
+                            }
+                            CountMode::Encoder => w.enc().set_bit().ckpol().bits(Edge::Both as u8),
+                        }
+                    });
+
+                    LowPowerTimer {
+                        lptim,
+                        input_freq,
+                    }
+                }
+
+                /// Starts a PWM at `freq` on `OUT`, see [`LowPowerTimer::set_duty`]
+                ///
+                /// The duty cycle starts at 0.
+                ///
+                /// # Panics
+                ///
+                /// Panics if `freq` is 0 Hz, or too low for the kernel clock, like `start`.
+                pub fn pwm<T>(&mut self, freq: T)
+                where
+                    T: Into<Hertz>,
+                {
+                    self.start(freq);
+                    self.set_duty(0);
+                }
+
+                /// Returns the duty cycle for a 100% PWM output
+                pub fn get_max_duty(&self) -> u16 {
+                    self.lptim.arr.read().arr().bits()
+                }
+
+                /// Sets the PWM duty cycle, the number of ticks per period `OUT` is high
+                ///
+                /// The compare value has to stay below the auto-reload value, so a duty of 0 gives
+                /// the shortest pulse, of one tick, like a duty of 1.
+                pub fn set_duty(&mut self, duty: u16) {
+                    // The output is set once the counter exceeds the compare value, and reset at
+                    // the end of the period
+                    let max = self.get_max_duty();
+                    let cmp = max.saturating_sub(duty).min(max.saturating_sub(1));
+
+                    // This is sound, as the full range is valid for this field.
+                    self.lptim.cmp.write(|w| unsafe { w.cmp().bits(cmp) });
+                    while self.lptim.isr.read().cmpok().bit_is_clear() {}
+                    self.lptim.icr.write(|w| w.cmpokcf().set_bit());
+                }
+
+                /// Starts counting continuously, from 0 to `max` and back to 0
+                ///
+                /// This is the way to start [`CountMode::External`] and [`CountMode::Encoder`],
+                /// with a [`CountMode::Encoder`] counting up and down.
+                pub fn start_counting(&mut self, max: u16) {
+                    self.lptim.cr.modify(|_, w| w.enable().clear_bit());
+                    self.lptim.cr.modify(|_, w| w.enable().set_bit());
+                    self.set_auto_reload(max);
+                    self.lptim.cr.modify(|_, w| w.cntstrt().set_bit());
+                }
+
+                /// Returns the counter value
+                pub fn count(&self) -> u16 {
+                    // The counter runs asynchronously to the APB clock, so it is only valid once
+                    // two consecutive reads match
+                    loop {
+                        let cnt = self.lptim.cnt.read().cnt().bits();
+                        if self.lptim.cnt.read().cnt().bits() == cnt {
+                            return cnt;
+                        }
+                    }
+                }
+
+                /// Starts listening for an `event`
+                ///
+                /// This also enables the EXTI line of the timer, which wakes the MCU from Stop
+                /// mode. The counter restarts, as it needs to be disabled to change the
+                /// interrupts.
+                pub fn listen(&mut self, exti: &mut EXTI, event: Event) {
+                    self.modify_interrupts(|w| match event {
+                        Event::AutoReloadMatch => w.arrmie().set_bit(),
+                        Event::CompareMatch => w.cmpmie().set_bit(),
+                    });
+
+                    exti.imr2.modify(|_, w| w.$mr().set_bit());
+                }
+
+                /// Stops listening for an `event`
+                ///
+                /// The counter restarts, as it needs to be disabled to change the interrupts.
+                pub fn unlisten(&mut self, exti: &mut EXTI, event: Event) {
+                    self.modify_interrupts(|w| match event {
+                        Event::AutoReloadMatch => w.arrmie().clear_bit(),
+                        Event::CompareMatch => w.cmpmie().clear_bit(),
+                    });
+
+                    let ier = self.lptim.ier.read();
+                    if ier.arrmie().bit_is_clear() && ier.cmpmie().bit_is_clear() {
+                        exti.imr2.modify(|_, w| w.$mr().clear_bit());
+                    }
+                }
+
+                /// Returns `true` if `event` is pending
+                pub fn is_pending(&self, event: Event) -> bool {
+                    let isr = self.lptim.isr.read();
+                    match event {
+                        Event::AutoReloadMatch => isr.arrm().bit_is_set(),
+                        Event::CompareMatch => isr.cmpm().bit_is_set(),
+                    }
+                }
+
+                /// Clears a pending `event`
+                pub fn clear_pending(&mut self, event: Event) {
+                    match event {
+                        Event::AutoReloadMatch => self.lptim.icr.write(|w| w.arrmcf().set_bit()),
+                        Event::CompareMatch => self.lptim.icr.write(|w| w.cmpmcf().set_bit()),
+                    }
+                }
+
+                /// Stops the counter and releases the LPTIM peripheral
+                pub fn free(self) -> $LPTIM {
+                    self.lptim.cr.modify(|_, w| w.enable().clear_bit());
+                    self.lptim
+                }
+
+                /// Writes the auto-reload value, which requires the timer to be enabled
+                fn set_auto_reload(&mut self, arr: u16) {
+                    // This is sound, as the full range is valid for this field.
+                    self.lptim.arr.write(|w| unsafe { w.arr().bits(arr) });
+                    while self.lptim.isr.read().arrok().bit_is_clear() {}
+                    self.lptim.icr.write(|w| w.arrokcf().set_bit());
+                }
+
+                /// Modifies `IER`, which can only be written while the timer is disabled
+                fn modify_interrupts<F>(&mut self, f: F)
+                where
+                    F: FnOnce(&mut crate::stm32::lptim1::ier::W) -> &mut crate::stm32::lptim1::ier::W,
+                {
+                    let enabled = self.lptim.cr.read().enable().bit_is_set();
+                    let arr = self.lptim.arr.read().arr().bits();
+                    let cmp = self.lptim.cmp.read().cmp().bits();
+
+                    self.lptim.cr.modify(|_, w| w.enable().clear_bit());
+                    self.lptim.ier.modify(|r, w| f(unsafe { w.bits(r.bits()) }));
+
+                    if enabled {
+                        self.lptim.cr.modify(|_, w| w.enable().set_bit());
+                        self.set_auto_reload(arr);
+                        // This is sound, as the full range is valid for this field.
+                        self.lptim.cmp.write(|w| unsafe { w.cmp().bits(cmp) });
+                        while self.lptim.isr.read().cmpok().bit_is_clear() {}
+                        self.lptim.icr.write(|w| w.cmpokcf().set_bit());
+                        self.lptim.cr.modify(|_, w| w.cntstrt().set_bit());
+                    }
+                }
+            }
+
+            impl Periodic for LowPowerTimer<$LPTIM> {}
+
+            impl CountDown for LowPowerTimer<$LPTIM> {
+                type Time = Hertz;
+
+                /// Starts counting the kernel clock, with an auto-reload match at `timeout`
+                ///
+                /// The prescaler is the smallest power of 2 that fits the period into the 16 bit
+                /// counter.
+                ///
+                /// # Panics
+                ///
+                /// Panics if `timeout` is 0 Hz, or if the period is too long for the kernel clock,
+                /// i.e. above 256 s with the LSE.
+                fn start<T>(&mut self, timeout: T)
+                where
+                    T: Into<Hertz>,
+                {
+                    let timeout = timeout.into();
+                    assert!(timeout.0 > 0, "LPTIM frequency of 0 Hz");
+                    let ticks = self.input_freq.0 / timeout.0;
+
+                    let presc = (0..8u8)
+                        .find(|presc| ticks >> presc <= 1 << 16)
+                        .expect("LPTIM period too long for the kernel clock");
+                    let arr = ((ticks >> presc).max(2) - 1) as u16;
+
+                    // The prescaler can only be changed while the timer is disabled
+                    self.lptim.cr.modify(|_, w| w.enable().clear_bit());
+                    // This is sound, as `presc` is limited to 3 bits.
+                    self.lptim.cfgr.modify(|_, w| unsafe { w.presc().bits(presc) });
+                    self.lptim.cr.modify(|_, w| w.enable().set_bit());
+
+                    self.set_auto_reload(arr);
+                    self.clear_pending(Event::AutoReloadMatch);
+                    self.lptim.cr.modify(|_, w| w.cntstrt().set_bit());
+                }
+
+                fn wait(&mut self) -> nb::Result<(), Void> {
+                    if self.is_pending(Event::AutoReloadMatch) {
+                        self.clear_pending(Event::AutoReloadMatch);
+                        Ok(())
+                    } else {
+                        Err(nb::Error::WouldBlock)
+                    }
+                }
+            }
+
+            impl crate::timer::PeriodicTimer for LowPowerTimer<$LPTIM> {
+                type Time = Hertz;
+
+                fn start(&mut self, period: Hertz) {
+                    CountDown::start(self, period);
+                }
+
+                fn is_expired(&mut self) -> bool {
+                    CountDown::wait(self).is_ok()
+                }
+
+                fn cancel(&mut self) {
+                    self.clear_pending(Event::AutoReloadMatch);
+                    self.lptim.cr.modify(|_, w| w.enable().clear_bit());
+                }
+            }
+        )+
+    }
+}
+
+hal! {
+    LPTIM1: (lptim1, lptim1en, lptim1rst, lptim1sel, APB1R1, mr32),
+    LPTIM2: (lptim2, lptim2en, lptim2rst, lptim2sel, APB1R2, mr33),
+}
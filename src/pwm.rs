@@ -1,5 +1,6 @@
 //! # Pulse Width Modulation
 
+use core::convert::TryFrom;
 use core::marker::PhantomData;
 use core::mem;
 
@@ -90,6 +91,9 @@ pins_to_channels_mapping! {
 }
 
 pub trait PwmExt1: Sized {
+    /// Configures the timer as PWM at `frequency`, see [`Pwm::frequency`] for the achieved one
+    ///
+    /// A `frequency` out of reach of the timer is clamped to the closest one it can run at.
     fn pwm<PINS, T>(self, _: PINS, frequency: T, clocks: Clocks, apb: &mut APB2) -> PINS::Channels
     where
         PINS: Pins<Self>,
@@ -97,6 +101,9 @@ pub trait PwmExt1: Sized {
 }
 
 pub trait PwmExt2: Sized {
+    /// Configures the timer as PWM at `frequency`, see [`Pwm::frequency`] for the achieved one
+    ///
+    /// A `frequency` out of reach of the timer is clamped to the closest one it can run at.
     fn pwm<PINS, T>(
         self,
         _: PINS,
@@ -145,6 +152,28 @@ impl PwmExt2 for TIM2 {
     }
 }
 
+/// Returns the clock of the timers on an APB bus, twice the bus clock unless it is not divided
+fn timer_clock(pclk: Hertz, ppre: u8) -> u32 {
+    if ppre == 1 {
+        pclk.0
+    } else {
+        pclk.0 * 2
+    }
+}
+
+/// Returns the prescaler and the auto-reload value of the period closest to `freq`
+///
+/// A `freq` above the timer clock gets the shortest period, and a `freq` of 0 the longest.
+fn dividers(timclk: u32, freq: Hertz) -> (u32, u32) {
+    let ticks = timclk.checked_div(freq.0).unwrap_or(u32::MAX).max(1);
+
+    // maybe this is all u32? also, why no `- 1` vs `timer.rs`?
+    let psc = ticks / (1 << 16);
+    let arr = ticks / (psc + 1);
+
+    (psc, arr)
+}
+
 pub struct Pwm<TIM, CHANNEL> {
     _channel: PhantomData<CHANNEL>,
     _tim: PhantomData<TIM>,
@@ -156,7 +185,7 @@ pub struct C3;
 pub struct C4;
 
 macro_rules! advanced_timer {
-    ($($TIMX:ident: ($timX:ident, $timXen:ident, $timXrst:ident, $apb:ident, $psc_width:ident, $arr_width:ident),)+) => {
+    ($($TIMX:ident: ($timX:ident, $timXen:ident, $timXrst:ident, $apb:ident, $pclkX:ident, $ppreX:ident, $psc_width:ident, $arr_width:ident),)+) => {
         $(
             fn $timX<PINS>(
                 tim: $TIMX,
@@ -188,14 +217,8 @@ macro_rules! advanced_timer {
                     tim.ccmr2_output().modify(|_, w| unsafe { w.oc4pe().set_bit().oc4m().bits(6) });
                 }
 
-                let clk = clocks.pclk2().0;
-                let freq = freq.0;
-                let ticks = clk / freq;
-
-                // maybe this is all u32? also, why no `- 1` vs `timer.rs`?
-                let psc = ticks / (1 << 16);
+                let (psc, arr) = dividers(timer_clock(clocks.$pclkX(), clocks.$ppreX()), freq);
                 tim.psc.write(|w| { w.psc().bits(psc as $psc_width) });
-                let arr = ticks / (psc + 1);
                 tim.arr.write(|w| { w.arr().bits(arr as $arr_width) });
 
                 // Only for the advanced control timer
@@ -215,7 +238,7 @@ macro_rules! advanced_timer {
             }
 
             pwm_channels! {
-                $TIMX:  (C1, $arr_width, cc1e, ccr1, ccr),
+                $TIMX, $pclkX, $ppreX: (C1, $arr_width, cc1e, ccr1, ccr),
                         (C2, $arr_width, cc2e, ccr2, ccr),
                         (C3, $arr_width, cc3e, ccr3, ccr),
                         (C4, $arr_width, cc4e, ccr4, ccr),
@@ -226,7 +249,7 @@ macro_rules! advanced_timer {
 }
 
 macro_rules! standard_timer {
-    ($($TIMX:ident: ($timX:ident, $timXen:ident, $timXrst:ident, $apb:ident, $psc_width:ident, $arr_width:ident),)+) => {
+    ($($TIMX:ident: ($timX:ident, $timXen:ident, $timXrst:ident, $apb:ident, $pclkX:ident, $ppreX:ident, $psc_width:ident, $arr_width:ident),)+) => {
         $(
             fn $timX<PINS>(
                 tim: $TIMX,
@@ -258,16 +281,13 @@ macro_rules! standard_timer {
                     tim.ccmr2_output().modify(|_, w| unsafe { w.oc4pe().set_bit().oc4m().bits(6) });
                 }
 
-                let clk = clocks.pclk1().0;
-                let freq = freq.0;
-                let ticks = clk / freq;
-
-                // maybe this is all u32? also, why no `- 1` vs `timer.rs`?
-                let psc = ticks / (1 << 16);
+                let (psc, arr) = dividers(timer_clock(clocks.$pclkX(), clocks.$ppreX()), freq);
                 tim.psc.write(|w| { w.psc().bits(psc as $psc_width) });
-                let arr = ticks / (psc + 1);
                 tim.arr.write(|w| { w.arr().bits(arr as $arr_width) });
 
+                // Load the preloaded prescaler and auto-reload values
+                tim.egr.write(|w| w.ug().set_bit());
+
                 tim.cr1.write(|w| {
                     w.cms()
                         .bits(0b00)
@@ -281,7 +301,7 @@ macro_rules! standard_timer {
             }
 
             pwm_channels! {
-                $TIMX:  (C1, $arr_width, cc1e, ccr1, ccr),
+                $TIMX, $pclkX, $ppreX: (C1, $arr_width, cc1e, ccr1, ccr),
                         (C2, $arr_width, cc2e, ccr2, ccr),
                         (C3, $arr_width, cc3e, ccr3, ccr),
                         (C4, $arr_width, cc4e, ccr4, ccr),
@@ -292,7 +312,7 @@ macro_rules! standard_timer {
 }
 
 macro_rules! small_timer {
-    ($($TIMX:ident: ($timX:ident, $timXen:ident, $timXrst:ident, $apb:ident, $psc_width:ident, $arr_width:ident),)+) => {
+    ($($TIMX:ident: ($timX:ident, $timXen:ident, $timXrst:ident, $apb:ident, $pclkX:ident, $ppreX:ident, $psc_width:ident, $arr_width:ident),)+) => {
         $(
             fn $timX<PINS>(
                 tim: $TIMX,
@@ -317,14 +337,8 @@ macro_rules! small_timer {
                 //     tim.ccmr1_output().modify(|_, w| unsafe { w.oc2pe().set_bit().oc2m().bits(6) });
                 // }
 
-                let clk = clocks.pclk1().0;
-                let freq = freq.0;
-                let ticks = clk / freq;
-
-                // maybe this is all u32? also, why no `- 1` vs `timer.rs`?
-                let psc = ticks / (1 << 16);
+                let (psc, arr) = dividers(timer_clock(clocks.$pclkX(), clocks.$ppreX()), freq);
                 tim.psc.write(|w| { w.psc().bits(psc as $psc_width) });
-                let arr = ticks / (psc + 1);
                 unsafe { tim.arr.write(|w| { w.arr().bits(arr as $arr_width) }); }
 
                 tim.bdtr.write(|w| w.moe().set_bit());
@@ -340,7 +354,7 @@ macro_rules! small_timer {
             }
 
             pwm_channels! {
-                $TIMX:  (C1, $arr_width, cc1e, ccr1, ccr1),
+                $TIMX, $pclkX, $ppreX: (C1, $arr_width, cc1e, ccr1, ccr1),
                 // TODO: The uncommented line is awaiting PAC updates to be valid.
                 //        (C2, $arr_width, cc2e, ccr2, ccr2),
             }
@@ -350,8 +364,83 @@ macro_rules! small_timer {
 }
 
 macro_rules! pwm_channels {
-    ($TIMX:ident: $(($channel:ident, $arr_width:ident, $ccXe:ident, $ccrX:ident, $ccr:ident),)+) => {
+    ($TIMX:ident, $pclkX:ident, $ppreX:ident: $(($channel:ident, $arr_width:ident, $ccXe:ident, $ccrX:ident, $ccr:ident),)+) => {
+        impl<CHANNEL> Pwm<$TIMX, CHANNEL> {
+            /// Returns the achieved frequency, computed from the prescaler, the period and
+            /// `clocks`
+            ///
+            /// This applies to all channels of the timer.
+            pub fn frequency(&self, clocks: Clocks) -> Hertz {
+                let tim = unsafe { &*$TIMX::ptr() };
+                let psc = u32::from(tim.psc.read().psc().bits()) + 1;
+                let arr = u32::from(tim.arr.read().arr().bits()) + 1;
+
+                Hertz(timer_clock(clocks.$pclkX(), clocks.$ppreX()) / psc / arr)
+            }
+
+            /// Holds back the update event, see [`Pwm::resume_update`]
+            ///
+            /// The duty cycles are preloaded and only latched at the next update event, at the
+            /// end of a period. Holding the update back while writing the duty cycles of
+            /// several channels, e.g. the colors of an RGB LED, makes them all change in the
+            /// same period. This applies to all channels of the timer.
+            pub fn pause_update(&mut self) {
+                // NOTE(unsafe) the channels of the timer share `CR1`, so it is modified in a
+                // critical section
+                cortex_m::interrupt::free(|_| unsafe {
+                    (*$TIMX::ptr()).cr1.modify(|_, w| w.udis().set_bit())
+                })
+            }
+
+            /// Lets the next update event latch the duty cycles written since
+            /// [`Pwm::pause_update`]
+            pub fn resume_update(&mut self) {
+                // NOTE(unsafe) the channels of the timer share `CR1`, so it is modified in a
+                // critical section
+                cortex_m::interrupt::free(|_| unsafe {
+                    (*$TIMX::ptr()).cr1.modify(|_, w| w.udis().clear_bit())
+                })
+            }
+
+            /// Latches the written duty cycles immediately, by generating an update event
+            ///
+            /// This restarts the current period.
+            pub fn force_update(&mut self) {
+                unsafe { (*$TIMX::ptr()).egr.write(|w| w.ug().set_bit()) }
+            }
+        }
+
         $(
+            impl Pwm<$TIMX, $channel> {
+                /// Sets the duty cycle to `fraction` of the period, clamped to 0.0 to 1.0
+                ///
+                /// The period lasts `get_max_duty() + 1` ticks, so 1.0 keeps the output active
+                /// for the whole period, while `get_max_duty()` itself leaves it inactive for one
+                /// tick. 0.0 and NaN keep the output inactive.
+                pub fn set_duty_fraction(&mut self, fraction: f32) {
+                    let fraction = fraction.max(0.0).min(1.0);
+                    let period = hal::PwmPin::get_max_duty(self) as f32 + 1.0;
+
+                    // Rounded to the closest tick, the cast saturates at the maximum of the type
+                    let duty = (fraction * period + 0.5) as $arr_width;
+                    hal::PwmPin::set_duty(self, duty);
+                }
+
+                /// Sets the duty cycle to `percent` of the period, clamped to 0 to 100, see
+                /// [`Pwm::set_duty_fraction`]
+                pub fn set_duty_percent(&mut self, percent: f32) {
+                    self.set_duty_fraction(percent / 100.0);
+                }
+
+                /// Returns the duty cycle in percent of the period, 0 to 100
+                pub fn get_duty_percent(&self) -> f32 {
+                    let duty = hal::PwmPin::get_duty(self) as f32;
+                    let period = hal::PwmPin::get_max_duty(self) as f32 + 1.0;
+
+                    (duty * 100.0 / period).min(100.0)
+                }
+            }
+
             impl hal::PwmPin for Pwm<$TIMX, $channel> {
                 type Duty = $arr_width;
 
@@ -385,13 +474,101 @@ macro_rules! pwm_channels {
 }
 
 advanced_timer! {
-    TIM1: (tim1, tim1en, tim1rst, APB2, u16, u16),
+    TIM1: (tim1, tim1en, tim1rst, APB2, pclk2, ppre2, u16, u16),
 }
 
 standard_timer! {
-    TIM2: (tim2, tim2en, tim2rst, APB1R1, u16, u32),
+    TIM2: (tim2, tim2en, tim2rst, APB1R1, pclk1, ppre1, u16, u32),
 }
 
 small_timer! {
-    TIM15: (tim15, tim15en, tim15rst, APB2, u16, u16),
+    TIM15: (tim15, tim15en, tim15rst, APB2, pclk2, ppre2, u16, u16),
+}
+
+/// Frame of the RC servo signal, in microseconds
+const SERVO_FRAME_US: u32 = 20_000;
+/// Pulse width at 0°, in microseconds
+const SERVO_MIN_PULSE_US: u16 = 1_000;
+/// Pulse width at 180°, in microseconds
+const SERVO_MAX_PULSE_US: u16 = 2_000;
+
+/// Hobby servo driven by a PWM channel
+///
+/// The position is set by a pulse of 1000 to 2000 us, repeated in a 20 ms frame, so the timer of
+/// the channel has to run at 50 Hz. The duty cycle is computed from `get_max_duty`, so it is only
+/// as fine as the period of the timer: use a timer clock that leaves a large auto-reload value.
+pub struct Servo<P> {
+    pwm: P,
+}
+
+impl<P> Servo<P>
+where
+    P: hal::PwmPin,
+    P::Duty: Into<u32> + TryFrom<u32>,
+{
+    /// Wraps a PWM channel of a timer running at 50 Hz and enables it
+    ///
+    /// The duty cycle is left as is until the position is set, a duty cycle of 0 gives no pulse
+    /// at all, which most servos take as "stay where you are".
+    pub fn new(mut pwm: P) -> Self {
+        pwm.enable();
+
+        Servo { pwm }
+    }
+
+    /// Moves to `deg` degrees, clamped to 0 to 180
+    pub fn set_angle(&mut self, deg: u8) {
+        let range = u32::from(SERVO_MAX_PULSE_US - SERVO_MIN_PULSE_US);
+        let offset = (u32::from(deg.min(180)) * range + 90) / 180;
+
+        // Can't truncate, as the offset is not above the range.
+        self.set_pulse_us(SERVO_MIN_PULSE_US + offset as u16);
+    }
+
+    /// Sends pulses of `us` microseconds, clamped to 1000 to 2000
+    pub fn set_pulse_us(&mut self, us: u16) {
+        let us = us.clamp(SERVO_MIN_PULSE_US, SERVO_MAX_PULSE_US);
+        let max: u32 = self.pwm.get_max_duty().into();
+        let duty = (u64::from(us) * u64::from(max) + u64::from(SERVO_FRAME_US / 2))
+            / u64::from(SERVO_FRAME_US);
+
+        // Can't truncate, as the pulse is shorter than the frame, so the duty is below the
+        // maximum one and fits its type.
+        let duty = match P::Duty::try_from(duty as u32) {
+            Ok(duty) => duty,
+            Err(_) => self.pwm.get_max_duty(),
+        };
+        self.pwm.set_duty(duty);
+    }
+
+    /// Disables the channel and releases it
+    pub fn free(mut self) -> P {
+        self.pwm.disable();
+
+        self.pwm
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timer_clocks() {
+        assert_eq!(timer_clock(Hertz(80_000_000), 1), 80_000_000);
+        assert_eq!(timer_clock(Hertz(40_000_000), 2), 80_000_000);
+        assert_eq!(timer_clock(Hertz(5_000_000), 16), 10_000_000);
+    }
+
+    #[test]
+    fn period_dividers() {
+        assert_eq!(dividers(80_000_000, Hertz(1_000)), (1, 40_000));
+        // The largest period without prescaler, and the smallest with one
+        assert_eq!(dividers(65_535, Hertz(1)), (0, 65_535));
+        assert_eq!(dividers(65_536, Hertz(1)), (1, 32_768));
+        // Clamped to the shortest and the longest period
+        assert_eq!(dividers(80_000_000, Hertz(80_000_000)), (0, 1));
+        assert_eq!(dividers(80_000_000, Hertz(100_000_000)), (0, 1));
+        assert_eq!(dividers(80_000_000, Hertz(0)), (65_535, 65_535));
+    }
 }
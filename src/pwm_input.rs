@@ -0,0 +1,204 @@
+//! # PWM input
+//!
+//! Measures the frequency and the duty cycle of a signal on a single timer input, with two
+//! capture channels of the timer: the rising edges reset the counter (slave reset mode) and are
+//! captured by one channel, which gives the period, while the falling edges are captured by the
+//! other channel, which gives the pulse width. Both are captured by the hardware in every period,
+//! without any interrupt.
+//!
+//! # Range
+//!
+//! The counter runs at the timer clock, divided by a prescaler chosen for the lowest frequency
+//! to measure, so that a period fits in the counter. The measured frequency is
+//! `counter clock / period ticks`, and the duty cycle is only as fine as a tick: the highest
+//! frequency measurable with a meaningful duty cycle is the counter clock divided by a few
+//! hundred ticks, e.g. 400 kHz for 200 ticks with an 80 MHz timer clock and no prescaler. Above,
+//! the frequency is still measured, with an error of up to a tick per period, and the duty cycle
+//! in coarser steps, down to 25 % at a quarter of the counter clock.
+
+use crate::gpio::gpioa::{PA0, PA1, PA15, PA5, PA8, PA9};
+use crate::gpio::gpiob::PB3;
+use crate::gpio::{Alternate, AF1};
+use crate::rcc::{Clocks, APB1R1, APB2};
+use crate::stm32::{TIM1, TIM2};
+use crate::time::Hertz;
+
+#[doc(hidden)]
+mod private {
+    pub trait Sealed {}
+}
+
+/// Input pin of a [`PwmInput`]. This trait is sealed and cannot be implemented.
+pub trait Pin<TIM>: private::Sealed {
+    /// Whether the pin is channel 2 of the timer rather than channel 1
+    #[doc(hidden)]
+    const CH2: bool;
+}
+
+macro_rules! pins {
+    ($($TIMX:ident: $AF:ident, C1: [$($C1:ident),*], C2: [$($C2:ident),*];)+) => {
+        $(
+            $(
+                impl<MODE> private::Sealed for $C1<Alternate<$AF, MODE>> {}
+                impl<MODE> Pin<$TIMX> for $C1<Alternate<$AF, MODE>> {
+                    const CH2: bool = false;
+                }
+            )*
+            $(
+                impl<MODE> private::Sealed for $C2<Alternate<$AF, MODE>> {}
+                impl<MODE> Pin<$TIMX> for $C2<Alternate<$AF, MODE>> {
+                    const CH2: bool = true;
+                }
+            )*
+        )+
+    };
+}
+
+pins! {
+    TIM1: AF1, C1: [PA8], C2: [PA9];
+    TIM2: AF1, C1: [PA0, PA5, PA15], C2: [PA1, PB3];
+}
+
+/// Frequency and duty cycle measurement of the signal on one timer input, see the module
+/// documentation
+pub struct PwmInput<TIM, PIN> {
+    tim: TIM,
+    pin: PIN,
+    clock: Hertz,
+}
+
+macro_rules! hal {
+    ($($TIMX:ident: ($timX:ident, $timXen:ident, $timXrst:ident, $apb:ident, $pclkX:ident, $ppreX:ident, $width:ident),)+) => {
+        $(
+            impl<PIN> PwmInput<$TIMX, PIN>
+            where
+                PIN: Pin<$TIMX>,
+            {
+                /// Configures the timer to measure the signal on `pin`, down to `min_frequency`
+                ///
+                /// Lower frequencies overflow the counter, and are measured wrong. The lower
+                /// `min_frequency`, the larger the prescaler, and the coarser the measurements,
+                /// see the module documentation.
+                ///
+                /// # Panics
+                ///
+                /// Panics if `min_frequency` is 0.
+                pub fn $timX<F>(
+                    tim: $TIMX,
+                    pin: PIN,
+                    min_frequency: F,
+                    clocks: Clocks,
+                    apb: &mut $apb,
+                ) -> Self
+                where
+                    F: Into<Hertz>,
+                {
+                    let min_frequency = min_frequency.into().0;
+                    assert!(min_frequency > 0, "impossible PWM input frequency");
+
+                    apb.enr().modify(|_, w| w.$timXen().set_bit());
+                    apb.rstr().modify(|_, w| w.$timXrst().set_bit());
+                    apb.rstr().modify(|_, w| w.$timXrst().clear_bit());
+
+                    // The timers run at twice the APB clock, unless APB is not divided
+                    let timclk = if clocks.$ppreX() == 1 {
+                        clocks.$pclkX().0
+                    } else {
+                        clocks.$pclkX().0 * 2
+                    };
+
+                    // Smallest prescaler fitting a period of `min_frequency` in the counter
+                    let max_ticks = u64::from($width::MAX) + 1;
+                    let psc = (u64::from(timclk) / u64::from(min_frequency)) / max_ticks;
+                    let psc = psc.min(u64::from(u16::MAX)) as u16;
+                    tim.psc.write(|w| w.psc().bits(psc));
+                    tim.arr.write(|w| w.arr().bits($width::MAX));
+
+                    // The channel of the pin (TIx) captures the rising edges, the other one the
+                    // falling edges of the same input
+                    // CC1S/CC2S: 01 = own input, 10 = input of the other channel
+                    let (cc1s, cc2s) = if PIN::CH2 { (0b10, 0b01) } else { (0b01, 0b10) };
+                    tim.ccmr1_input()
+                        .write(|w| unsafe { w.cc1s().bits(cc1s).cc2s().bits(cc2s) });
+                    tim.ccer.write(|w| {
+                        w.cc1p()
+                            .bit(PIN::CH2)
+                            .cc1np()
+                            .clear_bit()
+                            .cc2p()
+                            .bit(!PIN::CH2)
+                            .cc2np()
+                            .clear_bit()
+                            .cc1e()
+                            .set_bit()
+                            .cc2e()
+                            .set_bit()
+                    });
+
+                    // TS: trigger on the filtered input of the pin, 101 = TI1FP1, 110 = TI2FP2
+                    // SMS: 100 = reset mode, the rising edges reset the counter
+                    let ts = if PIN::CH2 { 0b110 } else { 0b101 };
+                    tim.smcr.write(|w| unsafe { w.ts().bits(ts).sms().bits(0b100) });
+
+                    // Load the prescaler
+                    tim.egr.write(|w| w.ug().set_bit());
+                    tim.cr1.write(|w| w.cen().set_bit());
+
+                    PwmInput {
+                        tim,
+                        pin,
+                        clock: Hertz(timclk / (u32::from(psc) + 1)),
+                    }
+                }
+
+                /// Returns the frequency of the counter, the resolution of the measurements
+                pub fn counter_clock(&self) -> Hertz {
+                    self.clock
+                }
+
+                /// Returns the frequency and the duty cycle, in percent, of the last period of
+                /// the signal
+                ///
+                /// Returns `None` if no period was captured since the previous call, e.g.
+                /// because the signal is stopped, constantly high or low.
+                pub fn read(&mut self) -> Option<(Hertz, u8)> {
+                    let (captured, period, width) = if PIN::CH2 {
+                        (
+                            self.tim.sr.read().cc2if().bit_is_set(),
+                            self.tim.ccr2.read().ccr().bits(),
+                            self.tim.ccr1.read().ccr().bits(),
+                        )
+                    } else {
+                        (
+                            self.tim.sr.read().cc1if().bit_is_set(),
+                            self.tim.ccr1.read().ccr().bits(),
+                            self.tim.ccr2.read().ccr().bits(),
+                        )
+                    };
+
+                    // Reading the period capture cleared its flag
+                    if !captured || period == 0 {
+                        return None;
+                    }
+
+                    let frequency = Hertz(self.clock.0 / u32::from(period));
+                    let duty = (u64::from(width) * 100 / u64::from(period)).min(100) as u8;
+
+                    Some((frequency, duty))
+                }
+
+                /// Stops the timer and releases it along with the pin
+                pub fn free(self) -> ($TIMX, PIN) {
+                    self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+
+                    (self.tim, self.pin)
+                }
+            }
+        )+
+    };
+}
+
+hal! {
+    TIM1: (tim1, tim1en, tim1rst, APB2, pclk2, ppre2, u16),
+    TIM2: (tim2, tim2en, tim2rst, APB1R1, pclk1, ppre1, u32),
+}
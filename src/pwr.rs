@@ -1,7 +1,11 @@
 //! Power management
 
-use crate::rcc::APB1R1;
-use crate::stm32::{pwr, PWR};
+use cortex_m::peripheral::SCB;
+
+use crate::flash::ACR;
+use crate::gpio::ExtiPin;
+use crate::rcc::{Clocks, APB1R1};
+use crate::stm32::{pwr, EXTI, PWR};
 
 pub struct Pwr {
     pub cr1: CR1,
@@ -10,6 +14,168 @@ pub struct Pwr {
     pub cr4: CR4,
 }
 
+impl Pwr {
+    /// Sets the voltage scaling range of the core regulator, see [`VoltageScale`]
+    ///
+    /// The flash wait states are adjusted to `clocks` in the new range: they are raised before
+    /// Range 2 is entered, and lowered once Range 1 is reached. [`CFGR::freeze`] already
+    /// selects the range fitting its clocks, this is meant to switch ranges at runtime, e.g. back
+    /// to Range 1 before enabling the USB or a PLLSAI with a faster VCO.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `clocks` are too fast for Range 2, see [`VoltageScale::Range2`].
+    ///
+    /// [`CFGR::freeze`]: crate::rcc::CFGR::freeze
+    pub fn set_voltage_scale(&mut self, range: VoltageScale, clocks: Clocks, acr: &mut ACR) {
+        let hclk = clocks.hclk().0;
+
+        match range {
+            VoltageScale::Range1 => {
+                self.write_voltage_scale(range);
+                set_flash_latency(acr, range, hclk);
+            }
+            VoltageScale::Range2 => {
+                assert!(
+                    clocks.sysclk().0 <= VoltageScale::RANGE2_MAX_FREQUENCY,
+                    "clocks too fast for voltage range 2"
+                );
+                set_flash_latency(acr, range, hclk);
+                self.write_voltage_scale(range);
+            }
+        }
+    }
+
+    /// Writes `CR1.VOS` and waits for the regulator to reach the new range
+    pub(crate) fn write_voltage_scale(&mut self, range: VoltageScale) {
+        // This is sound, as all values of `VoltageScale` are valid for this field.
+        self.cr1
+            .reg()
+            .modify(|_, w| unsafe { w.vos().bits(range as u8) });
+
+        // NOTE(unsafe) atomic read with no side effects
+        while unsafe { (*PWR::ptr()).sr2.read().vosf().bit_is_set() } {}
+    }
+
+    /// Enters Stop mode until the MCU is woken up, e.g. by one of `sources`
+    ///
+    /// The sources are armed before, and disarmed after. Their interrupts still have to be
+    /// unmasked in the NVIC, and their handlers run as soon as the MCU is awake. Called with
+    /// interrupts disabled, the handlers don't run, and the woken up source can be checked on
+    /// return.
+    ///
+    /// Any other EXTI line or interrupt enabled at the time wakes the MCU up as well, so this
+    /// may return without any of `sources` pending: check their flags, and call this again to
+    /// keep waiting for them.
+    ///
+    /// The MCU wakes up on MSI or HSI16, as selected by `RCC_CFGR.STOPWUCK`, with the PLL and
+    /// HSE off: the clocks frozen before are not restored.
+    pub fn stop_until(
+        &mut self,
+        mode: StopMode,
+        scb: &mut SCB,
+        exti: &mut EXTI,
+        sources: &mut [&mut dyn WakeupSource],
+    ) {
+        for source in sources.iter_mut() {
+            source.arm(exti);
+        }
+
+        // This is sound, as all values of `StopMode` are valid for this field.
+        self.cr1
+            .reg()
+            .modify(|_, w| unsafe { w.lpms().bits(mode as u8) });
+        scb.set_sleepdeep();
+
+        cortex_m::asm::dsb();
+        cortex_m::asm::wfi();
+
+        scb.clear_sleepdeep();
+
+        for source in sources.iter_mut() {
+            source.disarm(exti);
+        }
+    }
+}
+
+/// Voltage scaling range of the core regulator (`CR1.VOS`), see [`Pwr::set_voltage_scale`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum VoltageScale {
+    /// High performance range, up to 80 MHz, the reset range
+    Range1 = 0b01,
+    /// Low power range, up to 26 MHz for SYSCLK, HSE and the PLL outputs, and 128 MHz for the
+    /// PLL VCOs, with more flash wait states than Range 1 at the same frequency
+    ///
+    /// MSI is limited to 24 MHz, and the USB can't be used.
+    Range2 = 0b10,
+}
+
+impl VoltageScale {
+    /// Highest SYSCLK, HSE and PLL output frequency in Range 2
+    pub(crate) const RANGE2_MAX_FREQUENCY: u32 = 26_000_000;
+    /// Highest PLL VCO frequency in Range 2
+    pub(crate) const RANGE2_MAX_VCO: u32 = 128_000_000;
+
+    /// Flash wait states (`ACR.LATENCY`) needed at `hclk` in this range
+    pub(crate) fn flash_latency(self, hclk: u32) -> u8 {
+        // From RM0394, 3.3.3 Read access latency
+        let limits: &[u32] = match self {
+            VoltageScale::Range1 => &[16_000_000, 32_000_000, 48_000_000, 64_000_000],
+            VoltageScale::Range2 => &[6_000_000, 12_000_000, 18_000_000],
+        };
+
+        limits.iter().filter(|&&limit| hclk > limit).count() as u8
+    }
+}
+
+/// Writes the flash wait states needed at `hclk` in `range`
+pub(crate) fn set_flash_latency(acr: &mut ACR, range: VoltageScale, hclk: u32) {
+    let latency = range.flash_latency(hclk);
+    // This is sound, as the latency is within the 0 to 4 wait states of this field.
+    acr.acr()
+        .modify(|_, w| unsafe { w.latency().bits(latency) });
+}
+
+/// Stop mode, see [`Pwr::stop_until`]
+///
+/// The deeper the mode, the lower the consumption, but the longer the wakeup time and the fewer
+/// the peripherals kept running, see the reference manual.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StopMode {
+    /// Stop 0, with the main regulator on
+    Stop0 = 0b000,
+    /// Stop 1, with the low power regulator
+    Stop1 = 0b001,
+    /// Stop 2, with most of the core domain off
+    Stop2 = 0b010,
+}
+
+/// Source waking the MCU up from Stop mode, see [`Pwr::stop_until`]
+///
+/// Implemented by the EXTI pins, configured as interrupt source with their trigger edge
+/// beforehand, and the RTC events, see [`Rtc::wakeup_source`](crate::rtc::Rtc::wakeup_source).
+pub trait WakeupSource {
+    /// Clears the pending event and unmasks its EXTI interrupt
+    fn arm(&mut self, exti: &mut EXTI);
+
+    /// Masks the EXTI interrupt again, the event stays pending to be checked
+    fn disarm(&mut self, exti: &mut EXTI);
+}
+
+impl<PIN> WakeupSource for PIN
+where
+    PIN: ExtiPin,
+{
+    fn arm(&mut self, exti: &mut EXTI) {
+        self.clear_interrupt_pending_bit();
+        self.enable_interrupt(exti);
+    }
+
+    fn disarm(&mut self, exti: &mut EXTI) {
+        self.disable_interrupt(exti);
+    }
+}
+
 /// Extension trait that constrains the `PWR` peripheral
 pub trait PwrExt {
     /// Constrains the `PWR` peripheral so it plays nicely with the other abstractions
@@ -81,3 +247,28 @@ impl CR4 {
         unsafe { &(*PWR::ptr()).cr4 }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flash_latencies() {
+        // The upper bound of each number of wait states, from RM0394, 3.3.3 Read access latency
+        let range1 = [16_000_000, 32_000_000, 48_000_000, 64_000_000, 80_000_000];
+        let range2 = [6_000_000, 12_000_000, 18_000_000, 26_000_000];
+
+        for (range, limits) in [
+            (VoltageScale::Range1, &range1[..]),
+            (VoltageScale::Range2, &range2[..]),
+        ] {
+            assert_eq!(range.flash_latency(0), 0);
+            for (latency, &limit) in limits.iter().enumerate() {
+                assert_eq!(range.flash_latency(limit), latency as u8, "{}", limit);
+                if latency + 1 < limits.len() {
+                    assert_eq!(range.flash_latency(limit + 1), latency as u8 + 1);
+                }
+            }
+        }
+    }
+}
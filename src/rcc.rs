@@ -4,7 +4,7 @@ use crate::stm32::{rcc, RCC};
 use cast::u32;
 
 use crate::flash::ACR;
-use crate::pwr::Pwr;
+use crate::pwr::{set_flash_latency, Pwr, VoltageScale};
 use crate::time::Hertz;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -85,6 +85,8 @@ impl RccExt for RCC {
                 sysclk: None,
                 pll_source: None,
                 pll_config: None,
+                flash_prefetch: true,
+                flash_caches: true,
             },
         }
     }
@@ -128,6 +130,63 @@ impl CSR {
         // NOTE(unsafe) this proxy grants exclusive access to this register
         unsafe { &(*RCC::ptr()).csr }
     }
+
+    /// Returns the cause of the last reset, from the reset flags
+    ///
+    /// The flags add up over resets until [`CSR::clear_reset_flags`] is called, except for a
+    /// power-on or brown-out reset, which clears the others. If several are set, the most
+    /// specific one is returned, as the pin flag is also set by every internal reset.
+    pub fn reset_reason(&mut self) -> ResetReason {
+        let csr = self.csr().read();
+
+        if csr.firewallrstf().bit_is_set() {
+            ResetReason::Firewall
+        } else if csr.oblrstf().bit_is_set() {
+            ResetReason::OptionByteLoader
+        } else if csr.lpwrstf().bit_is_set() {
+            ResetReason::LowPower
+        } else if csr.iwdgrstf().bit_is_set() {
+            ResetReason::IndependentWatchdog
+        } else if csr.wwdgrstf().bit_is_set() {
+            ResetReason::WindowWatchdog
+        } else if csr.sftrstf().bit_is_set() {
+            ResetReason::Software
+        } else if csr.borrstf().bit_is_set() {
+            ResetReason::PowerOn
+        } else if csr.pinrstf().bit_is_set() {
+            ResetReason::Pin
+        } else {
+            ResetReason::Unknown
+        }
+    }
+
+    /// Clears the reset flags, so the next [`CSR::reset_reason`] only reports the next reset
+    pub fn clear_reset_flags(&mut self) {
+        self.csr().modify(|_, w| w.rmvf().set_bit());
+    }
+}
+
+/// Cause of the last reset, see [`CSR::reset_reason`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ResetReason {
+    /// Power-on or brown-out reset (`BORRSTF`), as the L4 has no separate power-on flag
+    PowerOn,
+    /// Reset by the `NRST` pin (`PINRSTF`)
+    Pin,
+    /// Software reset, e.g. `SCB::sys_reset` (`SFTRSTF`)
+    Software,
+    /// Timeout of the independent watchdog (`IWDGRSTF`)
+    IndependentWatchdog,
+    /// Timeout of the window watchdog (`WWDGRSTF`)
+    WindowWatchdog,
+    /// Entering Stop, Standby or Shutdown mode while forbidden by the option bytes (`LPWRRSTF`)
+    LowPower,
+    /// Reload of the option bytes (`OBLRSTF`)
+    OptionByteLoader,
+    /// Violation of the firewall (`FIREWALLRSTF`)
+    Firewall,
+    /// No reset flag is set, since they were cleared
+    Unknown,
 }
 
 /// Clock recovery RC register
@@ -353,6 +412,8 @@ pub struct CFGR {
     sysclk: Option<u32>,
     pll_source: Option<PllSource>,
     pll_config: Option<PllConfig>,
+    flash_prefetch: bool,
+    flash_caches: bool,
 }
 
 impl CFGR {
@@ -387,11 +448,32 @@ impl CFGR {
     }
 
     /// Enable the 48 MHz USB, RNG, SDMMC HSI clock source. Not available on all stm32l4x6 series
+    ///
+    /// The HSI48 is selected as 48 MHz clock, unless the MSI runs at 48 MHz. For crystal-less USB,
+    /// trim it with the [`crs`](crate::crs) module.
     pub fn hsi48(mut self, on: bool) -> Self {
         self.hsi48 = on;
         self
     }
 
+    /// Sets the flash prefetch buffer (`ACR.PRFTEN`) on (the default) or off
+    ///
+    /// The prefetch buffer reads the next flash line while the current one executes, which hides
+    /// the wait states of sequential code at the cost of some consumption.
+    pub fn flash_prefetch(mut self, on: bool) -> Self {
+        self.flash_prefetch = on;
+        self
+    }
+
+    /// Sets the flash instruction and data caches (`ACR.ICEN`, `ACR.DCEN`) on (the default) or off
+    ///
+    /// Without the caches every flash access takes the wait states, which makes the execution
+    /// time of a code path independent of what ran before it, e.g. for cycle counted timing.
+    pub fn flash_caches(mut self, on: bool) -> Self {
+        self.flash_caches = on;
+        self
+    }
+
     /// Enables the MSI with the specified speed
     pub fn msi(mut self, range: MsiFreq) -> Self {
         self.msi = Some(range);
@@ -448,6 +530,26 @@ impl CFGR {
     }
 
     /// Freezes the clock configuration, making it effective
+    ///
+    /// The voltage scaling range is selected for the clocks: the low power Range 2 if they all
+    /// fit in it, i.e. SYSCLK and HSE up to 26 MHz, MSI up to 24 MHz, the PLL VCO up to 128 MHz
+    /// and no HSI48, or Range 1 otherwise, see [`VoltageScale`]. The flash wait states are set
+    /// for the range. Range 2 also limits the PLLSAI VCOs to 128 MHz, and excludes the USB; use
+    /// [`Pwr::set_voltage_scale`] to switch to Range 1 afterwards.
+    ///
+    /// The flash wait states (`ACR.LATENCY`) follow HCLK, from RM0394 3.3.3:
+    ///
+    /// | Wait states | HCLK in Range 1 | HCLK in Range 2 |
+    /// |-------------|-----------------|-----------------|
+    /// | 0           | up to 16 MHz    | up to 6 MHz     |
+    /// | 1           | up to 32 MHz    | up to 12 MHz    |
+    /// | 2           | up to 48 MHz    | up to 18 MHz    |
+    /// | 3           | up to 64 MHz    | up to 26 MHz    |
+    /// | 4           | up to 80 MHz    |                 |
+    ///
+    /// They are raised before the clocks are switched, and lowered after, so the flash is never
+    /// read with too few wait states. The prefetch buffer and the caches are then enabled, unless
+    /// disabled with [`CFGR::flash_prefetch`] and [`CFGR::flash_caches`].
     pub fn freeze(&self, acr: &mut ACR, pwr: &mut Pwr) -> Clocks {
         let rcc = unsafe { &*RCC::ptr() };
 
@@ -556,6 +658,9 @@ impl CFGR {
 
             // Wait until HSI48 is running
             while rcc.crrcr.read().hsi48rdy().bit_is_clear() {}
+
+            // Select HSI48 as clock source for usb48, rng ...
+            unsafe { rcc.ccipr.modify(|_, w| w.clk48sel().bits(0b00)) };
         }
 
         // Select MSI as clock source for usb48, rng ...
@@ -681,21 +786,32 @@ impl CFGR {
 
         assert!(pclk2 <= sysclk);
 
-        // adjust flash wait states
-        unsafe {
-            acr.acr().write(|w| {
-                w.latency().bits(if hclk <= 16_000_000 {
-                    0b000
-                } else if hclk <= 32_000_000 {
-                    0b001
-                } else if hclk <= 48_000_000 {
-                    0b010
-                } else if hclk <= 64_000_000 {
-                    0b011
-                } else {
-                    0b100
-                })
-            })
+        // Range 2 is only selected if all the clocks enabled here fit in it, the USB needs Range 1
+        let range2_max = VoltageScale::RANGE2_MAX_FREQUENCY;
+        let fits_range2 = sysclk <= range2_max
+            && self.hse.as_ref().is_none_or(|hse| hse.speed <= range2_max)
+            && self.msi.is_none_or(|msi| msi.to_hertz().0 <= 24_000_000)
+            && !self.hsi48
+            && pllconf.is_none_or(|pllconf| {
+                clock_speed / (pllconf.m as u32 + 1) * pllconf.n as u32
+                    <= VoltageScale::RANGE2_MAX_VCO
+            });
+        let range = if fits_range2 {
+            VoltageScale::Range2
+        } else {
+            VoltageScale::Range1
+        };
+
+        // Range 1 is entered before the clocks are raised
+        if range == VoltageScale::Range1 {
+            pwr.write_voltage_scale(range);
+        }
+
+        // Flash wait states, raised before the clocks are and lowered after them; the wait states
+        // of Range 2 are also enough in Range 1
+        let raise_latency = range.flash_latency(hclk) > acr.acr().read().latency().bits();
+        if raise_latency {
+            set_flash_latency(acr, range, hclk);
         }
 
         let sysclk_src_bits;
@@ -772,6 +888,34 @@ impl CFGR {
 
         while rcc.cfgr.read().sws().bits() != sysclk_src_bits {}
 
+        if !raise_latency {
+            set_flash_latency(acr, range, hclk);
+        }
+
+        // The caches can only be reset while disabled, they are before being enabled again
+        let acr_bits = acr.acr().read();
+        if self.flash_caches && acr_bits.icen().bit_is_clear() {
+            acr.acr().modify(|_, w| w.icrst().set_bit());
+            acr.acr().modify(|_, w| w.icrst().clear_bit());
+        }
+        if self.flash_caches && acr_bits.dcen().bit_is_clear() {
+            acr.acr().modify(|_, w| w.dcrst().set_bit());
+            acr.acr().modify(|_, w| w.dcrst().clear_bit());
+        }
+        acr.acr().modify(|_, w| {
+            w.prften()
+                .bit(self.flash_prefetch)
+                .icen()
+                .bit(self.flash_caches)
+                .dcen()
+                .bit(self.flash_caches)
+        });
+
+        // Range 2 is entered once the clocks are lowered
+        if range == VoltageScale::Range2 {
+            pwr.write_voltage_scale(range);
+        }
+
         //
         // 3. Shutdown unused clocks that have auto-started
         //
@@ -918,6 +1062,11 @@ impl Clocks {
         self.lsi
     }
 
+    /// Returns status of the LSE
+    pub fn lse(&self) -> bool {
+        self.lse
+    }
+
     /// Returns the frequency of the APB1
     pub fn pclk1(&self) -> Hertz {
         self.pclk1
@@ -1,21 +1,83 @@
 //! RTC peripheral abstraction
 
+#[cfg(feature = "rtic")]
+pub mod monotonic;
+pub mod scheduler;
+
+use core::convert::TryFrom;
+use core::time::Duration;
+
+use cortex_m::interrupt;
 use void::Void;
 
 use crate::{
     datetime::*,
+    hal::blocking::delay::{DelayMs, DelayUs},
     hal::timer::{self, Cancel as _},
     pwr,
     rcc::{APB1R1, BDCR},
-    stm32::{EXTI, RTC},
+    stm32::{rtc::RegisterBlock, EXTI, RCC, RTC},
+    time::Hertz,
 };
 
-/// Interrupt event
+/// Number of register polls after which the RTC clock is considered dead
+const CLOCK_READY_TIMEOUT: u32 = 20_000_000;
+
+/// Number of register polls after which entering init mode is considered failed
+const INIT_TIMEOUT: u32 = 100_000;
+
+/// Number of backup registers, `BKP0R` to `BKP31R`
+pub const BACKUP_REGISTERS: usize = 32;
+
+/// Correction of one masked `RTCCLK` pulse in the 2^20 cycle smooth calibration window, in ppm
+const CALM_PPM: f32 = 1_000_000.0 / (1 << 20) as f32;
+
+/// RTC error
+#[non_exhaustive]
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The selected clock source did not become ready
+    ClockNotReady,
+    /// The RTC did not enter init mode
+    InitTimeout,
+    /// Another output function is already enabled on the RTC output
+    OutputInUse,
+    /// The function would share PC13 with an incompatible one, see [`Rtc::enable_alarm_output`]
+    PinConflict,
+    /// The range of backup registers ends past the last one, see [`Rtc::write_backup`]
+    BackupOutOfRange,
+}
+
+/// RTC interrupt events
+///
+/// The events reach the NVIC through EXTI lines, which [`Rtc::listen`] configures:
+///
+/// | Event                          | EXTI line | Interrupt    |
+/// |--------------------------------|-----------|--------------|
+/// | Alarm A and B                  | 18        | `RTC_ALARM`  |
+/// | Timestamp and tamper events    | 19        | `TAMP_STAMP` |
+/// | Wakeup timer                   | 20        | `RTC_WKUP`   |
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Event {
     WakeupTimer,
     AlarmA,
     AlarmB,
     Timestamp,
+    Tamper1,
+    Tamper2,
+    Tamper3,
+}
+
+impl Event {
+    /// EXTI line the event is connected to
+    pub fn exti_line(self) -> u8 {
+        match self {
+            Event::AlarmA | Event::AlarmB => 18,
+            Event::Timestamp | Event::Tamper1 | Event::Tamper2 | Event::Tamper3 => 19,
+            Event::WakeupTimer => 20,
+        }
+    }
 }
 
 pub enum Alarm {
@@ -23,6 +85,38 @@ pub enum Alarm {
     AlarmB,
 }
 
+/// Day on which an alarm matches, see [`Rtc::set_alarm_on`]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AlarmDay {
+    /// Day of the month, 1 to 31
+    Date(u32),
+    /// Day of the week, 1 (Monday) to 7 (Sunday)
+    Weekday(u32),
+    /// Every day, only the time has to match
+    EveryDay,
+}
+
+impl AlarmDay {
+    /// Returns `WDSEL`, `MSK4` and the tens and units of the day field of an alarm register
+    ///
+    /// # Panics
+    ///
+    /// Panics if the day of the month is not within 1 to 31, or the weekday within 1 to 7.
+    fn fields(self) -> (bool, bool, (u8, u8)) {
+        match self {
+            AlarmDay::Date(date) => {
+                assert!((1..=31).contains(&date));
+                (false, false, byte_to_bcd2(date as u8))
+            }
+            AlarmDay::Weekday(weekday) => {
+                assert!((1..=7).contains(&weekday));
+                (true, false, (0, weekday as u8))
+            }
+            AlarmDay::EveryDay => (false, true, (0, 1)),
+        }
+    }
+}
+
 impl From<Alarm> for Event {
     fn from(a: Alarm) -> Self {
         match a {
@@ -32,10 +126,208 @@ impl From<Alarm> for Event {
     }
 }
 
+/// Signal driven on the RTC_ALARM output
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AlarmOutput {
+    AlarmA = 0b01,
+    AlarmB = 0b10,
+    WakeupTimer = 0b11,
+}
+
+/// Configuration of the RTC_ALARM output, see [`Rtc::set_output`]
+///
+/// The default is the reset configuration: output disabled, on PC13, open-drain and active high.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OutputConfig {
+    /// Signal driven on the output, or `None` to disable it (`CR.OSEL`)
+    output: Option<AlarmOutput>,
+    /// Output on PB2 instead of PC13 (`OR.RTC_OUT_RMP`)
+    remap: bool,
+    /// Push-pull instead of open-drain output (`OR.RTC_ALARM_TYPE`)
+    push_pull: bool,
+    /// Active high instead of active low output (`CR.POL`)
+    active_high: bool,
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        OutputConfig {
+            output: None,
+            remap: false,
+            push_pull: false,
+            active_high: true,
+        }
+    }
+}
+
+impl OutputConfig {
+    /// Sets the signal driven on the output
+    pub fn output(mut self, output: AlarmOutput) -> Self {
+        self.output = Some(output);
+        self
+    }
+
+    /// Sets whether the output is remapped from PC13 to PB2
+    ///
+    /// PC13 and PB2 are not bonded on the 32 pin packages of the L4x2 parts (e.g. STM32L432KC),
+    /// which have no RTC output. On the other L4x2 packages PC13 is pin 2 and PB2 is pin 20 of
+    /// the 48 pin packages, and pins 2 and 28 of the 64 pin packages.
+    pub fn remap(mut self, remap: bool) -> Self {
+        self.remap = remap;
+        self
+    }
+
+    /// Sets whether the output is push-pull or open-drain
+    ///
+    /// The output type only applies on PC13, the output is always push-pull on PB2.
+    pub fn push_pull(mut self, push_pull: bool) -> Self {
+        self.push_pull = push_pull;
+        self
+    }
+
+    /// Sets the polarity of the output
+    pub fn active_high(mut self, active_high: bool) -> Self {
+        self.active_high = active_high;
+        self
+    }
+}
+
+/// Tamper input, RTC_TAMP1 is on PC13, RTC_TAMP2 on PA0 and RTC_TAMP3 on PE6
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Tamper {
+    Tamper1,
+    Tamper2,
+    Tamper3,
+}
+
+/// Edge of a tamper input that raises the tamper event
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TamperEdge {
+    Rising,
+    Falling,
+}
+
+/// Clock of the wakeup timer (`CR.WUCKSEL`), see [`wakeup_config_for`]
+///
+/// The `RTCCLK` dividers count in fine steps but reach at most 32 s with a 32.768 kHz clock,
+/// while `ck_spre`, 1 Hz with the default prescalers, counts whole seconds up to 2^17 s.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum WakeupClock {
+    /// `RTCCLK / 16`
+    RtcDiv16 = 0b000,
+    /// `RTCCLK / 8`
+    RtcDiv8 = 0b001,
+    /// `RTCCLK / 4`
+    RtcDiv4 = 0b010,
+    /// `RTCCLK / 2`
+    RtcDiv2 = 0b011,
+    /// `ck_spre`, 1 to 2^16 periods
+    Spre = 0b100,
+    /// `ck_spre`, with 2^16 added to the reload value: 2^16 + 1 to 2^17 periods
+    SpreExtended = 0b110,
+}
+
 /// RTC Abstraction
 pub struct Rtc {
     rtc: RTC,
     rtc_config: RtcConfig,
+    /// Instant of the calendar at which the uptime was 0, see [`Rtc::uptime_us`]
+    start: RtcInstant,
+    /// Uptime when the calendar was paused, see [`Rtc::pause`]
+    paused_uptime: Option<Duration>,
+    /// Local time minus the calendar, in minutes, see [`Rtc::local_now`]
+    utc_offset: i16,
+}
+
+/// Read-only access to the calendar, see [`Rtc::reader`]
+///
+/// The reader is `Copy` and doesn't borrow the [`Rtc`], so tasks that only display the time
+/// each get a copy, while the task setting the calendar owns the `Rtc`, without a lock around
+/// all of them:
+///
+/// ```ignore
+/// #[init]
+/// fn init(cx: init::Context) -> init::LateResources {
+///     let rtc = Rtc::rtc(/* ... */);
+///     let reader = rtc.reader();
+///
+///     init::LateResources { rtc, reader }
+/// }
+///
+/// #[task(binds = TIM2, resources = [reader])]
+/// fn display(cx: display::Context) {
+///     let (date, time) = cx.resources.reader.get_date_time();
+///     // ...
+/// }
+///
+/// #[task(binds = USART2, resources = [rtc])]
+/// fn set_clock(cx: set_clock::Context) {
+///     cx.resources.rtc.set_date_time(date, time);
+/// }
+/// ```
+///
+/// This is sound, as reading the calendar has no side effect on the RTC: the reads of `SSR`,
+/// `TR` and `DR` are done with interrupts disabled, so a read preempting another one can't
+/// unlock the shadow registers in between. A read racing with a write of the calendar returns
+/// the time before or after it, like a read just before or after the write.
+#[derive(Clone, Copy, Debug)]
+pub struct RtcReader {
+    _private: (),
+}
+
+impl RtcReader {
+    fn rtc(&self) -> &'static RegisterBlock {
+        // NOTE(unsafe) only the calendar registers are read, which has no side effects
+        unsafe { &*RTC::ptr() }
+    }
+
+    /// Get date and time touple, see [`Rtc::get_date_time`]
+    pub fn get_date_time(&self) -> (Date, Time) {
+        read_date_time(self.rtc())
+    }
+
+    /// Get the time, see [`Rtc::get_time`]
+    pub fn get_time(&self) -> Time {
+        self.get_date_time().1
+    }
+
+    /// Get the date, see [`Rtc::get_date`]
+    pub fn get_date(&self) -> Date {
+        self.get_date_time().0
+    }
+
+    /// Get the current instant of the calendar, see [`Rtc::now`]
+    pub fn now(&self) -> RtcInstant {
+        let (date, time) = self.get_date_time();
+
+        RtcInstant::from_date_time(date, time)
+    }
+
+    /// Get date and time touple, or `None` if the calendar was never initialized, see
+    /// [`Rtc::datetime_if_set`]
+    pub fn datetime_if_set(&self) -> Option<(Date, Time)> {
+        if self.rtc().isr.read().inits().bit_is_set() {
+            Some(self.get_date_time())
+        } else {
+            None
+        }
+    }
+
+    /// Returns the date and time as a Unix timestamp, see [`Rtc::unix_time`]
+    pub fn unix_time(&self) -> u32 {
+        self.second_counter()
+    }
+
+    /// Returns the calendar as a count of seconds since 1970-01-01 00:00:00, see
+    /// [`Rtc::second_counter`]
+    pub fn second_counter(&self) -> u32 {
+        read_second_counter(self.rtc())
+    }
+
+    /// Returns the seconds elapsed since `start`, see [`Rtc::elapsed_secs_since`]
+    pub fn elapsed_secs_since(&self, start: u32) -> u32 {
+        self.second_counter().saturating_sub(start)
+    }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -99,6 +391,17 @@ impl RtcConfig {
 }
 
 impl Rtc {
+    /// Creates and configures the RTC
+    ///
+    /// The backup domain is only reset if the RTC is not running yet or runs off another clock
+    /// source, so the time survives a reset of the MCU. Once created, the RTC is reconfigured
+    /// without ever resetting the backup domain, except by [`Rtc::set_config`] changing the clock
+    /// source.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the clock source does not become ready or init mode can't be
+    /// entered, see [`Rtc::try_new`].
     pub fn rtc(
         rtc: RTC,
         apb1r1: &mut APB1R1,
@@ -106,71 +409,320 @@ impl Rtc {
         pwrcr1: &mut pwr::CR1,
         rtc_config: RtcConfig,
     ) -> Self {
-        // assert_eq!(clocks.lsi(), true); // make sure LSI is enabled
+        match Self::try_new(rtc, apb1r1, bdcr, pwrcr1, rtc_config) {
+            Ok(rtc) => rtc,
+            Err((_, error)) => panic!("RTC initialization failed: {:?}", error),
+        }
+    }
+
+    /// Creates and configures the RTC, reporting clock failures instead of hanging
+    ///
+    /// Returns `Error::ClockNotReady` if the selected clock source (which has
+    /// to be enabled in the RCC beforehand) does not become ready within a
+    /// bounded time, and `Error::InitTimeout` if the RTC does not enter init
+    /// mode. The RTC peripheral is handed back with the error, so the caller
+    /// can retry with a different clock source, e.g. LSI if the LSE failed.
+    pub fn try_new(
+        rtc: RTC,
+        apb1r1: &mut APB1R1,
+        bdcr: &mut BDCR,
+        pwrcr1: &mut pwr::CR1,
+        rtc_config: RtcConfig,
+    ) -> Result<Self, (RTC, Error)> {
         // enable peripheral clock for communication
         apb1r1.enr().modify(|_, w| w.rtcapben().set_bit());
         pwrcr1.reg().read(); // read to allow the pwr clock to enable
 
-        let mut rtc_struct = Self { rtc, rtc_config };
-        rtc_struct.set_config(bdcr, pwrcr1, rtc_config);
-
-        rtc_struct
+        let mut rtc_struct = Self {
+            rtc,
+            rtc_config,
+            start: RtcInstant::EPOCH,
+            paused_uptime: None,
+            utc_offset: 0,
+        };
+        match rtc_struct.try_set_config(bdcr, pwrcr1, rtc_config, true) {
+            Ok(()) => {
+                rtc_struct.start = rtc_struct.now();
+                Ok(rtc_struct)
+            }
+            Err(error) => Err((rtc_struct.rtc, error)),
+        }
     }
 
     /// Get date and time touple
+    ///
+    /// Unless the shadow registers are bypassed, this waits for `ISR.RSF`, which the hardware
+    /// clears in init mode: a read right after setting the calendar returns the new values. After
+    /// waking up from a low power mode `RSF` is still set from before, use [`Rtc::snapshot`]
+    /// instead, see [`Rtc::wait_for_synchro`]. While the calendar is in init mode, e.g. paused,
+    /// this doesn't wait and returns the calendar as it was stopped, see [`Rtc::pause`].
     pub fn get_date_time(&self) -> (Date, Time) {
-        let time;
-        let date;
+        read_date_time(&self.rtc)
+    }
 
-        let sync_p = self.rtc_config.sync_prescaler as u32;
-        let micros =
-            1_000_000u32 / (sync_p + 1) * (sync_p - self.rtc.ssr.read().ss().bits() as u32);
-        let timer = self.rtc.tr.read();
-        let cr = self.rtc.cr.read();
+    /// Get the time
+    ///
+    /// The date is read as well, as reading `TR` locks the date shadow register until `DR` is
+    /// read, see [`Rtc::get_date_time`].
+    pub fn get_time(&self) -> Time {
+        self.get_date_time().1
+    }
 
-        // Reading either RTC_SSR or RTC_TR locks the values in the higher-order
-        // calendar shadow registers until RTC_DR is read.
-        let dater = self.rtc.dr.read();
+    /// Get the date
+    ///
+    /// The time is read as well: reading `DR` unlocks the shadow registers locked by a read of
+    /// `SSR` or `TR`, so a read of `DR` alone could be paired with the time of a previous read.
+    /// Like [`Rtc::get_time`], this waits for the shadow registers to be synchronized, see
+    /// [`Rtc::get_date_time`].
+    pub fn get_date(&self) -> Date {
+        self.get_date_time().0
+    }
 
-        time = Time::new(
-            bcd2_to_byte((timer.ht().bits(), timer.hu().bits())).into(),
-            bcd2_to_byte((timer.mnt().bits(), timer.mnu().bits())).into(),
-            bcd2_to_byte((timer.st().bits(), timer.su().bits())).into(),
-            micros.into(),
-            cr.bkp().bit(),
-        );
+    /// Get the current instant of the calendar, with its subseconds
+    ///
+    /// Like [`Rtc::get_date_time`], the shadow registers have to be synchronized first.
+    pub fn now(&self) -> RtcInstant {
+        let (date, time) = self.get_date_time();
 
-        date = Date::new(
-            dater.wdu().bits().into(),
-            bcd2_to_byte((dater.dt().bits(), dater.du().bits())).into(),
-            bcd2_to_byte((dater.mt().bit() as u8, dater.mu().bits())).into(),
-            (bcd2_to_byte((dater.yt().bits(), dater.yu().bits())) as u16 + 1970_u16).into(),
-        );
+        RtcInstant::from_date_time(date, time)
+    }
+
+    /// Sets the offset of the local time from UTC, in minutes, e.g. `-300` for UTC-05:00
+    ///
+    /// The calendar is kept in UTC, the offset only applies to [`Rtc::local_now`].
+    pub fn set_utc_offset(&mut self, minutes: i16) {
+        self.utc_offset = minutes;
+    }
+
+    /// Returns the offset of the local time from UTC, in minutes, see [`Rtc::set_utc_offset`]
+    pub fn utc_offset(&self) -> i16 {
+        self.utc_offset
+    }
+
+    /// Get the local date and time, the calendar shifted by the UTC offset
+    ///
+    /// The date follows when the offset crosses midnight, e.g. 23:30 UTC on 31 December is
+    /// 01:30 on 1 January at UTC+02:00, and the weekday with it. The daylight saving bit is
+    /// cleared, and a local time before 1970 reads as 1970-01-01 00:00:00.
+    pub fn local_now(&self) -> (Date, Time) {
+        self.now()
+            .checked_offset(self.utc_offset)
+            .unwrap_or(RtcInstant::EPOCH)
+            .to_date_time()
+    }
+
+    /// Get date and time touple, or `None` if the calendar was never initialized
+    ///
+    /// After a backup domain reset the calendar holds its reset value, which can't be told apart
+    /// from a calendar set to that date by the getters. `ISR.INITS` tells them apart, as the
+    /// hardware sets it once the year is written.
+    pub fn datetime_if_set(&self) -> Option<(Date, Time)> {
+        if self.rtc.isr.read().inits().bit_is_set() {
+            Some(self.get_date_time())
+        } else {
+            None
+        }
+    }
+
+    /// Returns a read-only handle on the calendar, for tasks that only read the time
+    ///
+    /// The handle can be copied to any number of tasks, while the `Rtc` stays with the one
+    /// changing the RTC, see [`RtcReader`].
+    pub fn reader(&self) -> RtcReader {
+        RtcReader { _private: () }
+    }
+
+    /// Waits until the calendar shadow registers are synchronized with the counters
+    ///
+    /// This clears `ISR.RSF` and waits for the hardware to set it again, which happens every two
+    /// RTC clock cycles. After waking up from a low power mode, the shadow registers hold stale
+    /// values until then, so a synchronization is mandatory before their reads are valid. After
+    /// exiting init mode (e.g. through [`Rtc::set_date_time`]) the hardware clears `RSF` itself,
+    /// and the getters wait for it. It is a no-op when the shadow registers are bypassed
+    /// (`CR.BYPSHAD`), or while the calendar is paused, see [`Rtc::pause`].
+    ///
+    /// This writes `ISR`, and therefore takes `&mut self` like all methods changing the RTC.
+    pub fn wait_for_synchro(&mut self) {
+        // The shadow registers are not updated at all while paused
+        if self.rtc.cr.read().bypshad().bit_is_set() || self.is_paused() {
+            return;
+        }
+
+        // Disable write protection.
+        // This is safe, as we're only writing the correct and expected values.
+        self.rtc.wpr.write(|w| unsafe { w.key().bits(0xca) });
+        self.rtc.wpr.write(|w| unsafe { w.key().bits(0x53) });
+
+        self.rtc.isr.modify(|_, w| w.rsf().clear_bit());
+
+        // Re-enable write protection.
+        // This is safe, as the field accepts the full range of 8-bit values.
+        self.rtc.wpr.write(|w| unsafe { w.key().bits(0xff) });
+
+        while self.rtc.isr.read().rsf().bit_is_clear() {}
+    }
 
-        (date, time)
+    /// Synchronizes the shadow registers and reads the date and time
+    ///
+    /// This is [`Rtc::wait_for_synchro`] followed by the coherent `SSR`, `TR` and `DR` read of
+    /// [`Rtc::get_date_time`], so consecutive snapshots in a tight loop never return stale values.
+    pub fn snapshot(&mut self) -> (Date, Time) {
+        self.wait_for_synchro();
+        self.get_date_time()
     }
 
     /// Set Date and Time
+    ///
+    /// Write protection is disabled and init mode is entered only once for both `TR` and `DR`,
+    /// so the calendar is stopped for a single, short window and both values take effect
+    /// together when init mode is exited.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the year is outside 2000 to 2099, the years the RTC can hold.
     pub fn set_date_time(&mut self, date: Date, time: Time) {
-        self.write(true, |rtc| {
-            set_time_raw(rtc, time);
-            set_date_raw(rtc, date);
-        })
+        self.with_write_access(|guard| guard.set_date_time(date, time))
+    }
+
+    /// Sets the date and time from a Unix timestamp (seconds since 1970-01-01 00:00:00 UTC)
+    ///
+    /// # Panics
+    ///
+    /// Panics if the timestamp lies outside 2000 to 2099, the years the RTC can hold.
+    pub fn set_unix_time(&mut self, secs: u32) {
+        let days = secs / 86_400;
+        let secs_of_day = secs % 86_400;
+
+        let date = Date::from_days_since_epoch(days);
+        assert!(
+            RTC_YEARS.contains(&date.year),
+            "Unix time outside the RTC range"
+        );
+        let time = Time {
+            hours: secs_of_day / 3600,
+            minutes: secs_of_day / 60 % 60,
+            seconds: secs_of_day % 60,
+            micros: 0,
+            daylight_savings: false,
+        };
+
+        self.set_date_time(date, time);
+    }
+
+    /// Returns the date and time as a Unix timestamp (seconds since 1970-01-01 00:00:00 UTC)
+    ///
+    /// The sub-seconds are truncated.
+    pub fn unix_time(&self) -> u32 {
+        self.second_counter()
+    }
+
+    /// Returns the calendar as a count of seconds since 1970-01-01 00:00:00, for interval timing
+    ///
+    /// Only `TR` and `DR` are read and combined, without decoding the sub-seconds, the weekday or
+    /// the daylight saving bit, so this is cheaper than [`Rtc::get_date_time`] for periodic
+    /// checks of long intervals. The count is continuous across midnight and month ends, it only
+    /// jumps when the calendar is set, e.g. for a daylight saving time change. Like the other
+    /// getters, this waits for the shadow registers to be synchronized.
+    pub fn second_counter(&self) -> u32 {
+        read_second_counter(&self.rtc)
+    }
+
+    /// Returns the seconds elapsed since `start`, a previous value of [`Rtc::second_counter`]
+    ///
+    /// E.g. from 23:59:58 to 00:00:03 the next day, this returns 5. If the calendar was set back to
+    /// before `start` in the meantime, this returns 0.
+    pub fn elapsed_secs_since(&self, start: u32) -> u32 {
+        self.second_counter().saturating_sub(start)
+    }
+
+    /// Adds one hour to the calendar and sets the daylight saving bit, for the summer time change
+    ///
+    /// Unlike [`Rtc::set_time`], this does not stop the calendar.
+    pub fn add_hour(&mut self) {
+        self.write(false, |rtc| {
+            rtc.cr.modify(|_, w| w.add1h().set_bit().bkp().set_bit());
+        });
+
+        self.start += Duration::from_secs(3600);
+        self.wait_for_synchro();
+    }
+
+    /// Subtracts one hour from the calendar and clears the daylight saving bit, for the winter
+    /// time change, returning `false` if the calendar did not change
+    ///
+    /// The hardware does not change the date, so between 00:00 and 00:59 this does nothing and
+    /// leaves the daylight saving bit set, for the change to be applied later.
+    pub fn subtract_hour(&mut self) -> bool {
+        if self.get_time().hours == 0 {
+            return false;
+        }
+
+        self.write(false, |rtc| {
+            rtc.cr.modify(|_, w| w.sub1h().set_bit().bkp().clear_bit());
+        });
+
+        self.start = self
+            .start
+            .checked_sub(Duration::from_secs(3600))
+            .unwrap_or(RtcInstant::EPOCH);
+        self.wait_for_synchro();
+
+        true
+    }
+
+    /// Applies a pending daylight saving time change, returning `true` if the calendar changed
+    ///
+    /// The daylight saving bit (`CR.BKP`, see [`Time::daylight_savings`]) records whether the
+    /// summer time is applied, so calling this again after a change, e.g. periodically or after
+    /// a reset, does not shift the clock twice. The calendar must be set in local time, with the
+    /// daylight saving bit matching it.
+    ///
+    /// A change back to standard time is deferred while the hour is 0, see [`Rtc::subtract_hour`].
+    pub fn apply_dst(&mut self, rule: DstRule) -> bool {
+        let (date, time) = self.get_date_time();
+        let secs =
+            date.days_since_epoch() * 86_400 + time.hours * 3600 + time.minutes * 60 + time.seconds;
+
+        // The rule is evaluated in standard time, so the hour repeated at the end of the summer
+        // time is not taken for summer time again
+        let standard_secs = if time.daylight_savings {
+            secs.saturating_sub(3600)
+        } else {
+            secs
+        };
+        let summer_time = rule.is_summer_time(standard_secs);
+
+        if summer_time == time.daylight_savings {
+            false
+        } else if summer_time {
+            self.add_hour();
+            true
+        } else {
+            self.subtract_hour()
+        }
     }
 
     /// Set Time
     /// Note: If setting both time and date, use set_date_time(...) to avoid errors.
     pub fn set_time(&mut self, time: Time) {
-        self.write(true, |rtc| {
-            set_time_raw(rtc, time);
+        self.keep_uptime(|rtc| {
+            rtc.write(true, |rtc| {
+                set_time_raw(rtc, time);
+            })
         })
     }
 
     /// Set Date
     /// Note: If setting both time and date, use set_date_time(...) to avoid errors.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the year is outside 2000 to 2099, the years the RTC can hold.
     pub fn set_date(&mut self, date: Date) {
-        self.write(true, |rtc| {
-            set_date_raw(rtc, date);
+        self.keep_uptime(|rtc| {
+            rtc.write(true, |rtc| {
+                set_date_raw(rtc, date);
+            })
         })
     }
 
@@ -180,21 +732,41 @@ impl Rtc {
 
     /// Sets the time at which an alarm will be triggered
     /// This also clears the alarm flag if it is set
+    ///
+    /// The alarm matches on the day of the month of `date`, see [`Rtc::set_alarm_on`] to match on
+    /// a weekday or every day.
     pub fn set_alarm(&mut self, alarm: Alarm, date: Date, time: Time) {
-        let (dt, du) = byte_to_bcd2(date.date as u8);
+        self.set_alarm_on(alarm, AlarmDay::Date(date.date), time)
+    }
+
+    /// Sets the day and time at which an alarm will be triggered
+    /// This also clears the alarm flag if it is set
+    ///
+    /// The hours, minutes and seconds of `time` always have to match, the sub-seconds are ignored.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the day of the month is not within 1 to 31, or the weekday within 1 to 7.
+    pub fn set_alarm_on(&mut self, alarm: Alarm, day: AlarmDay, time: Time) {
+        let (wdsel, msk4, (dt, du)) = day.fields();
         let (ht, hu) = byte_to_bcd2(time.hours as u8);
         let (mnt, mnu) = byte_to_bcd2(time.minutes as u8);
         let (st, su) = byte_to_bcd2(time.seconds as u8);
 
+        // This is sound, as all the fields are within their BCD range.
         self.write(false, |rtc| match alarm {
             Alarm::AlarmA => {
                 rtc.cr.modify(|_, w| w.alrae().clear_bit());
 
-                // Wait until we're allowed to update the alarm b configuration
+                // Wait until we're allowed to update the alarm a configuration
                 while rtc.isr.read().alrawf().bit_is_clear() {}
 
-                rtc.alrmar.modify(|_, w| unsafe {
-                    w.dt()
+                rtc.alrmar.write(|w| unsafe {
+                    w.msk4()
+                        .bit(msk4)
+                        .wdsel()
+                        .bit(wdsel)
+                        .dt()
                         .bits(dt)
                         .du()
                         .bits(du)
@@ -212,8 +784,6 @@ impl Rtc {
                         .bits(su)
                         .pm()
                         .clear_bit()
-                        .wdsel()
-                        .clear_bit()
                 });
                 rtc.cr.modify(|_, w| w.alrae().set_bit());
             }
@@ -223,8 +793,12 @@ impl Rtc {
                 // Wait until we're allowed to update the alarm b configuration
                 while rtc.isr.read().alrbwf().bit_is_clear() {}
 
-                rtc.alrmbr.modify(|_, w| unsafe {
-                    w.dt()
+                rtc.alrmbr.write(|w| unsafe {
+                    w.msk4()
+                        .bit(msk4)
+                        .wdsel()
+                        .bit(wdsel)
+                        .dt()
                         .bits(dt)
                         .du()
                         .bits(du)
@@ -242,8 +816,6 @@ impl Rtc {
                         .bits(su)
                         .pm()
                         .clear_bit()
-                        .wdsel()
-                        .clear_bit()
                 });
                 rtc.cr.modify(|_, w| w.alrbe().set_bit());
             }
@@ -251,95 +823,267 @@ impl Rtc {
         self.check_interrupt(alarm.into(), true);
     }
 
-    /// Starts listening for an interrupt event
-    pub fn listen(&mut self, exti: &mut EXTI, event: Event) {
-        self.write(false, |rtc| match event {
-            Event::WakeupTimer => {
-                exti.rtsr1.modify(|_, w| w.tr20().set_bit());
-                exti.imr1.modify(|_, w| w.mr20().set_bit());
-                rtc.cr.modify(|_, w| w.wutie().set_bit())
-            }
-            Event::AlarmA => {
-                // Workaround until tr17() is implemented ()
-                exti.rtsr1.modify(|_, w| w.tr18().set_bit());
-                exti.imr1.modify(|_, w| w.mr18().set_bit());
-                rtc.cr.modify(|_, w| w.alraie().set_bit())
-            }
-            Event::AlarmB => {
-                exti.rtsr1.modify(|_, w| w.tr18().set_bit());
-                exti.imr1.modify(|_, w| w.mr18().set_bit());
-                rtc.cr.modify(|_, w| w.alrbie().set_bit())
-            }
-            Event::Timestamp => {
-                exti.rtsr1.modify(|_, w| w.tr19().set_bit());
-                exti.imr1.modify(|_, w| w.mr19().set_bit());
-                rtc.cr.modify(|_, w| w.tsie().set_bit())
-            }
-        })
-    }
+    /// Sets an alarm firing periodically within the second, on the sub-second counter only
+    ///
+    /// All the calendar fields are masked, and the alarm matches when the `mask_bits` least
+    /// significant bits of the sub-second counter (`SSR.SS`) equal the ones of `ticks`
+    /// (`ALRMxSSR.MASKSS` and `ALRMxSSR.SS`). The counter counts down from `sync_prescaler` to 0
+    /// every second, at `ck_apre = sync_prescaler + 1` ticks a second, so the alarm fires every
+    /// `2^mask_bits` ticks, at `ck_apre / 2^mask_bits`, see [`Rtc::subsecond_alarm_interval`]:
+    /// with the default prescalers (`ck_apre` of 256 Hz), `mask_bits` of 5 gives 8 alarms a
+    /// second. `ticks` only sets the phase of the alarms within a period.
+    ///
+    /// The alarms are evenly spaced if `sync_prescaler + 1` is a multiple of `2^mask_bits`,
+    /// otherwise the period is shortened at the second boundary. With `2^mask_bits` of
+    /// `sync_prescaler + 1` or more, the alarm fires at most once a second (never if the compared
+    /// bits of `ticks` exceed `sync_prescaler`), and a `mask_bits`
+    /// of 0 compares no sub-second bit, the alarm fires every second.
+    ///
+    /// This also clears the alarm flag if it is set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mask_bits` is above 15, or `ticks` above the 15 bits of the counter.
+    pub fn set_subsecond_alarm(&mut self, alarm: Alarm, ticks: u16, mask_bits: u8) {
+        assert!(mask_bits <= 15);
+        assert!(ticks <= 0x7fff);
 
-    /// Stops listening for an interrupt event
-    pub fn unlisten(&mut self, exti: &mut EXTI, event: Event) {
-        self.write(false, |rtc| match event {
-            Event::WakeupTimer => {
-                exti.rtsr1.modify(|_, w| w.tr20().clear_bit());
-                exti.imr1.modify(|_, w| w.mr20().clear_bit());
-                rtc.cr.modify(|_, w| w.wutie().clear_bit())
-            }
-            Event::AlarmA => {
-                // Workaround until tr17() is implemented ()
-                exti.rtsr1.modify(|_, w| w.tr18().clear_bit());
-                exti.imr1.modify(|_, w| w.mr18().clear_bit());
-                rtc.cr.modify(|_, w| w.alraie().clear_bit())
-            }
-            Event::AlarmB => {
-                exti.rtsr1.modify(|_, w| w.tr18().clear_bit());
-                exti.imr1.modify(|_, w| w.mr18().clear_bit());
-                rtc.cr.modify(|_, w| w.alrbie().clear_bit())
+        // This is sound, as the masks are single bits and `SS` fits in its 15 bits.
+        self.write(false, |rtc| match alarm {
+            Alarm::AlarmA => {
+                rtc.cr.modify(|_, w| w.alrae().clear_bit());
+
+                // Wait until we're allowed to update the alarm a configuration
+                while rtc.isr.read().alrawf().bit_is_clear() {}
+
+                rtc.alrmar.write(|w| {
+                    w.msk1()
+                        .set_bit()
+                        .msk2()
+                        .set_bit()
+                        .msk3()
+                        .set_bit()
+                        .msk4()
+                        .set_bit()
+                });
+                rtc.alrmassr
+                    .write(|w| unsafe { w.maskss().bits(mask_bits).ss().bits(ticks) });
+                rtc.cr.modify(|_, w| w.alrae().set_bit());
             }
-            Event::Timestamp => {
-                exti.rtsr1.modify(|_, w| w.tr19().clear_bit());
-                exti.imr1.modify(|_, w| w.mr19().clear_bit());
-                rtc.cr.modify(|_, w| w.tsie().clear_bit())
+            Alarm::AlarmB => {
+                rtc.cr.modify(|_, w| w.alrbe().clear_bit());
+
+                // Wait until we're allowed to update the alarm b configuration
+                while rtc.isr.read().alrbwf().bit_is_clear() {}
+
+                rtc.alrmbr.write(|w| {
+                    w.msk1()
+                        .set_bit()
+                        .msk2()
+                        .set_bit()
+                        .msk3()
+                        .set_bit()
+                        .msk4()
+                        .set_bit()
+                });
+                rtc.alrmbssr
+                    .write(|w| unsafe { w.maskss().bits(mask_bits).ss().bits(ticks) });
+                rtc.cr.modify(|_, w| w.alrbe().set_bit());
             }
-        })
+        });
+        self.check_interrupt(alarm.into(), true);
     }
 
-    /// Checks for an interrupt event
-    pub fn check_interrupt(&mut self, event: Event, clear: bool) -> bool {
-        let result = match event {
-            Event::WakeupTimer => self.rtc.isr.read().wutf().bit_is_set(),
-            Event::AlarmA => self.rtc.isr.read().alraf().bit_is_set(),
-            Event::AlarmB => self.rtc.isr.read().alrbf().bit_is_set(),
-            Event::Timestamp => self.rtc.isr.read().tsf().bit_is_set(),
-        };
+    /// Returns the interval between the alarms of [`Rtc::set_subsecond_alarm`], in microseconds
+    ///
+    /// This is `2^mask_bits` ticks of the sub-second counter, capped to the second, e.g. 125 ms
+    /// for a `mask_bits` of 5 with the default prescalers. The interval is exact if
+    /// `sync_prescaler + 1` is a multiple of `2^mask_bits`, and then also rounded down to the
+    /// microsecond.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mask_bits` is above 15.
+    pub fn subsecond_alarm_interval(&self, mask_bits: u8) -> u32 {
+        assert!(mask_bits <= 15);
+
+        let ticks_per_second = u64::from(self.rtc_config.sync_prescaler) + 1;
+        if mask_bits == 0 {
+            return 1_000_000;
+        }
+        let ticks = (1_u64 << mask_bits).min(ticks_per_second);
+
+        (ticks * 1_000_000 / ticks_per_second) as u32
+    }
+
+    /// Starts listening for an interrupt event
+    ///
+    /// Besides the interrupt enable bit in the RTC, this also unmasks the rising
+    /// edge of the EXTI line the event is connected to (see [`Event`]); without
+    /// it the NVIC never sees the interrupt. The interrupt itself still has to be
+    /// unmasked in the NVIC (`RTC_ALARM`, `TAMP_STAMP` or `RTC_WKUP`).
+    ///
+    /// The interrupt also wakes the MCU up from Sleep and Stop modes entered with `WFI`.
+    pub fn listen(&mut self, exti: &mut EXTI, event: Event) {
+        let line = event.exti_line();
+        exti.rtsr1
+            .modify(|r, w| unsafe { w.bits(r.bits() | (1 << line)) });
+        exti.imr1
+            .modify(|r, w| unsafe { w.bits(r.bits() | (1 << line)) });
+
+        self.enable_event(event);
+    }
+
+    /// Stops listening for an interrupt event
+    ///
+    /// The EXTI line is only masked again once no other event routed to it is
+    /// enabled.
+    pub fn unlisten(&mut self, exti: &mut EXTI, event: Event) {
+        self.disable_event(event);
+
+        if !self.exti_line_in_use(event) {
+            let line = event.exti_line();
+            exti.rtsr1
+                .modify(|r, w| unsafe { w.bits(r.bits() & !(1 << line)) });
+            exti.imr1
+                .modify(|r, w| unsafe { w.bits(r.bits() & !(1 << line)) });
+        }
+    }
+
+    /// Starts generating a wakeup event, without an interrupt
+    ///
+    /// Like [`Rtc::listen`], but this unmasks the EXTI event instead of the interrupt, to wake
+    /// the MCU up from Sleep and Stop modes entered with `WFE` without running a handler. The
+    /// flag of the event still has to be cleared, see [`Rtc::clear_pending`].
+    pub fn listen_event(&mut self, exti: &mut EXTI, event: Event) {
+        let line = event.exti_line();
+        exti.rtsr1
+            .modify(|r, w| unsafe { w.bits(r.bits() | (1 << line)) });
+        exti.emr1
+            .modify(|r, w| unsafe { w.bits(r.bits() | (1 << line)) });
+
+        self.enable_event(event);
+    }
+
+    /// Stops generating a wakeup event, see [`Rtc::listen_event`]
+    pub fn unlisten_event(&mut self, exti: &mut EXTI, event: Event) {
+        self.disable_event(event);
+
+        if !self.exti_line_in_use(event) {
+            let line = event.exti_line();
+            exti.rtsr1
+                .modify(|r, w| unsafe { w.bits(r.bits() & !(1 << line)) });
+            exti.emr1
+                .modify(|r, w| unsafe { w.bits(r.bits() & !(1 << line)) });
+        }
+    }
+
+    /// Sets the enable bit of an event in the RTC
+    fn enable_event(&mut self, event: Event) {
+        self.write(false, |rtc| match event {
+            Event::WakeupTimer => rtc.cr.modify(|_, w| w.wutie().set_bit()),
+            Event::AlarmA => rtc.cr.modify(|_, w| w.alraie().set_bit()),
+            Event::AlarmB => rtc.cr.modify(|_, w| w.alrbie().set_bit()),
+            Event::Timestamp => rtc.cr.modify(|_, w| w.tsie().set_bit()),
+            Event::Tamper1 => rtc.tampcr.modify(|_, w| w.tamp1ie().set_bit()),
+            Event::Tamper2 => rtc.tampcr.modify(|_, w| w.tamp2ie().set_bit()),
+            Event::Tamper3 => rtc.tampcr.modify(|_, w| w.tamp3ie().set_bit()),
+        })
+    }
+
+    /// Clears the enable bit of an event in the RTC
+    fn disable_event(&mut self, event: Event) {
+        self.write(false, |rtc| match event {
+            Event::WakeupTimer => rtc.cr.modify(|_, w| w.wutie().clear_bit()),
+            Event::AlarmA => rtc.cr.modify(|_, w| w.alraie().clear_bit()),
+            Event::AlarmB => rtc.cr.modify(|_, w| w.alrbie().clear_bit()),
+            Event::Timestamp => rtc.cr.modify(|_, w| w.tsie().clear_bit()),
+            Event::Tamper1 => rtc.tampcr.modify(|_, w| w.tamp1ie().clear_bit()),
+            Event::Tamper2 => rtc.tampcr.modify(|_, w| w.tamp2ie().clear_bit()),
+            Event::Tamper3 => rtc.tampcr.modify(|_, w| w.tamp3ie().clear_bit()),
+        });
+    }
+
+    /// Checks if any event routed to the same EXTI line as `event` is enabled
+    fn exti_line_in_use(&self, event: Event) -> bool {
+        let cr = self.rtc.cr.read();
+        let tampcr = self.rtc.tampcr.read();
+        match event.exti_line() {
+            18 => cr.alraie().bit_is_set() || cr.alrbie().bit_is_set(),
+            19 => {
+                cr.tsie().bit_is_set()
+                    || tampcr.tamp1ie().bit_is_set()
+                    || tampcr.tamp2ie().bit_is_set()
+                    || tampcr.tamp3ie().bit_is_set()
+            }
+            _ => cr.wutie().bit_is_set(),
+        }
+    }
+
+    /// Returns `true` if the flag of the event is set
+    pub fn is_pending(&self, event: Event) -> bool {
+        let isr = self.rtc.isr.read();
+        match event {
+            Event::WakeupTimer => isr.wutf().bit_is_set(),
+            Event::AlarmA => isr.alraf().bit_is_set(),
+            Event::AlarmB => isr.alrbf().bit_is_set(),
+            Event::Timestamp => isr.tsf().bit_is_set(),
+            Event::Tamper1 => isr.tamp1f().bit_is_set(),
+            Event::Tamper2 => isr.tamp2f().bit_is_set(),
+            Event::Tamper3 => isr.tamp3f().bit_is_set(),
+        }
+    }
+
+    /// Clears the flag of the event, and the pending bit of its EXTI line
+    pub fn clear_pending(&mut self, event: Event) {
+        self.write(false, |rtc| match event {
+            Event::WakeupTimer => rtc.isr.modify(|_, w| w.wutf().clear_bit()),
+            Event::AlarmA => rtc.isr.modify(|_, w| w.alraf().clear_bit()),
+            Event::AlarmB => rtc.isr.modify(|_, w| w.alrbf().clear_bit()),
+            Event::Timestamp => rtc.isr.modify(|_, w| w.tsf().clear_bit()),
+            Event::Tamper1 => rtc.isr.modify(|_, w| w.tamp1f().clear_bit()),
+            Event::Tamper2 => rtc.isr.modify(|_, w| w.tamp2f().clear_bit()),
+            Event::Tamper3 => rtc.isr.modify(|_, w| w.tamp3f().clear_bit()),
+        });
+
+        // This is sound, as PR1 is write-1-to-clear, so only the bit of this
+        // line is affected.
+        unsafe { (*EXTI::ptr()).pr1.write(|w| w.bits(1 << event.exti_line())) };
+    }
+
+    /// Checks for an interrupt event, and optionally clears it
+    pub fn check_interrupt(&mut self, event: Event, clear: bool) -> bool {
+        let result = self.is_pending(event);
         if clear {
-            self.write(false, |rtc| match event {
-                Event::WakeupTimer => {
-                    rtc.isr.modify(|_, w| w.wutf().clear_bit());
-                    unsafe { (*EXTI::ptr()).pr1.write(|w| w.bits(1 << 20)) };
-                }
-                Event::AlarmA => {
-                    rtc.isr.modify(|_, w| w.alraf().clear_bit());
-                    unsafe { (*EXTI::ptr()).pr1.write(|w| w.bits(1 << 18)) };
-                }
-                Event::AlarmB => {
-                    rtc.isr.modify(|_, w| w.alrbf().clear_bit());
-                    unsafe { (*EXTI::ptr()).pr1.write(|w| w.bits(1 << 18)) };
-                }
-                Event::Timestamp => {
-                    rtc.isr.modify(|_, w| w.tsf().clear_bit());
-                    unsafe { (*EXTI::ptr()).pr1.write(|w| w.bits(1 << 19)) };
-                }
-            })
+            self.clear_pending(event);
         }
 
         result
     }
 
     /// Applies the RTC config
-    /// It this changes the RTC clock source the time will be reset
+    ///
+    /// The prescalers are changed on the running calendar, which keeps its time, as well as the
+    /// alarms, the wakeup timer, the calibration and the outputs. Only a change of the clock
+    /// source resets the backup domain, the time is then lost, see [`Rtc::rtc`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the clock source does not become ready or init mode can't be
+    /// entered.
     pub fn set_config(&mut self, bdcr: &mut BDCR, pwrcr1: &mut pwr::CR1, rtc_config: RtcConfig) {
+        self.keep_uptime(|rtc| rtc.try_set_config(bdcr, pwrcr1, rtc_config, false))
+            .unwrap();
+    }
+
+    /// Applies the RTC config, and clears the output configuration if `fresh`
+    fn try_set_config(
+        &mut self,
+        bdcr: &mut BDCR,
+        pwrcr1: &mut pwr::CR1,
+        rtc_config: RtcConfig,
+        fresh: bool,
+    ) -> Result<(), Error> {
         // Unlock the backup domain
         pwrcr1.reg().modify(|_, w| w.dbp().set_bit());
         while pwrcr1.reg().read().dbp().bit_is_clear() {}
@@ -351,47 +1095,32 @@ impl Rtc {
         );
 
         if !reg.rtcen().bit() || reg.rtcsel().bits() != rtc_config.clock_config as u8 {
-            bdcr.enr().modify(|_, w| w.bdrst().set_bit());
-
-            bdcr.enr().modify(|_, w| unsafe {
-                // Reset
-                w.bdrst().clear_bit();
-                // Select RTC source
-                w.rtcsel()
-                    .bits(rtc_config.clock_config as u8)
-                    .rtcen()
-                    .set_bit();
-
-                // Restore bcdr
-                w.lscosel()
-                    .bit(reg.lscosel().bit())
-                    .lscoen()
-                    .bit(reg.lscoen().bit());
-
-                w.lseon()
-                    .bit(reg.lseon().bit())
-                    .lsedrv()
-                    .bits(reg.lsedrv().bits())
-                    .lsebyp()
-                    .bit(reg.lsebyp().bit())
-            });
+            reset_backup_domain(bdcr, rtc_config.clock_config);
         }
 
-        self.write(true, |rtc| {
-            rtc.cr.modify(|_, w| unsafe {
-                w.fmt()
-                    .clear_bit() // 24hr
-                    .osel()
-                    /*
-                        00: Output disabled
-                        01: Alarm A output enabled
-                        10: Alarm B output enabled
-                        11: Wakeup output enabled
-                    */
-                    .bits(0b00)
-                    .pol()
-                    .clear_bit() // pol high
-            });
+        wait_clock_ready(bdcr, rtc_config.clock_config)?;
+
+        self.try_write(true, |rtc| {
+            rtc.cr.modify(|_, w| w.fmt().clear_bit()); // 24hr
+
+            if fresh {
+                rtc.cr.modify(|_, w| unsafe {
+                    w.osel()
+                        /*
+                            00: Output disabled
+                            01: Alarm A output enabled
+                            10: Alarm B output enabled
+                            11: Wakeup output enabled
+                        */
+                        .bits(0b00)
+                        .pol()
+                        .clear_bit() // pol high
+                });
+
+                // The output pins are configured with `set_output`
+                rtc.or
+                    .modify(|_, w| w.rtc_alarm_type().clear_bit().rtc_out_rmp().clear_bit());
+            }
 
             rtc.prer.modify(|_, w| unsafe {
                 w.prediv_s()
@@ -399,13 +1128,239 @@ impl Rtc {
                     .prediv_a()
                     .bits(rtc_config.async_prescaler)
             });
+        })?;
 
-            // TODO configuration for output pins
+        self.rtc_config = rtc_config;
+
+        Ok(())
+    }
+
+    /// Enables or disables the reference clock detection (`REFCKON`)
+    ///
+    /// With the detection enabled, the calendar is kept in sync with a precise
+    /// 50 or 60 Hz reference clock (e.g. derived from the mains) on the
+    /// RTC_REFIN pin, compensating for the drift of the LSE crystal. The pin
+    /// has to be configured by the caller.
+    ///
+    /// The reference clock is compared against the 256 Hz output of the
+    /// asynchronous prescaler, so the RTC must run with the default prescaler
+    /// values (`async_prescaler(127)` and `sync_prescaler(255)`) from a
+    /// 32.768 kHz clock. The smooth calibration must not be used while the
+    /// detection is enabled.
+    ///
+    /// # Panics
+    ///
+    /// Panics when enabling the detection with non-default prescaler values.
+    pub fn enable_reference_clock(&mut self, enable: bool) {
+        if enable {
+            assert!(
+                self.rtc_config.async_prescaler == 127 && self.rtc_config.sync_prescaler == 255,
+                "Reference clock detection requires the default RTC prescalers."
+            );
+        }
+
+        self.write(true, |rtc| {
+            rtc.cr.modify(|_, w| w.refckon().bit(enable));
+        });
+    }
+
+    /// Applies a smooth calibration of the RTC clock
+    ///
+    /// A positive `ppm` speeds the RTC up, a negative one slows it down. The correction is
+    /// applied over a 32 second window in steps of ~0.954 ppm, and is clamped to the range of
+    /// -487.1 to +488.5 ppm. It must not be used while the reference clock detection is enabled.
+    pub fn set_smooth_calibration(&mut self, ppm: f32) {
+        let (calp, calm) = smooth_calibration(ppm);
+
+        self.write(false, |rtc| {
+            // A new calibration can only be written once the previous one is applied
+            while rtc.isr.read().recalpf().bit_is_set() {}
+
+            // This is sound, as `calm` is at most 511.
+            rtc.calr
+                .write(|w| unsafe { w.calp().bit(calp).calm().bits(calm) });
+        });
+    }
+
+    /// Compensates the temperature drift of the crystal at `temp_c` °C
+    ///
+    /// The frequency error of tuning fork crystals follows a parabola around their turnover
+    /// temperature, see [`CrystalCurve`]. Calling this periodically with the temperature of the
+    /// die, e.g. from `Adc::to_degrees_centigrade`, keeps the RTC accurate over temperature. This
+    /// replaces any smooth calibration set before.
+    pub fn apply_temperature_compensation(&mut self, temp_c: f32, curve: CrystalCurve) {
+        self.set_smooth_calibration(-curve.ppm_error(temp_c));
+    }
+
+    /// Drives `output` on the RTC_ALARM output
+    ///
+    /// The output is on PC13, or on PB2 when `remap` is set. PC13 is also the RTC_TAMP1 and
+    /// RTC_TS input, and the RTC_CALIB output, which are mutually exclusive with the alarm output:
+    ///
+    /// - Without `remap`, the alarm output takes precedence over RTC_CALIB (`CR.COE`) on PC13,
+    ///   so this fails with [`Error::OutputInUse`] if the calibration output is enabled.
+    /// - With `remap` and RTC_CALIB enabled, RTC_CALIB is output on PB2 and the alarm output
+    ///   stays on PC13.
+    /// - Once the alarm output is on PC13, tamper 1 and the timestamp input can't be used, so
+    ///   this fails with [`Error::PinConflict`] if either is enabled.
+    ///
+    /// Fails with [`Error::OutputInUse`] if a different alarm output is already selected, see
+    /// [`Rtc::disable_alarm_output`]. With `push_pull` cleared, the output is open-drain
+    /// (PC13 only), `active_high` selects its polarity.
+    pub fn enable_alarm_output(
+        &mut self,
+        output: AlarmOutput,
+        remap: bool,
+        push_pull: bool,
+        active_high: bool,
+    ) -> Result<(), Error> {
+        let cr = self.rtc.cr.read();
+        let osel = cr.osel().bits();
+        if osel != 0 && osel != output as u8 {
+            return Err(Error::OutputInUse);
+        }
+        if !remap && cr.coe().bit_is_set() {
+            return Err(Error::OutputInUse);
+        }
+
+        let on_pc13 = !remap || cr.coe().bit_is_set();
+        if on_pc13 && self.pc13_input_in_use() {
+            return Err(Error::PinConflict);
+        }
+
+        self.write(false, |rtc| {
             rtc.or
-                .modify(|_, w| w.rtc_alarm_type().clear_bit().rtc_out_rmp().clear_bit());
+                .modify(|_, w| w.rtc_out_rmp().bit(remap).rtc_alarm_type().bit(push_pull));
+            // This is sound, as all `AlarmOutput` values are valid for this field.
+            rtc.cr
+                .modify(|_, w| unsafe { w.pol().bit(!active_high).osel().bits(output as u8) });
         });
 
-        self.rtc_config = rtc_config;
+        Ok(())
+    }
+
+    /// Configures the RTC_ALARM output, see [`OutputConfig`]
+    ///
+    /// This is [`Rtc::enable_alarm_output`], or [`Rtc::disable_alarm_output`] when no output is
+    /// selected, and fails the same way.
+    pub fn set_output(&mut self, config: OutputConfig) -> Result<(), Error> {
+        match config.output {
+            Some(output) => {
+                self.enable_alarm_output(output, config.remap, config.push_pull, config.active_high)
+            }
+            None => {
+                self.disable_alarm_output();
+                Ok(())
+            }
+        }
+    }
+
+    /// Disables the RTC_ALARM output
+    pub fn disable_alarm_output(&mut self) {
+        self.write(false, |rtc| {
+            // This is sound, as 0 disables the output.
+            rtc.cr.modify(|_, w| unsafe { w.osel().bits(0) });
+        });
+    }
+
+    /// Enables a tamper input, detecting `edge`
+    ///
+    /// RTC_TAMP1 shares PC13 with the RTC outputs, so enabling it fails with
+    /// [`Error::PinConflict`] while the alarm or calibration output is on PC13, see
+    /// [`Rtc::enable_alarm_output`]. The tamper event is enabled with [`Rtc::listen`].
+    pub fn enable_tamper(&mut self, tamper: Tamper, edge: TamperEdge) -> Result<(), Error> {
+        if tamper == Tamper::Tamper1 && self.pc13_output_in_use() {
+            return Err(Error::PinConflict);
+        }
+
+        let falling = edge == TamperEdge::Falling;
+        self.write(false, |rtc| match tamper {
+            Tamper::Tamper1 => rtc
+                .tampcr
+                .modify(|_, w| w.tamp1trg().bit(falling).tamp1e().set_bit()),
+            Tamper::Tamper2 => rtc
+                .tampcr
+                .modify(|_, w| w.tamp2trg().bit(falling).tamp2e().set_bit()),
+            Tamper::Tamper3 => rtc
+                .tampcr
+                .modify(|_, w| w.tamp3trg().bit(falling).tamp3e().set_bit()),
+        });
+
+        Ok(())
+    }
+
+    /// Disables a tamper input
+    pub fn disable_tamper(&mut self, tamper: Tamper) {
+        self.write(false, |rtc| match tamper {
+            Tamper::Tamper1 => rtc.tampcr.modify(|_, w| w.tamp1e().clear_bit()),
+            Tamper::Tamper2 => rtc.tampcr.modify(|_, w| w.tamp2e().clear_bit()),
+            Tamper::Tamper3 => rtc.tampcr.modify(|_, w| w.tamp3e().clear_bit()),
+        });
+    }
+
+    /// Writes `data` to the backup registers, from `BKPxR` with x = `start` on
+    ///
+    /// The backup registers keep their value across resets and in Standby mode, as long as
+    /// V<sub>BAT</sub> or V<sub>DD</sub> is present, and are erased by a tamper event. Write
+    /// access to the backup domain is enabled once by [`Rtc::rtc`], and the RTC write
+    /// protection doesn't cover the backup registers, so the slice is copied as is.
+    ///
+    /// Fails with [`Error::BackupOutOfRange`], without writing any register, if the registers
+    /// end past the last one, see [`BACKUP_REGISTERS`].
+    pub fn write_backup(&mut self, start: usize, data: &[u32]) -> Result<(), Error> {
+        let registers = backup_range(start, data.len())?;
+
+        for (register, &word) in self.rtc.bkpr[registers].iter().zip(data) {
+            // This is sound, as the register holds any 32-bit value
+            register.write(|w| unsafe { w.bkp().bits(word) });
+        }
+
+        Ok(())
+    }
+
+    /// Reads the backup registers into `out`, from `BKPxR` with x = `start` on
+    ///
+    /// Fails with [`Error::BackupOutOfRange`], leaving `out` untouched, if the registers end
+    /// past the last one, see [`Rtc::write_backup`].
+    pub fn read_backup(&self, start: usize, out: &mut [u32]) -> Result<(), Error> {
+        let registers = backup_range(start, out.len())?;
+
+        for (word, register) in out.iter_mut().zip(&self.rtc.bkpr[registers]) {
+            *word = register.read().bkp().bits();
+        }
+
+        Ok(())
+    }
+
+    /// Writes all backup registers at once, `words[x]` to `BKPxR`, see [`Rtc::write_backup`]
+    pub fn write_all_backup(&mut self, words: &[u32; BACKUP_REGISTERS]) {
+        // Can't fail, as the array spans the backup registers exactly
+        self.write_backup(0, words).unwrap();
+    }
+
+    /// Reads all backup registers at once, `BKPxR` into the element x, e.g. to restore the state
+    /// of the application after a wakeup from Standby
+    pub fn read_all_backup(&self) -> [u32; BACKUP_REGISTERS] {
+        let mut words = [0; BACKUP_REGISTERS];
+        // Can't fail, as the array spans the backup registers exactly
+        self.read_backup(0, &mut words).unwrap();
+
+        words
+    }
+
+    /// Returns `true` if the alarm or calibration output is driven on PC13
+    fn pc13_output_in_use(&self) -> bool {
+        let cr = self.rtc.cr.read();
+        let remap = self.rtc.or.read().rtc_out_rmp().bit_is_set();
+        let alarm = cr.osel().bits() != 0;
+        let calib = cr.coe().bit_is_set();
+
+        (alarm && (!remap || calib)) || (calib && !remap)
+    }
+
+    /// Returns `true` if PC13 is used as tamper 1 or timestamp input
+    fn pc13_input_in_use(&self) -> bool {
+        self.rtc.tampcr.read().tamp1e().bit_is_set() || self.rtc.cr.read().tse().bit_is_set()
     }
 
     /// Access the wakeup timer
@@ -413,35 +1368,289 @@ impl Rtc {
         WakeupTimer { rtc: self }
     }
 
+    /// Starts the wakeup timer with a period of `period`, for an `RTCCLK` of `rtc_clk`
+    ///
+    /// The clock and the reload value of the timer are picked by [`wakeup_config_for`], which
+    /// gives the finest resolution for the period. Periods of more than 32 s with a 32.768 kHz
+    /// clock are counted in seconds of `ck_spre`, which is only 1 Hz with prescalers dividing
+    /// `rtc_clk` down to 1 Hz, e.g. the default ones for a 32.768 kHz clock. The timer flag and
+    /// the interrupt are handled like with [`WakeupTimer`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `period` is 0 or longer than 2^17 s.
+    pub fn enable_wakeup(&mut self, period: Duration, rtc_clk: Hertz) {
+        let (clock, reload) =
+            wakeup_config_for(period, rtc_clk).expect("wakeup period out of range");
+
+        self.wakeup_timer().program(clock, reload);
+    }
+
+    /// Use `event` to wake up from Stop mode, see [`pwr::Pwr::stop_until`]
+    ///
+    /// The event itself, e.g. the alarm, still has to be set up.
+    pub fn wakeup_source(&mut self, event: Event) -> RtcWakeupSource<'_> {
+        RtcWakeupSource { rtc: self, event }
+    }
+
+    /// Use the subsecond counter as a delay provider, see [`RtcDelay`]
+    pub fn delay(&self) -> RtcDelay<'_> {
+        RtcDelay { rtc: self }
+    }
+
     fn write<F, R>(&mut self, init_mode: bool, f: F) -> R
     where
         F: FnOnce(&RTC) -> R,
     {
+        self.try_write(init_mode, f).unwrap()
+    }
+
+    fn try_write<F, R>(&mut self, init_mode: bool, f: F) -> Result<R, Error>
+    where
+        F: FnOnce(&RTC) -> R,
+    {
+        let guard = RtcGuard::new(&self.rtc, init_mode)?;
+
+        Ok(f(guard.rtc))
+    }
+
+    /// Runs `f` with the write protection disabled and the calendar in init mode, see [`RtcGuard`]
+    ///
+    /// Write protection and init mode are toggled once for all the writes of `f`, which keeps
+    /// the calendar stopped for a single window, e.g. in a short critical section. Both are
+    /// restored when `f` returns.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the RTC does not enter init mode, see [`Error::InitTimeout`].
+    pub fn with_write_access<F, R>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&RtcGuard) -> R,
+    {
+        self.keep_uptime(|rtc| {
+            let guard = RtcGuard::new(&rtc.rtc, true).unwrap();
+
+            f(&guard)
+        })
+    }
+
+    /// Stops the calendar, until [`Rtc::resume`]
+    ///
+    /// The calendar is held in init mode, so it doesn't advance while paused. The time can be
+    /// set meanwhile, e.g. with [`Rtc::set_date_time`], which keeps the calendar paused, and
+    /// then started at a precise moment, e.g. on the pulse of a reference clock, with the
+    /// latency of [`Rtc::resume`] alone. The write protection is enabled again when this
+    /// returns.
+    ///
+    /// The shadow registers are not updated while paused, so [`Rtc::get_date_time`], the other
+    /// getters and [`RtcReader`] return the calendar as it was stopped, without waiting, and a
+    /// time set meanwhile is only read after the resume. Unless the shadow registers are
+    /// bypassed, the frozen time is the one of the pause. [`RtcDelay`] returns right away, as
+    /// its counter is stopped. The uptime doesn't count the pause, see [`Rtc::is_paused`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the RTC does not enter init mode, see [`Error::InitTimeout`].
+    pub fn pause(&mut self) {
+        if self.paused_uptime.is_none() {
+            self.paused_uptime = Some(Duration::from_micros(self.uptime_us()));
+        }
+
+        self.write(false, enter_init_mode).unwrap();
+    }
+
+    /// Returns `true` if the calendar is stopped by [`Rtc::pause`]
+    pub fn is_paused(&self) -> bool {
+        self.paused_uptime.is_some()
+    }
+
+    /// Restarts the calendar stopped by [`Rtc::pause`]
+    ///
+    /// The counters run again 4 RTCCLK cycles after this exits init mode, about 122 µs with the
+    /// LSE, from the start of the second that was set. This waits for the shadow registers
+    /// afterwards, so the calendar can be read when it returns.
+    pub fn resume(&mut self) {
+        self.write(false, |rtc| rtc.isr.modify(|_, w| w.init().clear_bit()));
+
+        if let Some(uptime) = self.paused_uptime.take() {
+            // Exiting init mode cleared `ISR.RSF`, so this reads the new calendar
+            self.start = self.now().checked_sub(uptime).unwrap_or(RtcInstant::EPOCH);
+        }
+    }
+
+    /// Returns the time elapsed since the RTC was created, in microseconds
+    ///
+    /// The uptime is the calendar, with its sub-seconds, minus the time at creation, read
+    /// coherently like [`Rtc::get_date_time`]. As it is kept in 64 bits, it doesn't wrap within
+    /// the years the calendar can hold, unlike the 71 minutes of a 32-bit microsecond count.
+    ///
+    /// Changes of the calendar through the `Rtc`, e.g. [`Rtc::set_date_time`] or
+    /// [`Rtc::add_hour`], are compensated, so the uptime doesn't jump with them. The calendar
+    /// is stopped while it is set, this short time is not counted. If the calendar is set to a
+    /// time earlier than the uptime after 1970-01-01, e.g. after a backup domain reset, the
+    /// uptime starts again from 0.
+    pub fn uptime_us(&self) -> u64 {
+        if let Some(uptime) = self.paused_uptime {
+            return uptime.as_micros() as u64;
+        }
+
+        self.now().duration_since(self.start).as_micros() as u64
+    }
+
+    /// Runs `f`, which may set the calendar, and moves the start of the uptime along with it
+    ///
+    /// While paused, the start is moved by [`Rtc::resume`] instead.
+    fn keep_uptime<F, R>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&mut Self) -> R,
+    {
+        if self.paused_uptime.is_some() {
+            return f(self);
+        }
+
+        let uptime = Duration::from_micros(self.uptime_us());
+        let result = f(self);
+
+        // Exiting init mode cleared `ISR.RSF`, so this reads the new calendar
+        self.start = self.now().checked_sub(uptime).unwrap_or(RtcInstant::EPOCH);
+
+        result
+    }
+}
+
+/// Write access to the calendar, see [`Rtc::with_write_access`]
+///
+/// While the guard lives, the write protection of the RTC is disabled and the calendar is in
+/// init mode, so its counters are stopped. Dropping the guard exits init mode and re-enables
+/// the write protection, also on an early return out of the closure holding it. The new values
+/// take effect when init mode is exited, which is left to [`Rtc::resume`] if the calendar was
+/// paused.
+pub struct RtcGuard<'r> {
+    rtc: &'r RTC,
+    init_mode: bool,
+}
+
+impl<'r> RtcGuard<'r> {
+    fn new(rtc: &'r RTC, init_mode: bool) -> Result<Self, Error> {
+        // The live RTC is never configured with the backup domain in reset, see
+        // `reset_backup_domain`.
+        // NOTE(unsafe) atomic read with no side effects
+        debug_assert!(
+            unsafe { (*RCC::ptr()).bdcr.read().bdrst().bit_is_clear() },
+            "backup domain in reset"
+        );
+
         // Disable write protection.
         // This is safe, as we're only writin the correct and expected values.
-        self.rtc.wpr.write(|w| unsafe { w.key().bits(0xca) });
-        self.rtc.wpr.write(|w| unsafe { w.key().bits(0x53) });
+        rtc.wpr.write(|w| unsafe { w.key().bits(0xca) });
+        rtc.wpr.write(|w| unsafe { w.key().bits(0x53) });
 
-        if init_mode && self.rtc.isr.read().initf().bit_is_clear() {
-            // are we already in init mode?
-            self.rtc.isr.modify(|_, w| w.init().set_bit());
-            while self.rtc.isr.read().initf().bit_is_clear() {} // wait to return to init state
-        }
+        // A calendar already in init mode, paused by `Rtc::pause`, is left in it
+        let init_mode = init_mode && rtc.isr.read().initf().bit_is_clear();
 
-        let result = f(&self.rtc);
+        // From here on, dropping the guard restores the protection and exits init mode
+        let guard = RtcGuard { rtc, init_mode };
 
         if init_mode {
+            enter_init_mode(rtc)?;
+        }
+
+        Ok(guard)
+    }
+
+    /// Sets the time, see [`Rtc::set_time`]
+    pub fn set_time(&self, time: Time) {
+        set_time_raw(self.rtc, time);
+    }
+
+    /// Sets the date, see [`Rtc::set_date`]
+    pub fn set_date(&self, date: Date) {
+        set_date_raw(self.rtc, date);
+    }
+
+    /// Sets the date and time, see [`Rtc::set_date_time`]
+    pub fn set_date_time(&self, date: Date, time: Time) {
+        set_time_raw(self.rtc, time);
+        set_date_raw(self.rtc, date);
+    }
+
+    /// Checks if the calendar is in init mode, which holds for the whole life of a guard
+    /// handed out by [`Rtc::with_write_access`]
+    pub fn is_init_mode(&self) -> bool {
+        self.rtc.isr.read().initf().bit_is_set()
+    }
+}
+
+impl Drop for RtcGuard<'_> {
+    fn drop(&mut self) {
+        if self.init_mode {
             self.rtc.isr.modify(|_, w| w.init().clear_bit()); // Exits init mode
         }
 
         // Re-enable write protection.
         // This is safe, as the field accepts the full range of 8-bit values.
         self.rtc.wpr.write(|w| unsafe { w.key().bits(0xff) });
+    }
+}
 
-        result
+/// Frequency over temperature characteristic of a tuning fork crystal
+///
+/// The frequency error is `coefficient * (T - turnover)^2 + offset` ppm at `T` °C.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CrystalCurve {
+    /// Turnover temperature in °C, where the crystal is the fastest
+    pub turnover_c: f32,
+    /// Parabolic coefficient in ppm/°C²
+    pub coefficient: f32,
+    /// Frequency error at the turnover temperature in ppm
+    pub offset_ppm: f32,
+}
+
+impl CrystalCurve {
+    /// Typical 32.768 kHz watch crystal, with its turnover at 25 °C and -0.034 ppm/°C²
+    pub const WATCH_CRYSTAL: CrystalCurve = CrystalCurve {
+        turnover_c: 25.0,
+        coefficient: -0.034,
+        offset_ppm: 0.0,
+    };
+
+    /// Returns the frequency error in ppm at `temp_c` °C, negative if the crystal is slow
+    pub fn ppm_error(&self, temp_c: f32) -> f32 {
+        let delta = temp_c - self.turnover_c;
+
+        self.coefficient * delta * delta + self.offset_ppm
     }
 }
 
+impl Default for CrystalCurve {
+    /// A typical 32.768 kHz watch crystal
+    fn default() -> Self {
+        Self::WATCH_CRYSTAL
+    }
+}
+
+/// Waits a bounded time for the RTC clock source to become ready
+fn wait_clock_ready(bdcr: &mut BDCR, source: RtcClockSource) -> Result<(), Error> {
+    // NOTE(unsafe) read-only accesses to ready flags, which are not owned by the RTC
+    let rcc = unsafe { &*RCC::ptr() };
+
+    for _ in 0..CLOCK_READY_TIMEOUT {
+        let ready = match source {
+            RtcClockSource::NoClock => return Err(Error::ClockNotReady),
+            RtcClockSource::LSE => bdcr.enr().read().lserdy().bit_is_set(),
+            RtcClockSource::LSI => rcc.csr.read().lsirdy().bit_is_set(),
+            RtcClockSource::HSE => rcc.cr.read().hserdy().bit_is_set(),
+        };
+
+        if ready {
+            return Ok(());
+        }
+    }
+
+    trace!("RTC clock not ready");
+    Err(Error::ClockNotReady)
+}
+
 /// The RTC wakeup timer
 ///
 /// This timer can be used in two ways:
@@ -459,6 +1668,34 @@ pub struct WakeupTimer<'r> {
     rtc: &'r mut Rtc,
 }
 
+impl WakeupTimer<'_> {
+    /// Restarts the timer, counting `reload + 1` periods of `clock`
+    fn program(&mut self, clock: WakeupClock, reload: u16) {
+        // Can't panic, as the error type is `Void`.
+        self.cancel().unwrap();
+
+        self.rtc.write(false, |rtc| {
+            // Set the wakeup delay
+            // This is safe, as the field accepts a full 16 bit value.
+            rtc.wutr.write(|w| unsafe { w.wut().bits(reload) });
+
+            rtc.cr.modify(|_, w| {
+                // This is sound, as all `WakeupClock` values are valid for this field.
+                unsafe {
+                    w.wucksel().bits(clock as u8);
+                }
+
+                // Enable wakeup timer
+                w.wute().set_bit()
+            });
+        });
+
+        // Let's wait for WUTWF to clear. Otherwise we might run into a race
+        // condition, if the user calls this method again really quickly.
+        while self.rtc.rtc.isr.read().wutwf().bit_is_set() {}
+    }
+}
+
 impl timer::Periodic for WakeupTimer<'_> {}
 
 impl timer::CountDown for WakeupTimer<'_> {
@@ -481,37 +1718,14 @@ impl timer::CountDown for WakeupTimer<'_> {
         assert!(1 <= delay && delay <= 1 << 17);
 
         let delay = delay - 1;
+        let clock = if delay & 0x1_00_00 != 0 {
+            WakeupClock::SpreExtended
+        } else {
+            WakeupClock::Spre
+        };
 
-        // Can't panic, as the error type is `Void`.
-        self.cancel().unwrap();
-
-        self.rtc.write(false, |rtc| {
-            // Set the wakeup delay
-            rtc.wutr.write(|w|
-                // Write the lower 16 bits of `delay`. The 17th bit is taken
-                // care of via WUCKSEL in CR (see below).
-                // This is safe, as the field accepts a full 16 bit value.
-                unsafe { w.wut().bits(delay as u16) });
-
-            rtc.cr.modify(|_, w| {
-                if delay & 0x1_00_00 != 0 {
-                    unsafe {
-                        w.wucksel().bits(0b110);
-                    }
-                } else {
-                    unsafe {
-                        w.wucksel().bits(0b100);
-                    }
-                }
-
-                // Enable wakeup timer
-                w.wute().set_bit()
-            });
-        });
-
-        // Let's wait for WUTWF to clear. Otherwise we might run into a race
-        // condition, if the user calls this method again really quickly.
-        while self.rtc.rtc.isr.read().wutwf().bit_is_set() {}
+        // The 17th bit of `delay` is taken care of by the clock
+        self.program(clock, delay as u16);
     }
 
     fn wait(&mut self) -> nb::Result<(), Void> {
@@ -554,6 +1768,215 @@ impl timer::Cancel for WakeupTimer<'_> {
     }
 }
 
+impl crate::timer::PeriodicTimer for WakeupTimer<'_> {
+    /// Period in seconds, see [`timer::CountDown::start`]
+    type Time = u32;
+
+    fn start(&mut self, period: u32) {
+        timer::CountDown::start(self, period);
+    }
+
+    fn is_expired(&mut self) -> bool {
+        timer::CountDown::wait(self).is_ok()
+    }
+
+    fn cancel(&mut self) {
+        // Can't panic, as the error type is `Void`.
+        timer::Cancel::cancel(self).unwrap();
+    }
+}
+
+/// RTC event waking the MCU up from Stop mode, see [`Rtc::wakeup_source`]
+///
+/// Arming clears the flag of the event and listens for it, disarming stops listening. The flag
+/// is left set for [`Rtc::check_interrupt`].
+pub struct RtcWakeupSource<'r> {
+    rtc: &'r mut Rtc,
+    event: Event,
+}
+
+impl pwr::WakeupSource for RtcWakeupSource<'_> {
+    fn arm(&mut self, exti: &mut EXTI) {
+        self.rtc.clear_pending(self.event);
+        self.rtc.listen(exti, self.event);
+    }
+
+    fn disarm(&mut self, exti: &mut EXTI) {
+        self.rtc.unlisten(exti, self.event);
+    }
+}
+
+/// Delays busy-waiting on the RTC subsecond counter, which needs no other clock running
+///
+/// The subsecond counter (`SSR`) decrements at `ck_apre`, `sync_prescaler + 1` times a second,
+/// which is the resolution of the delays: about 30.5 µs for an LSE with `async_prescaler(0)` and
+/// `sync_prescaler(32767)`, but 3.9 ms with the default prescalers. A delay is rounded up to a
+/// whole number of counter periods, and lasts up to one period longer, as it starts in the middle
+/// of one. See [`RtcDelay::resolution`].
+///
+/// The counter is stopped in init mode, e.g. while the calendar is paused by [`Rtc::pause`], so a
+/// delay returns as soon as it is entered.
+pub struct RtcDelay<'r> {
+    rtc: &'r Rtc,
+}
+
+impl RtcDelay<'_> {
+    /// Returns the period of the subsecond counter, in microseconds, rounded up
+    pub fn resolution(&self) -> u32 {
+        let period = self.rtc.rtc_config.sync_prescaler as u32 + 1;
+
+        1_000_000_u32.div_ceil(period)
+    }
+
+    /// Waits for at least `us` microseconds
+    fn wait_us(&self, us: u64) {
+        let period = self.rtc.rtc_config.sync_prescaler as u32 + 1;
+        let ticks = subsecond_ticks(us, period);
+
+        let rtc = &self.rtc.rtc;
+        let mut elapsed = 0;
+        let mut last = rtc.ssr.read().ss().bits() as u32;
+        // Waiting for one more counter edge than ticks, as the first one comes after part of a period.
+        // The counter is stopped in init mode, e.g. while paused.
+        while elapsed <= ticks && rtc.isr.read().init().bit_is_clear() {
+            let ss = rtc.ssr.read().ss().bits() as u32;
+            elapsed += u64::from(subsecond_elapsed(last, ss, period));
+            last = ss;
+        }
+
+        // Reading SSR locks the higher-order calendar shadow registers until DR is read
+        let _ = rtc.dr.read();
+    }
+}
+
+impl DelayUs<u32> for RtcDelay<'_> {
+    fn delay_us(&mut self, us: u32) {
+        self.wait_us(us as u64)
+    }
+}
+
+impl DelayUs<u16> for RtcDelay<'_> {
+    fn delay_us(&mut self, us: u16) {
+        self.delay_us(us as u32)
+    }
+}
+
+impl DelayUs<u8> for RtcDelay<'_> {
+    fn delay_us(&mut self, us: u8) {
+        self.delay_us(us as u32)
+    }
+}
+
+impl DelayMs<u32> for RtcDelay<'_> {
+    fn delay_ms(&mut self, ms: u32) {
+        // A single wait, as every wait is rounded up to whole ticks
+        self.wait_us(ms as u64 * 1_000)
+    }
+}
+
+impl DelayMs<u16> for RtcDelay<'_> {
+    fn delay_ms(&mut self, ms: u16) {
+        self.delay_ms(ms as u32)
+    }
+}
+
+impl DelayMs<u8> for RtcDelay<'_> {
+    fn delay_ms(&mut self, ms: u8) {
+        self.delay_ms(ms as u32)
+    }
+}
+
+/// Returns the number of subsecond counter periods covering `us`, with `period` counts a second
+fn subsecond_ticks(us: u64, period: u32) -> u64 {
+    (us * u64::from(period)).div_ceil(1_000_000)
+}
+
+/// Returns the counts from `last` down to `ss` of the subsecond counter, which reloads to
+/// `period - 1` after reaching 0
+fn subsecond_elapsed(last: u32, ss: u32, period: u32) -> u32 {
+    (last + period - ss) % period
+}
+
+/// Returns the indices of `len` backup registers from `start`, if they all exist
+fn backup_range(start: usize, len: usize) -> Result<core::ops::Range<usize>, Error> {
+    match start.checked_add(len) {
+        Some(end) if end <= BACKUP_REGISTERS => Ok(start..end),
+        _ => Err(Error::BackupOutOfRange),
+    }
+}
+
+/// Returns the clock and the reload value (`WUTR`) of the wakeup timer for `period`
+///
+/// The finest `RTCCLK` divider the period fits in is picked, with the period rounded to the
+/// closest tick of it, so e.g. 500 µs is 8 ticks of `RTCCLK / 2` (the closest to 8.192) with a
+/// 32.768 kHz clock. Periods too long for `RTCCLK / 16`, more than 32 s at 32.768 kHz, are
+/// rounded to whole seconds of `ck_spre`, which has to run at 1 Hz, see [`Rtc::enable_wakeup`].
+/// Periods shorter than a tick are rounded up to one.
+///
+/// Returns `None` if `period` is 0, or longer than 2^17 s once rounded.
+pub fn wakeup_config_for(period: Duration, rtc_clk: Hertz) -> Option<(WakeupClock, u16)> {
+    const MAX_TICKS: u128 = 1 << 16;
+
+    let nanos = period.as_nanos();
+    if nanos == 0 {
+        return None;
+    }
+
+    let dividers = [
+        (WakeupClock::RtcDiv2, 2),
+        (WakeupClock::RtcDiv4, 4),
+        (WakeupClock::RtcDiv8, 8),
+        (WakeupClock::RtcDiv16, 16),
+    ];
+    for &(clock, divider) in dividers.iter() {
+        let tick_nanos = 1_000_000_000 * divider;
+        let ticks = (nanos * u128::from(rtc_clk.0) + tick_nanos / 2) / tick_nanos;
+        if ticks <= MAX_TICKS {
+            // Can't truncate, as the ticks are not above 2^16.
+            return Some((clock, (ticks.max(1) - 1) as u16));
+        }
+    }
+
+    let secs = ((nanos + 500_000_000) / 1_000_000_000).max(1);
+    if secs <= MAX_TICKS {
+        Some((WakeupClock::Spre, (secs - 1) as u16))
+    } else if secs <= 2 * MAX_TICKS {
+        Some((WakeupClock::SpreExtended, (secs - 1 - MAX_TICKS) as u16))
+    } else {
+        None
+    }
+}
+
+/// Resets the backup domain, selecting `clock` for the RTC and keeping the LSE configuration
+///
+/// This is the only place the backup domain is reset, which stops the calendar and loses the
+/// time. It is only done when the RTC is not running yet or its clock source changes.
+fn reset_backup_domain(bdcr: &mut BDCR, clock: RtcClockSource) {
+    let reg = bdcr.enr().read();
+
+    bdcr.enr().modify(|_, w| w.bdrst().set_bit());
+
+    bdcr.enr().modify(|_, w| unsafe {
+        // Reset
+        w.bdrst().clear_bit();
+        // Select RTC source
+        w.rtcsel().bits(clock as u8).rtcen().set_bit();
+
+        // Restore bcdr
+        w.lscosel()
+            .bit(reg.lscosel().bit())
+            .lscoen()
+            .bit(reg.lscoen().bit());
+
+        w.lseon()
+            .bit(reg.lseon().bit())
+            .lsedrv()
+            .bits(reg.lsedrv().bits())
+            .lsebyp()
+            .bit(reg.lsebyp().bit())
+    });
+}
+
 /// Raw set time
 /// Expects init mode enabled and write protection disabled
 fn set_time_raw(rtc: &RTC, time: Time) {
@@ -581,14 +2004,133 @@ fn set_time_raw(rtc: &RTC, time: Time) {
     rtc.cr.modify(|_, w| w.bkp().bit(time.daylight_savings));
 }
 
+/// Enters init mode, which requires the write protection to be disabled
+fn enter_init_mode(rtc: &RTC) -> Result<(), Error> {
+    rtc.isr.modify(|_, w| w.init().set_bit());
+
+    let mut polls = 0;
+    while rtc.isr.read().initf().bit_is_clear() {
+        polls += 1;
+        if polls == INIT_TIMEOUT {
+            trace!("RTC init mode timeout");
+            return Err(Error::InitTimeout);
+        }
+    }
+
+    Ok(())
+}
+
+/// Waits for the shadow registers to be synchronized, unless they are bypassed
+///
+/// `ISR.RSF` stays cleared in init mode, e.g. while the calendar is paused by [`Rtc::pause`] or
+/// set by a preempted task, so this returns right away then: the shadow registers keep the
+/// calendar of the moment it was stopped.
+fn wait_for_shadow(rtc: &RegisterBlock) {
+    if rtc.cr.read().bypshad().bit_is_set() {
+        return;
+    }
+
+    loop {
+        let isr = rtc.isr.read();
+        if isr.rsf().bit_is_set() || isr.init().bit_is_set() {
+            break;
+        }
+    }
+}
+
+/// Reads the calendar, see [`Rtc::get_date_time`]
+fn read_date_time(rtc: &RegisterBlock) -> (Date, Time) {
+    wait_for_shadow(rtc);
+
+    // Reading either RTC_SSR or RTC_TR locks the values in the higher-order calendar shadow
+    // registers until RTC_DR is read. The reads are not interrupted, so a read from a handler
+    // can't unlock them in between.
+    let (ssr, timer, dater, cr, prer) = interrupt::free(|_| {
+        (
+            rtc.ssr.read(),
+            rtc.tr.read(),
+            rtc.dr.read(),
+            rtc.cr.read(),
+            rtc.prer.read(),
+        )
+    });
+
+    let sync_p = prer.prediv_s().bits() as u32;
+    let micros = 1_000_000u32 / (sync_p + 1) * (sync_p - ssr.ss().bits() as u32);
+
+    let time = Time::new(
+        bcd2_to_byte((timer.ht().bits(), timer.hu().bits())).into(),
+        bcd2_to_byte((timer.mnt().bits(), timer.mnu().bits())).into(),
+        bcd2_to_byte((timer.st().bits(), timer.su().bits())).into(),
+        micros.into(),
+        cr.bkp().bit(),
+    );
+
+    let date = Date::new(
+        dater.wdu().bits().into(),
+        bcd2_to_byte((dater.dt().bits(), dater.du().bits())).into(),
+        read_month(dater.mt().bit(), dater.mu().bits()),
+        read_year(dater.yt().bits(), dater.yu().bits()).into(),
+    );
+
+    (date, time)
+}
+
+/// Reads the calendar as seconds since 1970, see [`Rtc::second_counter`]
+fn read_second_counter(rtc: &RegisterBlock) -> u32 {
+    wait_for_shadow(rtc);
+
+    // Reading RTC_TR locks the values in the higher-order calendar shadow registers until
+    // RTC_DR is read, see `read_date_time`.
+    let (timer, dater) = interrupt::free(|_| (rtc.tr.read(), rtc.dr.read()));
+
+    let date = Date {
+        day: dater.wdu().bits().into(),
+        date: bcd2_to_byte((dater.dt().bits(), dater.du().bits())).into(),
+        month: read_month(dater.mt().bit(), dater.mu().bits()),
+        year: read_year(dater.yt().bits(), dater.yu().bits()),
+    };
+    let hours = bcd2_to_byte((timer.ht().bits(), timer.hu().bits())) as u32;
+    let minutes = bcd2_to_byte((timer.mnt().bits(), timer.mnu().bits())) as u32;
+    let seconds = bcd2_to_byte((timer.st().bits(), timer.su().bits())) as u32;
+
+    date.days_since_epoch() * 86_400 + hours * 3600 + minutes * 60 + seconds
+}
+
+/// Decodes the BCD month of `RTC_DR`
+///
+/// Only [`set_date_raw`] writes the register, with a valid month, and it resets to January. A
+/// month out of range, e.g. written by other firmware, reads as January.
+pub(crate) fn read_month(tens: bool, units: u8) -> Month {
+    Month::try_from(bcd2_to_byte((tens as u8, units))).unwrap_or(Month::January)
+}
+
+/// Decodes the BCD year of `RTC_DR`, which counts the years since 2000
+pub(crate) fn read_year(tens: u8, units: u8) -> u32 {
+    u32::from(bcd2_to_byte((tens, units))) + RTC_YEARS.start()
+}
+
+/// Encodes `year` for `RTC_DR`, see [`read_year`]
+///
+/// # Panics
+///
+/// Panics if the year is outside 2000 to 2099, the years the RTC can hold.
+fn year_to_bcd2(year: u32) -> (u8, u8) {
+    assert!(
+        RTC_YEARS.contains(&year),
+        "year {} outside the RTC range",
+        year
+    );
+
+    byte_to_bcd2((year - RTC_YEARS.start()) as u8)
+}
+
 /// Raw set date
 /// Expects init mode enabled and write protection disabled
 fn set_date_raw(rtc: &RTC, date: Date) {
     let (dt, du) = byte_to_bcd2(date.date as u8);
-    let (mt, mu) = byte_to_bcd2(date.month as u8);
-    let yr = date.year as u16;
-    let yr_offset = (yr - 1970_u16) as u8;
-    let (yt, yu) = byte_to_bcd2(yr_offset);
+    let (mt, mu) = byte_to_bcd2(date.month.number());
+    let (yt, yu) = year_to_bcd2(date.year);
 
     rtc.dr.write(|w| unsafe {
         w.dt()
@@ -608,16 +2150,36 @@ fn set_date_raw(rtc: &RTC, date: Date) {
     });
 }
 
+/// Returns `CALR.CALP` and `CALR.CALM` for a smooth calibration of `ppm`, see
+/// [`Rtc::set_smooth_calibration`]
+fn smooth_calibration(ppm: f32) -> (bool, u16) {
+    // Masked pulses slow the clock down, CALP inserts 512 pulses
+    if ppm > 0.0 {
+        let pulses = (ppm / CALM_PPM + 0.5) as u16;
+        if pulses == 0 {
+            // CALP with no masked pulses would speed the clock up by 488.5 ppm
+            (false, 0)
+        } else {
+            (true, 512 - pulses.min(512))
+        }
+    } else {
+        let pulses = (-ppm / CALM_PPM + 0.5) as u16;
+        (false, pulses.min(511))
+    }
+}
+
+/// Converts `byte` into its BCD tens digit and the BCD byte holding both digits
+///
+/// No field of the RTC holds more than two digits, so a value above 99 is a bug of the caller.
+/// It is clamped to 99, rather than giving a tens digit that doesn't fit in its field.
 fn byte_to_bcd2(byte: u8) -> (u8, u8) {
-    let mut bcd_high: u8 = 0;
-    let mut value = byte;
+    debug_assert!(byte <= 99, "{} doesn't fit in two BCD digits", byte);
+    let byte = byte.min(99);
 
-    while value >= 10 {
-        bcd_high += 1;
-        value -= 10;
-    }
+    let bcd_high = byte / 10;
+    let value = byte % 10;
 
-    (bcd_high, ((bcd_high << 4) | value) as u8)
+    (bcd_high, (bcd_high << 4) | value)
 }
 
 fn bcd2_to_byte(bcd: (u8, u8)) -> u8 {
@@ -627,3 +2189,226 @@ fn bcd2_to_byte(bcd: (u8, u8)) -> u8 {
 
     tmp + (value & 0x0F)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes `date` to the year, month and date fields and reads it back, as the registers do
+    fn through_registers(date: Date) -> Date {
+        let (yt, yu) = year_to_bcd2(date.year);
+        let (mt, mu) = byte_to_bcd2(date.month.number());
+        let (dt, du) = byte_to_bcd2(date.date as u8);
+
+        Date {
+            day: date.day,
+            date: bcd2_to_byte((dt, du & 0xf)).into(),
+            month: read_month(mt > 0, mu & 0xf),
+            year: read_year(yt, yu & 0xf),
+        }
+    }
+
+    #[test]
+    fn year_round_trip() {
+        for year in RTC_YEARS {
+            let (yt, yu) = year_to_bcd2(year);
+            assert_eq!(read_year(yt, yu & 0xf), year);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn year_before_range() {
+        year_to_bcd2(1999);
+    }
+
+    #[test]
+    #[should_panic]
+    fn year_after_range() {
+        year_to_bcd2(2100);
+    }
+
+    #[test]
+    fn hardware_leap_years() {
+        // The hardware takes the years whose year field is divisible by 4 as leap years
+        for year in RTC_YEARS {
+            let (yt, yu) = year_to_bcd2(year);
+            assert_eq!(
+                bcd2_to_byte((yt, yu & 0xf)) % 4 == 0,
+                is_leap_year(year),
+                "{}",
+                year
+            );
+        }
+    }
+
+    #[test]
+    fn unix_time_round_trip() {
+        for &secs in &[
+            946_684_800,   // 2000-01-01 00:00:00
+            1_709_078_400, // 2024-02-28 00:00:00
+            1_709_164_800, // 2024-02-29 00:00:00
+            1_709_251_199, // 2024-02-29 23:59:59
+            1_709_251_200, // 2024-03-01 00:00:00
+            1_772_323_200, // 2026-03-01 00:00:00
+            4_102_444_799, // 2099-12-31 23:59:59
+        ] {
+            let date = through_registers(Date::from_days_since_epoch(secs / 86_400));
+            assert_eq!(date.days_since_epoch() * 86_400 + secs % 86_400, secs);
+        }
+
+        let leap_day = through_registers(Date::from_days_since_epoch(1_709_164_800 / 86_400));
+        assert_eq!(
+            leap_day,
+            Date::new(4.day(), 29.date(), Month::February, 2024.year())
+        );
+        // 2100 is not a leap year, and beyond the RTC range
+        assert_eq!(
+            Date::from_days_since_epoch(4_107_542_400 / 86_400),
+            Date::new(1.day(), 1.date(), Month::March, 2100.year())
+        );
+    }
+
+    #[test]
+    fn bcd_round_trip() {
+        for byte in 0..=99 {
+            let (tens, bcd) = byte_to_bcd2(byte);
+            assert_eq!(tens, byte / 10);
+            assert_eq!(bcd, ((byte / 10) << 4) | (byte % 10));
+            assert_eq!(bcd2_to_byte((tens, bcd & 0xf)), byte);
+        }
+    }
+
+    #[test]
+    #[cfg_attr(debug_assertions, should_panic)]
+    fn bcd_clamped() {
+        // A debug build catches the bug of the caller, a release build clamps to 99
+        assert_eq!(byte_to_bcd2(100), (9, 0x99));
+        assert_eq!(byte_to_bcd2(u8::MAX), (9, 0x99));
+    }
+
+    #[test]
+    fn wakeup_periods() {
+        let lse = Hertz(32_768);
+        let config = |period| wakeup_config_for(period, lse);
+
+        // The closest tick of the finest divider the period fits in
+        assert_eq!(
+            config(Duration::from_micros(500)),
+            Some((WakeupClock::RtcDiv2, 7))
+        );
+        assert_eq!(
+            config(Duration::from_secs(4)),
+            Some((WakeupClock::RtcDiv2, 65535))
+        );
+        assert_eq!(
+            config(Duration::from_secs(5)),
+            Some((WakeupClock::RtcDiv4, 40959))
+        );
+        assert_eq!(
+            config(Duration::from_secs(32)),
+            Some((WakeupClock::RtcDiv16, 65535))
+        );
+        // Less than a tick is one tick
+        assert_eq!(
+            config(Duration::from_nanos(1)),
+            Some((WakeupClock::RtcDiv2, 0))
+        );
+
+        // Whole seconds of ck_spre beyond 32 s
+        assert_eq!(
+            config(Duration::from_millis(32_300)),
+            Some((WakeupClock::Spre, 31))
+        );
+        assert_eq!(
+            config(Duration::from_secs(65_536)),
+            Some((WakeupClock::Spre, 65535))
+        );
+        assert_eq!(
+            config(Duration::from_secs(65_537)),
+            Some((WakeupClock::SpreExtended, 0))
+        );
+        assert_eq!(
+            config(Duration::from_secs(131_072)),
+            Some((WakeupClock::SpreExtended, 65535))
+        );
+
+        assert_eq!(config(Duration::ZERO), None);
+        assert_eq!(config(Duration::from_secs(131_073)), None);
+        assert_eq!(config(Duration::MAX), None);
+    }
+
+    #[test]
+    fn smooth_calibrations() {
+        assert_eq!(smooth_calibration(0.0), (false, 0));
+        // Less than half a step is no correction either way
+        assert_eq!(smooth_calibration(0.4), (false, 0));
+        assert_eq!(smooth_calibration(-0.4), (false, 0));
+
+        // Slowing down masks pulses, one per ~0.954 ppm
+        assert_eq!(smooth_calibration(-10.0), (false, 10));
+        assert_eq!(smooth_calibration(-487.1), (false, 511));
+        assert_eq!(smooth_calibration(-1000.0), (false, 511));
+
+        // Speeding up inserts 512 pulses and masks the excess
+        assert_eq!(smooth_calibration(10.0), (true, 502));
+        assert_eq!(smooth_calibration(488.5), (true, 0));
+        assert_eq!(smooth_calibration(1000.0), (true, 0));
+    }
+
+    #[test]
+    fn crystal_curve() {
+        let curve = CrystalCurve::WATCH_CRYSTAL;
+        assert_eq!(curve.ppm_error(25.0), 0.0);
+        // -0.034 ppm/°C² * 20² on both sides of the turnover
+        assert!((curve.ppm_error(5.0) + 13.6).abs() < 1e-3);
+        assert!((curve.ppm_error(45.0) + 13.6).abs() < 1e-3);
+
+        let aged = CrystalCurve {
+            offset_ppm: 2.0,
+            ..curve
+        };
+        assert_eq!(aged.ppm_error(25.0), 2.0);
+    }
+
+    #[test]
+    fn alarm_days() {
+        assert_eq!(AlarmDay::Date(1).fields(), (false, false, (0, 0x01)));
+        assert_eq!(AlarmDay::Date(31).fields(), (false, false, (3, 0x31)));
+        // The weekday is the units digit, with the tens cleared
+        assert_eq!(AlarmDay::Weekday(7).fields(), (true, false, (0, 7)));
+        assert_eq!(AlarmDay::EveryDay.fields(), (false, true, (0, 1)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn alarm_date_zero() {
+        AlarmDay::Date(0).fields();
+    }
+
+    #[test]
+    #[should_panic]
+    fn alarm_weekday_eight() {
+        AlarmDay::Weekday(8).fields();
+    }
+
+    #[test]
+    fn subsecond_delays() {
+        // 256 counts a second with the default prescalers, 3.9 ms each
+        assert_eq!(subsecond_ticks(0, 256), 0);
+        assert_eq!(subsecond_ticks(1, 256), 1);
+        assert_eq!(subsecond_ticks(3_906, 256), 1);
+        assert_eq!(subsecond_ticks(3_907, 256), 2);
+        assert_eq!(subsecond_ticks(1_000_000, 256), 256);
+        // The milliseconds of a u32 don't overflow
+        assert_eq!(
+            subsecond_ticks(u64::from(u32::MAX) * 1_000, 32_768),
+            140_737_488_323
+        );
+
+        // The counter counts down, and reloads after 0
+        assert_eq!(subsecond_elapsed(200, 200, 256), 0);
+        assert_eq!(subsecond_elapsed(200, 190, 256), 10);
+        assert_eq!(subsecond_elapsed(2, 250, 256), 8);
+    }
+}
@@ -0,0 +1,172 @@
+//! RTIC monotonic timer backed by the RTC
+//!
+//! [`RtcMonotonic`] counts the calendar (as seconds since 1970-01-01) and its sub-second
+//! counter, and uses alarm A as compare. As the RTC keeps running in Stop mode, scheduled tasks
+//! wake the MCU from it.
+//!
+//! The resolution is one tick of the synchronous prescaler: with the default [`RtcConfig`]
+//! (`async_prescaler` 127, `sync_prescaler` 255) and a 32.768 kHz RTC clock, that is
+//! [`TICKS_PER_SECOND`] ticks per second, or ~3.9 ms per tick.
+//!
+//! [`RtcConfig`]: super::RtcConfig
+
+use rtic_monotonic::Monotonic;
+
+use super::{bcd2_to_byte, byte_to_bcd2, read_month, read_year, Event, Rtc};
+use crate::{datetime::Date, stm32::EXTI};
+
+/// Number of ticks per second of [`RtcMonotonic`]
+pub const TICKS_PER_SECOND: u32 = 256;
+
+/// Sub-second ticks are compared on the 8 least significant bits of `SS`
+const MASKSS: u8 = 8;
+
+/// Instant of [`RtcMonotonic`]
+pub type Instant = fugit::TimerInstantU64<TICKS_PER_SECOND>;
+
+/// Duration of [`RtcMonotonic`]
+pub type Duration = fugit::TimerDurationU64<TICKS_PER_SECOND>;
+
+/// RTIC monotonic timer, bind it to the `RTC_ALARM` interrupt
+///
+/// The monotonic owns alarm A, which must not be used otherwise.
+pub struct RtcMonotonic {
+    rtc: Rtc,
+}
+
+impl RtcMonotonic {
+    /// Creates the monotonic timer out of a configured RTC
+    ///
+    /// The calendar should be set before, as the instants are derived from it. The shadow
+    /// registers are bypassed, so the counters can be read right after waking up from Stop mode.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the synchronous prescaler is not 255, which yields [`TICKS_PER_SECOND`].
+    pub fn new(mut rtc: Rtc, exti: &mut EXTI) -> Self {
+        assert_eq!(
+            rtc.rtc_config.sync_prescaler,
+            TICKS_PER_SECOND as u16 - 1,
+            "The RTC monotonic needs a synchronous prescaler of 255"
+        );
+
+        rtc.write(false, |rtc| {
+            rtc.cr
+                .modify(|_, w| w.bypshad().set_bit().alrae().clear_bit());
+        });
+        rtc.clear_pending(Event::AlarmA);
+        rtc.listen(exti, Event::AlarmA);
+
+        Self { rtc }
+    }
+
+    /// Releases the RTC
+    pub fn free(mut self, exti: &mut EXTI) -> Rtc {
+        self.rtc.unlisten(exti, Event::AlarmA);
+        self.rtc.write(false, |rtc| {
+            rtc.cr
+                .modify(|_, w| w.bypshad().clear_bit().alrae().clear_bit());
+        });
+
+        self.rtc
+    }
+}
+
+impl Monotonic for RtcMonotonic {
+    type Instant = Instant;
+    type Duration = Duration;
+
+    fn now(&mut self) -> Self::Instant {
+        let rtc = &self.rtc.rtc;
+
+        // With the shadow registers bypassed, the calendar is only consistent if the sub-second
+        // counter, which reloads as the seconds increment, did not change while reading it.
+        let (ss, tr, dr) = loop {
+            let ss = rtc.ssr.read().ss().bits();
+            let tr = rtc.tr.read();
+            let dr = rtc.dr.read();
+
+            if rtc.ssr.read().ss().bits() == ss {
+                break (ss, tr, dr);
+            }
+        };
+
+        let date = Date {
+            day: u32::from(dr.wdu().bits()),
+            date: u32::from(bcd2_to_byte((dr.dt().bits(), dr.du().bits()))),
+            month: read_month(dr.mt().bit(), dr.mu().bits()),
+            year: read_year(dr.yt().bits(), dr.yu().bits()),
+        };
+        let secs = u64::from(date.days_since_epoch()) * 86_400
+            + u64::from(bcd2_to_byte((tr.ht().bits(), tr.hu().bits()))) * 3600
+            + u64::from(bcd2_to_byte((tr.mnt().bits(), tr.mnu().bits()))) * 60
+            + u64::from(bcd2_to_byte((tr.st().bits(), tr.su().bits())));
+
+        // The sub-second counter counts down from the synchronous prescaler value
+        let subsecs = u64::from(TICKS_PER_SECOND - 1) - u64::from(ss);
+
+        Instant::from_ticks(secs * u64::from(TICKS_PER_SECOND) + subsecs)
+    }
+
+    fn set_compare(&mut self, instant: Self::Instant) {
+        let ticks = instant.ticks();
+        let secs = ticks / u64::from(TICKS_PER_SECOND);
+        let ss = (TICKS_PER_SECOND - 1) as u16 - (ticks % u64::from(TICKS_PER_SECOND)) as u16;
+
+        // The alarm matches on the date in the month, so instants more than a month ahead fire
+        // early; RTIC then simply sets the compare again.
+        let date = Date::from_days_since_epoch((secs / 86_400) as u32);
+        let secs_of_day = (secs % 86_400) as u32;
+        let (dt, du) = byte_to_bcd2(date.date as u8);
+        let (ht, hu) = byte_to_bcd2((secs_of_day / 3600) as u8);
+        let (mnt, mnu) = byte_to_bcd2((secs_of_day / 60 % 60) as u8);
+        let (st, su) = byte_to_bcd2((secs_of_day % 60) as u8);
+
+        self.rtc.write(false, |rtc| {
+            rtc.cr.modify(|_, w| w.alrae().clear_bit());
+            while rtc.isr.read().alrawf().bit_is_clear() {}
+
+            // This is sound, as all values are in range of their fields.
+            rtc.alrmar.write(|w| unsafe {
+                w.dt()
+                    .bits(dt)
+                    .du()
+                    .bits(du)
+                    .ht()
+                    .bits(ht)
+                    .hu()
+                    .bits(hu)
+                    .mnt()
+                    .bits(mnt)
+                    .mnu()
+                    .bits(mnu)
+                    .st()
+                    .bits(st)
+                    .su()
+                    .bits(su)
+            });
+            rtc.alrmassr
+                .write(|w| unsafe { w.maskss().bits(MASKSS).ss().bits(ss) });
+
+            rtc.cr.modify(|_, w| w.alrae().set_bit());
+        });
+    }
+
+    fn clear_compare_flag(&mut self) {
+        self.rtc.clear_pending(Event::AlarmA);
+    }
+
+    fn zero() -> Self::Instant {
+        Instant::from_ticks(0)
+    }
+
+    unsafe fn reset(&mut self) {
+        // The calendar keeps counting, instants are relative to 1970-01-01
+    }
+
+    fn disable_timer(&mut self) {
+        self.rtc.write(false, |rtc| {
+            rtc.cr.modify(|_, w| w.alrae().clear_bit());
+        });
+    }
+}
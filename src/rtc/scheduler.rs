@@ -0,0 +1,183 @@
+//! Software timers multiplexed on the RTC wakeup timer
+//!
+//! [`RtcScheduler`] keeps the deadlines of up to `N` timers and programs the wakeup timer to the
+//! nearest one, so the MCU can sleep, even in Stop mode, between the expiries of several timers.
+//!
+//! The deadlines are kept against the calendar, in seconds, with the resolution of the wakeup
+//! timer clocked by `ck_spre`: a timer never expires early, but up to 2 seconds late. As the
+//! calendar is read in whole seconds, a deadline is counted from the end of the current second.
+
+use super::{Event, Rtc};
+use crate::hal::timer::{Cancel as _, CountDown as _};
+use crate::stm32::EXTI;
+
+/// Longest delay of the wakeup timer, in seconds
+const MAX_DELAY: u32 = 1 << 17;
+
+/// Software timers on the RTC wakeup timer, bind it to the `RTC_WKUP` interrupt
+///
+/// The scheduler owns the wakeup timer, which must not be used otherwise.
+pub struct RtcScheduler<const N: usize> {
+    rtc: Rtc,
+    deadlines: [Option<u32>; N],
+    armed: Option<u32>,
+}
+
+impl<const N: usize> RtcScheduler<N> {
+    /// Creates the scheduler out of a configured RTC, with no timer scheduled
+    ///
+    /// The wakeup timer event is unmasked in the RTC and the EXTI, the `RTC_WKUP` interrupt still
+    /// has to be unmasked in the NVIC.
+    pub fn new(mut rtc: Rtc, exti: &mut EXTI) -> Self {
+        // Can't panic, as the error type is `Void`.
+        rtc.wakeup_timer().cancel().unwrap();
+        rtc.listen(exti, Event::WakeupTimer);
+
+        RtcScheduler {
+            rtc,
+            deadlines: [None; N],
+            armed: None,
+        }
+    }
+
+    /// Schedules timer `id` to expire in `secs` seconds, replacing its previous deadline
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` is not below `N`.
+    pub fn schedule(&mut self, id: usize, secs: u32) {
+        let now = self.now();
+        self.deadlines[id] = Some(deadline(now, secs));
+        self.rearm(now);
+    }
+
+    /// Cancels timer `id`, if it is scheduled
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` is not below `N`.
+    pub fn cancel(&mut self, id: usize) {
+        self.deadlines[id] = None;
+
+        let now = self.now();
+        self.rearm(now);
+    }
+
+    /// Checks if timer `id` is scheduled and has not expired yet
+    pub fn is_scheduled(&self, id: usize) -> bool {
+        self.deadlines[id].is_some()
+    }
+
+    /// Returns the next expired timer, call it until it returns `None`
+    ///
+    /// This also clears the wakeup timer event, so it is meant to be called from the `RTC_WKUP`
+    /// interrupt. Once all expired timers are returned, the wakeup timer is programmed to the
+    /// nearest remaining deadline, and stopped if there is none.
+    pub fn poll(&mut self) -> Option<usize> {
+        if self.rtc.check_interrupt(Event::WakeupTimer, true) {
+            // The wakeup timer is periodic, stop it until it is programmed to the next deadline.
+            // Its delay might also have been shortened to the longest one it supports.
+            self.rtc.wakeup_timer().cancel().unwrap();
+            self.armed = None;
+        }
+
+        let now = self.now();
+        match first_expired(&self.deadlines, now) {
+            Some(id) => {
+                self.deadlines[id] = None;
+                Some(id)
+            }
+            None => {
+                self.rearm(now);
+                None
+            }
+        }
+    }
+
+    /// Stops the wakeup timer and releases the RTC
+    pub fn free(mut self, exti: &mut EXTI) -> Rtc {
+        self.rtc.unlisten(exti, Event::WakeupTimer);
+        self.rtc.wakeup_timer().cancel().unwrap();
+
+        self.rtc
+    }
+
+    /// Current calendar time, in seconds
+    fn now(&mut self) -> u32 {
+        // The shadow registers are stale after waking up from a low power mode
+        self.rtc.wait_for_synchro();
+        self.rtc.unix_time()
+    }
+
+    /// Programs the wakeup timer to the nearest deadline, unless it already is
+    fn rearm(&mut self, now: u32) {
+        let next = nearest(&self.deadlines);
+        if next == self.armed {
+            return;
+        }
+
+        self.armed = next;
+        let mut wakeup = self.rtc.wakeup_timer();
+        match next {
+            // The wakeup timer needs a delay of at least a second, even for a deadline passed
+            Some(deadline) => wakeup.start(deadline.saturating_sub(now).clamp(1, MAX_DELAY)),
+            None => wakeup.cancel().unwrap(),
+        }
+    }
+}
+
+/// Returns the deadline of a timer expiring in `secs` seconds, at the calendar second `now`
+///
+/// Part of the second `now` has already passed, the deadline is counted from the next one so
+/// that the timer never expires early. It saturates at the end of the calendar counter.
+fn deadline(now: u32, secs: u32) -> u32 {
+    now.saturating_add(secs).saturating_add(1)
+}
+
+/// Returns the first timer whose deadline is not after `now`
+fn first_expired(deadlines: &[Option<u32>], now: u32) -> Option<usize> {
+    deadlines
+        .iter()
+        .position(|deadline| matches!(deadline, Some(deadline) if *deadline <= now))
+}
+
+/// Returns the nearest deadline
+fn nearest(deadlines: &[Option<u32>]) -> Option<u32> {
+    deadlines.iter().flatten().min().copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deadline_never_early() {
+        assert_eq!(deadline(100, 0), 101);
+        assert_eq!(deadline(100, 5), 106);
+        assert_eq!(deadline(u32::MAX - 1, 5), u32::MAX);
+        assert_eq!(deadline(u32::MAX, 0), u32::MAX);
+    }
+
+    #[test]
+    fn three_overlapping_timers() {
+        let mut deadlines = [None; 4];
+        deadlines[0] = Some(deadline(1000, 10));
+        deadlines[1] = Some(deadline(1000, 3));
+        deadlines[3] = Some(deadline(1002, 2));
+
+        // Expire the timers as `RtcScheduler::poll` does, waking up at each nearest deadline
+        let mut expired = [(0, 0); 3];
+        let mut count = 0;
+        while let Some(now) = nearest(&deadlines) {
+            while let Some(id) = first_expired(&deadlines, now) {
+                deadlines[id] = None;
+                expired[count] = (id, now);
+                count += 1;
+            }
+        }
+
+        assert_eq!(count, 3);
+        assert_eq!(expired, [(1, 1004), (3, 1005), (0, 1011)]);
+        assert_eq!(first_expired(&[Some(1004), None], 1003), None);
+    }
+}
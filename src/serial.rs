@@ -2,7 +2,7 @@
 //!
 //! This module support both polling and interrupt based accesses to the serial peripherals.
 
-use as_slice::AsMutSlice;
+use as_slice::{AsMutSlice, AsSlice};
 use core::fmt;
 use core::marker::PhantomData;
 use core::mem::MaybeUninit;
@@ -14,7 +14,7 @@ use stable_deref_trait::StableDeref;
 
 use crate::hal::serial::{self, Write};
 
-use crate::dma::{dma1, CircBuffer, DMAFrame, FrameReader, FrameSender};
+use crate::dma::{dma1, CircBuffer, DMAFrame, FrameReader, FrameSender, Transfer, R, W};
 use crate::gpio::{self, Alternate, AlternateOD, Floating, Input};
 use crate::pac;
 use crate::rcc::{Clocks, APB1R1, APB2};
@@ -22,6 +22,10 @@ use crate::time::{Bps, U32Ext};
 
 #[cfg(any(feature = "stm32l4x5", feature = "stm32l4x6",))]
 use crate::dma::dma2;
+#[cfg(feature = "async")]
+use crate::dma::{AsyncChannel, AsyncTransfer};
+#[cfg(feature = "async")]
+use cortex_m::interrupt;
 
 /// Interrupt event
 pub enum Event {
@@ -35,11 +39,14 @@ pub enum Event {
     CharacterMatch,
     /// Receiver timeout
     ReceiverTimeout,
+    /// LIN break detected, see [`Config::with_lin_mode`]
+    LinBreak,
 }
 
 /// Serial error
 #[non_exhaustive]
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     /// Framing error
     Framing,
@@ -49,6 +56,20 @@ pub enum Error {
     Overrun,
     /// Parity check error
     Parity,
+    /// The baud rate could not be detected, see [`Serial::auto_baud`]
+    AutoBaudRate,
+}
+
+#[cfg(feature = "async")]
+impl embedded_io_async::Error for Error {
+    fn kind(&self) -> embedded_io_async::ErrorKind {
+        match self {
+            Error::Framing | Error::Noise | Error::Parity => {
+                embedded_io_async::ErrorKind::InvalidData
+            }
+            _ => embedded_io_async::ErrorKind::Other,
+        }
+    }
 }
 
 /// USART parity settings
@@ -74,11 +95,30 @@ pub enum StopBits {
 }
 
 /// USART oversampling settings
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Oversampling {
     /// Oversample 8 times (allows for faster data rates)
     Over8,
     /// Oversample 16 times (higher stability)
     Over16,
+    /// Oversample 16 times, or 8 times if it gives a smaller baud rate error
+    ///
+    /// Both have a resolution of one clock period per bit, so oversampling 8 times is only
+    /// picked when the baud rate is beyond the reach of oversampling 16 times.
+    Auto,
+}
+
+/// Character measured by the auto baud rate detection, see [`Serial::auto_baud`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AutoBaudMode {
+    /// Any character starting with a 1 bit, measured on its start bit
+    StartBit = 0b00,
+    /// Any character starting with the bits 10, measured from falling edge to falling edge
+    FallingEdge = 0b01,
+    /// The character 0x7F
+    Char0x7F = 0b10,
+    /// The character 0x55
+    Char0x55 = 0b11,
 }
 
 /// USART Configuration structure
@@ -91,6 +131,11 @@ pub struct Config {
     receiver_timeout: Option<u32>,
     disable_overrun: bool,
     onebit_sampling: bool,
+    lin_mode: bool,
+    swap_tx_rx: bool,
+    invert_rx: bool,
+    invert_tx: bool,
+    invert_data: bool,
 }
 
 impl Config {
@@ -156,6 +201,41 @@ impl Config {
         self.onebit_sampling = true;
         self
     }
+
+    /// Enable LIN mode, with the detection of 11 bit breaks
+    ///
+    /// LIN requires 1 stop bit, and can't be combined with half duplex pins.
+    pub fn with_lin_mode(mut self) -> Self {
+        self.lin_mode = true;
+        self
+    }
+
+    /// Swap the functions of the TX and RX pins, for crossed traces
+    ///
+    /// The pins are still passed as `(tx, rx)` to the constructor, but the TX pin receives and
+    /// the RX pin transmits.
+    pub fn swap_tx_rx(mut self, swap: bool) -> Self {
+        self.swap_tx_rx = swap;
+        self
+    }
+
+    /// Invert the levels on the RX pin, idling low
+    pub fn invert_rx(mut self, invert: bool) -> Self {
+        self.invert_rx = invert;
+        self
+    }
+
+    /// Invert the levels on the TX pin, idling low
+    pub fn invert_tx(mut self, invert: bool) -> Self {
+        self.invert_tx = invert;
+        self
+    }
+
+    /// Invert the data bits, including parity, while the start and stop bits are kept
+    pub fn invert_data(mut self, invert: bool) -> Self {
+        self.invert_data = invert;
+        self
+    }
 }
 
 impl Default for Config {
@@ -170,10 +250,51 @@ impl Default for Config {
             receiver_timeout: None,
             disable_overrun: false,
             onebit_sampling: false,
+            lin_mode: false,
+            swap_tx_rx: false,
+            invert_rx: false,
+            invert_tx: false,
+            invert_data: false,
         }
     }
 }
 
+/// Computes `BRR` and `CR1.OVER8` for `baudrate`, with the divider rounded to the closest value
+///
+/// # Panics
+///
+/// Panics if the baud rate can't be reached with `oversampling`.
+fn compute_brr(pclk: u32, baudrate: u32, oversampling: Oversampling) -> (u32, bool) {
+    // Duration of a bit, in clock periods
+    let div = (pclk + baudrate / 2) / baudrate;
+
+    let over8 = match oversampling {
+        Oversampling::Over8 => true,
+        Oversampling::Over16 => false,
+        Oversampling::Auto => {
+            baudrate_error(pclk / div.max(16), baudrate).abs()
+                > baudrate_error(pclk / div.max(8), baudrate).abs()
+        }
+    };
+
+    if over8 {
+        // USARTDIV is twice the bit duration, its lowest bit is dropped when written to BRR
+        let uartdiv = 2 * div;
+        assert!((16..=0xffff).contains(&uartdiv), "impossible baud rate");
+
+        ((uartdiv & !0xf) | ((uartdiv & 0xf) >> 1), true)
+    } else {
+        assert!((16..=0xffff).contains(&div), "impossible baud rate");
+
+        (div, false)
+    }
+}
+
+/// Error of the `achieved` baud rate against the `requested` one, in percent
+fn baudrate_error(achieved: u32, requested: u32) -> f32 {
+    (achieved as f32 - requested as f32) * 100.0 / requested as f32
+}
+
 /// Serial abstraction
 pub struct Serial<USART, PINS> {
     usart: USART,
@@ -181,15 +302,64 @@ pub struct Serial<USART, PINS> {
 }
 
 /// Serial receiver
+///
+/// The receiver only touches the receive side of the USART, so it can be moved to a different
+/// task or interrupt handler than the matching [`Tx`].
 pub struct Rx<USART> {
     _usart: PhantomData<USART>,
 }
 
 /// Serial transmitter
+///
+/// The transmitter only touches the transmit side of the USART, so it can be moved to a different
+/// task or interrupt handler than the matching [`Rx`].
 pub struct Tx<USART> {
     _usart: PhantomData<USART>,
 }
 
+/// Serial receiver paired with its DMA channel, see [`Rx::with_dma`]
+pub struct RxDma<USART, CHANNEL> {
+    rx: Rx<USART>,
+    channel: CHANNEL,
+}
+
+/// Serial transmitter paired with its DMA channel, see [`Tx::with_dma`]
+pub struct TxDma<USART, CHANNEL> {
+    tx: Tx<USART>,
+    channel: CHANNEL,
+}
+
+impl<USART, CHANNEL> RxDma<USART, CHANNEL> {
+    /// Splits the pair back into the receiver and the DMA channel
+    pub fn release(self) -> (Rx<USART>, CHANNEL) {
+        (self.rx, self.channel)
+    }
+}
+
+impl<USART, CHANNEL> TxDma<USART, CHANNEL> {
+    /// Splits the pair back into the transmitter and the DMA channel
+    pub fn release(self) -> (Tx<USART>, CHANNEL) {
+        (self.tx, self.channel)
+    }
+}
+
+/// Receiver of variable length frames, delimited by an idle line, see [`RxDma::idle_read`]
+///
+/// The DMA continuously writes into a ring buffer, and every time the line goes idle
+/// [`DmaIdleRx::read_frame`] hands out the bytes received since the previous frame. A frame
+/// wrapping around the end of the ring buffer is copied into the scratch buffer, so it is always
+/// returned as a single slice.
+///
+/// Nothing stops the DMA from overwriting bytes that weren't read yet, so the ring buffer must
+/// hold more than the longest frame plus what arrives until `read_frame` is called.
+pub struct DmaIdleRx<USART, CHANNEL, BUFFER> {
+    rx: Rx<USART>,
+    channel: CHANNEL,
+    buffer: BUFFER,
+    scratch: BUFFER,
+    read_index: usize,
+}
+
 macro_rules! hal {
     ($(
         $(#[$meta:meta])*
@@ -220,6 +390,14 @@ macro_rules! hal {
                 /// `MAPR` and `APBX` are register handles which are passed for
                 /// configuration. (`MAPR` is used to map the USART to the
                 /// corresponding pins. `APBX` is used to reset the USART.)
+                ///
+                /// The achieved baud rate can differ slightly from the requested one, see
+                /// [`Serial::baudrate`].
+                ///
+                /// # Panics
+                ///
+                /// Panics if the baud rate can't be reached from the peripheral clock, i.e. it is
+                /// above 1/16 (1/8 when oversampling 8 times, or automatically) or below 1/65535 of the clock.
                 pub fn $usartX(
                     usart: pac::$USARTX,
                     pins: PINS,
@@ -241,24 +419,10 @@ macro_rules! hal {
                     usart.cr3.reset();
 
                     // Configure baud rate
-                    match config.oversampling {
-                        Oversampling::Over8 => {
-                            let uartdiv = 2 * clocks.$pclkX().0 / config.baudrate.0;
-                            assert!(uartdiv >= 16, "impossible baud rate");
-
-                            let lower = (uartdiv & 0xf) >> 1;
-                            let brr = (uartdiv & !0xf) | lower;
-
-                            usart.cr1.modify(|_, w| w.over8().set_bit());
-                            usart.brr.write(|w| unsafe { w.bits(brr) });
-                        }
-                        Oversampling::Over16 => {
-                            let brr = clocks.$pclkX().0 / config.baudrate.0;
-                            assert!(brr >= 16, "impossible baud rate");
-
-                            usart.brr.write(|w| unsafe { w.bits(brr) });
-                        }
-                    }
+                    let (brr, over8) =
+                        compute_brr(clocks.$pclkX().0, config.baudrate.0, config.oversampling);
+                    usart.cr1.modify(|_, w| w.over8().bit(over8));
+                    usart.brr.write(|w| unsafe { w.bits(brr) });
 
                     if let Some(val) = config.receiver_timeout {
                         usart.rtor.modify(|_, w| w.rto().bits(val));
@@ -333,7 +497,19 @@ macro_rules! hal {
                             w.rtoen().set_bit();
                         }
 
-                        w
+                        if config.lin_mode {
+                            w.linen().set_bit().lbdl().set_bit();
+                        }
+
+                        // Pin swap and signal inversions
+                        w.swap()
+                            .bit(config.swap_tx_rx)
+                            .rxinv()
+                            .bit(config.invert_rx)
+                            .txinv()
+                            .bit(config.invert_tx)
+                            .datainv()
+                            .bit(config.invert_data)
                     });
 
 
@@ -365,9 +541,133 @@ macro_rules! hal {
                         Event::ReceiverTimeout => {
                             self.usart.cr1.modify(|_, w| w.rtoie().set_bit())
                         },
+                        Event::LinBreak => {
+                            self.usart.cr2.modify(|_, w| w.lbdie().set_bit())
+                        },
                     }
                 }
 
+                /// Pulses the reset line of the peripheral and applies the configuration again
+                ///
+                /// This recovers the peripheral from a stuck state. Any transfer in progress is
+                /// lost, and the interrupts enabled with [`Serial::listen`] stay enabled.
+                pub fn reset(&mut self, apb: &mut $APB) {
+                    let usart = &self.usart;
+                    let brr = usart.brr.read().bits();
+                    let rtor = usart.rtor.read().bits();
+                    let cr1 = usart.cr1.read().bits();
+                    let cr2 = usart.cr2.read().bits();
+                    let cr3 = usart.cr3.read().bits();
+
+                    apb.rstr().modify(|_, w| w.$usartXrst().set_bit());
+                    apb.rstr().modify(|_, w| w.$usartXrst().clear_bit());
+
+                    // This is sound, as the values were read back from the same registers. Most
+                    // of the configuration can only be written while the USART is disabled, so
+                    // it is enabled last.
+                    usart.brr.write(|w| unsafe { w.bits(brr) });
+                    usart.rtor.write(|w| unsafe { w.bits(rtor) });
+                    usart.cr2.write(|w| unsafe { w.bits(cr2) });
+                    usart.cr3.write(|w| unsafe { w.bits(cr3) });
+                    usart.cr1.write(|w| unsafe { w.bits(cr1) }.ue().clear_bit());
+                    usart.cr1.modify(|_, w| w.ue().bit(cr1 & 1 != 0));
+                }
+
+                /// Returns the achieved baud rate, computed from the divider and `clocks`
+                ///
+                /// The divider is rounded to the closest value, see [`Serial::baudrate_error`].
+                pub fn baudrate(&self, clocks: Clocks) -> Bps {
+                    let brr = self.usart.brr.read().bits();
+
+                    if self.usart.cr1.read().over8().bit_is_set() {
+                        let uartdiv = (brr & !0xf) | ((brr & 0x7) << 1);
+                        Bps(2 * clocks.$pclkX().0 / uartdiv)
+                    } else {
+                        Bps(clocks.$pclkX().0 / brr)
+                    }
+                }
+
+                /// Returns the error of the achieved baud rate against `requested`, in percent
+                ///
+                /// A positive error means the achieved baud rate is above the requested one.
+                /// Receivers usually tolerate a few percent of error, depending on the oversampling
+                /// and the character length.
+                pub fn baudrate_error(&self, clocks: Clocks, requested: Bps) -> f32 {
+                    baudrate_error(self.baudrate(clocks).0, requested.0)
+                }
+
+                /// Detects the baud rate from the next received character, and returns it
+                ///
+                /// This blocks until a character is received. The detected rate is kept in the
+                /// divider, replacing the configured one, and the measured character is received
+                /// like any other. Calling this again detects the baud rate anew.
+                ///
+                /// The longer the measured part of the character, the more accurate the
+                /// detection: [`AutoBaudMode::Char0x7F`] and [`AutoBaudMode::Char0x55`] measure
+                /// several bits, but need the other end to send that character first.
+                ///
+                /// Returns [`Error::AutoBaudRate`] if the rate is out of the range of the
+                /// divider, or if the character does not match `mode`.
+                pub fn auto_baud(&mut self, mode: AutoBaudMode, clocks: Clocks) -> Result<Bps, Error> {
+                    let usart = &self.usart;
+
+                    // The detection can only be configured while the USART is disabled
+                    usart.cr1.modify(|_, w| w.ue().clear_bit());
+                    usart.cr2.modify(|_, w| w.abren().set_bit().abrmod().bits(mode as u8));
+                    usart.cr1.modify(|_, w| w.ue().set_bit());
+
+                    // Restart the detection, this clears the flags of a previous one
+                    usart.rqr.write(|w| w.abrrq().set_bit());
+
+                    loop {
+                        let isr = usart.isr.read();
+                        if isr.abre().bit_is_set() {
+                            trace!("USART auto baud rate detection failed");
+                            return Err(Error::AutoBaudRate);
+                        }
+                        if isr.abrf().bit_is_set() {
+                            return Ok(self.baudrate(clocks));
+                        }
+                    }
+                }
+
+                /// Sets the receiver timeout to `bits` bit durations, and enables it
+                ///
+                /// The timeout counts from the end of every received character, and sets the
+                /// flag once the line stayed silent for `bits` bit durations, see
+                /// [`Event::ReceiverTimeout`] and [`Rx::is_receiver_timeout`]. This tells the
+                /// end of messages delimited by a gap, e.g. the 3.5 characters of Modbus-RTU,
+                /// while the idle flag is set after a single character. The flag is only set
+                /// again after a new character.
+                ///
+                /// This can be changed on the fly, `bits` only takes 24 bits.
+                ///
+                /// # Panics
+                ///
+                /// Panics if `bits` doesn't fit in 24 bits.
+                pub fn set_receiver_timeout(&mut self, bits: u32) {
+                    assert!(bits < 1 << 24);
+
+                    self.usart.rtor.modify(|_, w| w.rto().bits(bits));
+                    self.usart.cr2.modify(|_, w| w.rtoen().set_bit());
+                }
+
+                /// Disables the receiver timeout, see [`Serial::set_receiver_timeout`]
+                pub fn disable_receiver_timeout(&mut self) {
+                    self.usart.cr2.modify(|_, w| w.rtoen().clear_bit());
+                }
+
+                /// Checks to see if the USART peripheral has detected an receiver timeout and
+                /// clears the flag
+                ///
+                /// See [`Rx::is_receiver_timeout`].
+                pub fn is_receiver_timeout(&mut self, clear: bool) -> bool {
+                    let mut rx: Rx<pac::$USARTX> = Rx {
+                        _usart: PhantomData,
+                    };
+                    rx.is_receiver_timeout(clear)
+                }
+
                 /// Check for, and return, any errors
                 ///
                 /// See [`Rx::check_for_error`].
@@ -396,10 +696,21 @@ macro_rules! hal {
                         Event::ReceiverTimeout => {
                             self.usart.cr1.modify(|_, w| w.rtoie().clear_bit())
                         },
+                        Event::LinBreak => {
+                            self.usart.cr2.modify(|_, w| w.lbdie().clear_bit())
+                        },
                     }
                 }
 
                 /// Splits the `Serial` abstraction into a transmitter and a receiver half
+                ///
+                /// The halves do not share any state and are both `Send`, so they can be handed
+                /// to different tasks or interrupt handlers, each with its own DMA channel (see
+                /// `Tx::with_dma` and `Rx::with_dma`).
+                ///
+                /// Splitting consumes the USART and the pins for good; there is no way to put
+                /// the halves back together. Use [`Serial::release`] instead of `split` if the
+                /// peripheral or the pins are needed again later.
                 pub fn split(self) -> (Tx<pac::$USARTX>, Rx<pac::$USARTX>) {
                     (
                         Tx {
@@ -412,6 +723,8 @@ macro_rules! hal {
                 }
 
                 /// Frees the USART peripheral
+                ///
+                /// Only available on an unsplit `Serial`, see [`Serial::split`].
                 pub fn release(self) -> (pac::$USARTX, PINS) {
                     (self.usart, self.pins)
                 }
@@ -505,6 +818,11 @@ macro_rules! hal {
                 for Tx<pac::$USARTX> {}
 
             impl Rx<pac::$USARTX> {
+                /// Pairs the receiver with its DMA channel
+                pub fn with_dma(self, channel: $rx_chan) -> RxDma<pac::$USARTX, $rx_chan> {
+                    RxDma { rx: self, channel }
+                }
+
                 pub fn circ_read<B, H>(
                     &self,
                     mut chan: $rx_chan,
@@ -527,9 +845,6 @@ macro_rules! hal {
                     chan.ccr().modify(|_, w| unsafe {
                         w.mem2mem()
                             .clear_bit()
-                            // 00: Low, 01: Medium, 10: High, 11: Very high
-                            .pl()
-                            .bits(0b01)
                             // 00: 8-bits, 01: 16-bits, 10: 32-bits, 11: Reserved
                             .msize()
                             .bits(0b00)
@@ -578,9 +893,6 @@ macro_rules! hal {
                     channel.ccr().modify(|_, w| unsafe {
                         w.mem2mem()
                             .clear_bit()
-                            // 00: Low, 01: Medium, 10: High, 11: Very high
-                            .pl()
-                            .bits(0b01)
                             // 00: 8-bits, 01: 16-bits, 10: 32-bits, 11: Reserved
                             .msize()
                             .bits(0b00)
@@ -649,6 +961,37 @@ macro_rules! hal {
                     }
                 }
 
+                /// Checks to see if the USART peripheral has detected a LIN break and clears
+                /// the flag
+                ///
+                /// Breaks are only detected in LIN mode, see [`Config::with_lin_mode`].
+                pub fn is_lin_break(&mut self, clear: bool) -> bool {
+                    let isr = unsafe { &(*pac::$USARTX::ptr()).isr.read() };
+                    let icr = unsafe { &(*pac::$USARTX::ptr()).icr };
+
+                    if isr.lbdf().bit_is_set() {
+                        if clear {
+                            icr.write(|w| w.lbdcf().set_bit() );
+                        }
+                        true
+                    } else {
+                        false
+                    }
+                }
+
+                /// Discards any received data and clears an overrun error
+                ///
+                /// Useful to resynchronize on the start of a frame, e.g. after a break.
+                pub fn flush(&mut self) {
+                    // NOTE(unsafe): Only used for atomic access.
+                    let usart = unsafe { &(*pac::$USARTX::ptr()) };
+
+                    while usart.isr.read().rxne().bit_is_set() {
+                        let _ = usart.rdr.read();
+                    }
+                    usart.icr.write(|w| w.orecf().clear());
+                }
+
                 /// Check for, and return, any errors
                 ///
                 /// The `read` methods can only return one error at a time, but
@@ -662,18 +1005,22 @@ macro_rules! hal {
                     let icr = unsafe { &(*pac::$USARTX::ptr()).icr };
 
                     if isr.pe().bit_is_set() {
+                        trace!("USART parity error");
                         icr.write(|w| w.pecf().clear());
                         return Err(Error::Parity);
                     }
                     if isr.fe().bit_is_set() {
+                        trace!("USART framing error");
                         icr.write(|w| w.fecf().clear());
                         return Err(Error::Framing);
                     }
                     if isr.nf().bit_is_set() {
+                        trace!("USART noise error");
                         icr.write(|w| w.ncf().clear());
                         return Err(Error::Noise);
                     }
                     if isr.ore().bit_is_set() {
+                        trace!("USART overrun");
                         icr.write(|w| w.orecf().clear());
                         return Err(Error::Overrun);
                     }
@@ -683,6 +1030,23 @@ macro_rules! hal {
             }
 
             impl Tx<pac::$USARTX> {
+                /// Pairs the transmitter with its DMA channel
+                pub fn with_dma(self, channel: $tx_chan) -> TxDma<pac::$USARTX, $tx_chan> {
+                    TxDma { tx: self, channel }
+                }
+
+                /// Sends a break character, and waits until it has been sent
+                ///
+                /// The break is sent after the character currently being transmitted. In LIN mode
+                /// it is 13 bits long, otherwise it lasts one frame.
+                pub fn send_break(&mut self) {
+                    // NOTE(unsafe): Only used for atomic access.
+                    let usart = unsafe { &(*pac::$USARTX::ptr()) };
+
+                    usart.rqr.write(|w| w.sbkrq().set_bit());
+                    while usart.isr.read().sbkf().bit_is_set() {}
+                }
+
                 /// Creates a new DMA frame sender
                 pub fn frame_sender<BUFFER, N>(
                     &self,
@@ -705,9 +1069,6 @@ macro_rules! hal {
                     channel.ccr().modify(|_, w| unsafe {
                         w.mem2mem()
                             .clear_bit()
-                            // 00: Low, 01: Medium, 10: High, 11: Very high
-                            .pl()
-                            .bits(0b01)
                             // 00: 8-bits, 01: 16-bits, 10: 32-bits, 11: Reserved
                             .msize()
                             .bits(0b00)
@@ -722,6 +1083,396 @@ macro_rules! hal {
                     FrameSender::new(channel)
                 }
             }
+
+            impl RxDma<pac::$USARTX, $rx_chan> {
+                /// Sets the channel up to receive `len` bytes at `address`, without starting it
+                fn configure(&mut self, address: *mut u8, len: u16, circular: bool) {
+                    self.channel.set_peripheral_address(unsafe{ &(*pac::$USARTX::ptr()).rdr as *const _ as u32 }, false);
+                    self.channel.set_memory_address(address as u32, true);
+                    self.channel.set_transfer_length(len);
+
+                    // Tell DMA to request from serial
+                    self.channel.cselr().modify(|_, w| {
+                        w.$dmacsr().bits(0b0010) // TODO: Fix this, not valid for DMA2
+                    });
+
+                    self.channel.ccr().modify(|_, w| unsafe {
+                        w.mem2mem()
+                            .clear_bit()
+                            // 00: 8-bits, 01: 16-bits, 10: 32-bits, 11: Reserved
+                            .msize()
+                            .bits(0b00)
+                            // 00: 8-bits, 01: 16-bits, 10: 32-bits, 11: Reserved
+                            .psize()
+                            .bits(0b00)
+                            .circ()
+                            .bit(circular)
+                            // Peripheral -> Mem
+                            .dir()
+                            .clear_bit()
+                    });
+                }
+
+                /// Receives until `buffer` is full
+                ///
+                /// The buffer is owned by the returned `Transfer` and only handed back, together
+                /// with the channel and the receiver, by `Transfer::wait` once the DMA is done.
+                pub fn read<B>(
+                    mut self,
+                    mut buffer: B,
+                ) -> Transfer<W, B, $rx_chan, Rx<pac::$USARTX>>
+                where
+                    B: StableDeref + DerefMut + 'static,
+                    B::Target: AsMutSlice<Element = u8>,
+                {
+                    // Stop the channel and clear its flags, so `is_done` can't see a stale
+                    // transfer complete flag
+                    self.channel.stop();
+
+                    let buf = buffer.as_mut_slice();
+                    self.configure(buf.as_mut_ptr(), buf.len() as u16, false);
+
+                    // NOTE(compiler_fence) operations on `buffer` should not be reordered after
+                    // the next statement, which starts the DMA transfer
+                    atomic::compiler_fence(Ordering::Release);
+
+                    self.channel.start();
+
+                    Transfer::w(buffer, self.channel, self.rx)
+                }
+
+                /// Starts a circular DMA transfer into `buffer`, see `Rx::circ_read`
+                ///
+                /// The receiver is handed back so it can still be used for error and event
+                /// checks while the transfer is running.
+                pub fn circ_read<B, H>(
+                    self,
+                    buffer: B,
+                ) -> (Rx<pac::$USARTX>, CircBuffer<B, $rx_chan>)
+                where
+                    B: StableDeref<Target = [H; 2]> + DerefMut + 'static,
+                    H: AsMutSlice<Element = u8>
+                {
+                    let circ_buffer = self.rx.circ_read(self.channel, buffer);
+                    (self.rx, circ_buffer)
+                }
+
+                /// Creates a frame reader, see `Rx::frame_read`
+                ///
+                /// The receiver is handed back so it can still be used for error and event
+                /// checks while the transfer is running.
+                pub fn frame_read<BUFFER, N>(
+                    self,
+                    buffer: BUFFER,
+                ) -> (Rx<pac::$USARTX>, FrameReader<BUFFER, $rx_chan, N>)
+                where
+                    BUFFER: Sized + StableDeref<Target = DMAFrame<N>> + DerefMut + 'static,
+                    N: ArrayLength<MaybeUninit<u8>>,
+                {
+                    let frame_reader = self.rx.frame_read(self.channel, buffer);
+                    (self.rx, frame_reader)
+                }
+
+                /// Receives idle line delimited frames into the ring buffer `buffer`
+                ///
+                /// `scratch` is used for frames wrapping around the end of `buffer`.
+                ///
+                /// # Panics
+                ///
+                /// Panics if `scratch` is smaller than `buffer`.
+                pub fn idle_read<B>(
+                    mut self,
+                    mut buffer: B,
+                    scratch: B,
+                ) -> DmaIdleRx<pac::$USARTX, $rx_chan, B>
+                where
+                    B: StableDeref + DerefMut + 'static,
+                    B::Target: AsMutSlice<Element = u8>,
+                {
+                    assert!(scratch.as_slice().len() >= buffer.as_slice().len());
+
+                    self.channel.stop();
+
+                    let buf = buffer.as_mut_slice();
+                    self.configure(buf.as_mut_ptr(), buf.len() as u16, true);
+
+                    // Don't report a line that went idle before the transfer started
+                    self.rx.is_idle(true);
+
+                    // NOTE(compiler_fence) operations on `buffer` should not be reordered after
+                    // the next statement, which starts the DMA transfer
+                    atomic::compiler_fence(Ordering::Release);
+
+                    self.channel.start();
+
+                    DmaIdleRx {
+                        rx: self.rx,
+                        channel: self.channel,
+                        buffer,
+                        scratch,
+                        read_index: 0,
+                    }
+                }
+            }
+
+            impl<B> DmaIdleRx<pac::$USARTX, $rx_chan, B>
+            where
+                B: StableDeref + DerefMut + 'static,
+                B::Target: AsMutSlice<Element = u8>,
+            {
+                /// Returns the frame received since the previous one, once the line went idle
+                ///
+                /// Returns `None` if the line is not idle, or went idle without new bytes. This
+                /// can be polled, or called from the interrupt handler of `Event::Idle`.
+                pub fn read_frame(&mut self) -> Option<&[u8]> {
+                    if !self.rx.is_idle(true) {
+                        return None;
+                    }
+
+                    // NOTE(compiler_fence) the bytes written by the DMA must not be read before
+                    // the progress of the transfer
+                    atomic::compiler_fence(Ordering::Acquire);
+
+                    let len = self.buffer.as_slice().len();
+                    let write_index = (len - self.channel.get_cndtr() as usize) % len;
+                    let start = self.read_index;
+                    self.read_index = write_index;
+
+                    let buffer = self.buffer.as_slice();
+                    if write_index > start {
+                        Some(&buffer[start..write_index])
+                    } else if write_index < start {
+                        // The frame wraps around the end of the ring buffer
+                        let tail = len - start;
+                        let scratch = self.scratch.as_mut_slice();
+                        scratch[..tail].copy_from_slice(&buffer[start..]);
+                        scratch[tail..tail + write_index].copy_from_slice(&buffer[..write_index]);
+
+                        Some(&scratch[..tail + write_index])
+                    } else {
+                        None
+                    }
+                }
+
+                /// Stops the DMA and releases the receiver, the channel and both buffers
+                pub fn release(mut self) -> (Rx<pac::$USARTX>, $rx_chan, B, B) {
+                    self.channel.stop();
+
+                    // NOTE(compiler_fence) operations on the buffer should not be reordered
+                    // before the DMA is stopped
+                    atomic::compiler_fence(Ordering::SeqCst);
+
+                    (self.rx, self.channel, self.buffer, self.scratch)
+                }
+            }
+
+            impl TxDma<pac::$USARTX, $tx_chan> {
+                /// Sets the channel up to send `len` bytes from `address`, without starting it
+                fn configure(&mut self, address: *const u8, len: u16) {
+                    self.channel.set_peripheral_address(unsafe{ &(*pac::$USARTX::ptr()).tdr as *const _ as u32 }, false);
+                    self.channel.set_memory_address(address as u32, true);
+                    self.channel.set_transfer_length(len);
+
+                    // Tell DMA to request from serial
+                    self.channel.cselr().modify(|_, w| {
+                        w.$dmacst().bits(0b0010) // TODO: Fix this, not valid for DMA2
+                    });
+
+                    self.channel.ccr().modify(|_, w| unsafe {
+                        w.mem2mem()
+                            .clear_bit()
+                            // 00: 8-bits, 01: 16-bits, 10: 32-bits, 11: Reserved
+                            .msize()
+                            .bits(0b00)
+                            // 00: 8-bits, 01: 16-bits, 10: 32-bits, 11: Reserved
+                            .psize()
+                            .bits(0b00)
+                            .circ()
+                            .clear_bit()
+                            // Mem -> Peripheral
+                            .dir()
+                            .set_bit()
+                    });
+                }
+
+                /// Sends the whole `buffer`
+                ///
+                /// The buffer is owned by the returned `Transfer` and only handed back, together
+                /// with the channel and the transmitter, by `Transfer::wait` once the DMA is done.
+                /// Note that the last byte may still be shifting out at that point.
+                pub fn write<B>(
+                    mut self,
+                    buffer: B,
+                ) -> Transfer<R, B, $tx_chan, Tx<pac::$USARTX>>
+                where
+                    B: StableDeref + 'static,
+                    B::Target: AsSlice<Element = u8>,
+                {
+                    // Stop the channel and clear its flags, so `is_done` can't see a stale
+                    // transfer complete flag
+                    self.channel.stop();
+
+                    let buf = buffer.as_slice();
+                    self.configure(buf.as_ptr(), buf.len() as u16);
+
+                    // NOTE(compiler_fence) operations on `buffer` should not be reordered after
+                    // the next statement, which starts the DMA transfer
+                    atomic::compiler_fence(Ordering::Release);
+
+                    self.channel.start();
+
+                    Transfer::r(buffer, self.channel, self.tx)
+                }
+
+                /// Creates a DMA frame sender, see `Tx::frame_sender`
+                ///
+                /// The transmitter is handed back so it can still be used for blocking writes
+                /// while no frame is being sent.
+                pub fn frame_sender<BUFFER, N>(
+                    self,
+                ) -> (Tx<pac::$USARTX>, FrameSender<BUFFER, $tx_chan, N>)
+                where
+                    BUFFER: Sized + StableDeref<Target = DMAFrame<N>> + DerefMut + 'static,
+                    N: ArrayLength<MaybeUninit<u8>>,
+                {
+                    let frame_sender = self.tx.frame_sender(self.channel);
+                    (self.tx, frame_sender)
+                }
+            }
+
+            #[cfg(feature = "async")]
+            impl Rx<pac::$USARTX> {
+                /// Completes the `async` read of the receiver on an idle line, to be called from
+                /// the interrupt handler of the USART
+                ///
+                /// Only the idle line interrupt enabled by the read is handled, the interrupt
+                /// can be shared with the other events.
+                pub fn on_interrupt() {
+                    // NOTE(unsafe) atomic read with no side effects
+                    let usart = unsafe { &*pac::$USARTX::ptr() };
+                    let idle = interrupt::free(|_| {
+                        let idle = usart.cr1.read().idleie().bit_is_set()
+                            && usart.isr.read().idle().bit_is_set();
+                        if idle {
+                            usart.cr1.modify(|_, w| w.idleie().clear_bit());
+                        }
+
+                        idle
+                    });
+
+                    if idle {
+                        <$rx_chan as AsyncChannel>::wake();
+                    }
+                }
+
+                /// Enables or disables the idle line interrupt of an `async` read
+                fn listen_idle(&mut self, listen: bool) {
+                    // NOTE(unsafe) `on_interrupt` only changes the register in a critical section
+                    // as well
+                    let usart = unsafe { &*pac::$USARTX::ptr() };
+                    interrupt::free(|_| usart.cr1.modify(|_, w| w.idleie().bit(listen)));
+                }
+            }
+
+            #[cfg(feature = "async")]
+            impl embedded_io_async::ErrorType for RxDma<pac::$USARTX, $rx_chan> {
+                type Error = Error;
+            }
+
+            /// Receives with the DMA, until the buffer is full or the line goes idle
+            ///
+            /// Besides the interrupt of the DMA channel, this needs the interrupt of the USART to
+            /// call [`Rx::on_interrupt`], see the [DMA documentation](crate::dma#async). A
+            /// reception error is returned instead of the bytes received along with it.
+            ///
+            /// The inherent `RxDma::read` takes precedence, call it as
+            /// `embedded_io_async::Read::read(&mut rx, buf)`.
+            #[cfg(feature = "async")]
+            impl embedded_io_async::Read for RxDma<pac::$USARTX, $rx_chan> {
+                async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+                    if buf.is_empty() {
+                        return Ok(0);
+                    }
+                    let len = buf.len().min(usize::from(u16::MAX));
+
+                    self.channel.stop();
+                    self.configure(buf.as_mut_ptr(), len as u16, false);
+
+                    // Only a line going idle after the start of the transfer ends it
+                    self.rx.is_idle(true);
+                    self.rx.listen_idle(true);
+
+                    // NOTE(compiler_fence) operations on `buf` should not be reordered after
+                    // the next statement, which starts the DMA transfer
+                    atomic::compiler_fence(Ordering::Release);
+
+                    self.channel.start();
+
+                    let rx = &mut self.rx;
+                    let mut transfer = AsyncTransfer::new(&mut self.channel);
+                    transfer
+                        .wait(|channel| {
+                            if !rx.is_idle(true) {
+                                return false;
+                            }
+                            if channel.remaining() < len {
+                                return true;
+                            }
+
+                            // Idle before the first byte, e.g. right after the previous read
+                            rx.listen_idle(true);
+                            false
+                        })
+                        .await;
+                    let received = len - transfer.remaining();
+                    drop(transfer);
+
+                    self.rx.listen_idle(false);
+                    self.rx.check_for_error()?;
+
+                    Ok(received)
+                }
+            }
+
+            #[cfg(feature = "async")]
+            impl embedded_io_async::ErrorType for TxDma<pac::$USARTX, $tx_chan> {
+                type Error = Error;
+            }
+
+            /// Sends with the DMA, see the [DMA documentation](crate::dma#async)
+            #[cfg(feature = "async")]
+            impl embedded_io_async::Write for TxDma<pac::$USARTX, $tx_chan> {
+                async fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+                    if buf.is_empty() {
+                        return Ok(0);
+                    }
+                    let len = buf.len().min(usize::from(u16::MAX));
+
+                    self.channel.stop();
+                    self.configure(buf.as_ptr(), len as u16);
+
+                    // NOTE(compiler_fence) operations on `buf` should not be reordered after
+                    // the next statement, which starts the DMA transfer
+                    atomic::compiler_fence(Ordering::Release);
+
+                    self.channel.start();
+
+                    AsyncTransfer::new(&mut self.channel).wait(|_| false).await;
+
+                    Ok(len)
+                }
+
+                /// Waits until the last byte is completely sent
+                ///
+                /// This busy waits for the last two characters at most, the ones in the data
+                /// and shift registers once the DMA is done.
+                async fn flush(&mut self) -> Result<(), Error> {
+                    // NOTE(unsafe) atomic read with no side effects
+                    while unsafe { (*pac::$USARTX::ptr()).isr.read().tc().bit_is_clear() } {}
+
+                    Ok(())
+                }
+            }
         )+
     }
 }
@@ -973,3 +1724,61 @@ mod private {
     pub trait SealedRtsDe {}
     pub trait SealedCts {}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn brr_rounding() {
+        // 80 MHz / 115200 = 694.4 clock periods per bit
+        assert_eq!(
+            compute_brr(80_000_000, 115_200, Oversampling::Over16),
+            (694, false)
+        );
+        // USARTDIV = 1388 = 0x56C, BRR[3:0] is USARTDIV[3:0] shifted right
+        assert_eq!(
+            compute_brr(80_000_000, 115_200, Oversampling::Over8),
+            (0x566, true)
+        );
+        // 16 MHz / 9600 = 1666.67, rounded up
+        assert_eq!(
+            compute_brr(16_000_000, 9_600, Oversampling::Over16),
+            (1667, false)
+        );
+    }
+
+    #[test]
+    fn brr_auto_oversampling() {
+        // Both are as close, 16 times oversampling is more tolerant
+        assert_eq!(
+            compute_brr(80_000_000, 115_200, Oversampling::Auto),
+            (694, false)
+        );
+        // 4 MHz / 460800 = 8.7 clock periods per bit, only 8 times oversampling reaches it
+        assert_eq!(
+            compute_brr(4_000_000, 460_800, Oversampling::Auto),
+            (0x11, true)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "impossible baud rate")]
+    fn brr_too_fast() {
+        compute_brr(4_000_000, 460_800, Oversampling::Over16);
+    }
+
+    #[test]
+    #[should_panic(expected = "impossible baud rate")]
+    fn brr_too_slow() {
+        compute_brr(80_000_000, 1_200, Oversampling::Over16);
+    }
+
+    #[test]
+    fn baudrate_errors() {
+        assert_eq!(baudrate_error(115_200, 115_200), 0.0);
+        assert!((baudrate_error(80_000_000 / 694, 115_200) - 0.063).abs() < 0.001);
+        assert!((baudrate_error(4_000_000 / 16, 460_800) + 45.75).abs() < 0.01);
+        assert_eq!(baudrate_error(0, 9_600), -100.0);
+    }
+}
@@ -61,6 +61,25 @@ impl Uid {
     }
 }
 
+/// Returns the 96-bit unique device ID as three words
+///
+/// The words are read from `0x1FFF_7590`, `0x1FFF_7594` and `0x1FFF_7598`, as
+/// documented in the device electronic signature chapter of RM0394 for the
+/// L4x2 (the other parts use the same addresses).
+pub fn device_id() -> [u32; 3] {
+    let ptr = Uid::ptr() as *const u32;
+    // This is sound, as the ID is a word-aligned, read-only factory value.
+    unsafe { [ptr.read(), ptr.add(1).read(), ptr.add(2).read()] }
+}
+
+/// Returns the size of the integrated flash in kilobytes
+///
+/// The value is read from the flash size register at `0x1FFF_75E0`, as
+/// documented in RM0394 for the L4x2.
+pub fn flash_size_kb() -> u16 {
+    FlashSize::get().kilo_bytes()
+}
+
 /// Size of integrated flash
 #[derive(Debug)]
 #[repr(C)]
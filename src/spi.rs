@@ -1,16 +1,48 @@
 //! Serial Peripheral Interface (SPI) bus
+//!
+//! # One direction
+//!
+//! Devices only written to, e.g. displays, or only read from, e.g. ADCs, leave one data line
+//! unused. The `spiX_tx_only` constructors take no MISO pin, and the `spiX_rx_only` constructors
+//! no MOSI pin, leaving that pin free for other uses:
+//!
+//! - When only sending, the peripheral runs in the 1-line bidirectional mode with its output
+//!   enabled, so it receives nothing. The writes neither wait for nor drain received bytes, and
+//!   no stale byte is left in the RX FIFO. Reads, e.g. through `Transfer`, return 0.
+//! - When only receiving, the peripheral runs in full duplex mode, the bytes sent to clock the
+//!   reception just don't leave the chip. The number of clocks is the one of full duplex mode.
 
+use core::cell::RefCell;
 use core::ptr;
+#[cfg(feature = "async")]
+use core::sync::atomic::{self, Ordering};
 
+use crate::hal::blocking;
+use crate::hal::digital::v2::OutputPin;
 use crate::hal::spi::{FullDuplex, Mode, Phase, Polarity};
 
+#[cfg(feature = "async")]
+use crate::dma::{dma1, AsyncTransfer};
 use crate::gpio::{Alternate, Floating, Input, AF5};
+#[cfg(feature = "async")]
+use crate::pac::spi1;
 use crate::rcc::{Clocks, APB1R1, APB2};
 use crate::time::Hertz;
 
+/// SPI interrupt event
+pub enum Event {
+    /// New data has been received
+    Rxne,
+    /// New data can be sent
+    Txe,
+    /// Overrun, mode fault or CRC error
+    Error,
+}
+
 /// SPI error
 #[non_exhaustive]
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     /// Overrun occurred
     Overrun,
@@ -20,17 +52,63 @@ pub enum Error {
     Crc,
 }
 
+#[cfg(feature = "async")]
+impl embedded_hal_async::spi::Error for Error {
+    fn kind(&self) -> embedded_hal_async::spi::ErrorKind {
+        match self {
+            Error::Overrun => embedded_hal_async::spi::ErrorKind::Overrun,
+            Error::ModeFault => embedded_hal_async::spi::ErrorKind::ModeFault,
+            Error::Crc => embedded_hal_async::spi::ErrorKind::Other,
+        }
+    }
+}
+
+/// Frame format of the SPI (`CR2.FRF`)
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FrameFormat {
+    /// Motorola frame format, with the clock polarity and phase of the [`Mode`]
+    Motorola,
+    /// TI frame format (synchronous serial), see the `spiX_ti` constructors
+    ///
+    /// The frame is framed by a one clock pulse of NSS right before its first bit. The clock
+    /// polarity and phase are forced by the hardware: the clock idles low, the data is shifted out
+    /// on the rising edge and sampled on the falling edge, whatever the `CPOL` and `CPHA` bits.
+    Ti,
+}
+
+/// Management of the NSS signal
+#[derive(Clone, Copy, PartialEq)]
+enum Nss {
+    /// Software slave management, the NSS pin is free for other uses
+    Software,
+    /// NSS output, driven by the peripheral (`CR2.SSOE`)
+    Output,
+    /// NSS input, a low level raises a mode fault (`MODF`)
+    Input,
+}
+
 #[doc(hidden)]
 mod private {
     pub trait Sealed {}
 }
 
+/// Error of a [`SpiDevice`] transaction
+#[derive(Debug)]
+pub enum DeviceError<SPI, CS> {
+    /// The SPI transfer failed
+    Spi(SPI),
+    /// Driving the chip select pin failed
+    ChipSelect(CS),
+}
+
 /// SCK pin. This trait is sealed and cannot be implemented.
 pub trait SckPin<SPI>: private::Sealed {}
 /// MISO pin. This trait is sealed and cannot be implemented.
 pub trait MisoPin<SPI>: private::Sealed {}
 /// MOSI pin. This trait is sealed and cannot be implemented.
 pub trait MosiPin<SPI>: private::Sealed {}
+/// NSS pin, driven or sampled by the peripheral. This trait is sealed and cannot be implemented.
+pub trait NssPin<SPI>: private::Sealed {}
 
 macro_rules! pins {
     ($spi:ident, $af:ident, SCK: [$($sck:ident),*], MISO: [$($miso:ident),*], MOSI: [$($mosi:ident),*]) => {
@@ -49,17 +127,234 @@ macro_rules! pins {
     }
 }
 
+macro_rules! nss_pins {
+    ($spi:ident, $af:ident, NSS: [$($nss:ident),*]) => {
+        $(
+            impl private::Sealed for $nss<Alternate<$af, Input<Floating>>> {}
+            impl NssPin<$spi> for $nss<Alternate<$af, Input<Floating>>> {}
+        )*
+    }
+}
+
 /// SPI peripheral operating in full duplex master mode
 pub struct Spi<SPI, PINS> {
     spi: SPI,
     pins: PINS,
 }
 
+/// SPI paired with its DMA channels, see `Spi::with_dma`
+///
+/// This is the bus of the `async` feature, implementing `embedded_hal_async::spi::SpiBus`.
+#[cfg(feature = "async")]
+pub struct SpiDma<SPI, PINS, RXCH, TXCH> {
+    spi: Spi<SPI, PINS>,
+    rx_channel: RXCH,
+    tx_channel: TXCH,
+}
+
+/// Restores the SPI after a DMA transfer of [`SpiDma`], also when its future is dropped
+///
+/// The DMA requests are disabled and the peripheral enable restored once the bytes left in the
+/// TX FIFO are sent, and the RX FIFO is drained, so a following blocking transfer neither runs
+/// with the DMA requests enabled nor reads stale bytes.
+#[cfg(feature = "async")]
+struct DmaGuard<'a> {
+    spi: &'a spi1::RegisterBlock,
+    spe: bool,
+}
+
+#[cfg(feature = "async")]
+impl Drop for DmaGuard<'_> {
+    fn drop(&mut self) {
+        while self.spi.sr.read().bsy().bit_is_set() {}
+        self.spi
+            .cr2
+            .modify(|_, w| w.txdmaen().clear_bit().rxdmaen().clear_bit());
+
+        while self.spi.sr.read().frlvl().bits() != 0 {
+            // NOTE(read_volatile) read only 1 byte (the svd2rust API only allows reading a
+            // half-word)
+            let _ = unsafe { ptr::read_volatile(&self.spi.dr as *const _ as *const u8) };
+        }
+
+        self.spi.cr1.modify(|_, w| w.spe().bit(self.spe));
+    }
+}
+
+#[cfg(feature = "async")]
+impl<SPI, PINS, RXCH, TXCH> SpiDma<SPI, PINS, RXCH, TXCH> {
+    /// Splits the pair back into the SPI and the DMA channels
+    pub fn release(self) -> (Spi<SPI, PINS>, RXCH, TXCH) {
+        (self.spi, self.rx_channel, self.tx_channel)
+    }
+}
+
+/// SPI bus that can switch to the mode and baud rate of a device, see [`SpiDevice`]
+pub trait Reconfigure {
+    /// Changes the mode and the baud rate
+    fn reconfigure(&mut self, mode: Mode, freq: Hertz, clocks: Clocks);
+}
+
+/// Mode and baud rate of a device on a [`SharedBus`]
+#[derive(Clone, Copy)]
+pub struct DeviceConfig {
+    /// SPI mode of the device
+    pub mode: Mode,
+    /// Baud rate of the device
+    pub freq: Hertz,
+    /// Clocks the baud rate is derived from
+    pub clocks: Clocks,
+}
+
+/// SPI bus shared by several devices, each with its own chip select pin
+///
+/// The bus lives in a `RefCell`, so it can be shared by devices in the same execution context.
+/// Starting a transaction from within another one panics.
+pub struct SharedBus<SPI> {
+    bus: RefCell<SPI>,
+}
+
+impl<SPI> SharedBus<SPI> {
+    /// Wraps the SPI bus
+    pub fn new(spi: SPI) -> Self {
+        SharedBus {
+            bus: RefCell::new(spi),
+        }
+    }
+
+    /// Creates a device on the bus, selected by `cs`
+    ///
+    /// The chip select pin is deasserted (driven high). With a `config`, the bus is switched to
+    /// the mode and baud rate of the device at the start of every transaction.
+    pub fn device<CS>(
+        &self,
+        mut cs: CS,
+        config: Option<DeviceConfig>,
+    ) -> Result<SpiDevice<'_, SPI, CS>, CS::Error>
+    where
+        CS: OutputPin,
+    {
+        cs.set_high()?;
+
+        Ok(SpiDevice {
+            bus: &self.bus,
+            cs,
+            config,
+        })
+    }
+
+    /// Releases the SPI bus
+    pub fn free(self) -> SPI {
+        self.bus.into_inner()
+    }
+}
+
+/// Device on a [`SharedBus`], see [`SharedBus::device`]
+///
+/// Implements the blocking `Transfer` and `Write` traits, each call being a transaction of its
+/// own.
+pub struct SpiDevice<'a, SPI, CS> {
+    bus: &'a RefCell<SPI>,
+    cs: CS,
+    config: Option<DeviceConfig>,
+}
+
+impl<'a, SPI, CS> SpiDevice<'a, SPI, CS>
+where
+    SPI: Reconfigure,
+    CS: OutputPin,
+{
+    /// Runs `f` on the bus while the device is selected
+    ///
+    /// The chip select pin is asserted before `f` is called and deasserted afterwards, even if
+    /// `f` fails.
+    pub fn transaction<R, E, F>(&mut self, f: F) -> Result<R, DeviceError<E, CS::Error>>
+    where
+        F: FnOnce(&mut SPI) -> Result<R, E>,
+    {
+        let mut bus = self.bus.borrow_mut();
+
+        if let Some(config) = self.config {
+            bus.reconfigure(config.mode, config.freq, config.clocks);
+        }
+
+        self.cs.set_low().map_err(DeviceError::ChipSelect)?;
+        let result = f(&mut bus);
+        self.cs.set_high().map_err(DeviceError::ChipSelect)?;
+
+        result.map_err(DeviceError::Spi)
+    }
+
+    /// Releases the chip select pin
+    pub fn free(self) -> CS {
+        self.cs
+    }
+}
+
+impl<'a, SPI, CS> blocking::spi::Transfer<u8> for SpiDevice<'a, SPI, CS>
+where
+    SPI: Reconfigure + blocking::spi::Transfer<u8>,
+    CS: OutputPin,
+{
+    type Error = DeviceError<SPI::Error, CS::Error>;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Error> {
+        self.transaction(move |bus| bus.transfer(words))
+    }
+}
+
+impl<'a, SPI, CS> blocking::spi::Write<u8> for SpiDevice<'a, SPI, CS>
+where
+    SPI: Reconfigure + blocking::spi::Write<u8>,
+    CS: OutputPin,
+{
+    type Error = DeviceError<SPI::Error, CS::Error>;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.transaction(|bus| bus.write(words))
+    }
+}
+
+/// Picks the divider closest to the ratio of `clocks` and `freq`
+///
+/// The ratio is clamped to the dividers, from 2 to 256, and a `freq` of 0 gets the slowest one.
+fn compute_baud_rate(clocks: Hertz, freq: Hertz) -> u8 {
+    match clocks.0.checked_div(freq.0).unwrap_or(u32::MAX) {
+        0..=2 => 0b000,
+        3..=5 => 0b001,
+        6..=11 => 0b010,
+        12..=23 => 0b011,
+        24..=39 => 0b100,
+        40..=95 => 0b101,
+        96..=191 => 0b110,
+        _ => 0b111,
+    }
+}
+
 macro_rules! hal {
-    ($($SPIX:ident: ($spiX:ident, $APBX:ident, $spiXen:ident, $spiXrst:ident, $pclkX:ident),)+) => {
+    ($($SPIX:ident: (
+        $spiX:ident,
+        $spiX_hardware_nss:ident,
+        $spiX_multi_master:ident,
+        $spiX_ti:ident,
+        $spiX_tx_only:ident,
+        $spiX_rx_only:ident,
+        $APBX:ident,
+        $spiXen:ident,
+        $spiXrst:ident,
+        $pclkX:ident,
+        rx: ($dmacsr:ident, $rx_chan:path),
+        tx: ($dmacst:ident, $tx_chan:path),
+        $dma_request:expr
+    ),)+) => {
         $(
             impl<SCK, MISO, MOSI> Spi<$SPIX, (SCK, MISO, MOSI)> {
                 /// Configures the SPI peripheral to operate in full duplex master mode
+                ///
+                /// The peripheral clock can only be divided by a power of two, from 2 to 256, the
+                /// achieved baud rate is returned by [`Spi::frequency`].
+                ///
+                /// A `freq` out of reach of the dividers is clamped, see [`Spi::reclock`].
                 pub fn $spiX<F>(
                     spi: $SPIX,
                     pins: (SCK, MISO, MOSI),
@@ -79,80 +374,228 @@ macro_rules! hal {
                     apb2.rstr().modify(|_, w| w.$spiXrst().set_bit());
                     apb2.rstr().modify(|_, w| w.$spiXrst().clear_bit());
 
-                    // FRXTH: RXNE event is generated if the FIFO level is greater than or equal to
-                    //        8-bit
-                    // DS: 8-bit data size
-                    // SSOE: Slave Select output disabled
-                    spi.cr2
-                        .write(|w| unsafe {
-                            w.frxth().set_bit().ds().bits(0b111).ssoe().clear_bit()
-                        });
+                    let br = compute_baud_rate(clocks.$pclkX(), freq.into());
+                    Self::configure(&spi, mode, br, Nss::Software, FrameFormat::Motorola, false);
 
-                    let br = Self::compute_baud_rate(clocks.$pclkX(), freq.into());
+                    Spi { spi, pins }
+                }
 
-                    // CPHA: phase
-                    // CPOL: polarity
-                    // MSTR: master mode
-                    // BR: 1 MHz
-                    // SPE: SPI disabled
-                    // LSBFIRST: MSB first
-                    // SSM: enable software slave management (NSS pin free for other uses)
-                    // SSI: set nss high = master mode
-                    // CRCEN: hardware CRC calculation disabled
-                    // BIDIMODE: 2 line unidirectional (full duplex)
-                    spi.cr1.write(|w| unsafe {
-                        w.cpha()
-                            .bit(mode.phase == Phase::CaptureOnSecondTransition)
-                            .cpol()
-                            .bit(mode.polarity == Polarity::IdleHigh)
-                            .mstr()
-                            .set_bit()
-                            .br()
-                            .bits(br)
-                            .spe()
-                            .set_bit()
-                            .lsbfirst()
-                            .clear_bit()
-                            .ssi()
-                            .set_bit()
-                            .ssm()
-                            .set_bit()
-                            .crcen()
-                            .clear_bit()
-                            .bidimode()
-                            .clear_bit()
-                    });
+                /// Releases the SPI peripheral and associated pins
+                pub fn free(self) -> ($SPIX, (SCK, MISO, MOSI)) {
+                    (self.spi, self.pins)
+                }
+            }
+
+            impl<SCK, PIN> Spi<$SPIX, (SCK, PIN)> {
+                /// Configures the SPI peripheral to only send, without a MISO pin
+                ///
+                /// The peripheral runs in the 1-line bidirectional mode, with the output enabled
+                /// (`CR1.BIDIMODE` and `CR1.BIDIOE`): nothing is received, so the RX FIFO stays
+                /// empty and the writes don't wait for received bytes to drain them. The MISO pin
+                /// is left free for other uses. Reads return 0, see the module documentation.
+                ///
+                /// A `freq` out of reach of the dividers is clamped, see [`Spi::reclock`].
+                pub fn $spiX_tx_only<F>(
+                    spi: $SPIX,
+                    pins: (SCK, PIN),
+                    mode: Mode,
+                    freq: F,
+                    clocks: Clocks,
+                    apb: &mut $APBX,
+                ) -> Self
+                where
+                    F: Into<Hertz>,
+                    SCK: SckPin<$SPIX>,
+                    PIN: MosiPin<$SPIX>,
+                {
+                    apb.enr().modify(|_, w| w.$spiXen().set_bit());
+                    apb.rstr().modify(|_, w| w.$spiXrst().set_bit());
+                    apb.rstr().modify(|_, w| w.$spiXrst().clear_bit());
+
+                    let br = compute_baud_rate(clocks.$pclkX(), freq.into());
+                    Self::configure(&spi, mode, br, Nss::Software, FrameFormat::Motorola, true);
 
                     Spi { spi, pins }
                 }
 
-                /// Change the baud rate of the SPI
-                pub fn reclock<F>(&mut self, freq: F, clocks: Clocks)
-                    where F: Into<Hertz>
+                /// Configures the SPI peripheral to only receive, without a MOSI pin
+                ///
+                /// The peripheral stays in full duplex mode, only its MOSI output isn't routed to
+                /// a pin, which is left free for other uses. Every byte read is clocked by sending
+                /// a byte, exactly as in full duplex mode, so a slave sees the exact number of
+                /// clocks. The receive only mode of the hardware (`CR1.RXONLY`) is not used, as
+                /// it runs the clock freely until the peripheral is disabled.
+                ///
+                /// A `freq` out of reach of the dividers is clamped, see [`Spi::reclock`].
+                pub fn $spiX_rx_only<F>(
+                    spi: $SPIX,
+                    pins: (SCK, PIN),
+                    mode: Mode,
+                    freq: F,
+                    clocks: Clocks,
+                    apb: &mut $APBX,
+                ) -> Self
+                where
+                    F: Into<Hertz>,
+                    SCK: SckPin<$SPIX>,
+                    PIN: MisoPin<$SPIX>,
                 {
-                    self.spi.cr1.modify(|_, w| w.spe().clear_bit());
-                    self.spi.cr1.modify(|_, w| {
-                        unsafe {w.br().bits(Self::compute_baud_rate(clocks.$pclkX(), freq.into()));}
-                        w.spe().set_bit()
-                    });
+                    apb.enr().modify(|_, w| w.$spiXen().set_bit());
+                    apb.rstr().modify(|_, w| w.$spiXrst().set_bit());
+                    apb.rstr().modify(|_, w| w.$spiXrst().clear_bit());
+
+                    let br = compute_baud_rate(clocks.$pclkX(), freq.into());
+                    Self::configure(&spi, mode, br, Nss::Software, FrameFormat::Motorola, false);
+
+                    Spi { spi, pins }
                 }
 
-                fn compute_baud_rate(clocks: Hertz, freq: Hertz) -> u8 {
-                    match clocks.0 / freq.0 {
-                        0 => unreachable!(),
-                        1..=2 => 0b000,
-                        3..=5 => 0b001,
-                        6..=11 => 0b010,
-                        12..=23 => 0b011,
-                        24..=39 => 0b100,
-                        40..=95 => 0b101,
-                        96..=191 => 0b110,
-                        _ => 0b111,
-                    }
+                /// Releases the SPI peripheral and associated pins
+                pub fn free(self) -> ($SPIX, (SCK, PIN)) {
+                    (self.spi, self.pins)
+                }
+            }
+
+            impl<SCK, MISO, MOSI, NSS> Spi<$SPIX, (SCK, MISO, MOSI, NSS)> {
+                /// Configures the SPI peripheral to operate in full duplex master mode, with the
+                /// peripheral driving the NSS pin (`CR2.SSOE`)
+                ///
+                /// Unlike a chip select driven as a GPIO, NSS follows the peripheral enable, which
+                /// is only set during [`Spi::transaction`]: NSS is asserted right before the
+                /// first clock edge and released right after the last one, with no software
+                /// latency in between. NSS is not driven outside of transactions, so it needs a
+                /// pull-up, e.g. `internal_pull_up(true)` on the pin.
+                ///
+                /// The NSS pin is an output in this mode, the peripheral can therefore not detect
+                /// another master through it, and must be the only master on the bus. A mode fault
+                /// (`MODF`) disabling the peripheral is only detected when NSS is an input, i.e.
+                /// never in this mode.
+                ///
+                /// A `freq` out of reach of the dividers is clamped, see [`Spi::reclock`].
+                pub fn $spiX_hardware_nss<F>(
+                    spi: $SPIX,
+                    pins: (SCK, MISO, MOSI, NSS),
+                    mode: Mode,
+                    freq: F,
+                    clocks: Clocks,
+                    apb2: &mut $APBX,
+                ) -> Self
+                where
+                    F: Into<Hertz>,
+                    SCK: SckPin<$SPIX>,
+                    MISO: MisoPin<$SPIX>,
+                    MOSI: MosiPin<$SPIX>,
+                    NSS: NssPin<$SPIX>,
+                {
+                    // enable or reset $SPIX
+                    apb2.enr().modify(|_, w| w.$spiXen().set_bit());
+                    apb2.rstr().modify(|_, w| w.$spiXrst().set_bit());
+                    apb2.rstr().modify(|_, w| w.$spiXrst().clear_bit());
+
+                    let br = compute_baud_rate(clocks.$pclkX(), freq.into());
+                    Self::configure(&spi, mode, br, Nss::Output, FrameFormat::Motorola, false);
+
+                    Spi { spi, pins }
+                }
+
+                /// Configures the SPI peripheral to operate in full duplex master mode on a bus
+                /// with several masters, with NSS as an input (`CR1.SSM` and `CR2.SSOE` cleared)
+                ///
+                /// Another master takes the bus by driving NSS low, which raises a mode fault:
+                /// the hardware clears `CR1.SPE` and `CR1.MSTR`, releasing SCK and MOSI, and the
+                /// transfers fail with [`Error::ModeFault`]. Once NSS is high again,
+                /// [`Spi::recover_from_modf`] takes the bus back. NSS must be pulled up, e.g. with
+                /// `internal_pull_up(true)` on the pin, or the peripheral faults as soon as it is
+                /// enabled. The devices are selected with their own chip select pins, as with the
+                /// `spiX` constructors, and [`Spi::transaction`] is not used in this mode.
+                ///
+                /// A `freq` out of reach of the dividers is clamped, see [`Spi::reclock`].
+                pub fn $spiX_multi_master<F>(
+                    spi: $SPIX,
+                    pins: (SCK, MISO, MOSI, NSS),
+                    mode: Mode,
+                    freq: F,
+                    clocks: Clocks,
+                    apb: &mut $APBX,
+                ) -> Self
+                where
+                    F: Into<Hertz>,
+                    SCK: SckPin<$SPIX>,
+                    MISO: MisoPin<$SPIX>,
+                    MOSI: MosiPin<$SPIX>,
+                    NSS: NssPin<$SPIX>,
+                {
+                    apb.enr().modify(|_, w| w.$spiXen().set_bit());
+                    apb.rstr().modify(|_, w| w.$spiXrst().set_bit());
+                    apb.rstr().modify(|_, w| w.$spiXrst().clear_bit());
+
+                    let br = compute_baud_rate(clocks.$pclkX(), freq.into());
+                    Self::configure(&spi, mode, br, Nss::Input, FrameFormat::Motorola, false);
+
+                    Spi { spi, pins }
+                }
+
+                /// Configures the SPI peripheral to operate in full duplex master mode, with the
+                /// TI frame format, see [`FrameFormat::Ti`]
+                ///
+                /// The TI frame format requires the peripheral to drive NSS, which pulses before
+                /// every frame, so there is no TI mode with a software chip select. The clock
+                /// polarity and phase are fixed by the frame format, hence the missing `Mode`.
+                /// [`Reconfigure`] only changes the baud rate in this mode.
+                ///
+                /// As with the hardware NSS, transfers must be run in [`Spi::transaction`]. There
+                /// is no pull-up needed on NSS, which is only sampled at the pulse.
+                ///
+                /// A `freq` out of reach of the dividers is clamped, see [`Spi::reclock`].
+                pub fn $spiX_ti<F>(
+                    spi: $SPIX,
+                    pins: (SCK, MISO, MOSI, NSS),
+                    freq: F,
+                    clocks: Clocks,
+                    apb2: &mut $APBX,
+                ) -> Self
+                where
+                    F: Into<Hertz>,
+                    SCK: SckPin<$SPIX>,
+                    MISO: MisoPin<$SPIX>,
+                    MOSI: MosiPin<$SPIX>,
+                    NSS: NssPin<$SPIX>,
+                {
+                    // enable or reset $SPIX
+                    apb2.enr().modify(|_, w| w.$spiXen().set_bit());
+                    apb2.rstr().modify(|_, w| w.$spiXrst().set_bit());
+                    apb2.rstr().modify(|_, w| w.$spiXrst().clear_bit());
+
+                    // The mode is ignored by the hardware in TI mode, take the one it forces
+                    let mode = Mode {
+                        polarity: Polarity::IdleLow,
+                        phase: Phase::CaptureOnSecondTransition,
+                    };
+                    let br = compute_baud_rate(clocks.$pclkX(), freq.into());
+                    Self::configure(&spi, mode, br, Nss::Output, FrameFormat::Ti, false);
+
+                    Spi { spi, pins }
+                }
+
+                /// Runs `f` with NSS asserted, releasing it once the last byte is sent
+                ///
+                /// The peripheral is only enabled during the transaction, transfers must not be
+                /// started outside of it.
+                pub fn transaction<R, F>(&mut self, f: F) -> R
+                where
+                    F: FnOnce(&mut Self) -> R,
+                {
+                    self.spi.cr1.modify(|_, w| w.spe().set_bit());
+                    let result = f(self);
+
+                    self.flush();
+                    self.spi.cr1.modify(|_, w| w.spe().clear_bit());
+
+                    result
                 }
 
                 /// Releases the SPI peripheral and associated pins
-                pub fn free(self) -> ($SPIX, (SCK, MISO, MOSI)) {
+                pub fn free(self) -> ($SPIX, (SCK, MISO, MOSI, NSS)) {
                     (self.spi, self.pins)
                 }
             }
@@ -161,13 +604,21 @@ macro_rules! hal {
                 type Error = Error;
 
                 fn read(&mut self) -> nb::Result<u8, Error> {
+                    // Nothing is ever received when only sending
+                    if self.is_tx_only() {
+                        return Ok(0);
+                    }
+
                     let sr = self.spi.sr.read();
 
                     Err(if sr.ovr().bit_is_set() {
+                        trace!("SPI overrun");
                         nb::Error::Other(Error::Overrun)
                     } else if sr.modf().bit_is_set() {
+                        trace!("SPI mode fault");
                         nb::Error::Other(Error::ModeFault)
                     } else if sr.crcerr().bit_is_set() {
+                        trace!("SPI CRC error");
                         nb::Error::Other(Error::Crc)
                     } else if sr.rxne().bit_is_set() {
                         // NOTE(read_volatile) read only 1 byte (the svd2rust API only allows
@@ -184,10 +635,13 @@ macro_rules! hal {
                     let sr = self.spi.sr.read();
 
                     Err(if sr.ovr().bit_is_set() {
+                        trace!("SPI overrun");
                         nb::Error::Other(Error::Overrun)
                     } else if sr.modf().bit_is_set() {
+                        trace!("SPI mode fault");
                         nb::Error::Other(Error::ModeFault)
                     } else if sr.crcerr().bit_is_set() {
+                        trace!("SPI CRC error");
                         nb::Error::Other(Error::Crc)
                     } else if sr.txe().bit_is_set() {
                         // NOTE(write_volatile) see note above
@@ -199,9 +653,476 @@ macro_rules! hal {
                 }
             }
 
-            impl<PINS> crate::hal::blocking::spi::transfer::Default<u8> for Spi<$SPIX, PINS> {}
+            impl<PINS> Reconfigure for Spi<$SPIX, PINS> {
+                fn reconfigure(&mut self, mode: Mode, freq: Hertz, clocks: Clocks) {
+                    let br = compute_baud_rate(clocks.$pclkX(), freq);
+
+                    while self.spi.sr.read().bsy().bit_is_set() {}
+                    let spe = self.spi.cr1.read().spe().bit_is_set();
+                    self.spi.cr1.modify(|_, w| w.spe().clear_bit());
+                    self.spi.cr1.modify(|_, w| unsafe {
+                        w.cpha()
+                            .bit(mode.phase == Phase::CaptureOnSecondTransition)
+                            .cpol()
+                            .bit(mode.polarity == Polarity::IdleHigh)
+                            .br()
+                            .bits(br)
+                            .spe()
+                            .bit(spe)
+                    });
+                }
+            }
+
+            impl<PINS> Spi<$SPIX, PINS> {
+                /// Writes the configuration of full duplex master mode, with or without hardware NSS,
+                /// or of transmit only master mode
+                fn configure(
+                    spi: &$SPIX,
+                    mode: Mode,
+                    br: u8,
+                    nss: Nss,
+                    format: FrameFormat,
+                    tx_only: bool,
+                ) {
+                    // The TI frame format only exists with NSS driven by the peripheral
+                    debug_assert!(nss == Nss::Output || format == FrameFormat::Motorola);
+
+                    // FRXTH: RXNE event is generated if the FIFO level is greater than or equal to
+                    //        8-bit
+                    // DS: 8-bit data size
+                    // SSOE: Slave Select output enabled with the hardware NSS
+                    // FRF: Motorola or TI frame format
+                    spi.cr2.write(|w| unsafe {
+                        w.frxth()
+                            .set_bit()
+                            .ds()
+                            .bits(0b111)
+                            .ssoe()
+                            .bit(nss == Nss::Output)
+                            .frf()
+                            .bit(format == FrameFormat::Ti)
+                    });
+
+                    // CPHA: phase
+                    // CPOL: polarity
+                    // MSTR: master mode
+                    // BR: 1 MHz
+                    // SPE: SPI enabled, unless the NSS output is asserted with it
+                    // LSBFIRST: MSB first
+                    // SSM: software slave management (NSS pin free for other uses), unless the
+                    //      NSS pin is an output or an input
+                    // SSI: set nss high = master mode, ignored without software slave management
+                    // CRCEN: hardware CRC calculation disabled
+                    // BIDIMODE: 2 line unidirectional (full duplex), or 1 line bidirectional when
+                    //           only sending
+                    // BIDIOE: output enabled when only sending
+                    spi.cr1.write(|w| unsafe {
+                        w.cpha()
+                            .bit(mode.phase == Phase::CaptureOnSecondTransition)
+                            .cpol()
+                            .bit(mode.polarity == Polarity::IdleHigh)
+                            .mstr()
+                            .set_bit()
+                            .br()
+                            .bits(br)
+                            .spe()
+                            .bit(nss != Nss::Output)
+                            .lsbfirst()
+                            .clear_bit()
+                            .ssi()
+                            .set_bit()
+                            .ssm()
+                            .bit(nss == Nss::Software)
+                            .crcen()
+                            .clear_bit()
+                            .bidimode()
+                            .bit(tx_only)
+                            .bidioe()
+                            .bit(tx_only)
+                    });
+                }
+
+                /// Returns `true` if the SPI was configured to only send, see the `spiX_tx_only`
+                /// constructors
+                pub fn is_tx_only(&self) -> bool {
+                    self.spi.cr1.read().bidimode().bit_is_set()
+                }
+
+                /// Returns the frame format the SPI was configured with
+                pub fn frame_format(&self) -> FrameFormat {
+                    if self.spi.cr2.read().frf().bit_is_set() {
+                        FrameFormat::Ti
+                    } else {
+                        FrameFormat::Motorola
+                    }
+                }
+
+                /// Change the baud rate of the SPI
+                ///
+                /// The closest divider is picked, so a `freq` above half the peripheral clock
+                /// runs at half of it, and one below 1/256 of it at 1/256. See
+                /// [`Spi::frequency`] for the achieved baud rate.
+                pub fn reclock<F>(&mut self, freq: F, clocks: Clocks)
+                    where F: Into<Hertz>
+                {
+                    let spe = self.spi.cr1.read().spe().bit_is_set();
+                    self.spi.cr1.modify(|_, w| w.spe().clear_bit());
+                    self.spi.cr1.modify(|_, w| {
+                        unsafe {w.br().bits(compute_baud_rate(clocks.$pclkX(), freq.into()));}
+                        w.spe().bit(spe)
+                    });
+                }
+
+                /// Starts listening for an interrupt event
+                pub fn listen(&mut self, event: Event) {
+                    match event {
+                        Event::Rxne => self.spi.cr2.modify(|_, w| w.rxneie().set_bit()),
+                        Event::Txe => self.spi.cr2.modify(|_, w| w.txeie().set_bit()),
+                        Event::Error => self.spi.cr2.modify(|_, w| w.errie().set_bit()),
+                    }
+                }
+
+                /// Stops listening for an interrupt event
+                pub fn unlisten(&mut self, event: Event) {
+                    match event {
+                        Event::Rxne => self.spi.cr2.modify(|_, w| w.rxneie().clear_bit()),
+                        Event::Txe => self.spi.cr2.modify(|_, w| w.txeie().clear_bit()),
+                        Event::Error => self.spi.cr2.modify(|_, w| w.errie().clear_bit()),
+                    }
+                }
+
+                /// Returns `true` if a received byte can be read
+                pub fn is_rxne(&self) -> bool {
+                    self.spi.sr.read().rxne().bit_is_set()
+                }
+
+                /// Returns `true` if a byte can be sent
+                pub fn is_txe(&self) -> bool {
+                    self.spi.sr.read().txe().bit_is_set()
+                }
+
+                /// Returns `true` if a received byte was lost, because the
+                /// previous one had not been read yet
+                pub fn is_overrun(&self) -> bool {
+                    self.spi.sr.read().ovr().bit_is_set()
+                }
+
+                /// Clears the overrun flag
+                ///
+                /// This reads (and discards) the data register, followed by the
+                /// status register, as required by the reference manual.
+                pub fn clear_overrun(&mut self) {
+                    // NOTE(read_volatile) read only 1 byte (the svd2rust API only allows
+                    // reading a half-word)
+                    let _ = unsafe { ptr::read_volatile(&self.spi.dr as *const _ as *const u8) };
+                    let _ = self.spi.sr.read();
+                }
+
+                /// Returns `true` if a mode fault occurred, i.e. another master drove NSS low
+                ///
+                /// The hardware then clears `CR1.SPE` and `CR1.MSTR`, the peripheral stays
+                /// disabled until [`Spi::recover_from_modf`] is called. Mode faults are only
+                /// detected with the `spiX_multi_master` constructors.
+                pub fn is_mode_fault(&self) -> bool {
+                    self.spi.sr.read().modf().bit_is_set()
+                }
+
+                /// Clears a mode fault and takes the bus as master again
+                ///
+                /// This follows the sequence of the reference manual, a read of `SR` with
+                /// `MODF` set followed by a write of `CR1`, which restores master mode and,
+                /// unless NSS is driven by the peripheral, enables it again. On a bus with several
+                /// masters, call it only once the other master released the bus, i.e. NSS is
+                /// high again, or the fault is raised again right away.
+                pub fn recover_from_modf(&mut self) {
+                    let _ = self.spi.sr.read();
+
+                    let spe = self.spi.cr2.read().ssoe().bit_is_clear();
+                    self.spi.cr1.modify(|_, w| w.mstr().set_bit());
+                    self.spi.cr1.modify(|_, w| w.spe().bit(spe));
+                }
+
+                /// Pulses the reset line of the peripheral and applies the configuration again
+                ///
+                /// This recovers the peripheral from a stuck state, e.g. a jammed FIFO. Any
+                /// transfer in progress is lost.
+                pub fn reset(&mut self, apb: &mut $APBX) {
+                    let cr1 = self.spi.cr1.read();
+                    let (cr1, spe) = (cr1.bits(), cr1.spe().bit_is_set());
+                    let cr2 = self.spi.cr2.read().bits();
+
+                    apb.rstr().modify(|_, w| w.$spiXrst().set_bit());
+                    apb.rstr().modify(|_, w| w.$spiXrst().clear_bit());
+
+                    // This is sound, as the values were read back from the same registers. The
+                    // configuration is written while disabled, then SPE is restored.
+                    self.spi.cr2.write(|w| unsafe { w.bits(cr2) });
+                    self.spi.cr1.write(|w| unsafe { w.bits(cr1) }.spe().clear_bit());
+                    self.spi.cr1.modify(|_, w| w.spe().bit(spe));
+                }
+
+                /// Returns the achieved baud rate, computed from the divider and `clocks`
+                pub fn frequency(&self, clocks: Clocks) -> Hertz {
+                    let br = self.spi.cr1.read().br().bits();
+
+                    Hertz(clocks.$pclkX().0 >> (br + 1))
+                }
+
+                /// Waits until the last byte is completely shifted out
+                ///
+                /// This waits for `TXE` and for `BSY` to clear, so the chip select can be
+                /// deasserted right after, and then drains the RX FIFO, so a following transfer
+                /// does not read stale bytes. Any overrun caused by not reading the received
+                /// bytes is cleared with them.
+                pub fn flush(&mut self) {
+                    while self.spi.sr.read().txe().bit_is_clear() {}
+                    while self.spi.sr.read().bsy().bit_is_set() {}
+
+                    while self.spi.sr.read().frlvl().bits() != 0 {
+                        // NOTE(read_volatile) read only 1 byte (the svd2rust API only allows
+                        // reading a half-word)
+                        let _ = unsafe { ptr::read_volatile(&self.spi.dr as *const _ as *const u8) };
+                    }
+                    let _ = self.spi.sr.read();
+                }
+            }
+
+            impl<PINS> blocking::spi::Transfer<u8> for Spi<$SPIX, PINS> {
+                type Error = Error;
 
-            impl<PINS> crate::hal::blocking::spi::write::Default<u8> for Spi<$SPIX, PINS> {}
+                fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Error> {
+                    for word in words.iter_mut() {
+                        nb::block!(self.send(*word))?;
+                        *word = nb::block!(self.read())?;
+                    }
+                    self.flush();
+
+                    Ok(words)
+                }
+            }
+
+            #[cfg(feature = "async")]
+            impl<PINS> Spi<$SPIX, PINS> {
+                /// Pairs the SPI with its DMA channels, for the `async` bus
+                pub fn with_dma(
+                    self,
+                    rx_channel: $rx_chan,
+                    tx_channel: $tx_chan,
+                ) -> SpiDma<$SPIX, PINS, $rx_chan, $tx_chan> {
+                    SpiDma {
+                        spi: self,
+                        rx_channel,
+                        tx_channel,
+                    }
+                }
+            }
+
+            #[cfg(feature = "async")]
+            impl<PINS> SpiDma<$SPIX, PINS, $rx_chan, $tx_chan> {
+                /// Exchanges `len` bytes between `tx` and `rx`
+                ///
+                /// Without increment, `tx` is sent repeatedly and `rx` overwritten by every
+                /// received byte.
+                async fn transfer_dma(
+                    &mut self,
+                    rx: *mut u8,
+                    rx_increment: bool,
+                    tx: *const u8,
+                    tx_increment: bool,
+                    len: u16,
+                ) -> Result<(), Error> {
+                    let dr = &self.spi.spi.dr as *const _ as u32;
+
+                    self.rx_channel.stop();
+                    self.rx_channel.set_peripheral_address(dr, false);
+                    self.rx_channel.set_memory_address(rx as u32, rx_increment);
+                    self.rx_channel.set_transfer_length(len);
+                    self.rx_channel
+                        .cselr()
+                        .modify(|_, w| w.$dmacsr().bits($dma_request));
+
+                    self.tx_channel.stop();
+                    self.tx_channel.set_peripheral_address(dr, false);
+                    self.tx_channel.set_memory_address(tx as u32, tx_increment);
+                    self.tx_channel.set_transfer_length(len);
+                    self.tx_channel
+                        .cselr()
+                        .modify(|_, w| w.$dmacst().bits($dma_request));
+
+                    self.rx_channel.ccr().modify(|_, w| unsafe {
+                        w.mem2mem()
+                            .clear_bit()
+                            // 00: 8-bits, 01: 16-bits, 10: 32-bits, 11: Reserved
+                            .msize()
+                            .bits(0b00)
+                            // 00: 8-bits, 01: 16-bits, 10: 32-bits, 11: Reserved
+                            .psize()
+                            .bits(0b00)
+                            .circ()
+                            .clear_bit()
+                            // Peripheral -> Mem
+                            .dir()
+                            .clear_bit()
+                    });
+                    self.tx_channel.ccr().modify(|_, w| unsafe {
+                        w.mem2mem()
+                            .clear_bit()
+                            // 00: 8-bits, 01: 16-bits, 10: 32-bits, 11: Reserved
+                            .msize()
+                            .bits(0b00)
+                            // 00: 8-bits, 01: 16-bits, 10: 32-bits, 11: Reserved
+                            .psize()
+                            .bits(0b00)
+                            .circ()
+                            .clear_bit()
+                            // Mem -> Peripheral
+                            .dir()
+                            .set_bit()
+                    });
+
+                    // With the hardware NSS, the transfer is a transaction of its own. From here
+                    // on, dropping the guard restores the SPI, also if the future is dropped.
+                    let guard = DmaGuard {
+                        spi: &self.spi.spi,
+                        spe: self.spi.spi.cr1.read().spe().bit_is_set(),
+                    };
+                    self.spi.spi.cr1.modify(|_, w| w.spe().set_bit());
+
+                    // NOTE(compiler_fence) operations on the buffers should not be reordered
+                    // after the next statements, which start the DMA transfer
+                    atomic::compiler_fence(Ordering::Release);
+
+                    if self.spi.is_tx_only() {
+                        // Nothing is received, the end of the transfer is the end of sending
+                        self.tx_channel.start();
+                        self.spi.spi.cr2.modify(|_, w| w.txdmaen().set_bit());
+
+                        AsyncTransfer::new(&mut self.tx_channel).wait(|_| false).await;
+                        while self.spi.spi.sr.read().ftlvl().bits() != 0 {}
+
+                        // Reads return 0, as with the blocking traits
+                        if rx_increment {
+                            // NOTE(unsafe) `rx` points to `len` bytes when incremented
+                            unsafe { ptr::write_bytes(rx, 0, usize::from(len)) };
+                        }
+                    } else {
+                        // The reference manual sequence: RX requests first, then both channels,
+                        // and the TX requests last, which start the transfer
+                        self.spi.spi.cr2.modify(|_, w| w.rxdmaen().set_bit());
+                        self.rx_channel.start();
+                        self.tx_channel.start();
+                        self.spi.spi.cr2.modify(|_, w| w.txdmaen().set_bit());
+
+                        // The last byte is received after the last one is sent, the end of
+                        // reception is the end of the transfer
+                        let tx_transfer = AsyncTransfer::new(&mut self.tx_channel);
+                        let mut rx_transfer = AsyncTransfer::new(&mut self.rx_channel);
+                        rx_transfer.wait(|_| false).await;
+                        drop(rx_transfer);
+                        drop(tx_transfer);
+                    }
+
+                    while self.spi.spi.sr.read().bsy().bit_is_set() {}
+                    let sr = self.spi.spi.sr.read();
+                    drop(guard);
+
+                    if sr.ovr().bit_is_set() {
+                        self.spi.clear_overrun();
+                        Err(Error::Overrun)
+                    } else if sr.modf().bit_is_set() {
+                        Err(Error::ModeFault)
+                    } else {
+                        Ok(())
+                    }
+                }
+            }
+
+            #[cfg(feature = "async")]
+            impl<PINS> embedded_hal_async::spi::ErrorType for SpiDma<$SPIX, PINS, $rx_chan, $tx_chan> {
+                type Error = Error;
+            }
+
+            /// Transfers with the DMA, see the [DMA documentation](crate::dma#async)
+            ///
+            /// Only the interrupt of the receive channel is used, or of the transmit channel if
+            /// the SPI only sends. The bytes sent while reading are 0.
+            #[cfg(feature = "async")]
+            impl<PINS> embedded_hal_async::spi::SpiBus<u8> for SpiDma<$SPIX, PINS, $rx_chan, $tx_chan> {
+                async fn read(&mut self, words: &mut [u8]) -> Result<(), Error> {
+                    let tx = 0u8;
+                    for chunk in words.chunks_mut(usize::from(u16::MAX)) {
+                        self.transfer_dma(chunk.as_mut_ptr(), true, &tx, false, chunk.len() as u16)
+                            .await?;
+                    }
+
+                    Ok(())
+                }
+
+                async fn write(&mut self, words: &[u8]) -> Result<(), Error> {
+                    let mut rx = 0u8;
+                    for chunk in words.chunks(usize::from(u16::MAX)) {
+                        self.transfer_dma(&mut rx, false, chunk.as_ptr(), true, chunk.len() as u16)
+                            .await?;
+                    }
+
+                    Ok(())
+                }
+
+                async fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Error> {
+                    let common = read.len().min(write.len());
+                    let (read, read_rest) = read.split_at_mut(common);
+                    let (write, write_rest) = write.split_at(common);
+
+                    for (read, write) in read
+                        .chunks_mut(usize::from(u16::MAX))
+                        .zip(write.chunks(usize::from(u16::MAX)))
+                    {
+                        self.transfer_dma(
+                            read.as_mut_ptr(),
+                            true,
+                            write.as_ptr(),
+                            true,
+                            read.len() as u16,
+                        )
+                        .await?;
+                    }
+
+                    // At most one of them is left
+                    self.read(read_rest).await?;
+                    self.write(write_rest).await
+                }
+
+                async fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Error> {
+                    // Every byte is sent before the one received in its place is written
+                    for chunk in words.chunks_mut(usize::from(u16::MAX)) {
+                        let len = chunk.len() as u16;
+                        let ptr = chunk.as_mut_ptr();
+                        self.transfer_dma(ptr, true, ptr, true, len).await?;
+                    }
+
+                    Ok(())
+                }
+
+                /// Does nothing, the transfers return once their last byte is completely sent
+                async fn flush(&mut self) -> Result<(), Error> {
+                    Ok(())
+                }
+            }
+
+            impl<PINS> blocking::spi::Write<u8> for Spi<$SPIX, PINS> {
+                type Error = Error;
+
+                /// Writes `words`, returning once the last one is completely sent
+                fn write(&mut self, words: &[u8]) -> Result<(), Error> {
+                    for word in words {
+                        nb::block!(self.send(*word))?;
+                        nb::block!(self.read())?;
+                    }
+                    self.flush();
+
+                    Ok(())
+                }
+            }
         )+
     }
 }
@@ -226,7 +1147,13 @@ use crate::stm32::SPI1;
     feature = "stm32l4x6"
 ))]
 hal! {
-    SPI1: (spi1, APB2, spi1en, spi1rst, pclk2),
+    SPI1: (
+        spi1, spi1_hardware_nss, spi1_multi_master, spi1_ti, spi1_tx_only, spi1_rx_only,
+        APB2, spi1en, spi1rst, pclk2,
+        rx: (c2s, dma1::C2),
+        tx: (c3s, dma1::C3),
+        0b0001
+    ),
 }
 
 #[cfg(any(
@@ -244,6 +1171,28 @@ pins!(SPI1, AF5,
 #[cfg(any(feature = "stm32l4x5", feature = "stm32l4x6"))]
 pins!(SPI1, AF5, SCK: [PG2], MISO: [PG3], MOSI: [PG4]);
 
+#[cfg(any(
+    feature = "stm32l4x1",
+    feature = "stm32l4x2",
+    feature = "stm32l4x3",
+    feature = "stm32l4x5",
+    feature = "stm32l4x6"
+))]
+nss_pins!(SPI1, AF5, NSS: [PA4, PA15, PB0, PE12]);
+
+#[cfg(any(feature = "stm32l4x5", feature = "stm32l4x6"))]
+nss_pins!(SPI1, AF5, NSS: [PG5]);
+
+#[cfg(all(
+    feature = "async",
+    any(
+        feature = "stm32l4x1",
+        feature = "stm32l4x2",
+        feature = "stm32l4x5",
+        feature = "stm32l4x6",
+    )
+))]
+use crate::dma::dma2;
 #[cfg(any(
     feature = "stm32l4x1",
     feature = "stm32l4x2",
@@ -259,7 +1208,13 @@ use crate::{gpio::AF6, stm32::SPI3};
     feature = "stm32l4x6",
 ))]
 hal! {
-    SPI3: (spi3, APB1R1, spi3en, spi3rst, pclk1),
+    SPI3: (
+        spi3, spi3_hardware_nss, spi3_multi_master, spi3_ti, spi3_tx_only, spi3_rx_only,
+        APB1R1, spi3en, spi3rst, pclk1,
+        rx: (c1s, dma2::C1),
+        tx: (c2s, dma2::C2),
+        0b0011
+    ),
 }
 
 #[cfg(any(
@@ -276,6 +1231,17 @@ pins!(SPI3, AF6,
 #[cfg(any(feature = "stm32l4x5", feature = "stm32l4x6",))]
 pins!(SPI3, AF6, SCK: [PG9], MISO: [PG10], MOSI: [PG11]);
 
+#[cfg(any(
+    feature = "stm32l4x1",
+    feature = "stm32l4x2",
+    feature = "stm32l4x5",
+    feature = "stm32l4x6",
+))]
+nss_pins!(SPI3, AF6, NSS: [PA4, PA15]);
+
+#[cfg(any(feature = "stm32l4x5", feature = "stm32l4x6",))]
+nss_pins!(SPI3, AF6, NSS: [PG12]);
+
 #[cfg(any(
     feature = "stm32l4x1",
     feature = "stm32l4x3",
@@ -291,7 +1257,13 @@ use crate::stm32::SPI2;
     feature = "stm32l4x6",
 ))]
 hal! {
-    SPI2: (spi2, APB1R1, spi2en, spi2rst, pclk1),
+    SPI2: (
+        spi2, spi2_hardware_nss, spi2_multi_master, spi2_ti, spi2_tx_only, spi2_rx_only,
+        APB1R1, spi2en, spi2rst, pclk1,
+        rx: (c4s, dma1::C4),
+        tx: (c5s, dma1::C5),
+        0b0001
+    ),
 }
 
 #[cfg(any(
@@ -304,3 +1276,43 @@ pins!(SPI2, AF5,
     SCK: [PB13, PB10, PD1],
     MISO: [PB14, PC2, PD3],
     MOSI: [PB15, PC3, PD4]);
+
+#[cfg(any(
+    feature = "stm32l4x1",
+    feature = "stm32l4x3",
+    feature = "stm32l4x5",
+    feature = "stm32l4x6",
+))]
+nss_pins!(SPI2, AF5, NSS: [PB9, PB12, PD0]);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn baud_rate_dividers() {
+        let clocks = Hertz(80_000_000);
+        let divider = |ratio: u32| 2 << compute_baud_rate(clocks, Hertz(clocks.0 / ratio));
+
+        assert_eq!(divider(2), 2);
+        assert_eq!(divider(3), 4);
+        assert_eq!(divider(5), 4);
+        assert_eq!(divider(6), 8);
+        assert_eq!(divider(128), 128);
+        assert_eq!(divider(191), 128);
+        assert_eq!(divider(192), 256);
+        assert_eq!(divider(256), 256);
+    }
+
+    #[test]
+    fn baud_rate_clamped() {
+        let clocks = Hertz(80_000_000);
+
+        // Above half the clock, and above the clock
+        assert_eq!(compute_baud_rate(clocks, Hertz(50_000_000)), 0b000);
+        assert_eq!(compute_baud_rate(clocks, Hertz(100_000_000)), 0b000);
+        // Below 1/256 of the clock
+        assert_eq!(compute_baud_rate(clocks, Hertz(1_000)), 0b111);
+        assert_eq!(compute_baud_rate(clocks, Hertz(0)), 0b111);
+    }
+}
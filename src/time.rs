@@ -35,6 +35,12 @@ pub trait U32Ext {
 
     /// Wrap in `MilliSeconds`
     fn milliseconds(self) -> MilliSeconds;
+
+    /// Wrap in `MilliSeconds`
+    fn ms(self) -> MilliSeconds;
+
+    /// Wrap in `MicroSeconds`
+    fn us(self) -> MicroSeconds;
 }
 
 impl U32Ext for u32 {
@@ -57,6 +63,14 @@ impl U32Ext for u32 {
     fn milliseconds(self) -> MilliSeconds {
         MilliSeconds(self)
     }
+
+    fn ms(self) -> MilliSeconds {
+        MilliSeconds(self)
+    }
+
+    fn us(self) -> MicroSeconds {
+        MicroSeconds(self)
+    }
 }
 
 impl Into<Hertz> for KiloHertz {
@@ -77,9 +91,26 @@ impl Into<KiloHertz> for MegaHertz {
     }
 }
 
+/// Interprets the value as a period in milliseconds
+///
+/// Deprecated, the unit is easy to get wrong. Use `MilliSeconds` (e.g.
+/// `500.ms()`) instead, this conversion will be removed in the next release.
+// `#[deprecated]` is rejected on trait impls (`useless_deprecated`), so the deprecation is only
+// documented here and in the changelog
 impl From<u32> for Hertz {
     fn from(ms: u32) -> Self {
-        if ms <= 1000 {
+        MilliSeconds(ms).into()
+    }
+}
+
+/// Converts a period into the matching frequency, saturating at 1 Hz and, for a zero period, at
+/// `u32::MAX` Hz
+impl From<MilliSeconds> for Hertz {
+    fn from(period: MilliSeconds) -> Self {
+        let ms = period.0;
+        if ms == 0 {
+            Hertz(u32::MAX)
+        } else if ms <= 1000 {
             Hertz((1000 + ms / 2) / ms)
         } else {
             Hertz(1)
@@ -87,6 +118,27 @@ impl From<u32> for Hertz {
     }
 }
 
+/// Converts a period into the matching frequency, saturating at 1 Hz and, for a zero period, at
+/// `u32::MAX` Hz
+impl From<MicroSeconds> for Hertz {
+    fn from(period: MicroSeconds) -> Self {
+        let us = period.0;
+        if us == 0 {
+            Hertz(u32::MAX)
+        } else if us <= 1_000_000 {
+            Hertz((1_000_000 + us / 2) / us)
+        } else {
+            Hertz(1)
+        }
+    }
+}
+
+impl From<MilliSeconds> for MicroSeconds {
+    fn from(ms: MilliSeconds) -> Self {
+        MicroSeconds(ms.0 * 1_000)
+    }
+}
+
 /// A monotonic nondecreasing timer
 #[derive(Clone, Copy, Debug)]
 pub struct MonoTimer {
@@ -133,5 +185,9 @@ impl Instant {
 }
 
 /// Time unit
-#[derive(PartialEq, PartialOrd, Clone, Copy)]
+#[derive(PartialEq, PartialOrd, Clone, Copy, Debug)]
 pub struct MilliSeconds(pub u32);
+
+/// Time unit
+#[derive(PartialEq, PartialOrd, Clone, Copy, Debug)]
+pub struct MicroSeconds(pub u32);
@@ -1,4 +1,26 @@
 //! Timers
+//!
+//! # 32-bit timers
+//!
+//! TIM2, and TIM5 on the L4x5 and L4x6, have a 32-bit counter, the other timers count on 16
+//! bits. [`Counter32`] runs them at 1 MHz, for durations up to 2^32 µs (71 minutes and 35
+//! seconds) without a software extension of the counter.
+//!
+//! # Synchronization
+//!
+//! A timer sends a trigger output (TRGO), selected by [`Timer::set_master_mode`], which other
+//! peripherals take as their trigger:
+//!
+//! - timer → ADC: e.g. `set_master_mode(MasterMode::Update)` on TIM6 and
+//!   `ExternalTrigger::Tim6Trgo` for `ADC::start_triggered`, for one conversion per period
+//! - timer → DAC: the TRGO of TIM6 is also a trigger of the DAC, selected by `DAC_CR.TSELx`,
+//!   which the `dac` driver doesn't configure yet
+//! - timer → timer: the TRGO of a master is an internal trigger (`ITRx`) of the slaves, see
+//!   [`TriggerInput`]. [`Timer::set_slave_mode`] then resets, gates or starts the counter of
+//!   the slave on it, or clocks it. TIM2 is a slave, as well as TIM4 and TIM5 on the L4x5 and
+//!   L4x6, e.g. TIM5 with TIM2 on `Itr0`, TIM4 with TIM2 on `Itr1`, or TIM2 with TIM4 on
+//!   `Itr3`. The other connections are in the "TIMx internal trigger connection" tables of the
+//!   reference manual.
 
 use crate::hal::timer::{CountDown, Periodic};
 use crate::stm32::{TIM15, TIM16, TIM2, TIM6, TIM7};
@@ -8,7 +30,7 @@ use cast::{u16, u32};
 use void::Void;
 
 use crate::rcc::{Clocks, APB1R1, APB2};
-use crate::time::Hertz;
+use crate::time::{Hertz, MicroSeconds};
 
 /// Hardware timers
 pub struct Timer<TIM> {
@@ -17,12 +39,104 @@ pub struct Timer<TIM> {
     timeout: Hertz,
 }
 
+/// 32-bit timer counting microseconds, see the [module documentation](self#32-bit-timers)
+///
+/// The counter ticks at 1 MHz, or slightly slower when the timer clock is not a whole number of
+/// megahertz, so periods are never shorter than requested. As a `CountDown` it expires every
+/// period of 2 µs up to 2^32 µs.
+pub struct Counter32<TIM> {
+    tim: TIM,
+}
+
 /// Interrupt events
 pub enum Event {
     /// Timer timed out / count down ended
     TimeOut,
 }
 
+/// Trigger output (TRGO) selection
+///
+/// TRGO can be used to synchronize other timers or to start ADC and DAC conversions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MasterMode {
+    /// The UG bit of the EGR register is used as TRGO
+    Reset = 0b000,
+    /// The counter enable signal is used as TRGO
+    Enable = 0b001,
+    /// The update event is used as TRGO, i.e. one pulse per timer period
+    Update = 0b010,
+}
+
+/// Trigger input (TRGI) of a slave timer, see [`Timer::set_slave_mode`]
+///
+/// The timer connected to each internal trigger depends on the slave, see the
+/// [module documentation](self#synchronization).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TriggerInput {
+    /// Internal trigger 0
+    Itr0 = 0b000,
+    /// Internal trigger 1
+    Itr1 = 0b001,
+    /// Internal trigger 2
+    Itr2 = 0b010,
+    /// Internal trigger 3
+    Itr3 = 0b011,
+    /// Both edges of the timer input 1
+    Ti1Edge = 0b100,
+    /// Filtered timer input 1
+    Ti1 = 0b101,
+    /// Filtered timer input 2
+    Ti2 = 0b110,
+}
+
+/// Reaction of a slave timer to its trigger input, see [`Timer::set_slave_mode`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SlaveMode {
+    /// The trigger is ignored, the timer runs on its own clock
+    Disabled = 0b000,
+    /// A rising edge of the trigger restarts the counter
+    Reset = 0b100,
+    /// The counter only counts while the trigger is high, and holds its value otherwise
+    Gated = 0b101,
+    /// A rising edge of the trigger starts the counter, which then runs on
+    Trigger = 0b110,
+    /// The rising edges of the trigger clock the counter
+    ExternalClock = 0b111,
+}
+
+/// Timer expiring periodically, to write drivers generic over their timing source
+///
+/// It is implemented by the general purpose and basic timers ([`Timer`]), the low power timers
+/// (`LowPowerTimer`) and the RTC wakeup timer (`WakeupTimer`), e.g. to run a protocol timeout
+/// off a cheap timer, or off the RTC to keep it running in Stop mode. The period is in the unit
+/// of the timer, see [`PeriodicTimer::Time`].
+///
+/// The methods clash with `CountDown` and `Cancel`, import only one of these traits where a
+/// timer implementing both is used.
+pub trait PeriodicTimer {
+    /// Unit of the period, `Hertz` for `Timer` and `LowPowerTimer`, `MicroSeconds` for
+    /// `Counter32`, seconds for `WakeupTimer`
+    type Time;
+
+    /// Starts the timer, expiring every `period`
+    fn start(&mut self, period: Self::Time);
+
+    /// Returns `true` once per expiry, clearing it
+    fn is_expired(&mut self) -> bool;
+
+    /// Returns `Ok` once per expiry, clearing it, see [`PeriodicTimer::is_expired`]
+    fn wait(&mut self) -> nb::Result<(), Void> {
+        if self.is_expired() {
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    /// Stops the timer, and clears a pending expiry
+    fn cancel(&mut self);
+}
+
 macro_rules! hal {
     ($($TIM:ident: ($tim:ident, $frname:ident, $timXen:ident, $timXrst:ident, $apb:ident, $width:ident),)+) => {
         $(
@@ -73,6 +187,23 @@ macro_rules! hal {
                 }
             }
 
+            impl PeriodicTimer for Timer<$TIM> {
+                type Time = Hertz;
+
+                fn start(&mut self, period: Hertz) {
+                    CountDown::start(self, period);
+                }
+
+                fn is_expired(&mut self) -> bool {
+                    CountDown::wait(self).is_ok()
+                }
+
+                fn cancel(&mut self) {
+                    self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+                    self.clear_update_interrupt_flag();
+                }
+            }
+
             impl Timer<$TIM> {
                 // XXX(why not name this `new`?) bummer: constructors need to have different names
                 // even if the `$TIM` are non overlapping (compare to the `free` function below
@@ -92,7 +223,7 @@ macro_rules! hal {
                         tim,
                         timeout: Hertz(0),
                     };
-                    timer.start(timeout);
+                    CountDown::start(&mut timer, timeout);
 
                     timer
                 }
@@ -211,6 +342,180 @@ macro_rules! hal {
     }
 }
 
+macro_rules! counter32 {
+    ($($TIM:ident: ($tim:ident, $timXen:ident, $timXrst:ident),)+) => {
+        $(
+            impl Counter32<$TIM> {
+                /// Configures the timer to count microseconds, stopped until a `start`
+                ///
+                /// # Panics
+                ///
+                /// Panics if the timer clock is slower than 1 MHz.
+                pub fn $tim(tim: $TIM, clocks: Clocks, apb: &mut APB1R1) -> Self {
+                    // enable and reset peripheral to a clean slate state
+                    apb.enr().modify(|_, w| w.$timXen().set_bit());
+                    apb.rstr().modify(|_, w| w.$timXrst().set_bit());
+                    apb.rstr().modify(|_, w| w.$timXrst().clear_bit());
+
+                    // The timers run at twice the APB clock, unless APB is not divided
+                    let timclk = if clocks.ppre1() == 1 {
+                        clocks.pclk1().0
+                    } else {
+                        clocks.pclk1().0 * 2
+                    };
+                    assert!(timclk >= 1_000_000);
+
+                    // 1 MHz tick, rounding the prescaler up so that a tick is never shorter
+                    // than 1 us
+                    let psc = u16(timclk.div_ceil(1_000_000) - 1).unwrap();
+                    tim.psc.write(|w| w.psc().bits(psc));
+
+                    Counter32 { tim }
+                }
+
+                /// Returns the counter, in microseconds since the start of the period
+                pub fn count(&self) -> u32 {
+                    self.tim.cnt.read().cnt().bits()
+                }
+
+                /// Returns the auto-reload value, the length of the period minus 1 µs
+                pub fn auto_reload(&self) -> u32 {
+                    self.tim.arr.read().arr().bits()
+                }
+
+                /// Starts listening for an `event`
+                pub fn listen(&mut self, event: Event) {
+                    match event {
+                        Event::TimeOut => self.tim.dier.write(|w| w.uie().set_bit()),
+                    }
+                }
+
+                /// Stops listening for an `event`
+                pub fn unlisten(&mut self, event: Event) {
+                    match event {
+                        Event::TimeOut => self.tim.dier.write(|w| w.uie().clear_bit()),
+                    }
+                }
+
+                /// Clears interrupt associated with `event`
+                pub fn clear_interrupt(&mut self, event: Event) {
+                    match event {
+                        Event::TimeOut => self.tim.sr.modify(|_, w| w.uif().clear_bit()),
+                    }
+                }
+
+                /// Releases the TIM peripheral
+                pub fn free(self) -> $TIM {
+                    self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+                    self.tim
+                }
+            }
+
+            impl Periodic for Counter32<$TIM> {}
+
+            impl CountDown for Counter32<$TIM> {
+                type Time = MicroSeconds;
+
+                /// Starts a period of `timeout`, up to 2^32 µs
+                ///
+                /// # Panics
+                ///
+                /// Panics if `timeout` is below 2 µs: with an auto-reload value of 0 the timer
+                /// never expires.
+                fn start<T>(&mut self, timeout: T)
+                where
+                    T: Into<MicroSeconds>,
+                {
+                    let us = timeout.into().0;
+                    assert!(us >= 2, "a period of at least 2 us is required");
+
+                    self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+                    self.tim.arr.write(|w| w.arr().bits(us - 1));
+
+                    // Load the prescaler and the period, and restart the count, without an
+                    // expiry
+                    self.tim.egr.write(|w| w.ug().set_bit());
+                    self.tim.sr.modify(|_, w| w.uif().clear_bit());
+
+                    self.tim.cr1.modify(|_, w| w.cen().set_bit());
+                }
+
+                fn wait(&mut self) -> nb::Result<(), Void> {
+                    if self.tim.sr.read().uif().bit_is_clear() {
+                        Err(nb::Error::WouldBlock)
+                    } else {
+                        self.tim.sr.modify(|_, w| w.uif().clear_bit());
+                        Ok(())
+                    }
+                }
+            }
+
+            impl PeriodicTimer for Counter32<$TIM> {
+                type Time = MicroSeconds;
+
+                fn start(&mut self, period: MicroSeconds) {
+                    CountDown::start(self, period);
+                }
+
+                fn is_expired(&mut self) -> bool {
+                    CountDown::wait(self).is_ok()
+                }
+
+                fn cancel(&mut self) {
+                    self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+                    self.tim.sr.modify(|_, w| w.uif().clear_bit());
+                }
+            }
+        )+
+    }
+}
+
+counter32! {
+    TIM2: (tim2, tim2en, tim2rst),
+}
+
+#[cfg(any(feature = "stm32l4x5", feature = "stm32l4x6",))]
+counter32! {
+    TIM5: (tim5, tim5en, tim5rst),
+}
+
+macro_rules! master_mode {
+    ($($TIM:ident,)+) => {
+        $(
+            impl Timer<$TIM> {
+                /// Selects the signal that is sent out on the trigger output (TRGO), see the
+                /// [module documentation](self#synchronization)
+                // NOTE(allow) `w.mms().bits()` is safe for TIM2 but not for TIM{6,7}
+                #[allow(unused_unsafe)]
+                pub fn set_master_mode(&mut self, mode: MasterMode) {
+                    self.tim.cr2.modify(|_, w| unsafe { w.mms().bits(mode as u8) });
+                }
+            }
+        )+
+    }
+}
+
+macro_rules! slave_mode {
+    ($($TIM:ident,)+) => {
+        $(
+            impl Timer<$TIM> {
+                /// Makes the counter react to `trigger` as set by `mode`, e.g. to count only
+                /// while another timer runs, see the [module documentation](self#synchronization)
+                ///
+                /// The counter still has to be enabled in every mode but `SlaveMode::Trigger`,
+                /// which starts it.
+                pub fn set_slave_mode(&mut self, trigger: TriggerInput, mode: SlaveMode) {
+                    // The trigger may only change while the slave mode is disabled
+                    self.tim.smcr.modify(|_, w| w.sms().bits(SlaveMode::Disabled as u8));
+                    // This is sound, as all `TriggerInput` values are valid for this field.
+                    self.tim.smcr.modify(|_, w| unsafe { w.ts().bits(trigger as u8) });
+                    self.tim.smcr.modify(|_, w| w.sms().bits(mode as u8));
+                }
+            }
+        )+
+    }
+}
+
 hal! {
     TIM2:  (tim2, free_running_tim2, tim2en, tim2rst, APB1R1, u32),
     TIM6:  (tim6, free_running_tim6, tim6en, tim6rst, APB1R1, u16),
@@ -225,3 +530,25 @@ hal! {
     TIM5:  (tim5, free_running_tim5, tim5en, tim5rst, APB1R1, u32),
     TIM17: (tim17, free_running_tim17, tim17en, tim17rst, APB2, u16),
 }
+
+master_mode! {
+    TIM2,
+    TIM6,
+    TIM7,
+}
+
+#[cfg(any(feature = "stm32l4x5", feature = "stm32l4x6",))]
+master_mode! {
+    TIM4,
+    TIM5,
+}
+
+slave_mode! {
+    TIM2,
+}
+
+#[cfg(any(feature = "stm32l4x5", feature = "stm32l4x6",))]
+slave_mode! {
+    TIM4,
+    TIM5,
+}